@@ -1,8 +1,10 @@
-use addin1c::{AddinResult, MethodInfo, Methods, PropInfo, SimpleAddin, Variant, name};
-use git_core::AuthType;
+use std::{path::Path, sync::Arc, time::Duration};
+
+use addin1c::{AddinResult, Connection, MethodInfo, PropInfo, SimpleAddin, Variant, name};
+use git_core::{AuthType, VerifyPolicy};
 use log::debug;
 
-use crate::git::Git;
+use crate::{descriptors, git::Git, logging, metrics::Metrics};
 
 pub struct GitAddin {
     git: Git,
@@ -14,6 +16,11 @@ impl GitAddin {
         Self { git: Git::default() }
     }
 
+    /// What the dispatch of [`GitAddin::method_table`] measures the calls with.
+    fn metrics(&self) -> Arc<Metrics> {
+        self.git.metrics.clone()
+    }
+
     fn clone_repo(&mut self, url: &mut Variant, ret_value: &mut Variant) -> AddinResult {
         debug!("clone_repo()");
         let message = self.git.clone_repo(&url.get_string()?);
@@ -21,6 +28,24 @@ impl GitAddin {
         Ok(())
     }
 
+    fn clone_mirror(&mut self, url: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("clone_mirror()");
+        let message = self.git.clone_mirror(&url.get_string()?);
+        ret_value.set_str1c(message)?;
+        Ok(())
+    }
+
+    fn init_from_template(
+        &mut self,
+        template_name: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("init_from_template()");
+        let message = self.git.init_from_template(&template_name.get_string()?);
+        ret_value.set_str1c(message)?;
+        Ok(())
+    }
+
     fn get_branches(&mut self, ret_value: &mut Variant) -> AddinResult {
         debug!("get_branches()");
         let branches = self.git.branches();
@@ -28,6 +53,13 @@ impl GitAddin {
         Ok(())
     }
 
+    fn branches_detailed(&mut self, ret_value: &mut Variant) -> AddinResult {
+        debug!("branches_detailed()");
+        let branches = self.git.branches_detailed();
+        ret_value.set_str1c(branches)?;
+        Ok(())
+    }
+
     fn get_current_branch(&mut self, ret_value: &mut Variant) -> AddinResult {
         debug!("get_current_branch()");
         let result = self.git.current_branch();
@@ -35,6 +67,13 @@ impl GitAddin {
         Ok(())
     }
 
+    fn head_ref(&mut self, ret_value: &mut Variant) -> AddinResult {
+        debug!("head_ref()");
+        let result = self.git.head_ref();
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
     fn status(&mut self, ret_value: &mut Variant) -> AddinResult {
         debug!("status()");
         let status = self.git.status();
@@ -42,6 +81,13 @@ impl GitAddin {
         Ok(())
     }
 
+    fn get_status_fingerprint(&mut self, ret_value: &mut Variant) -> AddinResult {
+        debug!("get_status_fingerprint()");
+        let fingerprint = self.git.status_fingerprint();
+        ret_value.set_str1c(fingerprint)?;
+        Ok(())
+    }
+
     fn add_all(&mut self, ret_value: &mut Variant) -> AddinResult {
         debug!("add_all()");
         let message = self.git.add_all();
@@ -49,6 +95,18 @@ impl GitAddin {
         Ok(())
     }
 
+    fn stage_lines(
+        &mut self,
+        path: &mut Variant,
+        ranges: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("stage_lines()");
+        let result = self.git.stage_lines(&path.get_string()?, &ranges.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
     fn commit(&mut self, message: &mut Variant, ret_value: &mut Variant) -> AddinResult {
         debug!("commit()");
         let result = self.git.commit(&message.get_string()?);
@@ -56,6 +114,40 @@ impl GitAddin {
         Ok(())
     }
 
+    fn commit_coauthors(
+        &mut self,
+        message: &mut Variant,
+        coauthors: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("commit_coauthors()");
+        let result =
+            self.git.commit_with_coauthors(&message.get_string()?, &coauthors.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn commit_signoff(&mut self, message: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("commit_signoff()");
+        let result = self.git.commit_signed_off(&message.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn add_and_amend(&mut self, paths: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("add_and_amend()");
+        let result = self.git.add_and_amend(&paths.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn write_identity(&mut self, ret_value: &mut Variant) -> AddinResult {
+        debug!("write_identity()");
+        let result = self.git.write_identity();
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
     fn checkout(&mut self, branch_name: &mut Variant, ret_value: &mut Variant) -> AddinResult {
         debug!("checkout()");
         let result = self.git.checkout(&branch_name.get_string()?);
@@ -63,6 +155,254 @@ impl GitAddin {
         Ok(())
     }
 
+    fn checkout_local(
+        &mut self,
+        branch_name: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("checkout_local()");
+        let result = self.git.checkout_local(&branch_name.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn begin_commit(&mut self, message: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("begin_commit()");
+        let result = self.git.begin_commit(&message.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn confirm_commit(&mut self, token: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("confirm_commit()");
+        let result = self.git.confirm_commit(&token.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn abort_commit(&mut self, token: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("abort_commit()");
+        let result = self.git.abort_commit(&token.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn discard_changes(&mut self, paths: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("discard_changes()");
+        let result = self.git.discard_changes(&paths.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn relocate(&mut self, new_path: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("relocate()");
+        let result = self.git.relocate(&new_path.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn checkout_tag(&mut self, tag: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("checkout_tag()");
+        let result = self.git.checkout_tag(&tag.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn checkout_paths(
+        &mut self,
+        revspec: &mut Variant,
+        paths: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("checkout_paths()");
+        let result = self.git.checkout_paths(&revspec.get_string()?, &paths.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn create_bundle(
+        &mut self,
+        path: &mut Variant,
+        range: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("create_bundle()");
+        let result = self.git.create_bundle(&path.get_string()?, &range.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn import_bundle(&mut self, path: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("import_bundle()");
+        let result = self.git.import_bundle(&path.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn checkout_file_from(
+        &mut self,
+        revspec: &mut Variant,
+        path: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("checkout_file_from()");
+        let result = self.git.checkout_file_from(&revspec.get_string()?, &path.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn sparse_checkout(&mut self, ret_value: &mut Variant) -> AddinResult {
+        debug!("sparse_checkout()");
+        let result = self.git.sparse_checkout();
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn set_sparse_checkout(
+        &mut self,
+        directories: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("set_sparse_checkout()");
+        let result = self.git.set_sparse_checkout(&directories.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn stash_save_paths(
+        &mut self,
+        message: &mut Variant,
+        paths: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("stash_save_paths()");
+        let result = self.git.stash_save_paths(&message.get_string()?, &paths.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn checkout_dry_run(
+        &mut self,
+        branch_name: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("checkout_dry_run()");
+        let result = self.git.checkout_dry_run(&branch_name.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn diff_names(
+        &mut self,
+        from: &mut Variant,
+        to: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("diff_names()");
+        let result = self.git.diff_names(&from.get_string()?, &to.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn diff(
+        &mut self,
+        from: &mut Variant,
+        to: &mut Variant,
+        context_lines: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("diff()");
+        let result =
+            self.git.diff(&from.get_string()?, &to.get_string()?, context_lines.get_i32()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn diff_staged(&mut self, context_lines: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("diff_staged()");
+        ret_value.set_str1c(self.git.diff_staged(context_lines.get_i32()?))?;
+        Ok(())
+    }
+
+    fn diff_paged(
+        &mut self,
+        from: &mut Variant,
+        to: &mut Variant,
+        context_lines: &mut Variant,
+        cursor: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("diff_paged()");
+        let (chunk, next) = self.git.diff_paged(
+            &from.get_string()?,
+            &to.get_string()?,
+            context_lines.get_i32()?,
+            &cursor.get_string()?,
+        );
+        cursor.set_str1c(next)?;
+        ret_value.set_str1c(chunk)?;
+        Ok(())
+    }
+
+    fn get_branches_paged(&mut self, cursor: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("get_branches_paged()");
+        let (chunk, next) = self.git.branches_paged(&cursor.get_string()?);
+        cursor.set_str1c(next)?;
+        ret_value.set_str1c(chunk)?;
+        Ok(())
+    }
+
+    fn incoming_paged(
+        &mut self,
+        branch: &mut Variant,
+        cursor: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("incoming_paged()");
+        let (chunk, next) = self.git.incoming_paged(&branch.get_string()?, &cursor.get_string()?);
+        cursor.set_str1c(next)?;
+        ret_value.set_str1c(chunk)?;
+        Ok(())
+    }
+
+    fn status_to_file(
+        &mut self,
+        path: &mut Variant,
+        format: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("status_to_file()");
+        let result = self.git.status_to_file(&path.get_string()?, &format.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn diff_to_file(
+        &mut self,
+        path: &mut Variant,
+        format: &mut Variant,
+        from: &mut Variant,
+        to: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("diff_to_file()");
+        let result = self.git.diff_to_file(
+            &path.get_string()?,
+            &format.get_string()?,
+            &from.get_string()?,
+            &to.get_string()?,
+        );
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn get_file_attributes(&mut self, path: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("get_file_attributes()");
+        let result = self.git.file_attributes(&path.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
     fn push(&mut self, ret_value: &mut Variant) -> AddinResult {
         debug!("push()");
         let result = self.git.push();
@@ -70,6 +410,94 @@ impl GitAddin {
         Ok(())
     }
 
+    fn get_effective_config(&mut self, ret_value: &mut Variant) -> AddinResult {
+        debug!("get_effective_config()");
+        let result = self.git.effective_config();
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn get_sync_state(&mut self, ret_value: &mut Variant) -> AddinResult {
+        debug!("get_sync_state()");
+        let result = self.git.sync_state();
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn get_commit_template(&mut self, ret_value: &mut Variant) -> AddinResult {
+        debug!("get_commit_template()");
+        let result = self.git.commit_template();
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn set_push_remote(
+        &mut self,
+        branch: &mut Variant,
+        remote: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("set_push_remote()");
+        let result = self.git.set_push_remote(&branch.get_string()?, &remote.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn add_remote(
+        &mut self,
+        name: &mut Variant,
+        url: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("add_remote()");
+        let result = self.git.add_remote(&name.get_string()?, &url.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn set_push_url(
+        &mut self,
+        remote: &mut Variant,
+        url: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("set_push_url()");
+        let result = self.git.set_push_url(&remote.get_string()?, &url.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn push_to(
+        &mut self,
+        local: &mut Variant,
+        remote_branch: &mut Variant,
+        remote: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("push_to()");
+        let result = self.git.push_to(
+            &local.get_string()?,
+            &remote_branch.get_string()?,
+            &remote.get_string()?,
+        );
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn push_refs(
+        &mut self,
+        remote: &mut Variant,
+        refspecs: &mut Variant,
+        atomic: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("push_refs()");
+        let result =
+            self.git.push_refs(&remote.get_string()?, &refspecs.get_string()?, atomic.get_bool()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
     fn pull(&mut self, branch_name: &mut Variant, ret_value: &mut Variant) -> AddinResult {
         debug!("pull()");
         let result = self.git.pull(&branch_name.get_string()?);
@@ -84,130 +512,1169 @@ impl GitAddin {
         Ok(())
     }
 
-    fn get_login(&mut self, ret_value: &mut Variant) -> AddinResult {
-        ret_value.set_str1c(self.git.config.username.clone())?;
+    fn file_authors(&mut self, path: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("file_authors()");
+        let result = self.git.file_authors(&path.get_string()?);
+        ret_value.set_str1c(result)?;
         Ok(())
     }
 
-    fn set_login(&mut self, login: &Variant) -> AddinResult {
-        self.git.config.username = login.get_string()?;
+    fn file_log(
+        &mut self,
+        path: &mut Variant,
+        max: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("file_log()");
+        let result = self.git.file_log(&path.get_string()?, max.get_i32()?);
+        ret_value.set_str1c(result)?;
         Ok(())
     }
 
-    fn get_password(&mut self, ret_value: &mut Variant) -> AddinResult {
-        let password = match &self.git.config.auth {
-            AuthType::Password(password) => password,
-            AuthType::None => "",
-        };
+    fn blame_line(
+        &mut self,
+        path: &mut Variant,
+        line: &mut Variant,
+        ignore_whitespace: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("blame_line()");
+        let result = self.git.blame_line(
+            &path.get_string()?,
+            line.get_i32()?,
+            ignore_whitespace.get_bool()?,
+        );
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
 
-        ret_value.set_str1c(password)?;
+    fn parents(&mut self, revspec: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("parents()");
+        let result = self.git.parents(&revspec.get_string()?);
+        ret_value.set_str1c(result)?;
         Ok(())
     }
 
-    fn set_password(&mut self, password: &Variant) -> AddinResult {
-        self.git.config.auth = AuthType::Password(password.get_string()?);
+    fn enable_auto_fetch(&mut self, seconds: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("enable_auto_fetch()");
+        let result = self.git.enable_auto_fetch(seconds.get_i32()?);
+        ret_value.set_str1c(result)?;
         Ok(())
     }
 
-    fn get_email(&mut self, ret_value: &mut Variant) -> AddinResult {
-        ret_value.set_str1c(self.git.config.email.as_str())?;
+    fn disable_auto_fetch(&mut self, ret_value: &mut Variant) -> AddinResult {
+        debug!("disable_auto_fetch()");
+        self.git.auto_fetch.stop();
+        ret_value.set_str1c("auto fetch disabled")?;
         Ok(())
     }
 
-    fn set_email(&mut self, email: &Variant) -> AddinResult {
-        self.git.config.email = email.get_string()?;
+    fn commit_message(&mut self, revspec: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("commit_message()");
+        let result = self.git.commit_message(&revspec.get_string()?);
+        ret_value.set_str1c(result)?;
         Ok(())
     }
 
-    fn get_catalog(&mut self, ret_value: &mut Variant) -> AddinResult {
-        ret_value.set_str1c(self.git.config.path.to_str().unwrap_or(""))?;
+    fn clear_locks(&mut self, ret_value: &mut Variant) -> AddinResult {
+        debug!("clear_locks()");
+        let result = self.git.clear_locks();
+        ret_value.set_str1c(result)?;
         Ok(())
     }
 
-    fn set_catalog(&mut self, catalog: &Variant) -> AddinResult {
-        self.git.config.path = catalog.get_string()?.into();
+    fn health_check(&mut self, ret_value: &mut Variant) -> AddinResult {
+        debug!("health_check()");
+        let result = self.git.health_check();
+        ret_value.set_str1c(result)?;
         Ok(())
     }
-}
 
-impl SimpleAddin for GitAddin {
-    fn name() -> &'static [u16] {
-        name!("GitAddin")
+    fn rebuild_index(&mut self, confirm: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("rebuild_index()");
+        let result = self.git.rebuild_index(confirm.get_bool()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
     }
 
-    fn methods() -> &'static [MethodInfo<Self>] {
-        &[
-            MethodInfo {
-                name: name!("CloneRepo"),
-                method: Methods::Method1(Self::clone_repo),
-            },
-            MethodInfo {
-                name: name!("GetBranches"),
-                method: Methods::Method0(Self::get_branches),
-            },
-            MethodInfo {
-                name: name!("Status"),
-                method: Methods::Method0(Self::status),
-            },
-            MethodInfo {
-                name: name!("AddAll"),
-                method: Methods::Method0(Self::add_all),
-            },
-            MethodInfo {
-                name: name!("Commit"),
-                method: Methods::Method1(Self::commit),
-            },
-            MethodInfo {
-                name: name!("Checkout"),
-                method: Methods::Method1(Self::checkout),
-            },
-            MethodInfo {
-                name: name!("Push"),
-                method: Methods::Method0(Self::push),
-            },
-            MethodInfo {
-                name: name!("GetCurrentBranch"),
-                method: Methods::Method0(Self::get_current_branch),
-            },
-            MethodInfo {
-                name: name!("Pull"),
-                method: Methods::Method1(Self::pull),
-            },
-            MethodInfo {
-                name: name!("Merge"),
-                method: Methods::Method0(Self::merge),
-            },
-        ]
+    fn preflight(&mut self, operation: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("preflight()");
+        let result = self.git.preflight(&operation.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
     }
 
-    fn properties() -> &'static [PropInfo<Self>] {
-        &[
-            PropInfo {
-                name: name!("Login"),
-                getter: Some(Self::get_login),
-                setter: Some(Self::set_login),
-            },
-            PropInfo {
-                name: name!("Password"),
-                getter: Some(Self::get_password),
-                setter: Some(Self::set_password),
-            },
-            PropInfo {
-                name: name!("Email"),
-                getter: Some(Self::get_email),
-                setter: Some(Self::set_email),
-            },
-            PropInfo {
-                name: name!("Catalog"),
-                getter: Some(Self::get_catalog),
-                setter: Some(Self::set_catalog),
-            },
-        ]
+    fn lock_file(&mut self, path: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("lock_file()");
+        let result = self.git.lock_file(&path.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
     }
-}
 
-impl Drop for GitAddin {
-    fn drop(&mut self) {
-        log::info!("GitAddin::drop()");
+    fn unlock_file(&mut self, path: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("unlock_file()");
+        let result = self.git.unlock_file(&path.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn get_locks(&mut self, ret_value: &mut Variant) -> AddinResult {
+        debug!("get_locks()");
+        let result = self.git.locks();
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn resolve_rev(&mut self, text: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("resolve_rev()");
+        let result = self.git.resolve_rev(&text.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn fetch_all(&mut self, ret_value: &mut Variant) -> AddinResult {
+        debug!("fetch_all()");
+        let result = self.git.fetch_all();
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn prune(&mut self, remote: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("prune()");
+        let result = self.git.prune(&remote.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn branch_tip(
+        &mut self,
+        name: &mut Variant,
+        branch_type: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("branch_tip()");
+        let result = self.git.branch_tip(&name.get_string()?, &branch_type.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn suggest_branch_name(&mut self, raw: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("suggest_branch_name()");
+        let result = self.git.suggest_branch_name(&raw.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn create_orphan_branch(
+        &mut self,
+        name: &mut Variant,
+        message: &mut Variant,
+        files: &mut Variant,
+        checkout: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("create_orphan_branch()");
+        let result = self.git.create_orphan_branch(
+            &name.get_string()?,
+            &message.get_string()?,
+            &files.get_string()?,
+            checkout.get_bool()?,
+        );
+        ret_value.set_str1c(result)?;
+        Ok(())
     }
-}
\ No newline at end of file
+
+    fn ls_remote(
+        &mut self,
+        remote: &mut Variant,
+        branch: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("ls_remote()");
+        let result = self.git.ls_remote(&remote.get_string()?, &branch.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn rename_current_branch(
+        &mut self,
+        new_name: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("rename_current_branch()");
+        let result = self.git.rename_current_branch(&new_name.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn get_stale_branches(&mut self, ret_value: &mut Variant) -> AddinResult {
+        debug!("get_stale_branches()");
+        let result = self.git.stale_branches();
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn delete_stale_branches(
+        &mut self,
+        dry_run: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("delete_stale_branches()");
+        let result = self.git.delete_stale_branches(dry_run.get_bool()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn verify_commits(&mut self, range: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("verify_commits()");
+        let result = self.git.verify_commits(&range.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn create_profile(
+        &mut self,
+        name: &mut Variant,
+        profile_json: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("create_profile()");
+        let result = self.git.create_profile(&name.get_string()?, &profile_json.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn use_profile(&mut self, name: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("use_profile()");
+        let result = self.git.use_profile(&name.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn list_profiles(&mut self, ret_value: &mut Variant) -> AddinResult {
+        debug!("list_profiles()");
+        ret_value.set_str1c(self.git.profiles())?;
+        Ok(())
+    }
+
+    fn compare_branches(
+        &mut self,
+        base: &mut Variant,
+        head: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("compare_branches()");
+        let result = self.git.compare_branches(&base.get_string()?, &head.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn incoming(&mut self, branch: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("incoming()");
+        let result = self.git.incoming(&branch.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn outgoing(&mut self, branch: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("outgoing()");
+        let result = self.git.outgoing(&branch.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn merge_base(
+        &mut self,
+        a: &mut Variant,
+        b: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("merge_base()");
+        let result = self.git.merge_base(&a.get_string()?, &b.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn continue_operation(&mut self, ret_value: &mut Variant) -> AddinResult {
+        debug!("continue_operation()");
+        let result = self.git.continue_operation();
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn is_ancestor(
+        &mut self,
+        maybe_ancestor: &mut Variant,
+        descendant: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("is_ancestor()");
+        let result = self.git.is_ancestor(&maybe_ancestor.get_string()?, &descendant.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn rev_count(
+        &mut self,
+        from: &mut Variant,
+        to: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("rev_count()");
+        let result = self.git.rev_count(&from.get_string()?, &to.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn has_upstream(&mut self, branch: &mut Variant, ret_value: &mut Variant) -> AddinResult {
+        debug!("has_upstream()");
+        let result = self.git.has_upstream(&branch.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn bisect_start(
+        &mut self,
+        good: &mut Variant,
+        bad: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("bisect_start()");
+        let result = self.git.bisect_start(&good.get_string()?, &bad.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn bisect_mark(
+        &mut self,
+        commit: &mut Variant,
+        good: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("bisect_mark()");
+        let result = self.git.bisect_mark(&commit.get_string()?, good.get_bool()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn bisect_reset(&mut self, ret_value: &mut Variant) -> AddinResult {
+        debug!("bisect_reset()");
+        let result = self.git.bisect_reset();
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn add_note(
+        &mut self,
+        commit: &mut Variant,
+        namespace: &mut Variant,
+        text: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("add_note()");
+        let result =
+            self.git.add_note(&commit.get_string()?, &namespace.get_string()?, &text.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn get_note(
+        &mut self,
+        commit: &mut Variant,
+        namespace: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("get_note()");
+        let result = self.git.note(&commit.get_string()?, &namespace.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn remove_note(
+        &mut self,
+        commit: &mut Variant,
+        namespace: &mut Variant,
+        ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("remove_note()");
+        let result = self.git.remove_note(&commit.get_string()?, &namespace.get_string()?);
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn conflicts(&mut self, ret_value: &mut Variant) -> AddinResult {
+        debug!("conflicts()");
+        let result = self.git.conflicts();
+        ret_value.set_str1c(result)?;
+        Ok(())
+    }
+
+    fn get_metrics(&mut self, ret_value: &mut Variant) -> AddinResult {
+        debug!("get_metrics()");
+        ret_value.set_str1c(self.git.metrics.to_json())?;
+        Ok(())
+    }
+
+    #[allow(clippy::unused_self)]
+    fn get_methods_info(&mut self, ret_value: &mut Variant) -> AddinResult {
+        debug!("get_methods_info()");
+        ret_value.set_str1c(descriptors::methods_json(Self::METHODS))?;
+        Ok(())
+    }
+
+    #[allow(clippy::unused_self)]
+    fn get_properties_info(&mut self, ret_value: &mut Variant) -> AddinResult {
+        debug!("get_properties_info()");
+        ret_value.set_str1c(descriptors::properties_json(Self::PROPERTIES))?;
+        Ok(())
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn reset_metrics(&mut self, _ret_value: &mut Variant) -> AddinResult {
+        debug!("reset_metrics()");
+        self.git.metrics.reset();
+        Ok(())
+    }
+
+    fn credential_request(&mut self, ret_value: &mut Variant) -> AddinResult {
+        debug!("credential_request()");
+        ret_value.set_str1c(self.git.credentials.pending())?;
+        Ok(())
+    }
+
+    fn provide_credential(
+        &mut self,
+        secret: &mut Variant,
+        _ret_value: &mut Variant,
+    ) -> AddinResult {
+        debug!("provide_credential()");
+        self.git.credentials.provide(secret.get_string()?);
+        Ok(())
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn cancel_credential(&mut self, _ret_value: &mut Variant) -> AddinResult {
+        debug!("cancel_credential()");
+        self.git.credentials.cancel();
+        Ok(())
+    }
+
+    fn get_login(&mut self, ret_value: &mut Variant) -> AddinResult {
+        ret_value.set_str1c(self.git.config.username.clone())?;
+        Ok(())
+    }
+
+    fn set_login(&mut self, login: &Variant) -> AddinResult {
+        self.git.config.username = login.get_string()?;
+        Ok(())
+    }
+
+    fn get_password(&mut self, ret_value: &mut Variant) -> AddinResult {
+        let password = match &self.git.config.auth {
+            AuthType::Password(password) => password,
+            AuthType::SshKey { .. } | AuthType::None => "",
+        };
+
+        ret_value.set_str1c(password)?;
+        Ok(())
+    }
+
+    fn set_password(&mut self, password: &Variant) -> AddinResult {
+        self.git.config.auth = AuthType::Password(password.get_string()?);
+        Ok(())
+    }
+
+    fn get_ssh_key(&mut self, ret_value: &mut Variant) -> AddinResult {
+        let private_key = match &self.git.config.auth {
+            AuthType::SshKey { private_key, .. } => private_key.to_str().unwrap_or(""),
+            AuthType::Password(_) | AuthType::None => "",
+        };
+
+        ret_value.set_str1c(private_key)?;
+        Ok(())
+    }
+
+    fn set_ssh_key(&mut self, private_key: &Variant) -> AddinResult {
+        let private_key = private_key.get_string()?;
+        self.git.config.auth = if private_key.is_empty() {
+            AuthType::None
+        } else {
+            AuthType::SshKey {
+                private_key: private_key.into(),
+                passphrase: None,
+            }
+        };
+        Ok(())
+    }
+
+    fn get_email(&mut self, ret_value: &mut Variant) -> AddinResult {
+        ret_value.set_str1c(self.git.config.email.as_str())?;
+        Ok(())
+    }
+
+    fn set_email(&mut self, email: &Variant) -> AddinResult {
+        self.git.config.email = email.get_string()?;
+        Ok(())
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn get_max_chunk_kb(&mut self, ret_value: &mut Variant) -> AddinResult {
+        ret_value.set_i32(i32::try_from(self.git.chunks.max_bytes / 1024).unwrap_or(i32::MAX));
+        Ok(())
+    }
+
+    fn set_max_chunk_kb(&mut self, kb: &Variant) -> AddinResult {
+        self.git.chunks.max_bytes = usize::try_from(kb.get_i32()?.max(1))? * 1024;
+        Ok(())
+    }
+
+    fn get_commit_encoding(&mut self, ret_value: &mut Variant) -> AddinResult {
+        ret_value.set_str1c(self.git.config.commit_encoding.as_deref().unwrap_or(""))?;
+        Ok(())
+    }
+
+    fn set_commit_encoding(&mut self, encoding: &Variant) -> AddinResult {
+        let encoding = encoding.get_string()?;
+        self.git.config.commit_encoding = (!encoding.is_empty()).then_some(encoding);
+        Ok(())
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn get_auto_fetch_enabled(&mut self, ret_value: &mut Variant) -> AddinResult {
+        ret_value.set_bool(self.git.auto_fetch.is_running());
+        Ok(())
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn get_commits_ahead(&mut self, ret_value: &mut Variant) -> AddinResult {
+        let (ahead, _) = self.git.auto_fetch.counts();
+        ret_value.set_i32(i32::try_from(ahead).unwrap_or(i32::MAX));
+        Ok(())
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn get_commits_behind(&mut self, ret_value: &mut Variant) -> AddinResult {
+        let (_, behind) = self.git.auto_fetch.counts();
+        ret_value.set_i32(i32::try_from(behind).unwrap_or(i32::MAX));
+        Ok(())
+    }
+
+    fn get_last_auto_fetch_error(&mut self, ret_value: &mut Variant) -> AddinResult {
+        ret_value.set_str1c(self.git.auto_fetch.last_error())?;
+        Ok(())
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn get_detect_renames(&mut self, ret_value: &mut Variant) -> AddinResult {
+        ret_value.set_bool(self.git.config.detect_renames);
+        Ok(())
+    }
+
+    fn set_detect_renames(&mut self, detect: &Variant) -> AddinResult {
+        self.git.config.detect_renames = detect.get_bool()?;
+        Ok(())
+    }
+
+    fn get_status_perf_mode(&mut self, ret_value: &mut Variant) -> AddinResult {
+        ret_value.set_str1c(self.git.config.status_perf_mode.to_string())?;
+        Ok(())
+    }
+
+    fn set_status_perf_mode(&mut self, mode: &Variant) -> AddinResult {
+        self.git.config.status_perf_mode = mode.get_string()?.parse()?;
+        Ok(())
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn get_transliterate_branch_names(&mut self, ret_value: &mut Variant) -> AddinResult {
+        ret_value.set_bool(self.git.config.transliterate_ref_names);
+        Ok(())
+    }
+
+    fn set_transliterate_branch_names(&mut self, transliterate: &Variant) -> AddinResult {
+        self.git.config.transliterate_ref_names = transliterate.get_bool()?;
+        Ok(())
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn get_pack_threads(&mut self, ret_value: &mut Variant) -> AddinResult {
+        ret_value.set_i32(i32::try_from(self.git.config.pack_threads).unwrap_or(i32::MAX));
+        Ok(())
+    }
+
+    fn set_pack_threads(&mut self, threads: &Variant) -> AddinResult {
+        self.git.config.pack_threads = u32::try_from(threads.get_i32()?.max(0))?;
+        Ok(())
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn get_checkout_retries(&mut self, ret_value: &mut Variant) -> AddinResult {
+        ret_value.set_i32(i32::try_from(self.git.config.checkout_retries).unwrap_or(i32::MAX));
+        Ok(())
+    }
+
+    fn set_checkout_retries(&mut self, retries: &Variant) -> AddinResult {
+        self.git.config.checkout_retries = u32::try_from(retries.get_i32()?.max(0))?;
+        Ok(())
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn get_checkout_retry_delay_ms(&mut self, ret_value: &mut Variant) -> AddinResult {
+        let ms = self.git.config.checkout_retry_delay.as_millis();
+        ret_value.set_i32(i32::try_from(ms).unwrap_or(i32::MAX));
+        Ok(())
+    }
+
+    fn set_checkout_retry_delay_ms(&mut self, ms: &Variant) -> AddinResult {
+        let ms = u64::try_from(ms.get_i32()?.max(0))?;
+        self.git.config.checkout_retry_delay = Duration::from_millis(ms);
+        Ok(())
+    }
+
+    /// 0 keeps the repository's own threshold.
+    #[allow(clippy::unnecessary_wraps)]
+    fn get_big_file_threshold_kb(&mut self, ret_value: &mut Variant) -> AddinResult {
+        let kb = self.git.config.big_file_threshold.map_or(0, |bytes| bytes / 1024);
+        ret_value.set_i32(i32::try_from(kb).unwrap_or(i32::MAX));
+        Ok(())
+    }
+
+    fn set_big_file_threshold_kb(&mut self, kb: &Variant) -> AddinResult {
+        let kb = u64::try_from(kb.get_i32()?.max(0))?;
+        self.git.config.big_file_threshold = (kb > 0).then_some(kb * 1024);
+        Ok(())
+    }
+
+    fn get_extra_http_headers(&mut self, ret_value: &mut Variant) -> AddinResult {
+        ret_value.set_str1c(self.git.config.custom_headers.join("\n"))?;
+        Ok(())
+    }
+
+    fn set_extra_http_headers(&mut self, headers: &Variant) -> AddinResult {
+        let headers = git_core::http::parse_headers(&headers.get_string()?)?;
+        logging::hide_headers(&headers);
+        self.git.config.custom_headers = headers;
+        Ok(())
+    }
+
+    fn get_extra_ignore_patterns(&mut self, ret_value: &mut Variant) -> AddinResult {
+        ret_value.set_str1c(self.git.config.extra_ignore_patterns.join("\n"))?;
+        Ok(())
+    }
+
+    fn set_extra_ignore_patterns(&mut self, patterns: &Variant) -> AddinResult {
+        self.git.config.extra_ignore_patterns = patterns
+            .get_string()?
+            .lines()
+            .map(str::trim)
+            .filter(|pattern| !pattern.is_empty())
+            .map(str::to_string)
+            .collect();
+        Ok(())
+    }
+
+    fn get_default_branch(&mut self, ret_value: &mut Variant) -> AddinResult {
+        ret_value.set_str1c(self.git.config.default_branch.as_deref().unwrap_or(""))?;
+        Ok(())
+    }
+
+    fn set_default_branch(&mut self, branch: &Variant) -> AddinResult {
+        let branch = branch.get_string()?;
+        self.git.config.default_branch = (!branch.is_empty()).then_some(branch);
+        Ok(())
+    }
+
+    fn get_ssh_user(&mut self, ret_value: &mut Variant) -> AddinResult {
+        ret_value.set_str1c(self.git.config.ssh_user.as_deref().unwrap_or(""))?;
+        Ok(())
+    }
+
+    fn set_ssh_user(&mut self, user: &Variant) -> AddinResult {
+        let user = user.get_string()?;
+        self.git.config.ssh_user = (!user.is_empty()).then_some(user);
+        Ok(())
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn get_block_locked_commits(&mut self, ret_value: &mut Variant) -> AddinResult {
+        ret_value.set_bool(self.git.config.block_locked_commits);
+        Ok(())
+    }
+
+    fn set_block_locked_commits(&mut self, block: &Variant) -> AddinResult {
+        self.git.config.block_locked_commits = block.get_bool()?;
+        Ok(())
+    }
+
+    fn get_allowed_signers(&mut self, ret_value: &mut Variant) -> AddinResult {
+        let policy = self.git.config.verify_policy.as_ref();
+        let path = policy.and_then(|policy| policy.allowed_signers.as_deref());
+        ret_value.set_str1c(path.and_then(Path::to_str).unwrap_or(""))?;
+        Ok(())
+    }
+
+    fn set_allowed_signers(&mut self, path: &Variant) -> AddinResult {
+        let path = path.get_string()?;
+        self.update_verify_policy(|policy| {
+            policy.allowed_signers = (!path.is_empty()).then(|| path.into());
+        });
+        Ok(())
+    }
+
+    fn get_gpg_home(&mut self, ret_value: &mut Variant) -> AddinResult {
+        let policy = self.git.config.verify_policy.as_ref();
+        let path = policy.and_then(|policy| policy.gpg_home.as_deref());
+        ret_value.set_str1c(path.and_then(Path::to_str).unwrap_or(""))?;
+        Ok(())
+    }
+
+    fn set_gpg_home(&mut self, path: &Variant) -> AddinResult {
+        let path = path.get_string()?;
+        self.update_verify_policy(|policy| {
+            policy.gpg_home = (!path.is_empty()).then(|| path.into());
+        });
+        Ok(())
+    }
+
+    /// Pulls are only verified while an allowed signers file or a GPG home is set.
+    fn update_verify_policy(&mut self, update: impl FnOnce(&mut VerifyPolicy)) {
+        let mut policy = self.git.config.verify_policy.take().unwrap_or_default();
+        update(&mut policy);
+        if policy.allowed_signers.is_some() || policy.gpg_home.is_some() {
+            self.git.config.verify_policy = Some(policy);
+        }
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn get_sync_notes(&mut self, ret_value: &mut Variant) -> AddinResult {
+        ret_value.set_bool(self.git.config.sync_notes);
+        Ok(())
+    }
+
+    fn set_sync_notes(&mut self, sync: &Variant) -> AddinResult {
+        self.git.config.sync_notes = sync.get_bool()?;
+        Ok(())
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn get_read_only(&mut self, ret_value: &mut Variant) -> AddinResult {
+        ret_value.set_bool(self.git.config.read_only);
+        Ok(())
+    }
+
+    fn set_read_only(&mut self, read_only: &Variant) -> AddinResult {
+        self.git.config.read_only = read_only.get_bool()?;
+        Ok(())
+    }
+
+    fn get_origin_url(&mut self, ret_value: &mut Variant) -> AddinResult {
+        ret_value.set_str1c(self.git.origin_url())?;
+        Ok(())
+    }
+
+    fn get_repository_name(&mut self, ret_value: &mut Variant) -> AddinResult {
+        ret_value.set_str1c(self.git.repository_name())?;
+        Ok(())
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    fn get_is_repository_open(&mut self, ret_value: &mut Variant) -> AddinResult {
+        ret_value.set_bool(self.git.is_repository_open());
+        Ok(())
+    }
+
+    fn get_catalog(&mut self, ret_value: &mut Variant) -> AddinResult {
+        ret_value.set_str1c(self.git.config.path.to_str().unwrap_or(""))?;
+        Ok(())
+    }
+
+    fn set_catalog(&mut self, catalog: &Variant) -> AddinResult {
+        self.git.config.path = catalog.get_string()?.into();
+        Ok(())
+    }
+}
+
+impl GitAddin {
+    descriptors::methods! {
+        "CloneRepo" / "КлонироватьРепозиторий" => clone_repo(url: string)
+            -> "confirmation or error text", since "0.1.0";
+        "CloneMirror" / "КлонироватьЗеркало" => clone_mirror(url: string)
+            -> "confirmation or error text", since "0.1.0";
+        "InitFromTemplate" / "СоздатьИзШаблона" => init_from_template(template_name: string)
+            -> "confirmation or error text", since "0.1.0";
+        "GetBranches" / "ПолучитьВетки" => get_branches()
+            -> "a branch per line with its remotes, * marking the current one, or error text",
+            since "0.1.0";
+        "GetBranchesPaged" / "ПолучитьВеткиПорциями" => get_branches_paged(cursor: string)
+            -> "chunk of GetBranches and the cursor of the next one", since "0.1.0";
+        "BranchesDetailed" / "ВеткиСПоследнимКоммитом" => branches_detailed()
+            -> "JSON array of branches with their last commits, newest first", since "0.1.0";
+        "Status" / "Статус" => status()
+            -> "status text", since "0.1.0";
+        "GetStatusFingerprint" / "ПолучитьОтпечатокСтатуса" => get_status_fingerprint()
+            -> "value that changes whenever the status does", since "0.1.0";
+        "AddAll" / "ДобавитьВсе" => add_all()
+            -> "number of files staged, then their paths one per line", since "0.1.0";
+        "StageLines" / "ДобавитьСтроки" => stage_lines(path: string, ranges: string)
+            -> "confirmation or error text", since "0.1.0";
+        "Commit" / "Зафиксировать" => commit(message: string)
+            -> "commit id and lock warnings, or error text", since "0.1.0";
+        "CommitCoauthors" / "ЗафиксироватьССоавторами"
+            => commit_coauthors(message: string, coauthors: string)
+            -> "commit id and lock warnings, or error text", since "0.1.0";
+        "CommitSignoff" / "ЗафиксироватьСПодписью" => commit_signoff(message: string)
+            -> "commit id and lock warnings, or error text", since "0.1.0";
+        "AddAndAmend" / "ДобавитьИИсправитьКоммит" => add_and_amend(paths: string)
+            -> "amended commit id and lock warnings, or error text", since "0.1.0";
+        "WriteIdentity" / "ЗаписатьАвтора" => write_identity()
+            -> "confirmation or error text", since "0.1.0";
+        "Checkout" / "ПереключитьВетку" => checkout(branch_name: string)
+            -> "confirmation or error text", since "0.1.0";
+        "CheckoutLocal" / "ПереключитьЛокальнуюВетку" => checkout_local(branch_name: string)
+            -> "confirmation or error text", since "0.1.0";
+        "CheckoutTag" / "ПереключитьНаТег" => checkout_tag(tag: string)
+            -> "confirmation or error text", since "0.1.0";
+        "CheckoutPaths" / "ВзятьПути" => checkout_paths(revspec: string, paths: string)
+            -> "number of files checked out, or error text", since "0.1.0";
+        "CheckoutFileFrom" / "ВзятьФайлИз" => checkout_file_from(revspec: string, path: string)
+            -> "number of files checked out, or error text", since "0.1.0";
+        "SparseCheckout" / "ЧастичнаяВыгрузка" => sparse_checkout()
+            -> "directories one per line, or error text", since "0.1.0";
+        "SetSparseCheckout" / "УстановитьЧастичнуюВыгрузку" => set_sparse_checkout(directories: string)
+            -> "confirmation or error text", since "0.1.0";
+        "StashSavePaths" / "ОтложитьПути" => stash_save_paths(message: string, paths: string)
+            -> "stash commit id or error text", since "0.1.0";
+        "CreateBundle" / "СоздатьПакет" => create_bundle(path: string, range: string)
+            -> "number of objects written, or error text", since "0.1.0";
+        "ImportBundle" / "ЗагрузитьПакет" => import_bundle(path: string)
+            -> "refs imported one per line, or error text", since "0.1.0";
+        "CheckoutDryRun" / "ПроверитьПереключение" => checkout_dry_run(branch_name: string)
+            -> "paths a checkout would overwrite, or error text", since "0.1.0";
+        "DiffNames" / "ИменаИзмененныхФайлов" => diff_names(from: string, to: string)
+            -> "changed paths, or error text", since "0.1.0";
+        "Diff" / "Различия" => diff(from: string, to: string, context_lines: number)
+            -> "patch text", since "0.1.0";
+        "DiffStaged" / "РазличияИндекса" => diff_staged(context_lines: number)
+            -> "patch text of the staged changes", since "0.1.0";
+        "DiffPaged" / "РазличияПорциями"
+            => diff_paged(from: string, to: string, context_lines: number, cursor: string)
+            -> "chunk of Diff and the cursor of the next one", since "0.1.0";
+        "StatusToFile" / "СтатусВФайл" => status_to_file(path: string, format: string)
+            -> "number of bytes written, or error text", since "0.1.0";
+        "DiffToFile" / "РазличияВФайл"
+            => diff_to_file(path: string, format: string, from: string, to: string)
+            -> "number of bytes written, or error text", since "0.1.0";
+        "GetFileAttributes" / "ПолучитьАтрибутыФайла" => get_file_attributes(path: string)
+            -> "JSON object of gitattributes, or error text", since "0.1.0";
+        "Push" / "Отправить" => push()
+            -> "confirmation or error text", since "0.1.0";
+        "PushTo" / "ОтправитьВ" => push_to(local: string, remote_branch: string, remote: string)
+            -> "confirmation or error text", since "0.1.0";
+        "PushRefs" / "ОтправитьСсылки"
+            => push_refs(remote: string, refspecs: string, atomic: boolean)
+            -> "refs pushed or error text", since "0.1.0";
+        "AddRemote" / "ДобавитьУдаленныйРепозиторий" => add_remote(name: string, url: string)
+            -> "confirmation or error text", since "0.1.0";
+        "SetPushUrl" / "УстановитьАдресОтправки" => set_push_url(remote: string, url: string)
+            -> "confirmation or error text", since "0.1.0";
+        "SetPushRemote" / "УстановитьРепозиторийОтправки"
+            => set_push_remote(branch: string, remote: string)
+            -> "confirmation or error text", since "0.1.0";
+        "GetEffectiveConfig" / "ПолучитьДействующиеНастройки" => get_effective_config()
+            -> "JSON git settings followed, or error text", since "0.1.0";
+        "GetCommitTemplate" / "ПолучитьШаблонКоммита" => get_commit_template()
+            -> "commit message template, or error text", since "0.1.0";
+        "GetSyncState" / "ПолучитьСостояниеСинхронизации" => get_sync_state()
+            -> "JSON last fetch, pull and push times per remote, or error text", since "0.1.0";
+        "GetCurrentBranch" / "ПолучитьТекущуюВетку" => get_current_branch()
+            -> "branch name or error text", since "0.1.0";
+        "HeadRef" / "ТекущаяСсылка" => head_ref()
+            -> "JSON {full_name, oid, short_id, detached}", since "0.1.0";
+        "Pull" / "Получить" => pull(branch_name: string)
+            -> "merge result or error text", since "0.1.0";
+        "Merge" / "Слить" => merge()
+            -> "confirmation or error text", since "0.1.0";
+        "FileAuthors" / "АвторыФайла" => file_authors(path: string)
+            -> "authors of the file, or error text", since "0.1.0";
+        "FileLog" / "ИсторияФайла" => file_log(path: string, max: number)
+            -> "JSON array of commits that changed the file", since "0.1.0";
+        "BlameLine" / "АвторСтроки"
+            => blame_line(path: string, line: number, ignore_whitespace: boolean)
+            -> "JSON commit that last changed the line", since "0.1.0";
+        "Parents" / "Родители" => parents(revspec: string)
+            -> "parent commit ids, or error text", since "0.1.0";
+        "CommitMessage" / "СообщениеКоммита" => commit_message(revspec: string)
+            -> "full commit message, or error text", since "0.1.0";
+        "EnableAutoFetch" / "ВключитьАвтоЗагрузку" => enable_auto_fetch(seconds: number)
+            -> "interval used, or error text", since "0.1.0";
+        "DisableAutoFetch" / "ОтключитьАвтоЗагрузку" => disable_auto_fetch()
+            -> "confirmation", since "0.1.0";
+        "HealthCheck" / "ПроверитьРепозиторий" => health_check()
+            -> "JSON array of problems found", since "0.1.0";
+        "RebuildIndex" / "ПерестроитьИндекс" => rebuild_index(confirm: boolean)
+            -> "number of files in the index, or error text", since "0.1.0";
+        "ClearLocks" / "УдалитьФайлыБлокировок" => clear_locks()
+            -> "lock files deleted, or error text", since "0.1.0";
+        "Preflight" / "ПредварительнаяПроверка" => preflight(operation: string)
+            -> "JSON array of {blocking, code, message}", since "0.1.0";
+        "LockFile" / "ЗаблокироватьФайл" => lock_file(path: string)
+            -> "confirmation or error text", since "0.1.0";
+        "UnlockFile" / "РазблокироватьФайл" => unlock_file(path: string)
+            -> "confirmation or error text", since "0.1.0";
+        "GetLocks" / "ПолучитьБлокировки" => get_locks()
+            -> "JSON array of locks, or error text", since "0.1.0";
+        "FetchAll" / "ПолучитьИзменения" => fetch_all()
+            -> "a line per remote, ok with counts or failed with the reason", since "0.1.0";
+        "Prune" / "ОчиститьУдаленныеВетки" => prune(remote: string)
+            -> "remote-tracking refs pruned, or error text", since "0.1.0";
+        "BranchTip" / "ВершинаВетки" => branch_tip(name: string, branch_type: string)
+            -> "commit id or error text", since "0.1.0";
+        "CreateOrphanBranch" / "СоздатьНезависимуюВетку"
+            => create_orphan_branch(name: string, message: string, files: string, checkout: boolean)
+            -> "commit id or error text", since "0.1.0";
+        "RenameCurrentBranch" / "ПереименоватьТекущуюВетку"
+            => rename_current_branch(new_name: string)
+            -> "confirmation or error text", since "0.1.0";
+        "SuggestBranchName" / "ПредложитьИмяВетки" => suggest_branch_name(raw: string)
+            -> "valid branch name", since "0.1.0";
+        "GetStaleBranches" / "ПолучитьУстаревшиеВетки" => get_stale_branches()
+            -> "branch names one per line, or error text", since "0.1.0";
+        "DeleteStaleBranches" / "УдалитьУстаревшиеВетки" => delete_stale_branches(dry_run: boolean)
+            -> "branches deleted, or error text", since "0.1.0";
+        "ResolveRev" / "РазобратьРевизию" => resolve_rev(text: string)
+            -> "JSON of the object the revision names, or error text", since "0.1.0";
+        "CompareBranches" / "СравнитьВетки" => compare_branches(base: string, head: string)
+            -> "JSON {ahead, behind, merge_base, files}", since "0.1.0";
+        "IsAncestor" / "ЯвляетсяПредком" => is_ancestor(maybe_ancestor: string, descendant: string)
+            -> "\"true\", \"false\" or error text", since "0.1.0";
+        "RevCount" / "КоличествоКоммитов" => rev_count(from: string, to: string)
+            -> "number of commits, or error text", since "0.1.0";
+        "HasUpstream" / "ЕстьВышестоящаяВетка" => has_upstream(branch: string)
+            -> "\"true\", \"false\" or error text", since "0.1.0";
+        "BisectStart" / "НачатьДвоичныйПоиск" => bisect_start(good: string, bad: string)
+            -> "JSON {found, commit, short_id, remaining}", since "0.1.0";
+        "BisectMark" / "ОтметитьВДвоичномПоиске" => bisect_mark(commit: string, good: boolean)
+            -> "JSON {found, commit, short_id, remaining}", since "0.1.0";
+        "BisectReset" / "СброситьДвоичныйПоиск" => bisect_reset()
+            -> "confirmation or error text", since "0.1.0";
+        "AddNote" / "ДобавитьЗаметку" => add_note(commit: string, namespace: string, text: string)
+            -> "note id or error text", since "0.1.0";
+        "GetNote" / "ПолучитьЗаметку" => get_note(commit: string, namespace: string)
+            -> "note text, empty when none", since "0.1.0";
+        "RemoveNote" / "УдалитьЗаметку" => remove_note(commit: string, namespace: string)
+            -> "confirmation or error text", since "0.1.0";
+        "Conflicts" / "Конфликты" => conflicts()
+            -> "JSON array of {path, base, ours, theirs}", since "0.1.0";
+        "Incoming" / "Входящие" => incoming(branch: string)
+            -> "JSON array of commits", since "0.1.0";
+        "IncomingPaged" / "ВходящиеПорциями" => incoming_paged(branch: string, cursor: string)
+            -> "chunk of Incoming and the cursor of the next one", since "0.1.0";
+        "Outgoing" / "Исходящие" => outgoing(branch: string)
+            -> "JSON array of commits", since "0.1.0";
+        "LsRemote" / "КоммитВеткиНаСервере" => ls_remote(remote: string, branch: string)
+            -> "commit id or error text", since "0.1.0";
+        "MergeBase" / "ОбщийПредок" => merge_base(a: string, b: string)
+            -> "commit id or error text", since "0.1.0";
+        "Continue" / "Продолжить" => continue_operation()
+            -> "commit id or error text", since "0.1.0";
+        "VerifyCommits" / "ПроверитьПодписи" => verify_commits(range: string)
+            -> "JSON array of {commit, summary, valid, status}", since "0.1.0";
+        "CreateProfile" / "СоздатьПрофиль" => create_profile(name: string, profile_json: string)
+            -> "confirmation or error text", since "0.1.0";
+        "BeginCommit" / "НачатьФиксацию" => begin_commit(message: string)
+            -> "JSON {token, files}", since "0.1.0";
+        "ConfirmCommit" / "ПодтвердитьФиксацию" => confirm_commit(token: string)
+            -> "commit id or error text", since "0.1.0";
+        "AbortCommit" / "ОтменитьФиксацию" => abort_commit(token: string)
+            -> "confirmation or error text", since "0.1.0";
+        "DiscardChanges" / "ОтменитьИзменения" => discard_changes(paths: string)
+            -> "confirmation or error text", since "0.1.0";
+        "Relocate" / "Переместить" => relocate(new_path: string)
+            -> "confirmation or error text", since "0.1.0";
+        "UseProfile" / "ИспользоватьПрофиль" => use_profile(name: string)
+            -> "confirmation or error text", since "0.1.0";
+        "ListProfiles" / "СписокПрофилей" => list_profiles()
+            -> "JSON array of {name, active, username, email}", since "0.1.0";
+        "GetMetrics" / "ПолучитьМетрики" => get_metrics()
+            -> "JSON timings of the methods called", since "0.1.0";
+        "ResetMetrics" / "СброситьМетрики" => reset_metrics()
+            -> "nothing", since "0.1.0";
+        "CredentialRequest" / "ЗапросУчетныхДанных" => credential_request()
+            -> "\"url\nkind\" of the pending request, empty when none", since "0.1.0";
+        "ProvideCredential" / "ПередатьУчетныеДанные" => provide_credential(secret: string)
+            -> "nothing", since "0.1.0";
+        "CancelCredential" / "ОтменитьЗапросУчетныхДанных" => cancel_credential()
+            -> "nothing", since "0.1.0";
+        "GetMethodsInfo" / "ПолучитьОписаниеМетодов" => get_methods_info()
+            -> "JSON array of {name, synonym, params, returns, since}", since "0.1.0";
+        "GetPropertiesInfo" / "ПолучитьОписаниеСвойств" => get_properties_info()
+            -> "JSON array of {name, synonym, type, writable, since}", since "0.1.0";
+    }
+
+    descriptors::properties! {
+        "Login" / "Логин": string => get_login / set_login, since "0.1.0";
+        "Password" / "Пароль": string => get_password / set_password, since "0.1.0";
+        "SshKey" / "КлючSsh": string => get_ssh_key / set_ssh_key, since "0.1.0";
+        "Email" / "ЭлектроннаяПочта": string => get_email / set_email, since "0.1.0";
+        "MaxChunkKb" / "МаксимальнаяПорцияКб": number
+            => get_max_chunk_kb / set_max_chunk_kb, since "0.1.0";
+        "CommitEncoding" / "КодировкаКоммитов": string
+            => get_commit_encoding / set_commit_encoding, since "0.1.0";
+        "DetectRenames" / "ОпределятьПереименования": boolean
+            => get_detect_renames / set_detect_renames, since "0.1.0";
+        "StatusPerfMode" / "РежимСтатуса": string
+            => get_status_perf_mode / set_status_perf_mode, since "0.1.0";
+        "TransliterateBranchNames" / "ТранслитерироватьИменаВеток": boolean
+            => get_transliterate_branch_names / set_transliterate_branch_names, since "0.1.0";
+        "PackThreads" / "ПотокиУпаковки": number
+            => get_pack_threads / set_pack_threads, since "0.1.0";
+        "BigFileThresholdKb" / "ПорогБольшихФайловКб": number
+            => get_big_file_threshold_kb / set_big_file_threshold_kb, since "0.1.0";
+        "CheckoutRetries" / "ПовторыПриБлокировкеФайла": number
+            => get_checkout_retries / set_checkout_retries, since "0.1.0";
+        "CheckoutRetryDelayMs" / "ПаузаПовтораМс": number
+            => get_checkout_retry_delay_ms / set_checkout_retry_delay_ms, since "0.1.0";
+        "ExtraHttpHeaders" / "ДополнительныеЗаголовкиHttp": string
+            => get_extra_http_headers / set_extra_http_headers, since "0.1.0";
+        "ExtraIgnorePatterns" / "ДополнительныеШаблоныИгнорирования": string
+            => get_extra_ignore_patterns / set_extra_ignore_patterns, since "0.1.0";
+        "DefaultBranch" / "ВеткаПоУмолчанию": string
+            => get_default_branch / set_default_branch, since "0.1.0";
+        "SshUser" / "ПользовательSsh": string => get_ssh_user / set_ssh_user, since "0.1.0";
+        "BlockLockedCommits" / "ЗапрещатьФиксациюЗаблокированных": boolean
+            => get_block_locked_commits / set_block_locked_commits, since "0.1.0";
+        "AllowedSigners" / "ДоверенныеПодписи": string
+            => get_allowed_signers / set_allowed_signers, since "0.1.0";
+        "GpgHome" / "КаталогGpg": string => get_gpg_home / set_gpg_home, since "0.1.0";
+        "SyncNotes" / "СинхронизироватьЗаметки": boolean
+            => get_sync_notes / set_sync_notes, since "0.1.0";
+        "ReadOnly" / "ТолькоЧтение": boolean => get_read_only / set_read_only, since "0.1.0";
+        "Catalog" / "Каталог": string => get_catalog / set_catalog, since "0.1.0";
+        "OriginUrl" / "АдресOrigin": string => get_origin_url, since "0.1.0";
+        "RepositoryName" / "ИмяРепозитория": string => get_repository_name, since "0.1.0";
+        "IsRepositoryOpen" / "РепозиторийОткрыт": boolean => get_is_repository_open, since "0.1.0";
+        "AutoFetchEnabled" / "АвтоЗагрузкаВключена": boolean
+            => get_auto_fetch_enabled, since "0.1.0";
+        "CommitsAhead" / "КоммитовВпереди": number => get_commits_ahead, since "0.1.0";
+        "CommitsBehind" / "КоммитовПозади": number => get_commits_behind, since "0.1.0";
+        "LastAutoFetchError" / "ПоследняяОшибкаАвтоЗагрузки": string
+            => get_last_auto_fetch_error, since "0.1.0";
+    }
+}
+
+impl SimpleAddin for GitAddin {
+    fn name() -> &'static [u16] {
+        name!("GitAddin")
+    }
+
+    fn init(&mut self, interface: &'static Connection) -> bool {
+        self.git.events.connect(interface);
+        true
+    }
+
+    fn methods() -> &'static [MethodInfo<Self>] {
+        Self::method_table()
+    }
+
+    fn properties() -> &'static [PropInfo<Self>] {
+        Self::property_table()
+    }
+}
+
+impl Drop for GitAddin {
+    fn drop(&mut self) {
+        log::info!("GitAddin::drop()");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use addin1c::{Methods, SimpleAddin};
+    use git_core::json;
+
+    use super::GitAddin;
+    use crate::descriptors;
+
+    fn name(utf16: &[u16]) -> String {
+        String::from_utf16(utf16.strip_suffix(&[0]).unwrap_or(utf16)).unwrap()
+    }
+
+    /// The entries of the metadata array `metadata` as their names and `detail`, sorted.
+    fn described<T: Ord>(metadata: &str, detail: impl Fn(&json::Value) -> T) -> Vec<(String, T)> {
+        let Ok(json::Value::Array(entries)) = json::parse(metadata) else {
+            panic!("metadata isn't a JSON array: {metadata}");
+        };
+        let mut described = entries
+            .iter()
+            .map(|entry| {
+                let name = entry.get("name").and_then(json::Value::as_str).unwrap();
+                (name.to_string(), detail(entry))
+            })
+            .collect::<Vec<_>>();
+        described.sort();
+        described
+    }
+
+    /// Asserts `described` and `dispatched` list the same names with the same details, and
+    /// each name once.
+    fn assert_same<T: Ord + std::fmt::Debug>(
+        described: &[(String, T)],
+        mut dispatched: Vec<(String, T)>,
+    ) {
+        dispatched.sort();
+        for pair in dispatched.windows(2) {
+            assert_ne!(pair[0].0, pair[1].0, "registered twice");
+        }
+        for entry in &dispatched {
+            assert!(described.contains(entry), "{entry:?} is missing from the metadata");
+        }
+        for entry in described {
+            assert!(dispatched.contains(entry), "{entry:?} is described but not dispatched");
+        }
+    }
+
+    #[test]
+    fn methods_match_their_metadata() {
+        let described = described(&descriptors::methods_json(GitAddin::METHODS), |method| {
+            match method.get("params") {
+                Some(json::Value::Array(params)) => params.len(),
+                params => panic!("params are {params:?}"),
+            }
+        });
+        let dispatched = GitAddin::methods()
+            .iter()
+            .map(|method| {
+                let arity = match method.method {
+                    Methods::Method0(_) => 0,
+                    Methods::Method1(_) => 1,
+                    Methods::Method2(_) => 2,
+                    Methods::Method3(_) => 3,
+                    Methods::Method4(_) => 4,
+                };
+                (name(method.name), arity)
+            })
+            .collect();
+
+        assert_same(&described, dispatched);
+    }
+
+    #[test]
+    fn properties_match_their_metadata() {
+        let described = described(
+            &descriptors::properties_json(GitAddin::PROPERTIES),
+            |property| match property.get("writable") {
+                Some(json::Value::Bool(writable)) => *writable,
+                writable => panic!("writable is {writable:?}"),
+            },
+        );
+        let dispatched = GitAddin::properties()
+            .iter()
+            .map(|property| {
+                assert!(property.getter.is_some(), "{} can't be read", name(property.name));
+                (name(property.name), property.setter.is_some())
+            })
+            .collect();
+
+        assert_same(&described, dispatched);
+    }
+}
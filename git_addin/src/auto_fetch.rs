@@ -0,0 +1,232 @@
+use std::{
+    sync::{
+        Arc,
+        Condvar,
+        Mutex,
+        PoisonError,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use git_core::{Config, Repo};
+
+use crate::git::error_text;
+
+/// Shortest interval between fetches, anything shorter would only load the server.
+const MIN_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long a round waits for the running methods of the component before checking again.
+const BUSY_PAUSE: Duration = Duration::from_millis(200);
+
+/// Called on the fetching thread with the ahead and behind counts whenever they changed.
+pub type OnUpdate = Box<dyn Fn((usize, usize)) + Send>;
+
+/// Fetching in the background, so the form can show how far the current branch is from
+/// its upstream without the user pressing anything. A round is put off while a method of
+/// the component is running.
+#[derive(Default)]
+pub struct AutoFetch {
+    worker: Option<Worker>,
+    state: Arc<Mutex<State>>,
+}
+
+struct Worker {
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    thread: JoinHandle<()>,
+}
+
+#[derive(Default)]
+struct State {
+    counts: Option<(usize, usize)>,
+    last_error: String,
+}
+
+impl AutoFetch {
+    /// Fetches with `config` now and then every `interval`, at least [`MIN_INTERVAL`],
+    /// until stopped, restarting when already running. `running` counts the operations of
+    /// the component in progress. Returns the interval used.
+    pub fn start(
+        &mut self,
+        config: Config,
+        interval: Duration,
+        running: Arc<AtomicUsize>,
+        on_update: OnUpdate,
+    ) -> Duration {
+        let interval = interval.max(MIN_INTERVAL);
+        self.spawn(config, interval, running, on_update);
+        interval
+    }
+
+    fn spawn(
+        &mut self,
+        config: Config,
+        interval: Duration,
+        running: Arc<AtomicUsize>,
+        on_update: OnUpdate,
+    ) {
+        self.stop();
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let state = self.state.clone();
+        let worker_stop = stop.clone();
+        let thread = std::thread::spawn(move || {
+            let (stopped, wake) = &*worker_stop;
+            let mut pause = Duration::ZERO;
+            loop {
+                let stopped = stopped.lock().unwrap_or_else(PoisonError::into_inner);
+                let (stopped, _) = wake
+                    .wait_timeout_while(stopped, pause, |stopped| !*stopped)
+                    .unwrap_or_else(PoisonError::into_inner);
+                if *stopped {
+                    return;
+                }
+                drop(stopped);
+
+                // the first round waits for the call that started it to end
+                pause = if running.load(Ordering::SeqCst) == 0 {
+                    let result = fetch(&config);
+                    let updated =
+                        state.lock().unwrap_or_else(PoisonError::into_inner).record(result);
+                    if let Some(counts) = updated {
+                        on_update(counts);
+                    }
+                    interval
+                } else {
+                    BUSY_PAUSE
+                };
+            }
+        });
+        self.worker = Some(Worker { stop, thread });
+    }
+
+    /// Stops the background fetch, waiting for a fetch in progress to end.
+    pub fn stop(&mut self) {
+        let Some(worker) = self.worker.take() else { return };
+        let (stopped, wake) = &*worker.stop;
+        *stopped.lock().unwrap_or_else(PoisonError::into_inner) = true;
+        wake.notify_one();
+        let _ = worker.thread.join();
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.worker.is_some()
+    }
+
+    /// Commits the current branch is ahead and behind its upstream as of the last fetch,
+    /// zeros before the first one.
+    pub fn counts(&self) -> (usize, usize) {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).counts.unwrap_or_default()
+    }
+
+    /// The error of the last fetch, empty when it succeeded. Failing fetches are retried
+    /// every interval without calling back.
+    pub fn last_error(&self) -> String {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).last_error.clone()
+    }
+}
+
+impl Drop for AutoFetch {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl State {
+    /// Keeps the outcome of a fetch, returning the counts when they changed.
+    fn record(&mut self, result: Result<(usize, usize), git2::Error>) -> Option<(usize, usize)> {
+        match result {
+            Ok(counts) => {
+                self.last_error.clear();
+                (self.counts.replace(counts) != Some(counts)).then_some(counts)
+            },
+            Err(e) => {
+                self.last_error = error_text(&e);
+                None
+            },
+        }
+    }
+}
+
+fn fetch(config: &Config) -> Result<(usize, usize), git2::Error> {
+    let repo = Repo::open(config)?;
+    repo.prune_report()?;
+    let branch = repo.current_branch()?.local_name();
+    repo.ahead_behind(&branch)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+            mpsc::{self, Receiver},
+        },
+        time::Duration,
+    };
+
+    use git_core::{Config, testing::SimulatedRemote};
+
+    use super::AutoFetch;
+
+    const INTERVAL: Duration = Duration::from_millis(20);
+    /// Long enough for many rounds, the counts of the tests arrive well within it.
+    const WAIT: Duration = Duration::from_secs(10);
+
+    /// Fetches with `config` every [`INTERVAL`], the updates arriving on the receiver.
+    fn start(config: &Config, running: &Arc<AtomicUsize>) -> (AutoFetch, Receiver<(usize, usize)>) {
+        let (sender, updates) = mpsc::channel();
+        let on_update = Box::new(move |counts| {
+            let _ = sender.send(counts);
+        });
+        let mut auto_fetch = AutoFetch::default();
+        auto_fetch.spawn(config.clone(), INTERVAL, running.clone(), on_update);
+        (auto_fetch, updates)
+    }
+
+    #[test]
+    fn a_change_on_the_remote_is_reported_once() -> Result<(), git2::Error> {
+        let remote = SimulatedRemote::new("main")?;
+        let local = remote.clone_workspace()?;
+        let (mut auto_fetch, updates) = start(&local.config, &Arc::default());
+
+        assert_eq!(updates.recv_timeout(WAIT), Ok((0, 0)));
+        remote.push_commit("main", "a.txt", "theirs\n", "concurrent change")?;
+        assert_eq!(updates.recv_timeout(WAIT), Ok((0, 1)));
+        assert_eq!(auto_fetch.counts(), (0, 1));
+
+        // later rounds fetch the same, nothing more is reported
+        assert!(updates.recv_timeout(INTERVAL * 10).is_err());
+        auto_fetch.stop();
+        assert!(!auto_fetch.is_running());
+        Ok(())
+    }
+
+    #[test]
+    fn rounds_wait_for_the_running_methods() -> Result<(), git2::Error> {
+        let remote = SimulatedRemote::new("main")?;
+        let local = remote.clone_workspace()?;
+        let running = Arc::new(AtomicUsize::new(1));
+        let (_auto_fetch, updates) = start(&local.config, &running);
+
+        assert!(updates.recv_timeout(INTERVAL * 10).is_err());
+        running.store(0, Ordering::SeqCst);
+        assert_eq!(updates.recv_timeout(WAIT), Ok((0, 0)));
+        Ok(())
+    }
+
+    #[test]
+    fn failures_are_recorded_without_reporting() -> Result<(), git2::Error> {
+        let remote = SimulatedRemote::new("main")?;
+        let local = remote.clone_workspace()?;
+        git2::Repository::open(&local.config.path)?
+            .remote_set_url("origin", "file:///nonexistent/remote.git")?;
+        let (auto_fetch, updates) = start(&local.config, &Arc::default());
+
+        assert!(updates.recv_timeout(INTERVAL * 10).is_err());
+        assert!(!auto_fetch.last_error().is_empty());
+        assert_eq!(auto_fetch.counts(), (0, 0));
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Mutex, PoisonError},
+};
+
+/// Outputs not read to the end are dropped, oldest first, beyond this many.
+const PENDING_OUTPUTS: usize = 16;
+
+const DEFAULT_CHUNK_KB: u32 = 1024;
+
+/// Large results handed to 1C in pieces, for platform versions that can't take them in a
+/// single string. A paged method returns the first chunk and a cursor, passing the cursor
+/// back returns the next one; an empty cursor marks the last chunk.
+///
+/// A cursor names the output and the offset of its next chunk, so repeating a call with
+/// the same cursor returns the same chunk again until the last chunk was read.
+pub struct Chunks {
+    /// Chunk size in bytes, a chunk ends earlier rather than splitting a character.
+    pub max_bytes: usize,
+    pending: Mutex<Pending>,
+}
+
+#[derive(Default)]
+struct Pending {
+    next_id: u64,
+    outputs: BTreeMap<u64, String>,
+}
+
+impl Default for Chunks {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_CHUNK_KB as usize * 1024,
+            pending: Mutex::default(),
+        }
+    }
+}
+
+impl Chunks {
+    /// The first chunk of `output` and the cursor of the rest.
+    pub fn start(&self, output: String) -> (String, String) {
+        let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        let id = pending.next_id;
+        pending.next_id += 1;
+        pending.outputs.insert(id, output);
+        while pending.outputs.len() > PENDING_OUTPUTS {
+            pending.outputs.pop_first();
+        }
+        self.take(&mut pending, id, 0)
+    }
+
+    /// The chunk at `cursor` and the cursor of the rest.
+    pub fn next(&self, cursor: &str) -> Result<(String, String), String> {
+        let (id, offset) = cursor
+            .split_once(':')
+            .and_then(|(id, offset)| Some((id.parse().ok()?, offset.parse().ok()?)))
+            .ok_or_else(|| format!("invalid cursor '{cursor}'"))?;
+
+        let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        match pending.outputs.get(&id) {
+            Some(output) if output.is_char_boundary(offset) =>
+                Ok(self.take(&mut pending, id, offset)),
+            _ => Err(format!("cursor '{cursor}' expired, request the output again")),
+        }
+    }
+
+    fn take(&self, pending: &mut Pending, id: u64, offset: usize) -> (String, String) {
+        let output = &pending.outputs[&id];
+        let mut end = (offset + self.max_bytes.max(4)).min(output.len());
+        while !output.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        let chunk = output[offset..end].to_string();
+        if end == output.len() {
+            pending.outputs.remove(&id);
+            (chunk, String::new())
+        } else {
+            (chunk, format!("{id}:{end}"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Chunks;
+
+    /// Reads `output` to the end through `chunks`, returning the chunks.
+    fn read_all(chunks: &Chunks, output: &str) -> Vec<String> {
+        let (mut chunk, mut cursor) = chunks.start(output.to_string());
+        let mut read = vec![chunk];
+        while !cursor.is_empty() {
+            (chunk, cursor) = chunks.next(&cursor).unwrap();
+            read.push(chunk);
+        }
+        read
+    }
+
+    #[test]
+    fn chunks_reassemble_to_the_output() {
+        // 1, 2, 3 and 4 byte characters, so some chunks end early at every size
+        let output = "ветка main: 🌿 изменён файл Справочник.xml\n".repeat(50);
+        for max_bytes in [1, 4, 5, 7, 64, output.len() - 1, output.len(), output.len() + 1] {
+            let chunks = Chunks { max_bytes, ..Chunks::default() };
+
+            let read = read_all(&chunks, &output);
+
+            assert_eq!(read.concat().as_bytes(), output.as_bytes(), "max_bytes {max_bytes}");
+            assert!(read.iter().all(|chunk| !chunk.is_empty() && chunk.len() <= max_bytes.max(4)));
+        }
+    }
+
+    #[test]
+    fn empty_output_is_a_single_empty_chunk() {
+        assert_eq!(Chunks::default().start(String::new()), (String::new(), String::new()));
+    }
+
+    #[test]
+    fn a_cursor_repeats_its_chunk_until_the_end_is_read() {
+        let chunks = Chunks {
+            max_bytes: 4,
+            ..Chunks::default()
+        };
+        let (first, cursor) = chunks.start("abcdefghij".to_string());
+        assert_eq!(first, "abcd");
+
+        let second = chunks.next(&cursor).unwrap();
+        assert_eq!(second.0, "efgh");
+        assert_eq!(chunks.next(&cursor), Ok(second.clone()));
+        assert_eq!(chunks.next(&second.1), Ok(("ij".to_string(), String::new())));
+        assert!(chunks.next(&second.1).unwrap_err().contains("expired"));
+        assert!(chunks.next("garbage").unwrap_err().contains("invalid cursor"));
+    }
+}
@@ -0,0 +1,175 @@
+//! What the component offers 1C, listed once. The tables addin1c dispatches on and the
+//! metadata returned by `GetMethodsInfo` and `GetPropertiesInfo` are generated from the
+//! same lists by [`methods`] and [`properties`], so they can't disagree.
+
+use git_core::json;
+
+/// A method as `GetMethodsInfo` describes it.
+pub struct MethodDescriptor {
+    pub name: &'static str,
+    /// Russian name for 1C developers, the component itself answers to `name` only.
+    pub synonym: &'static str,
+    /// Names and types of the parameters: `string`, `number` or `boolean`.
+    pub params: &'static [(&'static str, &'static str)],
+    pub returns: &'static str,
+    /// Version of the component the method first appeared in.
+    pub since: &'static str,
+}
+
+/// A property as `GetPropertiesInfo` describes it, every property can be read.
+pub struct PropertyDescriptor {
+    pub name: &'static str,
+    pub synonym: &'static str,
+    pub kind: &'static str,
+    pub writable: bool,
+    pub since: &'static str,
+}
+
+/// JSON array of `{name, synonym, params: [{name, type}], returns, since}`.
+pub fn methods_json(methods: &[MethodDescriptor]) -> String {
+    json::array(methods.iter().map(|method| {
+        let params = method
+            .params
+            .iter()
+            .map(|(name, kind)| json::Object::new().str("name", name).str("type", kind));
+        json::Object::new()
+            .str("name", method.name)
+            .str("synonym", method.synonym)
+            .raw("params", json::array(params))
+            .str("returns", method.returns)
+            .str("since", method.since)
+    }))
+}
+
+/// JSON array of `{name, synonym, type, writable, since}`.
+pub fn properties_json(properties: &[PropertyDescriptor]) -> String {
+    json::array(properties.iter().map(|property| {
+        json::Object::new()
+            .str("name", property.name)
+            .str("synonym", property.synonym)
+            .str("type", property.kind)
+            .bool("writable", property.writable)
+            .str("since", property.since)
+    }))
+}
+
+/// Generates the associated const `METHODS` of descriptors and `method_table`, the table
+/// for [`addin1c::SimpleAddin::methods`], from entries of the form
+/// `"Name" / "Синоним" => handler(param: type, ..) -> "returns", since "version";`.
+/// The arity of the addin1c method follows from the parameters. Every call is measured
+/// under its name with the [`Metrics`](crate::metrics::Metrics) of `Self::metrics`.
+macro_rules! methods {
+    (@method $name:tt $handler:ident) => {
+        addin1c::Methods::Method0(|addin: &mut Self, ret: &mut addin1c::Variant| {
+            addin.metrics().measure($name, || Self::$handler(addin, ret))
+        })
+    };
+    (@method $name:tt $handler:ident $a:ident) => {
+        addin1c::Methods::Method1(
+            |addin: &mut Self, a: &mut addin1c::Variant, ret: &mut addin1c::Variant| {
+                addin.metrics().measure($name, || Self::$handler(addin, a, ret))
+            },
+        )
+    };
+    (@method $name:tt $handler:ident $a:ident $b:ident) => {
+        addin1c::Methods::Method2(
+            |addin: &mut Self,
+             a: &mut addin1c::Variant,
+             b: &mut addin1c::Variant,
+             ret: &mut addin1c::Variant| {
+                addin.metrics().measure($name, || Self::$handler(addin, a, b, ret))
+            },
+        )
+    };
+    (@method $name:tt $handler:ident $a:ident $b:ident $c:ident) => {
+        addin1c::Methods::Method3(
+            |addin: &mut Self,
+             a: &mut addin1c::Variant,
+             b: &mut addin1c::Variant,
+             c: &mut addin1c::Variant,
+             ret: &mut addin1c::Variant| {
+                addin.metrics().measure($name, || Self::$handler(addin, a, b, c, ret))
+            },
+        )
+    };
+    (@method $name:tt $handler:ident $a:ident $b:ident $c:ident $d:ident) => {
+        addin1c::Methods::Method4(
+            |addin: &mut Self,
+             a: &mut addin1c::Variant,
+             b: &mut addin1c::Variant,
+             c: &mut addin1c::Variant,
+             d: &mut addin1c::Variant,
+             ret: &mut addin1c::Variant| {
+                addin.metrics().measure($name, || Self::$handler(addin, a, b, c, d, ret))
+            },
+        )
+    };
+    ($(
+        $name:tt / $synonym:tt => $handler:ident($($param:ident: $kind:ident),*)
+            -> $returns:tt, since $since:tt;
+    )*) => {
+        pub const METHODS: &'static [$crate::descriptors::MethodDescriptor] = &[$(
+            $crate::descriptors::MethodDescriptor {
+                name: $name,
+                synonym: $synonym,
+                params: &[$((stringify!($param), stringify!($kind))),*],
+                returns: $returns,
+                since: $since,
+            },
+        )*];
+
+        fn method_table() -> &'static [addin1c::MethodInfo<Self>] {
+            &[$(
+                addin1c::MethodInfo {
+                    name: addin1c::name!($name),
+                    method: $crate::descriptors::methods!(@method $name $handler $($param)*),
+                },
+            )*]
+        }
+    };
+}
+
+/// Like [`methods`] for properties, from entries of the form
+/// `"Name" / "Синоним": type => getter / setter, since "version";` with the setter left
+/// out for read-only ones.
+macro_rules! properties {
+    (@setter) => {
+        None
+    };
+    (@setter $setter:ident) => {
+        Some(Self::$setter)
+    };
+    (@writable) => {
+        false
+    };
+    (@writable $setter:ident) => {
+        true
+    };
+    ($(
+        $name:tt / $synonym:tt: $kind:ident => $getter:ident $(/ $setter:ident)?,
+            since $since:tt;
+    )*) => {
+        pub const PROPERTIES: &'static [$crate::descriptors::PropertyDescriptor] = &[$(
+            $crate::descriptors::PropertyDescriptor {
+                name: $name,
+                synonym: $synonym,
+                kind: stringify!($kind),
+                writable: $crate::descriptors::properties!(@writable $($setter)?),
+                since: $since,
+            },
+        )*];
+
+        fn property_table() -> &'static [addin1c::PropInfo<Self>] {
+            &[$(
+                addin1c::PropInfo {
+                    name: addin1c::name!($name),
+                    getter: Some(Self::$getter),
+                    setter: $crate::descriptors::properties!(@setter $($setter)?),
+                },
+            )*]
+        }
+    };
+}
+
+pub(crate) use methods;
+pub(crate) use properties;
@@ -0,0 +1,73 @@
+use std::{
+    sync::{Arc, OnceLock},
+    thread::{self, ThreadId},
+};
+
+use addin1c::Connection;
+
+/// Source of the events as `ОбработкаВнешнегоСобытия` receives it.
+const SOURCE: &str = "GitAddin";
+
+/// External events raised in 1C, from any thread of the component. Nothing is raised until
+/// 1C initialized the component with its connection.
+#[derive(Clone, Default)]
+pub struct Events {
+    connection: Arc<OnceLock<Connected>>,
+    /// Stands in for the connection in tests, see [`Events::recording`].
+    #[cfg(test)]
+    recorded: Option<Arc<std::sync::Mutex<Vec<String>>>>,
+}
+
+struct Connected {
+    connection: &'static Connection,
+    /// The thread 1C calls the component on, it handles events only between those calls.
+    thread: ThreadId,
+}
+
+// SAFETY: 1C accepts `ExternalEvent` from any thread, the connection is used for nothing
+// else.
+unsafe impl Send for Connected {}
+unsafe impl Sync for Connected {}
+
+impl Events {
+    /// Keeps `connection` for the events, called by 1C on its thread when initializing.
+    pub fn connect(&self, connection: &'static Connection) {
+        let _ = self.connection.set(Connected {
+            connection,
+            thread: thread::current().id(),
+        });
+    }
+
+    /// Raises the event `name` with `data`, false when 1C didn't take it.
+    pub fn raise(&self, name: &str, data: &str) -> bool {
+        #[cfg(test)]
+        if let Some(recorded) = &self.recorded {
+            recorded.lock().unwrap().push(format!("{name}: {data}"));
+            return true;
+        }
+        let utf16 = |text: &str| text.encode_utf16().chain([0]).collect::<Vec<_>>();
+        self.connection.get().is_some_and(|connected| {
+            connected.connection.external_event(&utf16(SOURCE), &utf16(name), &utf16(data))
+        })
+    }
+
+    /// Whether this is the thread 1C calls the component on, where waiting for 1C to handle
+    /// an event is waiting for nothing.
+    pub fn on_1c_thread(&self) -> bool {
+        self.connection.get().is_some_and(|connected| connected.thread == thread::current().id())
+    }
+}
+
+#[cfg(test)]
+impl Events {
+    /// Events taken as by a 1C calling the component on another thread, each kept as
+    /// "name: data".
+    pub fn recording() -> (Self, Arc<std::sync::Mutex<Vec<String>>>) {
+        let recorded = Arc::default();
+        let events = Self {
+            recorded: Some(Arc::clone(&recorded)),
+            ..Self::default()
+        };
+        (events, recorded)
+    }
+}
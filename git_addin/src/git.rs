@@ -1,163 +1,1973 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    fs::File,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        Mutex,
+        MutexGuard,
+        PoisonError,
+        atomic::Ordering,
+        mpsc::{self, SendError, Sender},
+    },
+    time::Duration,
+};
+
 use git_core::{
-    FileStatus,
-    INVALID_UTF8,
-    StatusSummary,
+    BisectStep,
+    CommitInfo,
+    CredentialKind,
+    CredentialProvider,
+    CredentialsRequired,
+    InitTemplate,
+    Profile,
+    PushAtomicity,
+    PushReport,
+    RepositoryMoved,
+    Upstream,
     git::{Config, PullResult, Repo},
+    json,
+    refname,
+    report,
 };
-use itertools::Itertools;
+use git2::{ErrorClass, ErrorCode, Oid};
+
+use crate::{
+    auto_fetch::AutoFetch,
+    chunks::Chunks,
+    events::Events,
+    metrics::Metrics,
+    prepared::PreparedCommits,
+};
+
+/// Files changed by a pull listed in the message of [`Git::pull`].
+const PULL_FILES_SHOWN: usize = 20;
 
-#[derive(Default)]
 pub struct Git {
     pub config: Config,
+    pub credentials: Arc<AddinCredentials>,
+    pub events: Events,
+    pub metrics: Arc<Metrics>,
+    pub chunks: Chunks,
+    pub auto_fetch: AutoFetch,
+    prepared_commits: PreparedCommits,
+    profiles: BTreeMap<String, Profile>,
+    /// The active profile and the config it was applied over, restored when switching.
+    active_profile: Option<(String, Config)>,
+    /// The catalog last opened and its [`Repo::repository_id`], to recognize the repository
+    /// once it was moved. Repositories without commits can't be recognized.
+    known_repository: Mutex<Option<(PathBuf, Oid)>>,
+}
+
+impl Default for Git {
+    fn default() -> Self {
+        let events = Events::default();
+        let credentials = Arc::new(AddinCredentials::new(events.clone()));
+        let config = Config {
+            credential_provider: Some(credentials.clone()),
+            ..Config::default()
+        };
+        Self {
+            config,
+            credentials,
+            events,
+            metrics: Arc::default(),
+            chunks: Chunks::default(),
+            auto_fetch: AutoFetch::default(),
+            prepared_commits: PreparedCommits::default(),
+            profiles: BTreeMap::new(),
+            active_profile: None,
+            known_repository: Mutex::default(),
+        }
+    }
+}
+
+/// How long an operation off the 1C thread waits for the form to answer a credential
+/// request before it fails.
+const CREDENTIAL_TIMEOUT: Duration = Duration::from_mins(2);
+
+/// Secrets entered in 1C for git operations. A missing secret raises the external event
+/// `CredentialRequired` with "url\nkind", the form answers with `ProvideCredential` or
+/// `CancelCredential`, see [`AddinCredentials::request`] for when an operation waits.
+pub struct AddinCredentials {
+    events: Events,
+    timeout: Duration,
+    state: Mutex<CredentialState>,
+}
+
+#[derive(Default)]
+struct CredentialState {
+    requested: Option<(String, CredentialKind)>,
+    /// Where the answer to `requested` goes while an operation waits for it, `None` for
+    /// cancelled.
+    waiting: Option<Sender<Option<String>>>,
+    /// A secret for the operation repeated, with the url and kind it was requested for.
+    provided: Option<(String, CredentialKind, String)>,
+}
+
+impl AddinCredentials {
+    pub fn new(events: Events) -> Self {
+        Self {
+            events,
+            timeout: CREDENTIAL_TIMEOUT,
+            state: Mutex::default(),
+        }
+    }
+
+    /// The pending request as "url\nkind", empty when nothing is awaited.
+    pub fn pending(&self) -> String {
+        self.state()
+            .requested
+            .as_ref()
+            .map(|(url, kind)| format!("{url}\n{kind}"))
+            .unwrap_or_default()
+    }
+
+    /// Answers the pending request with `secret`, ignored when there is none.
+    pub fn provide(&self, secret: String) {
+        let mut state = self.state();
+        let Some((url, kind)) = state.requested.take() else { return };
+        let secret = match state.waiting.take() {
+            Some(waiting) => match waiting.send(Some(secret)) {
+                Ok(()) => return,
+                // the operation gave up waiting just now, keep it for the repeated one
+                Err(SendError(secret)) => secret.unwrap_or_default(),
+            },
+            None => secret,
+        };
+        state.provided = Some((url, kind, secret));
+    }
+
+    /// Answers the pending request with a cancel, which fails the operation waiting for it.
+    pub fn cancel(&self) {
+        let mut state = self.state();
+        state.requested = None;
+        state.provided = None;
+        if let Some(waiting) = state.waiting.take() {
+            let _ = waiting.send(None);
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, CredentialState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl CredentialProvider for AddinCredentials {
+    /// Only an operation on another thread whose event 1C took waits for the answer, up to
+    /// the timeout. On the 1C thread the form can't answer before the call returns, and
+    /// without a connection nobody was told, so these fail at once, as does a wait timing
+    /// out: the call repeated after `ProvideCredential` picks up the secret, for the same
+    /// url and kind only.
+    fn request(&self, url: &str, kind: CredentialKind) -> Result<String, git2::Error> {
+        let wait = !self.events.on_1c_thread();
+        let answer = {
+            let mut state = self.state();
+            if let Some((for_url, for_kind, _)) = &state.provided
+                && for_url == url
+                && *for_kind == kind
+                && let Some((_, _, secret)) = state.provided.take()
+            {
+                return Ok(secret);
+            }
+            state.requested = Some((url.to_string(), kind));
+            // registered before raising, the form may answer at once
+            wait.then(|| {
+                let (waiting, answer) = mpsc::channel();
+                state.waiting = Some(waiting);
+                answer
+            })
+        };
+        let raised = self.events.raise("CredentialRequired", &format!("{url}\n{kind}"));
+
+        let repeat = |reason: &str| {
+            git2::Error::new(
+                ErrorCode::Auth,
+                ErrorClass::Callback,
+                format!("{kind} for '{url}' {reason}, repeat the call after ProvideCredential"),
+            )
+        };
+        let Some(answer) = answer else {
+            return Err(repeat("is awaited"));
+        };
+        let secret = if raised { answer.recv_timeout(self.timeout).ok() } else { None };
+        let secret = secret.or_else(|| {
+            // the request stays for the form to answer and the call to be repeated, an answer
+            // sent meanwhile is taken
+            self.state().waiting = None;
+            answer.try_recv().ok()
+        });
+        match secret {
+            Some(Some(secret)) => Ok(secret),
+            Some(None) => Err(git2::Error::new(
+                ErrorCode::User,
+                ErrorClass::Callback,
+                format!("{kind} for '{url}' was cancelled"),
+            )),
+            None if raised =>
+                Err(repeat(&format!("was not provided within {}s", self.timeout.as_secs()))),
+            None => Err(repeat("is awaited")),
+        }
+    }
 }
 
 impl Git {
+    /// Saves the profile `name` from its JSON, see [`Profile::from_json`]. Saving the active
+    /// profile again applies the new settings right away.
+    pub fn create_profile(&mut self, name: &str, profile_json: &str) -> String {
+        let profile = match Profile::from_json(profile_json) {
+            Ok(profile) => profile,
+            Err(e) => return self.failed(error_text(&e)),
+        };
+        self.profiles.insert(name.to_string(), profile);
+
+        match &self.active_profile {
+            Some((active, _)) if active == name => self.use_profile(name),
+            _ => "profile saved".to_string(),
+        }
+    }
+
+    /// Switches the login, password, key, email and catalog to the profile `name`, settings
+    /// it leaves out keep their values from before the first switch. An empty name goes
+    /// back to those values. An unknown name leaves the active profile as it is.
+    pub fn use_profile(&mut self, name: &str) -> String {
+        let profile = match self.profiles.get(name) {
+            Some(profile) => Some(profile.clone()),
+            None if name.is_empty() => None,
+            None => return self.failed(format!("no profile '{name}'")),
+        };
+        // read-only mode is a property of the session, not of the identity
+        let read_only = self.config.read_only;
+        if let Some((_, base)) = self.active_profile.take() {
+            self.config = base;
+            self.config.read_only = read_only;
+        }
+        let Some(profile) = profile else {
+            return "profile cleared".to_string();
+        };
+
+        let base = std::mem::take(&mut self.config);
+        self.config = profile.apply(&base);
+        self.active_profile = Some((name.to_string(), base));
+        format!("using profile '{name}'")
+    }
+
+    /// JSON array of `{name, active, username, email}`, secrets are left out.
+    pub fn profiles(&self) -> String {
+        let active = self.active_profile.as_ref().map(|(name, _)| name.as_str());
+        json::array(self.profiles.iter().map(|(name, profile)| {
+            json::Object::new()
+                .str("name", name)
+                .bool("active", active == Some(name.as_str()))
+                .opt_str("username", profile.username.as_deref())
+                .opt_str("email", profile.email.as_deref())
+        }))
+    }
+
     pub fn clone_repo(&self, url: &str) -> String {
         Repo::clone_from(url, &self.config)
-            .map_or_else(|e| e.to_string(), |_repo| "Repository cloned".to_string())
+            .map_or_else(|e| self.failed(error_text(&e)), |_repo| "Repository cloned".to_string())
+    }
+
+    /// A bare mirror of `url` in the repository directory, e.g. for a scheduled backup,
+    /// which [`Git::fetch_all`] keeps up to date.
+    pub fn clone_mirror(&self, url: &str) -> String {
+        Repo::clone_mirror(url, &self.config)
+            .map_or_else(|e| self.failed(error_text(&e)), |_repo| "Mirror cloned".to_string())
+    }
+
+    /// `template_name` is a built-in template like "1c-config" or a template directory.
+    pub fn init_from_template(&self, template_name: &str) -> String {
+        let template = InitTemplate::named(template_name);
+        Repo::init(&self.config, Some(&template)).map_or_else(
+            |e| self.failed(error_text(&e)),
+            |_repo| "Repository initialized".to_string(),
+        )
     }
 
     pub fn branches(&self) -> String {
-        self.branches_().unwrap_or_else(|e| e.to_string())
+        self.branches_().unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    /// The branches with their last commits as a JSON array, the most recently committed
+    /// to first: `{"name", "current", "remote_only", "remotes": [...], "last_commit"}`,
+    /// `last_commit` as in [`Git::incoming`].
+    pub fn branches_detailed(&self) -> String {
+        self.branches_detailed_().unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    /// [`Git::branches`] in chunks, see [`Chunks`]. Returns the chunk and the cursor of
+    /// the next one.
+    pub fn branches_paged(&self, cursor: &str) -> (String, String) {
+        self.paged(cursor, || self.branches())
     }
 
     pub fn current_branch(&self) -> String {
-        self.current_branch_().unwrap_or_else(|e| e.to_string())
+        self.current_branch_().unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    /// `{"full_name": ..., "oid": ..., "short_id": ..., "detached": ...}`, `full_name` is
+    /// null when detached, `oid` and `short_id` before the first commit.
+    pub fn head_ref(&self) -> String {
+        self.head_ref_().unwrap_or_else(|e| self.failed(error_text(&e)))
     }
 
     pub fn status(&self) -> String {
-        self.status_().unwrap_or_else(|e| e.to_string())
+        self.status_().unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    pub fn status_fingerprint(&self) -> String {
+        self.open_repo()
+            .and_then(|repo| repo.status_fingerprint())
+            .unwrap_or_else(|e| self.failed(error_text(&e)))
     }
 
+    /// The number of files staged on the first line, then their paths one per line.
     pub fn add_all(&self) -> String {
-        self.add_all_().unwrap_or_else(|e| e.to_string())
+        self.add_all_().unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    /// `ranges` lists working tree lines like "1-3,7,10-12", see [`Repo::stage_lines`].
+    pub fn stage_lines(&self, path: &str, ranges: &str) -> String {
+        self.stage_lines_(path, ranges).unwrap_or_else(|e| self.failed(error_text(&e)))
     }
 
     pub fn commit(&self, message: &str) -> String {
-        self.commit_(message).unwrap_or_else(|e| e.to_string())
+        self.commit_(message).unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    pub fn commit_with_coauthors(&self, message: &str, coauthors: &str) -> String {
+        self.commit_with_coauthors_(message, coauthors)
+            .unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    /// Commits with a `Signed-off-by` trailer for the Login and Email properties.
+    pub fn commit_signed_off(&self, message: &str) -> String {
+        self.commit_signed_off_(message).unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    /// Stages `paths`, one per line, and amends HEAD with them, see [`Repo::add_and_amend`].
+    pub fn add_and_amend(&self, paths: &str) -> String {
+        self.add_and_amend_(paths).unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    /// Persists the Login and Email properties as the repository's `user.name`/`user.email`.
+    pub fn write_identity(&self) -> String {
+        self.open_repo()
+            .and_then(|repo| repo.write_identity(&self.config.username, &self.config.email))
+            .map_or_else(|e| self.failed(error_text(&e)), |()| "identity written".to_string())
+    }
+
+    /// Commits a merge, cherry-pick or revert once its conflicts are resolved and staged.
+    pub fn continue_operation(&self) -> String {
+        self.open_repo()
+            .and_then(|repo| repo.continue_operation())
+            .map_or_else(|e| self.failed(error_text(&e)), |oid| oid.to_string())
+    }
+
+    /// Starts a bisect and checks out the first commit to test, see [`Git::bisect_mark`]
+    /// for the result.
+    pub fn bisect_start(&self, good: &str, bad: &str) -> String {
+        self.bisect_start_(good, bad).unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    /// Marks `commit`, the checked out one when empty, and returns JSON
+    /// `{found, commit, short_id, remaining}`: the next commit to test, or the first bad one once
+    /// `found` is true.
+    pub fn bisect_mark(&self, commit: &str, good: bool) -> String {
+        self.bisect_mark_(commit, good).unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    pub fn bisect_reset(&self) -> String {
+        self.open_repo()
+            .and_then(|repo| repo.bisect_reset())
+            .map_or_else(|e| self.failed(error_text(&e)), |()| "bisect reset".to_string())
+    }
+
+    /// "true" when `descendant` contains `maybe_ancestor`, so merging `descendant` into a
+    /// branch at `maybe_ancestor` fast-forwards.
+    pub fn is_ancestor(&self, maybe_ancestor: &str, descendant: &str) -> String {
+        self.open_repo()
+            .and_then(|repo| repo.is_ancestor(maybe_ancestor, descendant))
+            .map_or_else(|e| self.failed(error_text(&e)), |is_ancestor| is_ancestor.to_string())
+    }
+
+    /// Number of commits `to` has that `from` lacks, see [`Repo::rev_count`].
+    pub fn rev_count(&self, from: &str, to: &str) -> String {
+        self.open_repo()
+            .and_then(|repo| repo.rev_count(from, to))
+            .map_or_else(|e| self.failed(error_text(&e)), |count| count.to_string())
+    }
+
+    /// "true" when `branch`, the current one when empty, has an upstream to pull from.
+    pub fn has_upstream(&self, branch: &str) -> String {
+        self.open_repo()
+            .and_then(|repo| repo.has_upstream(non_empty(branch)))
+            .map_or_else(|e| self.failed(error_text(&e)), |has_upstream| has_upstream.to_string())
     }
 
     pub fn checkout(&self, branch_name: &str) -> String {
-        self.checkout_(branch_name)
-            .map_or_else(|e| e.to_string(), |()| format!("Switched to branch {branch_name}"))
+        self.checkout_(branch_name).map_or_else(
+            |e| self.failed(error_text(&e)),
+            |()| format!("Switched to branch {branch_name}"),
+        )
+    }
+
+    pub fn checkout_local(&self, branch_name: &str) -> String {
+        self.open_repo().and_then(|repo| repo.checkout_local(branch_name)).map_or_else(
+            |e| self.failed(error_text(&e)),
+            |()| format!("Switched to branch {branch_name}"),
+        )
+    }
+
+    /// `paths` holds one path per line.
+    pub fn discard_changes(&self, paths: &str) -> String {
+        self.discard_changes_(paths)
+            .map_or_else(|e| self.failed(error_text(&e)), |()| "changes discarded".to_string())
+    }
+
+    /// Points the catalog at `new_path` where the repository was moved, after checking it
+    /// is the same repository when the old catalog was opened in this session.
+    pub fn relocate(&mut self, new_path: &str) -> String {
+        let id = match self.relocated_id(new_path) {
+            Ok(id) => id,
+            Err(e) => return self.failed(error_text(&e)),
+        };
+
+        let old_path = std::mem::replace(&mut self.config.path, new_path.into());
+        if let Some((_, base)) = &mut self.active_profile
+            && base.path == old_path
+        {
+            base.path.clone_from(&self.config.path);
+        }
+        if let Some(id) = id {
+            *self.known_repository.lock().unwrap_or_else(PoisonError::into_inner) =
+                Some((self.config.path.clone(), id));
+        }
+        format!("catalog set to '{new_path}'")
+    }
+
+    /// Fetch URL of origin without embedded credentials, empty when there is none or the
+    /// repository doesn't open.
+    pub fn origin_url(&self) -> String {
+        let url = self.open_repo().and_then(|repo| repo.remote_url("origin"));
+        url.ok().flatten().unwrap_or_default()
+    }
+
+    /// See [`Repo::name`], empty when the repository doesn't open.
+    pub fn repository_name(&self) -> String {
+        self.open_repo().map(|repo| repo.name()).unwrap_or_default()
+    }
+
+    pub fn is_repository_open(&self) -> bool {
+        self.open_repo().is_ok()
+    }
+
+    /// Prepares a commit of the staged changes for 1C to check before confirming or aborting
+    /// it: `{"token": ..., "files": [...]}`.
+    pub fn begin_commit(&self, message: &str) -> String {
+        self.begin_commit_(message).unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    /// Makes the commit begun with `token`, unless the index or HEAD changed since.
+    pub fn confirm_commit(&self, token: &str) -> String {
+        self.confirm_commit_(token).unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    pub fn abort_commit(&self, token: &str) -> String {
+        self.prepared_commits
+            .take(token)
+            .map_or_else(|e| self.failed(e), |_| "commit aborted".to_string())
+    }
+
+    pub fn checkout_tag(&self, tag: &str) -> String {
+        self.checkout_tag_(tag).unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    /// `paths` holds one path or pathspec per line.
+    pub fn checkout_paths(&self, revspec: &str, paths: &str) -> String {
+        self.checkout_paths_(revspec, paths).map_or_else(
+            |e| self.failed(error_text(&e)),
+            |taken| format!("{taken} files checked out"),
+        )
+    }
+
+    /// Directories of the sparse checkout one per line, empty for the whole tree.
+    pub fn sparse_checkout(&self) -> String {
+        self.open_repo()
+            .and_then(|repo| repo.sparse_checkout())
+            .map_or_else(|e| self.failed(error_text(&e)), |directories| directories.join("\n"))
+    }
+
+    /// Checks out only `directories`, one per line, or the whole tree again when there are
+    /// none, see [`Repo::set_sparse_checkout`].
+    pub fn set_sparse_checkout(&self, directories: &str) -> String {
+        self.set_sparse_checkout_(directories).map_or_else(
+            |e| self.failed(error_text(&e)),
+            |(directories, kept)| {
+                let done = if directories == 0 {
+                    "Whole tree checked out".to_string()
+                } else {
+                    format!("Sparse checkout of {directories} directories")
+                };
+                if kept.is_empty() {
+                    done
+                } else {
+                    format!("{done}, kept for their local changes:\n{}", kept.join("\n"))
+                }
+            },
+        )
+    }
+
+    /// Takes `path`, a file or directory, from `revspec` into the working tree and index.
+    pub fn checkout_file_from(&self, revspec: &str, path: &str) -> String {
+        self.open_repo().and_then(|repo| repo.checkout_paths_from(revspec, &[path])).map_or_else(
+            |e| self.failed(error_text(&e)),
+            |restored| match restored.first().map_or(0, |path| path.files) {
+                0 => format!("'{path}' not found at {revspec}"),
+                files => format!("{files} files checked out from {revspec}"),
+            },
+        )
+    }
+
+    /// Stashes the changes to `paths`, one per line, see [`Repo::stash_save_paths`].
+    /// Returns the stash commit.
+    pub fn stash_save_paths(&self, message: &str, paths: &str) -> String {
+        self.stash_save_paths_(message, paths)
+            .map_or_else(|e| self.failed(error_text(&e)), |oid| oid.to_string())
     }
 
     pub fn push(&self) -> String {
-        self.push_()
-            .map_or_else(|e| e.to_string(), |()| "Successfully pushed the branch".to_string())
+        self.push_().map_or_else(
+            |e| self.failed(error_text(&e)),
+            |_| "Successfully pushed the branch".to_string(),
+        )
     }
 
-    pub fn pull(&self, branch_name: &str) -> String {
-        self.pull_(branch_name).map_or_else(
-            |e| e.to_string(),
-            |res| match res {
-                PullResult::FastForwarded { old_id, new_id } =>
-                    format!("Successfully pulled branch '{branch_name}', {old_id} -> {new_id}"),
-                PullResult::UpToDate => format!("Branch '{branch_name}' already up to date"),
-                PullResult::None => format!("No branch '{branch_name} merge possible"),
-                PullResult::Normal =>
-                    format!("Local and remote '{branch_name}' diverged. Make a merge"),
-                PullResult::Unborn =>
-                    format!("HEAD of '{branch_name}' doesn't point to a valid commit"),
+    /// Pushes `local`, the current branch when empty, to `remote_branch` on `remote`,
+    /// origin when empty.
+    pub fn push_to(&self, local: &str, remote_branch: &str, remote: &str) -> String {
+        self.push_to_(local, remote_branch, remote).map_or_else(
+            |e| self.failed(error_text(&e)),
+            |()| format!("Pushed the branch to {remote_branch}"),
+        )
+    }
+
+    /// Pushes `refspecs`, one per line, to `remote`, origin when empty, in one go, e.g. a
+    /// release branch with its tag, see [`Repo::push_refspecs`]. With `atomic` nothing is
+    /// pushed when any of the refs isn't a fast-forward, see [`PushAtomicity::Atomic`].
+    pub fn push_refs(&self, remote: &str, refspecs: &str, atomic: bool) -> String {
+        let atomicity = if atomic { PushAtomicity::Atomic } else { PushAtomicity::NonAtomic };
+        self.push_refs_(remote, refspecs, atomicity).map_or_else(
+            |e| self.failed(error_text(&e)),
+            |updated| {
+                if updated.is_empty() {
+                    "Everything up-to-date".to_string()
+                } else {
+                    format!("Pushed {}", updated.join(", "))
+                }
+            },
+        )
+    }
+
+    /// The repository's git settings the component follows as a JSON object of
+    /// `{hooks_path, commit_template, push_default, fetch_prune}`, null where unset.
+    pub fn effective_config(&self) -> String {
+        self.open_repo().and_then(|repo| repo.effective_config()).map_or_else(
+            |e| self.failed(error_text(&e)),
+            |settings| {
+                let path = |path: &Option<PathBuf>| {
+                    path.as_ref().map(|path| path.to_string_lossy().into_owned())
+                };
+                json::Object::new()
+                    .opt_str("hooks_path", path(&settings.hooks_path).as_deref())
+                    .opt_str("commit_template", path(&settings.commit_template).as_deref())
+                    .opt_str(
+                        "push_default",
+                        settings.push_default.map(|value| value.to_string()).as_deref(),
+                    )
+                    .raw(
+                        "fetch_prune",
+                        settings.fetch_prune.map_or("null".into(), |prune| prune.to_string()),
+                    )
+                    .to_string()
+            },
+        )
+    }
+
+    /// The `commit.template` to start commit messages from, empty when none is set.
+    pub fn commit_template(&self) -> String {
+        self.open_repo()
+            .and_then(|repo| repo.commit_template())
+            .map_or_else(|e| self.failed(error_text(&e)), Option::unwrap_or_default)
+    }
+
+    /// When the clone last talked to its remotes as a JSON object of `{remotes: [{remote,
+    /// last_fetch, last_pull, last_push}], ahead, behind}`, times in seconds since the Unix
+    /// epoch, null for never and `ahead` and `behind` null without an upstream.
+    pub fn sync_state(&self) -> String {
+        self.open_repo().and_then(|repo| repo.sync_state()).map_or_else(
+            |e| self.failed(error_text(&e)),
+            |state| {
+                let number = |value: Option<i64>| value.map_or("null".into(), |n| n.to_string());
+                let remotes = json::array(state.remotes.iter().map(|sync| {
+                    json::Object::new()
+                        .str("remote", &sync.remote)
+                        .raw("last_fetch", number(sync.last_fetch))
+                        .raw("last_pull", number(sync.last_pull))
+                        .raw("last_push", number(sync.last_push))
+                }));
+                let (ahead, behind) = state.ahead_behind.unzip();
+                let count = |value: Option<usize>| value.map_or("null".into(), |n| n.to_string());
+                json::Object::new()
+                    .raw("remotes", remotes)
+                    .raw("ahead", count(ahead))
+                    .raw("behind", count(behind))
+                    .to_string()
+            },
+        )
+    }
+
+    /// Makes pushes of `branch`, the current one when empty, go to `remote`, an empty
+    /// `remote` removes the setting. See [`Repo::set_push_remote`].
+    pub fn set_push_remote(&self, branch: &str, remote: &str) -> String {
+        self.set_push_remote_(branch, remote).map_or_else(
+            |e: git2::Error| self.failed(error_text(&e)),
+            |branch| match remote {
+                "" => format!("Push remote of {branch} removed"),
+                _ => format!("{branch} pushes to {remote}"),
+            },
+        )
+    }
+
+    /// Adds the remote `name`, origin when empty, fetching from `url`.
+    pub fn add_remote(&self, name: &str, url: &str) -> String {
+        let name = non_empty(name).unwrap_or("origin");
+        self.open_repo()
+            .and_then(|repo| repo.add_remote(name, url))
+            .map_or_else(|e| self.failed(error_text(&e)), |()| format!("Remote {name} added"))
+    }
+
+    /// Sets the push URL of `remote`, origin when empty, see [`Repo::set_remote_push_url`].
+    pub fn set_push_url(&self, remote: &str, url: &str) -> String {
+        let remote = non_empty(remote).unwrap_or("origin");
+        self.open_repo().and_then(|repo| repo.set_remote_push_url(remote, url)).map_or_else(
+            |e| self.failed(error_text(&e)),
+            |()| match url {
+                "" => format!("Push URL of {remote} removed"),
+                _ => format!("Push URL of {remote} set"),
             },
         )
     }
 
+    pub fn pull(&self, branch_name: &str) -> String {
+        self.pull_(branch_name).unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
     pub fn merge(&self) -> String {
-        self.merge_()
-            .map_or_else(|e| e.to_string(), |()| "Successfully merged the branch".to_string())
+        self.merge_().map_or_else(
+            |e| self.failed(error_text(&e)),
+            |()| "Successfully merged the branch".to_string(),
+        )
     }
 
-    fn branches_(&self) -> Result<String, git2::Error> {
-        let repo = self.open_repo()?;
-        let branches = repo.branches()?;
+    /// Fetches and returns the commits pulling `branch`, the current one when empty, would
+    /// bring in as a JSON array of `{id, parents, author, email, time, summary, message}`.
+    pub fn incoming(&self, branch: &str) -> String {
+        self.incoming_(branch).unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
 
-        let res = branches
-            .into_iter()
-            .map(|(branch, branch_type)| {
-                let branch_type = match branch_type {
-                    git2::BranchType::Local => "Local",
-                    git2::BranchType::Remote => "Remote",
-                };
+    /// The commits pushing `branch`, the current one when empty, would send, in the format
+    /// of [`Git::incoming`]. Nothing is fetched.
+    pub fn outgoing(&self, branch: &str) -> String {
+        self.outgoing_(branch).unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
 
-                let branch_name = match branch.name() {
-                    Ok(Some(name)) => name,
-                    Ok(None) => INVALID_UTF8,
-                    Err(e) => &e.to_string(),
-                };
+    pub fn incoming_paged(&self, branch: &str, cursor: &str) -> (String, String) {
+        self.paged(cursor, || self.incoming(branch))
+    }
 
-                format!("{branch_type} {branch_name}")
-            })
-            .join("\n");
-        Ok(res)
+    /// The commits that changed `path`, newest first, in the format of [`Git::incoming`],
+    /// at most `max` of them unless it is 0.
+    pub fn file_log(&self, path: &str, max: i32) -> String {
+        self.file_log_(path, max).unwrap_or_else(|e| self.failed(error_text(&e)))
     }
 
-    fn current_branch_(&self) -> Result<String, git2::Error> {
-        let repo = self.open_repo()?;
-        let current_branch = repo.current_branch()?;
-        let local = current_branch.local_name();
-        let upstream = current_branch
-            .upstream_name()
-            .unwrap_or_else(|| "[No upstream branch tracked]".to_string());
+    pub fn file_authors(&self, path: &str) -> String {
+        self.file_authors_(path).unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
 
-        Ok(format!("{local}:{upstream}"))
+    /// Who last changed `line` of `path`, as a small JSON object for the gutter of an editor.
+    /// `ignore_whitespace` looks past commits that only reformatted the line.
+    pub fn blame_line(&self, path: &str, line: i32, ignore_whitespace: bool) -> String {
+        self.blame_line_(path, line, ignore_whitespace)
+            .unwrap_or_else(|e| self.failed(error_text(&e)))
     }
 
-    fn status_(&self) -> Result<String, git2::Error> {
-        let StatusSummary {
-            branch_name,
-            staged,
-            not_staged,
-            untracked,
-        } = self.open_repo().and_then(|repo| repo.status())?;
-
-        let mut res = format!("on branch {branch_name}");
-        if staged.is_empty() && not_staged.is_empty() && untracked.is_empty() {
-            res.push_str("\nnothing to commit, working tree clean");
-            return Ok(res);
-        }
-
-        let mut write_section = |header, contents: &[_]| {
-            if !contents.is_empty() {
-                res.push_str(header);
-                res.push_str(&contents.iter().map(FileStatus::to_string).join("\n\t"));
-            }
+    pub fn parents(&self, revspec: &str) -> String {
+        self.parents_(revspec).unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    /// Starts fetching every `seconds` in the background, see [`AutoFetch`], raising the
+    /// external event `RemoteUpdated` with "ahead\nbehind" when the counts changed. The
+    /// fetch uses the settings in effect now, enable it again after changing them. A
+    /// missing secret fails the fetch instead of being requested from the form.
+    pub fn enable_auto_fetch(&mut self, seconds: i32) -> String {
+        let config = Config {
+            credential_provider: None,
+            ..self.config.clone()
         };
+        let interval = Duration::from_secs(u64::try_from(seconds).unwrap_or_default());
+        let events = self.events.clone();
+        let on_update = Box::new(move |(ahead, behind)| {
+            events.raise("RemoteUpdated", &format!("{ahead}\n{behind}"));
+        });
+        let interval = self.auto_fetch.start(config, interval, self.metrics.running(), on_update);
+        format!("fetching every {} seconds", interval.as_secs())
+    }
 
-        write_section("\nChanges to be committed:\n\t", &staged);
-        write_section("\nChanges not staged for commit:\n\t", &not_staged);
-        write_section("\nUntracked files:\n\t", &untracked);
+    /// See [`Repo::commit_raw_message`].
+    pub fn commit_message(&self, revspec: &str) -> String {
+        self.open_repo()
+            .and_then(|repo| repo.commit_raw_message(revspec))
+            .unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
 
-        Ok(res)
+    pub fn health_check(&self) -> String {
+        self.health_check_().unwrap_or_else(|e| self.failed(error_text(&e)))
     }
 
-    fn add_all_(&self) -> Result<String, git2::Error> {
-        let _index = self.open_repo()?.add_all()?;
-        Ok("files added".to_string())
+    /// Rebuilds the index from HEAD, see [`Repo::rebuild_index`]. Staged changes are lost,
+    /// so nothing happens unless `confirm` is set.
+    pub fn rebuild_index(&self, confirm: bool) -> String {
+        if !confirm {
+            return "rebuilding the index drops everything staged, call again with confirmation"
+                .to_string();
+        }
+        self.open_repo().and_then(|repo| repo.rebuild_index()).map_or_else(
+            |e| self.failed(error_text(&e)),
+            |files| {
+                format!(
+                    "index rebuilt from HEAD with {files} files, staged changes were dropped \
+                         and show as unstaged"
+                )
+            },
+        )
     }
 
-    fn commit_(&self, message: &str) -> Result<String, git2::Error> {
-        self.open_repo()?.commit(message).map(|oid| oid.to_string())
+    /// Deletes the lock files a crashed operation left behind, see [`Repo::clear_locks`].
+    /// Refused while another method of the component runs, its locks aren't stale.
+    pub fn clear_locks(&self) -> String {
+        // the call measured for this method counts as running too
+        if self.metrics.running().load(Ordering::SeqCst) > 1 {
+            return "another operation is running, clear the locks once it ends".to_string();
+        }
+        self.open_repo().and_then(|repo| repo.clear_locks()).map_or_else(
+            |e| self.failed(error_text(&e)),
+            |locks| match locks.len() {
+                0 => "No lock files found".to_string(),
+                count => format!(
+                    "{count} lock files deleted\n{}",
+                    locks
+                        .iter()
+                        .map(|lock| lock.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                ),
+            },
+        )
     }
 
-    fn checkout_(&self, branch_name: &str) -> Result<(), git2::Error> {
-        self.open_repo()?.checkout(branch_name)
+    /// JSON array of `{blocking, code, message}` for what would stop or disturb
+    /// `operation`: `commit`, `push` or `commit_and_push`.
+    pub fn preflight(&self, operation: &str) -> String {
+        self.preflight_(operation).unwrap_or_else(|e| self.failed(error_text(&e)))
     }
 
-    fn push_(&self) -> Result<(), git2::Error> {
-        self.open_repo()?.push()
+    pub fn checkout_dry_run(&self, branch_name: &str) -> String {
+        self.checkout_dry_run_(branch_name).unwrap_or_else(|e| self.failed(error_text(&e)))
     }
 
-    fn pull_(&self, branch_name: &str) -> Result<PullResult, git2::Error> {
-        self.open_repo()?.pull(branch_name)
+    pub fn diff_names(&self, from: &str, to: &str) -> String {
+        self.diff_names_(from, to).unwrap_or_else(|e| self.failed(error_text(&e)))
     }
 
-    #[allow(clippy::unnecessary_wraps, clippy::unused_self)]
-    fn merge_(&self) -> Result<(), git2::Error> {
-        Ok(())
+    pub fn lock_file(&self, path: &str) -> String {
+        self.open_repo()
+            .and_then(|repo| repo.lock_file(path, &self.config.username))
+            .map_or_else(|e| self.failed(error_text(&e)), |()| format!("'{path}' locked"))
+    }
+
+    pub fn unlock_file(&self, path: &str) -> String {
+        self.open_repo()
+            .and_then(|repo| repo.unlock_file(path))
+            .map_or_else(|e| self.failed(error_text(&e)), |()| format!("'{path}' unlocked"))
+    }
+
+    pub fn locks(&self) -> String {
+        self.locks_().unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    pub fn prune(&self, remote: &str) -> String {
+        self.prune_(remote).unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    /// Resolves the revision `text` as typed by the user, see [`Repo::resolve`], to
+    /// `{"id", "short_id", "kind", "ref_name", "summary"}`, `kind` being commit, tag, tree
+    /// or blob. Returns the error text when it names nothing, is ambiguous or is a range.
+    pub fn resolve_rev(&self, text: &str) -> String {
+        self.resolve_rev_(text).unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    /// Fetches every remote and reports each on a line, e.g. `origin: ok, 12 objects, 2 refs
+    /// updated, 1 pruned` or `upstream: failed (...)`. One remote failing doesn't stop the
+    /// others.
+    pub fn fetch_all(&self) -> String {
+        self.fetch_all_().unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    /// SHA of the branch tip. `branch_type` is "Local" or "Remote" as in
+    /// [`Git::branches`], empty means local.
+    pub fn branch_tip(&self, name: &str, branch_type: &str) -> String {
+        self.branch_tip_(name, branch_type).unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    /// A valid branch name close to `raw`, `raw` itself when valid, so the form can offer
+    /// the fix before anything fails, see [`refname::suggest`].
+    pub fn suggest_branch_name(&self, raw: &str) -> String {
+        refname::suggest(raw, self.config.transliterate_ref_names)
+    }
+
+    pub fn rename_current_branch(&self, new_name: &str) -> String {
+        self.open_repo().and_then(|repo| repo.rename_current_branch(new_name)).map_or_else(
+            |e| self.failed(error_text(&e)),
+            |()| format!("Renamed the current branch to {new_name}"),
+        )
+    }
+
+    /// Creates the orphan branch `name` from `files`, a JSON object of file contents by
+    /// path, see [`Repo::branch_create_orphan`]. Returns the commit.
+    pub fn create_orphan_branch(
+        &self,
+        name: &str,
+        message: &str,
+        files: &str,
+        checkout: bool,
+    ) -> String {
+        self.create_orphan_branch_(name, message, files, checkout)
+            .map_or_else(|e| self.failed(error_text(&e)), |oid| oid.to_string())
+    }
+
+    /// The commit `branch`, the current one when empty, points at on `remote`, origin when
+    /// empty, without fetching, see [`Repo::ls_remote_head`]. Returns the id or the error
+    /// text.
+    pub fn ls_remote(&self, remote: &str, branch: &str) -> String {
+        self.ls_remote_(remote, branch)
+            .map_or_else(|e| self.failed(error_text(&e)), |oid| oid.to_string())
+    }
+
+    /// Fetches and lists local branches whose upstream was deleted, one per line.
+    pub fn stale_branches(&self) -> String {
+        self.stale_branches_().unwrap_or_else(|e| self.failed(error_text(&e)))
     }
 
-    fn open_repo(&self) -> Result<Repo, git2::Error> {
-        Repo::open(&self.config)
+    pub fn delete_stale_branches(&self, dry_run: bool) -> String {
+        self.delete_stale_branches_(dry_run)
+            .unwrap_or_else(|e: git2::Error| self.failed(error_text(&e)))
+    }
+
+    /// JSON array of `{commit, summary, valid, status}` for every commit in `range`.
+    pub fn verify_commits(&self, range: &str) -> String {
+        self.verify_commits_(range).unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    /// JSON `{ahead, behind, merge_base, files: [{path, old_path, change, insertions,
+    /// deletions}]}`, see [`Repo::compare_refs`].
+    pub fn compare_branches(&self, base: &str, head: &str) -> String {
+        self.compare_branches_(base, head).unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    /// SHA of the commit where `a` and `b` diverged.
+    pub fn merge_base(&self, a: &str, b: &str) -> String {
+        self.open_repo()
+            .and_then(|repo| repo.merge_base(a, b))
+            .map_or_else(|e| self.failed(error_text(&e)), |oid| oid.to_string())
+    }
+
+    /// Adds a note to `commit` in `namespace`, "commits" when empty. An existing note is
+    /// kept and reported as an error, remove it first to replace it.
+    pub fn add_note(&self, commit: &str, namespace: &str, text: &str) -> String {
+        self.open_repo()
+            .and_then(|repo| repo.note_add(commit, namespace, text, false))
+            .map_or_else(|e| self.failed(error_text(&e)), |oid| oid.to_string())
+    }
+
+    /// The note text, empty when the commit has none.
+    pub fn note(&self, commit: &str, namespace: &str) -> String {
+        self.open_repo()
+            .and_then(|repo| repo.note_read(commit, namespace))
+            .map_or_else(|e| self.failed(error_text(&e)), Option::unwrap_or_default)
+    }
+
+    pub fn remove_note(&self, commit: &str, namespace: &str) -> String {
+        self.open_repo()
+            .and_then(|repo| repo.note_remove(commit, namespace))
+            .map_or_else(|e| self.failed(error_text(&e)), |()| "note removed".to_string())
+    }
+
+    /// JSON array of `{path, base, ours, theirs}` with blob ids, `null` for a missing side.
+    pub fn conflicts(&self) -> String {
+        self.conflicts_().unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    /// The patch between `from` and `to` with `context_lines` around each change.
+    pub fn diff(&self, from: &str, to: &str, context_lines: i32) -> String {
+        let config = self.with_context_lines(context_lines);
+        self.open_repo_with(&config)
+            .and_then(|repo| repo.diff(non_empty(from), non_empty(to)))
+            .unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    /// The patch of the staged changes with `context_lines` around each change.
+    pub fn diff_staged(&self, context_lines: i32) -> String {
+        let config = self.with_context_lines(context_lines);
+        self.open_repo_with(&config)
+            .and_then(|repo| repo.diff_staged())
+            .unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    pub fn diff_paged(
+        &self,
+        from: &str,
+        to: &str,
+        context_lines: i32,
+        cursor: &str,
+    ) -> (String, String) {
+        self.paged(cursor, || self.diff(from, to, context_lines))
+    }
+
+    /// Writes the status report to `path` in `format` (text, json or html) and returns the
+    /// number of bytes written.
+    pub fn status_to_file(&self, path: &str, format: &str) -> String {
+        self.status_to_file_(path, format).unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    /// Writes the commits of `range` to the bundle file `path`, see [`Repo::bundle_create`].
+    pub fn create_bundle(&self, path: &str, range: &str) -> String {
+        self.open_repo().and_then(|repo| repo.bundle_create(Path::new(path), range)).map_or_else(
+            |e| self.failed(error_text(&e)),
+            |objects| format!("{objects} objects written to {path}"),
+        )
+    }
+
+    /// Imports the bundle file `path` and lists the refs it brought in, one per line.
+    pub fn import_bundle(&self, path: &str) -> String {
+        self.open_repo().and_then(|repo| repo.bundle_import(Path::new(path))).map_or_else(
+            |e| self.failed(error_text(&e)),
+            |refs| match refs.len() {
+                0 => "Nothing new in the bundle".to_string(),
+                count => format!("{count} refs imported\n{}", refs.join("\n")),
+            },
+        )
+    }
+
+    /// Same as [`Git::status_to_file`] for the diff between `from` and `to`.
+    pub fn diff_to_file(&self, path: &str, format: &str, from: &str, to: &str) -> String {
+        self.diff_to_file_(path, format, from, to).unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    pub fn file_attributes(&self, path: &str) -> String {
+        self.file_attributes_(path).unwrap_or_else(|e| self.failed(error_text(&e)))
+    }
+
+    fn branches_(&self) -> Result<String, git2::Error> {
+        Ok(self.open_repo()?.branch_list()?.to_string())
+    }
+
+    fn current_branch_(&self) -> Result<String, git2::Error> {
+        let repo = self.open_repo()?;
+        let current_branch = repo.current_branch()?;
+        let local = current_branch.local_name();
+        let upstream = match current_branch.upstream {
+            Upstream::Configured(name) => name,
+            Upstream::Gone(name) => format!("[upstream {name} is gone]"),
+            Upstream::None => "[No upstream branch tracked]".to_string(),
+        };
+
+        Ok(format!("{local}:{upstream}"))
+    }
+
+    fn status_(&self) -> Result<String, git2::Error> {
+        Ok(self.open_repo()?.status()?.to_string())
+    }
+
+    fn status_to_file_(&self, path: &str, format: &str) -> Result<String, git2::Error> {
+        let format = format.parse()?;
+        let summary = self.open_repo()?.status()?;
+        let written = report::write_status(&summary, format, create_report(path)?)?;
+        Ok(written.to_string())
+    }
+
+    fn diff_to_file_(
+        &self,
+        path: &str,
+        format: &str,
+        from: &str,
+        to: &str,
+    ) -> Result<String, git2::Error> {
+        let format = format.parse()?;
+        let repo = self.open_repo()?;
+        let written =
+            repo.write_diff(non_empty(from), non_empty(to), format, create_report(path)?)?;
+        Ok(written.to_string())
+    }
+
+    fn add_all_(&self) -> Result<String, git2::Error> {
+        let staged = self.open_repo()?.add_all()?;
+        Ok(std::iter::once(format!("{} files added", staged.len()))
+            .chain(staged)
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    fn stage_lines_(&self, path: &str, ranges: &str) -> Result<String, git2::Error> {
+        let ranges = ranges
+            .split(',')
+            .map(|range| {
+                let range = range.trim();
+                let (start, end) = range.split_once('-').unwrap_or((range, range));
+                match (start.trim().parse(), end.trim().parse()) {
+                    (Ok(start), Ok(end)) => Ok((start, end)),
+                    _ => Err(git2::Error::from_str(&format!("invalid line range '{range}'"))),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let staged = self.open_repo()?.stage_lines(path, &ranges)?;
+        Ok(format!("{staged} hunks staged"))
+    }
+
+    fn commit_(&self, message: &str) -> Result<String, git2::Error> {
+        let repo = self.open_repo()?;
+        let warnings = lock_warnings(&repo)?;
+        let oid = repo.commit(message)?;
+        Ok(format!("{oid}{warnings}"))
+    }
+
+    /// The id of the signed-off commit, followed by its [`lock_warnings`].
+    fn commit_signed_off_(&self, message: &str) -> Result<String, git2::Error> {
+        let repo = self.open_repo()?;
+        let warnings = lock_warnings(&repo)?;
+        let oid = repo.commit_signed_off(message)?;
+        Ok(format!("{oid}{warnings}"))
+    }
+
+    fn add_and_amend_(&self, paths: &str) -> Result<String, git2::Error> {
+        let paths: Vec<&str> =
+            paths.lines().map(str::trim).filter(|path| !path.is_empty()).collect();
+        let repo = self.open_repo()?;
+        let oid = repo.add_and_amend(&paths)?;
+        let warnings = lock_warnings(&repo)?;
+        Ok(format!("{oid}{warnings}"))
+    }
+
+    /// `coauthors` holds one `Name <email>` per line.
+    fn commit_with_coauthors_(
+        &self,
+        message: &str,
+        coauthors: &str,
+    ) -> Result<String, git2::Error> {
+        let coauthors = coauthors
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let (name, email) = line
+                    .trim()
+                    .strip_suffix('>')
+                    .and_then(|line| line.split_once('<'))
+                    .ok_or_else(|| {
+                        git2::Error::from_str(&format!("co-author '{line}' is not 'Name <email>'"))
+                    })?;
+                Ok((name.trim().to_string(), email.trim().to_string()))
+            })
+            .collect::<Result<Vec<_>, git2::Error>>()?;
+
+        let repo = self.open_repo()?;
+        let warnings = lock_warnings(&repo)?;
+        let oid = repo.commit_with_coauthors(message, &coauthors)?;
+        Ok(format!("{oid}{warnings}"))
+    }
+
+    fn checkout_(&self, branch_name: &str) -> Result<(), git2::Error> {
+        self.open_repo()?.checkout(branch_name)
+    }
+
+    fn checkout_dry_run_(&self, branch_name: &str) -> Result<String, git2::Error> {
+        let conflicts = self.open_repo()?.checkout_dry_run(branch_name)?;
+        Ok(conflicts.join("\n"))
+    }
+
+    /// Empty `from`/`to` stand for the working tree sides, as in [`Repo::diff_names`].
+    fn diff_names_(&self, from: &str, to: &str) -> Result<String, git2::Error> {
+        let names = self.open_repo()?.diff_names(non_empty(from), non_empty(to))?;
+        Ok(names.join("\n"))
+    }
+
+    fn push_(&self) -> Result<PushReport, git2::Error> {
+        self.open_repo()?.push()
+    }
+
+    fn pull_(&self, branch_name: &str) -> Result<String, git2::Error> {
+        let repo = self.open_repo()?;
+        let res = match repo.pull(branch_name)? {
+            PullResult::FastForwarded {
+                old_id,
+                new_id,
+                files,
+                truncated,
+            } => {
+                let mut res = format!(
+                    "Successfully pulled branch '{branch_name}', {} -> {}",
+                    repo.short_id(old_id)?,
+                    repo.short_id(new_id)?
+                );
+                for file in files.iter().take(PULL_FILES_SHOWN) {
+                    let _ = write!(res, "\n{}: {}", file.change, file.path);
+                }
+                if truncated || files.len() > PULL_FILES_SHOWN {
+                    res.push_str("\n...");
+                }
+                res
+            },
+            PullResult::UpToDate => format!("Branch '{branch_name}' already up to date"),
+            PullResult::None => format!("No branch '{branch_name} merge possible"),
+            PullResult::Normal =>
+                format!("Local and remote '{branch_name}' diverged. Make a merge"),
+            PullResult::Unborn =>
+                format!("HEAD of '{branch_name}' doesn't point to a valid commit"),
+            PullResult::UpstreamGone { upstream } => format!(
+                "Upstream {upstream} of '{branch_name}' is gone. Push the branch to track it \
+                 again or delete it with DeleteStaleBranches"
+            ),
+        };
+        Ok(res)
+    }
+
+    fn file_authors_(&self, path: &str) -> Result<String, git2::Error> {
+        let authors = self.open_repo()?.file_authors(path)?;
+        let res = authors
+            .iter()
+            .enumerate()
+            .map(|(place, (name, lines))| format!("{}. {name}: {lines}", place + 1))
+            .collect::<Vec<_>>()
+            .join("\n");
+        Ok(res)
+    }
+
+    fn blame_line_(
+        &self,
+        path: &str,
+        line: i32,
+        ignore_whitespace: bool,
+    ) -> Result<String, git2::Error> {
+        let line = u32::try_from(line).unwrap_or_default();
+        let blame = self.open_repo()?.blame_line(path, line, ignore_whitespace)?;
+        let res = json::Object::new()
+            .raw("line", blame.line)
+            .str("commit", &blame.commit.id.to_string())
+            .str("short_id", &blame.commit.short_id)
+            .str("author", &blame.commit.author)
+            .str("email", &blame.commit.email)
+            .raw("time", blame.commit.time)
+            .str("summary", &blame.commit.summary)
+            .raw("original_line", blame.original_line)
+            .to_string();
+        Ok(res)
+    }
+
+    fn parents_(&self, revspec: &str) -> Result<String, git2::Error> {
+        let parents = self.open_repo()?.parents(revspec)?;
+        Ok(parents.iter().map(Oid::to_string).collect::<Vec<_>>().join("\n"))
+    }
+
+    fn preflight_(&self, operation: &str) -> Result<String, git2::Error> {
+        let issues = self.open_repo()?.preflight(operation.parse()?);
+        let res = json::array(issues.iter().map(|issue| {
+            json::Object::new()
+                .bool("blocking", issue.blocking)
+                .str("code", issue.code)
+                .str("message", &issue.message)
+        }));
+        Ok(res)
+    }
+
+    fn health_check_(&self) -> Result<String, git2::Error> {
+        let issues = self.open_repo()?.health_check();
+        let res = json::array(issues.iter().map(|issue| {
+            json::Object::new()
+                .str("severity", &issue.severity.to_string())
+                .str("code", issue.code)
+                .str("description", &issue.description)
+                .str("suggested_fix", &issue.suggested_fix)
+        }));
+        Ok(res)
+    }
+
+    fn locks_(&self) -> Result<String, git2::Error> {
+        let locks = self.open_repo()?.list_locks()?;
+        let res = json::array(locks.iter().map(|lock| {
+            json::Object::new()
+                .str("path", &lock.path)
+                .str("owner", &lock.owner)
+                .raw("locked_at", lock.locked_at)
+        }));
+        Ok(res)
+    }
+
+    fn prune_(&self, remote: &str) -> Result<String, git2::Error> {
+        let pruned = self.open_repo()?.prune(non_empty(remote).unwrap_or("origin"))?;
+        if pruned.is_empty() {
+            return Ok("nothing to prune".to_string());
+        }
+        Ok(format!("pruned:\n{}", pruned.join("\n")))
+    }
+
+    fn branch_tip_(&self, name: &str, branch_type: &str) -> Result<String, git2::Error> {
+        let branch_type = match non_empty(branch_type) {
+            None | Some("Local") => git2::BranchType::Local,
+            Some("Remote") => git2::BranchType::Remote,
+            Some(other) => {
+                return Err(git2::Error::from_str(&format!(
+                    "unknown branch type '{other}', expected Local or Remote"
+                )));
+            },
+        };
+        Ok(self.open_repo()?.branch_tip(name, branch_type)?.to_string())
+    }
+
+    fn stale_branches_(&self) -> Result<String, git2::Error> {
+        let repo = self.open_repo()?;
+        let _pruned = repo.prune_report()?;
+        Ok(repo.stale_local_branches()?.join("\n"))
+    }
+
+    fn verify_commits_(&self, range: &str) -> Result<String, git2::Error> {
+        let policy = self.config.verify_policy.clone().unwrap_or_default();
+        let results = self.open_repo()?.verify_commits(range, &policy)?;
+
+        let res = json::array(results.iter().map(|result| {
+            json::Object::new()
+                .str("commit", &result.commit.to_string())
+                .str("summary", &result.summary)
+                .bool("valid", result.is_valid())
+                .str("status", &result.status.to_string())
+        }));
+        Ok(res)
+    }
+
+    /// Runs `output` for an empty `cursor` and returns its first chunk, otherwise continues
+    /// the output the cursor belongs to.
+    fn paged(&self, cursor: &str, output: impl FnOnce() -> String) -> (String, String) {
+        match non_empty(cursor) {
+            None => self.chunks.start(output()),
+            Some(cursor) => self.chunks.next(cursor).unwrap_or_else(|e| (e, String::new())),
+        }
+    }
+
+    fn incoming_(&self, branch: &str) -> Result<String, git2::Error> {
+        let repo = self.open_repo()?;
+        let commits = repo.incoming(&branch_or_current(&repo, branch)?)?;
+        Ok(json::array(commits.iter().map(commit_json)))
+    }
+
+    fn outgoing_(&self, branch: &str) -> Result<String, git2::Error> {
+        let repo = self.open_repo()?;
+        let commits = repo.outgoing(&branch_or_current(&repo, branch)?)?;
+        Ok(json::array(commits.iter().map(commit_json)))
+    }
+
+    fn compare_branches_(&self, base: &str, head: &str) -> Result<String, git2::Error> {
+        let compare = self.open_repo()?.compare_refs(base, head)?;
+
+        let files = json::array(compare.files.iter().map(|file| {
+            json::Object::new()
+                .str("path", &file.path)
+                .opt_str("old_path", file.old_path.as_deref())
+                .str("change", &file.change.to_string())
+                .raw("insertions", file.insertions)
+                .raw("deletions", file.deletions)
+        }));
+        let res = json::Object::new()
+            .raw("ahead", compare.ahead)
+            .raw("behind", compare.behind)
+            .opt_str("merge_base", compare.merge_base.map(|id| id.to_string()).as_deref())
+            .raw("files", files);
+        Ok(res.to_string())
+    }
+
+    fn conflicts_(&self) -> Result<String, git2::Error> {
+        let conflicts = self.open_repo()?.conflicts()?;
+        let id = |id: Option<git2::Oid>| id.map(|id| id.to_string());
+
+        let res = json::array(conflicts.iter().map(|conflict| {
+            json::Object::new()
+                .str("path", &conflict.path)
+                .opt_str("base", id(conflict.base).as_deref())
+                .opt_str("ours", id(conflict.ours).as_deref())
+                .opt_str("theirs", id(conflict.theirs).as_deref())
+        }));
+        Ok(res)
+    }
+
+    fn file_attributes_(&self, path: &str) -> Result<String, git2::Error> {
+        let attributes = self.open_repo()?.file_attributes(path)?;
+        Ok(attributes
+            .iter()
+            .map(|(name, value)| format!("{name}: {value}"))
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    fn branches_detailed_(&self) -> Result<String, git2::Error> {
+        let branches = self.open_repo()?.branches_with_last_commit()?;
+        Ok(json::array(branches.iter().map(|branch| {
+            json::Object::new()
+                .str("name", &branch.name)
+                .bool("current", branch.is_head)
+                .bool("remote_only", branch.remote_only)
+                .raw(
+                    "remotes",
+                    json::array(branch.remote_names.iter().map(|name| json::string(name))),
+                )
+                .raw("last_commit", commit_json(&branch.last_commit))
+        })))
+    }
+
+    fn head_ref_(&self) -> Result<String, git2::Error> {
+        let repo = self.open_repo()?;
+        let head = repo.head_ref()?;
+        let short_id = head.oid.map(|oid| repo.short_id(oid)).transpose()?;
+        Ok(json::Object::new()
+            .opt_str("full_name", head.full_name.as_deref())
+            .opt_str("oid", head.oid.map(|oid| oid.to_string()).as_deref())
+            .opt_str("short_id", short_id.as_deref())
+            .bool("detached", head.detached)
+            .to_string())
+    }
+
+    fn bisect_start_(&self, good: &str, bad: &str) -> Result<String, git2::Error> {
+        let repo = self.open_repo()?;
+        let step = repo.bisect_start(good, bad)?;
+        bisect_step_json(&repo, step)
+    }
+
+    fn bisect_mark_(&self, commit: &str, good: bool) -> Result<String, git2::Error> {
+        let repo = self.open_repo()?;
+        let commit = repo.resolve_commit(non_empty(commit).unwrap_or("HEAD"))?;
+        let step = repo.bisect_mark(commit, good)?;
+        bisect_step_json(&repo, step)
+    }
+
+    fn discard_changes_(&self, paths: &str) -> Result<(), git2::Error> {
+        let paths: Vec<&str> =
+            paths.lines().map(str::trim).filter(|path| !path.is_empty()).collect();
+        self.open_repo()?.discard_changes(&paths)
+    }
+
+    fn begin_commit_(&self, message: &str) -> Result<String, git2::Error> {
+        let prepared = self.open_repo()?.prepare_commit(message)?;
+        let files = json::array(prepared.files.iter().map(|file| json::string(file)));
+        let token = self.prepared_commits.insert(self.config.path.clone(), prepared);
+        Ok(json::Object::new().str("token", &token).raw("files", files).to_string())
+    }
+
+    fn confirm_commit_(&self, token: &str) -> Result<String, git2::Error> {
+        let (catalog, prepared) =
+            self.prepared_commits.take(token).map_err(|e| git2::Error::from_str(&e))?;
+        if catalog != self.config.path {
+            return Err(git2::Error::from_str(&format!(
+                "commit '{token}' was begun in '{}'",
+                catalog.display()
+            )));
+        }
+        let repo = self.open_repo()?;
+        let warnings = lock_warnings(&repo)?;
+        let oid = repo.finish_commit(&prepared)?;
+        Ok(format!("{oid}{warnings}"))
+    }
+
+    fn checkout_tag_(&self, tag: &str) -> Result<String, git2::Error> {
+        let repo = self.open_repo()?;
+        let commit = repo.checkout_tag(tag)?;
+        Ok(format!("HEAD is now at {} ({tag})", repo.short_id(commit)?))
+    }
+
+    fn checkout_paths_(&self, revspec: &str, paths: &str) -> Result<usize, git2::Error> {
+        let paths: Vec<&str> =
+            paths.lines().map(str::trim).filter(|path| !path.is_empty()).collect();
+        self.open_repo()?.checkout_paths(revspec, &paths)
+    }
+
+    fn set_sparse_checkout_(&self, directories: &str) -> Result<(usize, Vec<String>), git2::Error> {
+        let directories = directories
+            .lines()
+            .map(str::trim)
+            .filter(|directory| !directory.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let kept = self.open_repo()?.set_sparse_checkout(&directories)?;
+        Ok((directories.len(), kept))
+    }
+
+    fn stash_save_paths_(&self, message: &str, paths: &str) -> Result<Oid, git2::Error> {
+        let paths: Vec<&str> =
+            paths.lines().map(str::trim).filter(|path| !path.is_empty()).collect();
+        self.open_repo()?.stash_save_paths(non_empty(message), &paths)
+    }
+
+    fn push_to_(&self, local: &str, remote_branch: &str, remote: &str) -> Result<(), git2::Error> {
+        let repo = self.open_repo()?;
+        let local = branch_or_current(&repo, local)?;
+        repo.push_to(&local, remote_branch, non_empty(remote))
+    }
+
+    fn push_refs_(
+        &self,
+        remote: &str,
+        refspecs: &str,
+        atomicity: PushAtomicity,
+    ) -> Result<Vec<String>, git2::Error> {
+        let refspecs =
+            refspecs.lines().map(str::trim).filter(|p| !p.is_empty()).collect::<Vec<_>>();
+        let remote = non_empty(remote).unwrap_or("origin");
+        self.open_repo()?.push_refspecs(remote, &refspecs, atomicity)
+    }
+
+    fn set_push_remote_(&self, branch: &str, remote: &str) -> Result<String, git2::Error> {
+        let repo = self.open_repo()?;
+        let branch = branch_or_current(&repo, branch)?;
+        repo.set_push_remote(&branch, remote)?;
+        Ok(branch)
+    }
+
+    fn file_log_(&self, path: &str, max: i32) -> Result<String, git2::Error> {
+        let max = usize::try_from(max).ok().filter(|max| *max > 0);
+        let commits = self.open_repo()?.file_log(path, max)?;
+        Ok(json::array(commits.iter().map(commit_json)))
+    }
+
+    fn resolve_rev_(&self, text: &str) -> Result<String, git2::Error> {
+        let repo = self.open_repo()?;
+        let rev = repo.resolve(text.trim())?;
+        Ok(json::Object::new()
+            .str("id", &rev.id.to_string())
+            .str("short_id", &repo.short_id(rev.id)?)
+            .str("kind", &rev.kind.to_string())
+            .opt_str("ref_name", rev.ref_name.as_deref())
+            .opt_str("summary", rev.summary.as_deref())
+            .to_string())
+    }
+
+    fn fetch_all_(&self) -> Result<String, git2::Error> {
+        let report = self.open_repo()?.fetch_all_report()?;
+        if report.is_empty() {
+            return Ok("no remotes configured".to_string());
+        }
+        let lines = report.iter().map(|(remote, result)| match result {
+            Ok(stats) => format!(
+                "{remote}: ok, {} objects, {} refs updated, {} pruned",
+                stats.received_objects,
+                stats.updated.len(),
+                stats.pruned.len()
+            ),
+            Err(e) => format!("{remote}: failed ({})", error_text(e)),
+        });
+        Ok(lines.collect::<Vec<_>>().join("\n"))
+    }
+
+    fn create_orphan_branch_(
+        &self,
+        name: &str,
+        message: &str,
+        files: &str,
+        checkout: bool,
+    ) -> Result<Oid, git2::Error> {
+        let files = json::parse(files).map_err(|e| git2::Error::from_str(&e))?;
+        let json::Value::Object(files) = files else {
+            return Err(git2::Error::from_str("files must be a JSON object"));
+        };
+        let files = files
+            .iter()
+            .map(|(path, contents)| match contents {
+                json::Value::String(contents) => Ok((path.as_str(), contents.as_bytes())),
+                _ => Err(git2::Error::from_str(&format!("contents of '{path}' must be a string"))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.open_repo()?.branch_create_orphan(name, message, &files, checkout)
+    }
+
+    fn ls_remote_(&self, remote: &str, branch: &str) -> Result<Oid, git2::Error> {
+        let repo = self.open_repo()?;
+        let branch = branch_or_current(&repo, branch)?;
+        repo.ls_remote_head(non_empty(remote).unwrap_or("origin"), &branch)
+    }
+
+    fn delete_stale_branches_(&self, dry_run: bool) -> Result<String, git2::Error> {
+        Ok(self.open_repo()?.delete_stale_branches(dry_run)?.join("\n"))
+    }
+
+    /// The id of the repository at `new_path`, see [`Git::relocate`].
+    fn relocated_id(&self, new_path: &str) -> Result<Option<Oid>, git2::Error> {
+        let config = Config {
+            path: new_path.into(),
+            ..self.config.clone()
+        };
+        let repo = match self.known_repository_id() {
+            Some(expected_id) =>
+                Repo::relocate(&self.config, Path::new(new_path), Some(expected_id))?,
+            None => Repo::open(&config)?,
+        };
+        repo.repository_id()
+    }
+
+    #[allow(clippy::unnecessary_wraps, clippy::unused_self)]
+    fn merge_(&self) -> Result<(), git2::Error> {
+        Ok(())
+    }
+
+    /// `error` as returned to 1C, counting the call as failed in the metrics.
+    fn failed(&self, error: String) -> String {
+        self.metrics.fail();
+        error
+    }
+
+    /// A catalog that opened before and is now missing fails with a [`RepositoryMoved`]
+    /// naming where the repository may have gone.
+    fn open_repo(&self) -> Result<Repo<'_>, git2::Error> {
+        self.open_repo_with(&self.config)
+    }
+
+    /// [`Git::open_repo`] with `config`, the settings of the component changed for a call.
+    fn open_repo_with<'c>(&self, config: &'c Config) -> Result<Repo<'c>, git2::Error> {
+        match Repo::open(config) {
+            Ok(repo) => {
+                self.remember_repository(&repo);
+                Ok(repo)
+            },
+            Err(e) if e.code() == ErrorCode::NotFound => match self.known_repository_id() {
+                Some(id) => Err(RepositoryMoved::search(&self.config.path, Some(id)).into()),
+                None => Err(e),
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The settings with `context_lines` of context in diffs, none when negative.
+    fn with_context_lines(&self, context_lines: i32) -> Config {
+        Config {
+            diff_context_lines: u32::try_from(context_lines).unwrap_or_default(),
+            ..self.config.clone()
+        }
+    }
+
+    fn remember_repository(&self, repo: &Repo) {
+        let mut known = self.known_repository.lock().unwrap_or_else(PoisonError::into_inner);
+        if known.as_ref().is_none_or(|(path, _)| *path != self.config.path)
+            && let Ok(Some(id)) = repo.repository_id()
+        {
+            *known = Some((self.config.path.clone(), id));
+        }
+    }
+
+    /// The id remembered for the current catalog, `None` when it never opened.
+    fn known_repository_id(&self) -> Option<Oid> {
+        let known = self.known_repository.lock().unwrap_or_else(PoisonError::into_inner);
+        known.as_ref().filter(|(path, _)| *path == self.config.path).map(|(_, id)| *id)
+    }
+}
+
+/// Lines warning about staged files someone else holds a lock on, each starting with a newline.
+fn lock_warnings(repo: &Repo) -> Result<String, git2::Error> {
+    let locks = repo.staged_locks()?;
+    Ok(locks.iter().fold(String::new(), |mut res, lock| {
+        let _ = write!(res, "\nwarning: '{}' is locked by {}", lock.path, lock.owner);
+        res
+    }))
+}
+
+/// Error text of a failed operation, pointing at the properties to set when a remote wants
+/// credentials and none are configured.
+pub fn error_text(error: &git2::Error) -> String {
+    match CredentialsRequired::from_error(error) {
+        Some(required) => format!(
+            "'{}' requires authentication: set the Login and Password or SshKey properties \
+             (accepted: {})",
+            required.url,
+            required.accepted_methods.join(", ")
+        ),
+        None => error.to_string(),
+    }
+}
+
+/// 1C passes an empty string for an omitted optional argument.
+fn non_empty(value: &str) -> Option<&str> {
+    Some(value).filter(|value| !value.is_empty())
+}
+
+fn branch_or_current(repo: &Repo, branch: &str) -> Result<String, git2::Error> {
+    match non_empty(branch) {
+        Some(branch) => Ok(branch.to_string()),
+        None => Ok(repo.current_branch()?.local_name()),
+    }
+}
+
+fn commit_json(commit: &CommitInfo) -> json::Object {
+    json::Object::new()
+        .str("id", &commit.id.to_string())
+        .str("short_id", &commit.short_id)
+        .raw("parents", json::array(commit.parents.iter().map(|id| json::string(&id.to_string()))))
+        .str("author", &commit.author)
+        .str("email", &commit.email)
+        .raw("time", commit.time)
+        .str("summary", &commit.summary)
+        .str("message", &commit.message)
+}
+
+fn bisect_step_json(repo: &Repo, step: BisectStep) -> Result<String, git2::Error> {
+    let (found, commit, remaining) = match step {
+        BisectStep::Next { commit, remaining } => (false, commit, remaining),
+        BisectStep::Found(commit) => (true, commit, 1),
+    };
+    let res = json::Object::new()
+        .bool("found", found)
+        .str("commit", &commit.to_string())
+        .str("short_id", &repo.short_id(commit)?)
+        .raw("remaining", remaining)
+        .to_string();
+    Ok(res)
+}
+
+fn create_report(path: &str) -> Result<File, git2::Error> {
+    File::create(path)
+        .map_err(|e| git2::Error::from_str(&format!("failed to create report '{path}': {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::Arc,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use git_core::{
+        CredentialKind,
+        CredentialProvider,
+        Repo,
+        json,
+        testing::{SimulatedRemote, Workspace},
+    };
+    use git2::ErrorCode;
+
+    use super::{AddinCredentials, Git};
+    use crate::events::Events;
+
+    const URL: &str = "https://example.com/repo.git";
+
+    /// Credentials whose events 1C takes on another thread than the operations'.
+    fn credentials(timeout: Duration) -> Arc<AddinCredentials> {
+        Arc::new(AddinCredentials {
+            timeout,
+            ..AddinCredentials::new(Events::recording().0)
+        })
+    }
+
+    /// Calls `request` on another thread, like an operation running asynchronously.
+    fn request_async(
+        credentials: &Arc<AddinCredentials>,
+    ) -> thread::JoinHandle<Result<String, git2::Error>> {
+        let operation = credentials.clone();
+        let handle = thread::spawn(move || operation.request(URL, CredentialKind::Password));
+        while credentials.pending().is_empty() {
+            thread::yield_now();
+        }
+        handle
+    }
+
+    fn assert_repeat_after_answer(result: Result<String, git2::Error>) {
+        let e = result.expect_err("nothing was provided");
+        assert_eq!(e.code(), ErrorCode::Auth, "{e}");
+        assert!(e.message().ends_with(", repeat the call after ProvideCredential"), "{e}");
+    }
+
+    #[test]
+    fn the_event_names_the_url_and_kind() {
+        let (events, recorded) = Events::recording();
+        let credentials = AddinCredentials {
+            timeout: Duration::ZERO,
+            ..AddinCredentials::new(events)
+        };
+
+        assert_repeat_after_answer(credentials.request(URL, CredentialKind::SshPassphrase));
+
+        assert_eq!(
+            *recorded.lock().unwrap(),
+            [format!("CredentialRequired: {URL}\nssh key passphrase")]
+        );
+    }
+
+    #[test]
+    fn a_secret_answers_only_the_url_and_kind_requested() {
+        let credentials = credentials(Duration::ZERO);
+        assert_repeat_after_answer(credentials.request(URL, CredentialKind::Password));
+        assert_eq!(credentials.pending(), format!("{URL}\npassword"));
+
+        credentials.provide("secret".to_string());
+        assert_eq!(credentials.pending(), "");
+        assert_repeat_after_answer(
+            credentials.request("https://other.example/repo.git", CredentialKind::Password),
+        );
+        assert_repeat_after_answer(credentials.request(URL, CredentialKind::SshPassphrase));
+
+        assert_eq!(credentials.request(URL, CredentialKind::Password).unwrap(), "secret");
+    }
+
+    #[test]
+    fn a_waiting_operation_gets_the_secret_provided_meanwhile() {
+        let credentials = credentials(Duration::from_mins(1));
+        let operation = request_async(&credentials);
+
+        credentials.provide("secret".to_string());
+
+        assert_eq!(operation.join().unwrap().unwrap(), "secret");
+        assert_eq!(credentials.pending(), "");
+        // used up by the operation, not kept for another
+        assert_eq!(credentials.state().provided, None);
+    }
+
+    #[test]
+    fn cancel_aborts_the_waiting_operation() {
+        let credentials = credentials(Duration::from_mins(1));
+        let started = Instant::now();
+        let operation = request_async(&credentials);
+
+        credentials.cancel();
+
+        let e = operation.join().unwrap().expect_err("cancelled");
+        assert_eq!(e.code(), ErrorCode::User, "{e}");
+        assert!(started.elapsed() < Duration::from_secs(30));
+        assert_eq!(credentials.pending(), "");
+    }
+
+    #[test]
+    fn a_timed_out_request_stays_for_the_repeated_call() {
+        let credentials = credentials(Duration::from_millis(10));
+
+        assert_repeat_after_answer(credentials.request(URL, CredentialKind::Password));
+
+        assert_eq!(credentials.pending(), format!("{URL}\npassword"));
+        credentials.provide("secret".to_string());
+        assert_eq!(credentials.request(URL, CredentialKind::Password).unwrap(), "secret");
+    }
+
+    #[test]
+    fn without_a_connection_nothing_is_waited_for() {
+        let credentials = Arc::new(AddinCredentials::new(Events::default()));
+        let started = Instant::now();
+
+        assert_repeat_after_answer(credentials.request(URL, CredentialKind::Password));
+
+        assert!(started.elapsed() < Duration::from_secs(30));
+        // a form polling CredentialRequest still answers the repeated call
+        assert_eq!(credentials.pending(), format!("{URL}\npassword"));
+        credentials.provide("secret".to_string());
+        assert_eq!(credentials.request(URL, CredentialKind::Password).unwrap(), "secret");
+    }
+
+    /// A component working in the catalog of `local`.
+    fn git_in(local: &Workspace) -> Git {
+        let mut git = Git::default();
+        git.config.path = local.config.path.clone();
+        git.config.username = local.config.username.clone();
+        git.config.email = local.config.email.clone();
+        git
+    }
+
+    /// Reads a paged output to the end, `paged` taking the cursor.
+    fn read_paged(paged: impl Fn(&str) -> (String, String)) -> String {
+        let (mut output, mut cursor) = paged("");
+        while !cursor.is_empty() {
+            let (chunk, next) = paged(&cursor);
+            output.push_str(&chunk);
+            cursor = next;
+        }
+        output
+    }
+
+    #[test]
+    fn paged_outputs_reassemble_byte_for_byte() -> Result<(), git2::Error> {
+        let remote = SimulatedRemote::new("main")?;
+        remote.push_commit("main", "Справочник.xml", "<Имя>Товары</Имя>\n", "справочник")?;
+        remote.push_commit("feature", "f.txt", "🌿\n", "feature work")?;
+        let local = remote.clone_workspace()?;
+        remote.push_commit("main", "Документ.xml", "<Имя>Заказ</Имя>\n", "документ")?;
+        let mut git = git_in(&local);
+        git.chunks.max_bytes = 7;
+
+        let diff = git.diff("HEAD~1", "HEAD", 3);
+        assert!(diff.contains("Товары"), "{diff}");
+        assert_eq!(read_paged(|cursor| git.diff_paged("HEAD~1", "HEAD", 3, cursor)), diff);
+
+        let incoming = git.incoming("main");
+        assert!(incoming.contains("документ"), "{incoming}");
+        assert_eq!(read_paged(|cursor| git.incoming_paged("main", cursor)), incoming);
+
+        let branches = git.branches();
+        assert!(branches.contains("feature"), "{branches}");
+        assert_eq!(read_paged(|cursor| git.branches_paged(cursor)), branches);
+        Ok(())
+    }
+
+    /// The token of a `BeginCommit` result.
+    fn token(begun: &str) -> String {
+        let begun = json::parse(begun).unwrap();
+        begun.get("token").and_then(json::Value::as_str).unwrap().to_string()
+    }
+
+    #[test]
+    fn begun_commits_are_confirmed_or_aborted_once() -> Result<(), git2::Error> {
+        let remote = SimulatedRemote::new("main")?;
+        let local = remote.clone_workspace()?;
+        let git = git_in(&local);
+        std::fs::write(local.config.path.join("a.txt"), "a\n").unwrap();
+        Repo::open(&git.config)?.add(["a.txt"])?;
+
+        let begun = git.begin_commit("add a");
+        assert!(begun.contains(r#""files":["a.txt"]"#), "{begun}");
+        let aborted = token(&begun);
+        assert_eq!(git.abort_commit(&aborted), "commit aborted");
+        assert!(git.confirm_commit(&aborted).contains("no commit"));
+
+        let confirmed = token(&git.begin_commit("add a"));
+        let commit = git.confirm_commit(&confirmed);
+        let repo = Repo::open(&git.config)?;
+        assert_eq!(repo.resolve_commit("HEAD")?.to_string(), commit);
+        assert!(git.confirm_commit(&confirmed).contains("no commit"));
+        assert!(git.abort_commit(&confirmed).contains("no commit"));
+        Ok(())
+    }
+
+    #[test]
+    fn commits_are_authored_by_the_active_profile() -> Result<(), git2::Error> {
+        let remote = SimulatedRemote::new("main")?;
+        let local = remote.clone_workspace()?;
+        let mut git = git_in(&local);
+        for name in ["alice", "bob"] {
+            let profile = format!(r#"{{"username":"{name}","email":"{name}@example.com"}}"#);
+            assert_eq!(git.create_profile(name, &profile), "profile saved");
+        }
+        let head_author = || -> Result<String, git2::Error> {
+            let repo = git2::Repository::open(&local.config.path)?;
+            let commit = repo.head()?.peel_to_commit()?;
+            let author = commit.author();
+            Ok(format!("{} <{}>", author.name().unwrap(), author.email().unwrap()))
+        };
+        let commit_as = |git: &mut Git, name: &str| -> Result<String, git2::Error> {
+            assert_eq!(git.use_profile(name), format!("using profile '{name}'"));
+            std::fs::write(local.config.path.join(format!("{name}.txt")), "work\n").unwrap();
+            Repo::open(&git.config)?.add([format!("{name}.txt")])?;
+            git.commit(&format!("work of {name}"));
+            head_author()
+        };
+
+        assert_eq!(commit_as(&mut git, "alice")?, "alice <alice@example.com>");
+        assert_eq!(commit_as(&mut git, "bob")?, "bob <bob@example.com>");
+
+        assert_eq!(git.use_profile("typo"), "no profile 'typo'");
+        assert!(git.profiles().contains(r#""name":"bob","active":true"#), "{}", git.profiles());
+        assert_eq!(git.config.username, "bob");
+        assert_eq!(git.config.email, "bob@example.com");
+        assert_eq!(git.use_profile(""), "profile cleared");
+        assert_eq!(git.config.username, local.config.username);
+        Ok(())
+    }
+
+    #[test]
+    fn a_moved_catalog_is_named_and_relocated() -> Result<(), git2::Error> {
+        let remote = SimulatedRemote::new("main")?;
+        let local = remote.clone_workspace()?;
+        let mut git = git_in(&local);
+        let before = git.status();
+        let moved = local.config.path.with_file_name("moved");
+        std::fs::rename(&local.config.path, &moved).unwrap();
+
+        let missing = git.status();
+        assert!(missing.contains("was moved or deleted"), "{missing}");
+        assert!(missing.contains(&format!("it may now be at '{}'", moved.display())), "{missing}");
+
+        let new_path = moved.to_str().unwrap();
+        assert_eq!(git.relocate(new_path), format!("catalog set to '{new_path}'"));
+        assert_eq!(git.config.path, moved);
+        assert_eq!(git.status(), before);
+        Ok(())
+    }
+
+    #[test]
+    fn messages_show_short_ids_and_data_full_ones() -> Result<(), git2::Error> {
+        let remote = SimulatedRemote::new("main")?;
+        let local = remote.clone_workspace()?;
+        let old = remote.ref_target("refs/heads/main").unwrap();
+        let new = remote.push_commit("main", "Form.xml", "<Form/>\n", "form")?;
+        let git = git_in(&local);
+        Repo::open(&local.config)?.prune_report()?;
+
+        let pulled = git.pull("main");
+        let head = git.head_ref();
+
+        let repo = Repo::open(&local.config)?;
+        let (old_short, new_short) = (repo.short_id(old)?, repo.short_id(new)?);
+        assert!(pulled.contains(&format!("{old_short} -> {new_short}")), "{pulled}");
+        assert!(head.contains(&format!("\"oid\":\"{new}\"")), "{head}");
+        assert!(head.contains(&format!("\"short_id\":\"{new_short}\"")), "{head}");
+        Ok(())
+    }
+
+    #[test]
+    fn confirm_fails_when_the_index_changed_meanwhile() -> Result<(), git2::Error> {
+        let remote = SimulatedRemote::new("main")?;
+        let local = remote.clone_workspace()?;
+        let git = git_in(&local);
+        let repo = Repo::open(&local.config)?;
+        let head = repo.resolve_commit("HEAD")?;
+        std::fs::write(local.config.path.join("a.txt"), "a\n").unwrap();
+        repo.add(["a.txt"])?;
+        let token = token(&git.begin_commit("add a"));
+
+        std::fs::write(local.config.path.join("b.txt"), "b\n").unwrap();
+        repo.add(["b.txt"])?;
+
+        assert!(git.confirm_commit(&token).contains("index changed"));
+        assert_eq!(repo.resolve_commit("HEAD")?, head);
+        Ok(())
+    }
+
+    #[test]
+    fn an_upstream_gone_is_reported_by_branch_status_and_pull() -> Result<(), git2::Error> {
+        let remote = SimulatedRemote::new("main")?;
+        remote.push_commit("feature", "f.txt", "f\n", "feature work")?;
+        let local = remote.clone_workspace()?;
+        let git = git_in(&local);
+        let repo = Repo::open(&local.config)?;
+        repo.checkout("feature")?;
+        assert_eq!(git.current_branch(), "feature:origin/feature");
+
+        remote.delete_branch("feature")?;
+        repo.prune_report()?;
+
+        assert_eq!(git.current_branch(), "feature:[upstream origin/feature is gone]");
+        assert!(git.status().contains("\nupstream origin/feature is gone"));
+        assert!(git.pull("feature").starts_with("Upstream origin/feature of 'feature' is gone"));
+        Ok(())
     }
 }
@@ -9,7 +9,14 @@
 )]
 
 mod addin;
+mod auto_fetch;
+mod chunks;
+mod descriptors;
+mod events;
 mod git;
+mod logging;
+mod metrics;
+mod prepared;
 
 use std::{
     ffi::{c_int, c_long, c_void},
@@ -30,7 +37,7 @@ pub static PLATFORM_CAPABILITIES: AtomicI32 = AtomicI32::new(-1);
 pub unsafe extern "C" fn GetClassObject(name: *const u16, component: *mut *mut c_void) -> c_long {
     match unsafe { *name } as u8 {
         b'1' => {
-            let _res = simple_logging::log_to_file(
+            let _res = logging::log_to_file(
                 "D:\\users\\sdp\\Documents\\log\\git-addin.log",
                 LevelFilter::Debug,
             );
@@ -0,0 +1,105 @@
+//! The log file of the component. Every line goes through [`http::redact`] before it is
+//! written, so the value of an extra HTTP header never ends up there, whatever logs it.
+
+use std::{
+    fs::File,
+    io::{self, Write},
+    mem,
+    path::Path,
+    sync::{Mutex, PoisonError},
+};
+
+use git_core::http;
+use log::LevelFilter;
+
+/// Headers whose values are kept out of the log, including those replaced since.
+static HIDDEN_HEADERS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Logs to the file at `path` from now on, replacing it.
+pub fn log_to_file(path: impl AsRef<Path>, level: LevelFilter) -> io::Result<()> {
+    simple_logging::log_to(Scrubbed::new(File::create(path)?), level);
+    Ok(())
+}
+
+/// Keeps the values of `headers` out of the log.
+pub fn hide_headers(headers: &[String]) {
+    let mut hidden = HIDDEN_HEADERS.lock().unwrap_or_else(PoisonError::into_inner);
+    for header in headers {
+        if !hidden.contains(header) {
+            hidden.push(header.clone());
+        }
+    }
+}
+
+/// Writes whole lines to `inner` with the hidden header values redacted. A value is
+/// caught also when it reaches the writer in pieces.
+pub struct Scrubbed<W: Write> {
+    inner: W,
+    line: Vec<u8>,
+}
+
+impl<W: Write> Scrubbed<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner, line: Vec::new() }
+    }
+
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        let hidden = HIDDEN_HEADERS.lock().unwrap_or_else(PoisonError::into_inner);
+        let line = http::redact(&String::from_utf8_lossy(line), &hidden);
+        self.inner.write_all(line.as_bytes())
+    }
+}
+
+impl<W: Write> Write for Scrubbed<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.line.extend_from_slice(buf);
+        while let Some(end) = self.line.iter().position(|&byte| byte == b'\n') {
+            let line = self.line.drain(..=end).collect::<Vec<_>>();
+            self.write_line(&line)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let line = mem::take(&mut self.line);
+        if !line.is_empty() {
+            self.write_line(&line)?;
+        }
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::{Scrubbed, hide_headers};
+
+    #[test]
+    fn hidden_header_values_never_reach_the_log() {
+        hide_headers(&["Private-Token: glpat-7f3a9c".to_string(), "X-Tenant: acme-42".to_string()]);
+        let mut log = Scrubbed::new(Vec::new());
+
+        writeln!(log, "request failed: Private-Token: glpat-7f3a9c").unwrap();
+        // a value formatted in pieces is still caught
+        log.write_all(b"tenant acme").unwrap();
+        log.write_all(b"-42 rejected\n").unwrap();
+        write!(log, "unfinished glpat-7f3a9c").unwrap();
+        log.flush().unwrap();
+
+        let written = String::from_utf8(log.inner).unwrap();
+        assert_eq!(
+            written,
+            "request failed: Private-Token: ***\ntenant *** rejected\nunfinished ***"
+        );
+    }
+
+    #[test]
+    fn lines_without_secrets_pass_unchanged() {
+        let mut log = Scrubbed::new(Vec::new());
+
+        writeln!(log, "[00:00:01.250] (1a) DEBUG  push() — ветка main").unwrap();
+
+        assert_eq!(log.inner, "[00:00:01.250] (1a) DEBUG  push() — ветка main\n".as_bytes());
+    }
+}
@@ -0,0 +1,191 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{
+        Arc,
+        Mutex,
+        PoisonError,
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use git_core::json;
+
+/// Durations kept per method for the percentile, older ones are dropped.
+const RECENT_SAMPLES: usize = 1024;
+
+/// Call counts and durations of the git operations since the component was loaded.
+#[derive(Default)]
+pub struct Metrics {
+    methods: Mutex<BTreeMap<&'static str, MethodMetrics>>,
+    /// Operations running right now, background work stays out of their way.
+    running: Arc<AtomicUsize>,
+    /// Set by [`Metrics::fail`] during the operation being measured.
+    failed: AtomicBool,
+    /// Counts the resets, an operation that began before the last one isn't recorded.
+    generation: AtomicU64,
+}
+
+#[derive(Default)]
+struct MethodMetrics {
+    calls: u64,
+    errors: u64,
+    total: Duration,
+    min: Duration,
+    max: Duration,
+    recent: VecDeque<Duration>,
+}
+
+impl Metrics {
+    /// Runs `operation` recording its duration under `method`, counting it as an error
+    /// when it fails or calls [`Metrics::fail`]. Operations are measured one at a time.
+    pub fn measure<T, E>(
+        &self,
+        method: &'static str,
+        operation: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        let generation = self.generation.load(Ordering::SeqCst);
+        self.failed.store(false, Ordering::SeqCst);
+        self.running.fetch_add(1, Ordering::SeqCst);
+        let started = Instant::now();
+        let result = operation();
+        let elapsed = started.elapsed();
+        self.running.fetch_sub(1, Ordering::SeqCst);
+        let failed = self.failed.swap(false, Ordering::SeqCst) || result.is_err();
+
+        let mut methods = self.methods.lock().unwrap_or_else(PoisonError::into_inner);
+        if self.generation.load(Ordering::SeqCst) == generation {
+            methods.entry(method).or_default().record(elapsed, failed);
+        }
+
+        result
+    }
+
+    /// Counts the operation being measured as an error, for operations that return their
+    /// error as text.
+    pub fn fail(&self) {
+        self.failed.store(true, Ordering::SeqCst);
+    }
+
+    /// The count of operations running, zero while the component is idle.
+    pub fn running(&self) -> Arc<AtomicUsize> {
+        self.running.clone()
+    }
+
+    /// Forgets the operations recorded, including the one running.
+    pub fn reset(&self) {
+        let mut methods = self.methods.lock().unwrap_or_else(PoisonError::into_inner);
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        methods.clear();
+    }
+
+    /// `{"method": {"calls", "errors", "min_us", "avg_us", "p95_us", "max_us"}, ...}`
+    pub fn to_json(&self) -> String {
+        let methods = self.methods.lock().unwrap_or_else(PoisonError::into_inner);
+
+        methods
+            .iter()
+            .fold(json::Object::new(), |object, (method, metrics)| {
+                object.raw(method, metrics.to_json())
+            })
+            .to_string()
+    }
+}
+
+impl MethodMetrics {
+    fn record(&mut self, elapsed: Duration, failed: bool) {
+        self.min = if self.calls == 0 { elapsed } else { self.min.min(elapsed) };
+        self.max = self.max.max(elapsed);
+        self.calls += 1;
+        self.errors += u64::from(failed);
+        self.total += elapsed;
+
+        if self.recent.len() == RECENT_SAMPLES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(elapsed);
+    }
+
+    fn p95(&self) -> Duration {
+        let mut sorted = self.recent.iter().copied().collect::<Vec<_>>();
+        sorted.sort_unstable();
+        let rank = (sorted.len() * 95).div_ceil(100).saturating_sub(1);
+        sorted.get(rank).copied().unwrap_or_default()
+    }
+
+    fn to_json(&self) -> json::Object {
+        let avg = u32::try_from(self.calls).ok().and_then(|calls| self.total.checked_div(calls));
+
+        json::Object::new()
+            .raw("calls", self.calls)
+            .raw("errors", self.errors)
+            .raw("min_us", self.min.as_micros())
+            .raw("avg_us", avg.unwrap_or_default().as_micros())
+            .raw("p95_us", self.p95().as_micros())
+            .raw("max_us", self.max.as_micros())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use git_core::{json, testing::SimulatedRemote};
+
+    use super::Metrics;
+    use crate::git::Git;
+
+    /// Calls `method` the way the dispatch of the addin does.
+    fn call(git: &Git, name: &'static str, method: impl FnOnce(&Git) -> String) -> String {
+        git.metrics.measure(name, || Ok::<_, Infallible>(method(git))).unwrap()
+    }
+
+    fn number(metrics: &json::Value, key: &str) -> i64 {
+        match metrics.get(key) {
+            Some(json::Value::Number(number)) => *number as i64,
+            other => panic!("{key} is {other:?}"),
+        }
+    }
+
+    #[test]
+    fn calls_are_counted_with_their_durations() {
+        let remote = SimulatedRemote::new("main").unwrap();
+        let workspace = remote.clone_workspace().unwrap();
+        let mut git = Git::default();
+        git.config.path.clone_from(&workspace.config.path);
+
+        for _ in 0..3 {
+            call(&git, "Status", Git::status);
+        }
+        call(&git, "GetBranches", Git::branches);
+        call(&git, "Checkout", |git| git.checkout("no-such-branch"));
+
+        let json = json::parse(&git.metrics.to_json()).unwrap();
+        for (method, calls, errors) in [("Status", 3, 0), ("GetBranches", 1, 0), ("Checkout", 1, 1)]
+        {
+            let metrics = json.get(method).unwrap_or_else(|| panic!("{method} not measured"));
+            assert_eq!(number(metrics, "calls"), calls, "{method}");
+            assert_eq!(number(metrics, "errors"), errors, "{method}");
+
+            let durations =
+                ["min_us", "avg_us", "p95_us", "max_us"].map(|key| number(metrics, key));
+            assert!(durations[0] > 0, "{method}: {durations:?}");
+            assert!(durations.is_sorted(), "{method}: {durations:?}");
+        }
+    }
+
+    #[test]
+    fn reset_forgets_the_call_running() {
+        let metrics = Metrics::default();
+        metrics.measure("Status", || Ok::<_, Infallible>(())).unwrap();
+
+        metrics
+            .measure("ResetMetrics", || {
+                metrics.reset();
+                Ok::<_, Infallible>(())
+            })
+            .unwrap();
+
+        assert_eq!(metrics.to_json(), "{}");
+    }
+}
@@ -0,0 +1,112 @@
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use git_core::PreparedCommit;
+
+/// A prepared commit not confirmed or aborted within this time is dropped.
+const EXPIRY: Duration = Duration::from_mins(10);
+
+/// Commits begun by `BeginCommit` and waiting for 1C to confirm or abort them, by token.
+/// Each remembers the catalog it was prepared in.
+pub struct PreparedCommits {
+    expiry: Duration,
+    pending: Mutex<Pending>,
+}
+
+#[derive(Default)]
+struct Pending {
+    next_id: u64,
+    commits: BTreeMap<u64, (Instant, PathBuf, PreparedCommit)>,
+}
+
+impl Default for PreparedCommits {
+    fn default() -> Self {
+        Self {
+            expiry: EXPIRY,
+            pending: Mutex::default(),
+        }
+    }
+}
+
+impl PreparedCommits {
+    /// Keeps `commit` and returns its token, dropping the expired ones.
+    pub fn insert(&self, catalog: PathBuf, commit: PreparedCommit) -> String {
+        let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        pending.commits.retain(|_, (prepared_at, ..)| prepared_at.elapsed() < self.expiry);
+        let id = pending.next_id;
+        pending.next_id += 1;
+        pending.commits.insert(id, (Instant::now(), catalog, commit));
+        id.to_string()
+    }
+
+    /// Removes the commit of `token`, a token is good for one confirm or abort.
+    pub fn take(&self, token: &str) -> Result<(PathBuf, PreparedCommit), String> {
+        let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        match token.parse().ok().and_then(|id| pending.commits.remove(&id)) {
+            Some((prepared_at, catalog, commit)) if prepared_at.elapsed() < self.expiry =>
+                Ok((catalog, commit)),
+            Some(_) => Err(format!("commit '{token}' expired, begin it again")),
+            None => Err(format!("no commit '{token}' is waiting, it was finished or expired")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use git2::Oid;
+
+    use super::{PreparedCommit, PreparedCommits};
+
+    fn prepared(message: &str) -> PreparedCommit {
+        PreparedCommit {
+            tree: Oid::zero(),
+            parent: None,
+            message: message.to_string(),
+            files: vec!["a.txt".to_string()],
+        }
+    }
+
+    #[test]
+    fn a_token_is_good_for_one_take() {
+        let commits = PreparedCommits::default();
+        let first = commits.insert(PathBuf::from("catalog"), prepared("first"));
+        let second = commits.insert(PathBuf::from("catalog"), prepared("second"));
+        assert_ne!(first, second);
+
+        assert_eq!(commits.take(&second), Ok((PathBuf::from("catalog"), prepared("second"))));
+        assert!(commits.take(&second).unwrap_err().contains("no commit"));
+        assert_eq!(commits.take(&first).map(|(_, commit)| commit), Ok(prepared("first")));
+        assert!(commits.take("garbage").unwrap_err().contains("no commit"));
+    }
+
+    #[test]
+    fn an_expired_token_is_rejected() {
+        let commits = PreparedCommits {
+            expiry: Duration::ZERO,
+            ..PreparedCommits::default()
+        };
+        let token = commits.insert(PathBuf::from("catalog"), prepared("late"));
+
+        assert!(commits.take(&token).unwrap_err().contains("expired"));
+        // and dropped with it
+        assert!(commits.take(&token).unwrap_err().contains("no commit"));
+    }
+
+    #[test]
+    fn expired_commits_are_dropped_on_insert() {
+        let commits = PreparedCommits {
+            expiry: Duration::ZERO,
+            ..PreparedCommits::default()
+        };
+        let stale = commits.insert(PathBuf::from("catalog"), prepared("stale"));
+        commits.insert(PathBuf::from("catalog"), prepared("fresh"));
+
+        assert!(commits.take(&stale).unwrap_err().contains("no commit"));
+    }
+}
@@ -1,77 +1,575 @@
-use std::error::Error;
+mod mirror;
+mod prompt;
+mod watch;
+
+use std::{
+    error::Error,
+    fs::File,
+    io::IsTerminal,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use clap::{Args, Parser, Subcommand};
-use git_core::{AuthType, Config, Repo, StatusSummary, git::branch_name};
+use git_core::{
+    AuthType,
+    BisectStep,
+    Config,
+    CredentialsRequired,
+    InitTemplate,
+    OperationKind,
+    Profile,
+    PullResult,
+    PushAtomicity,
+    Repo,
+    ReportFormat,
+    StatusPerfMode,
+    StatusPolicy,
+    StatusSummary,
+    Upstream,
+    VerificationResult,
+    VerifyPolicy,
+    json,
+    report,
+};
+use prompt::TtyPrompt;
 
 
 fn main() -> Result<(), Box<dyn Error>> {
     let repo_name = std::env::var("REPO_NAME").expect("repo name is not set");
     let repos_dir = std::env::var("REPOS_DIR").expect("repo root path is not set");
 
+    let auth = match std::env::var("SSH_KEY") {
+        Ok(private_key) => AuthType::SshKey {
+            private_key: private_key.into(),
+            passphrase: None,
+        },
+        Err(_) => AuthType::None,
+    };
+
+    let cli = Cli::parse();
     let config = Config {
         username: "RUST".to_string(),
-        auth: AuthType::None,
+        auth,
         email: "rust@rust.rs".to_string(),
         path: format!("{repos_dir}/{repo_name}").into(),
+        credential_provider: Some(Arc::new(TtyPrompt)),
+        ssh_user: std::env::var("SSH_USER").ok(),
+        ..Config::default()
+    };
+    let config = match &cli.profile {
+        Some(name) => load_profile(name)?.apply(&config),
+        None => config,
+    };
+    // not a clap value_parser, its errors would echo the value
+    for header in &cli.headers {
+        git_core::http::validate_header(header)?;
+    }
+    let config = Config {
+        custom_headers: cli.headers,
+        extra_ignore_patterns: cli.ignore,
+        ..config
     };
 
-    match Cli::parse().command {
-        Commands::Clone(CloneArgs { url }) => {
-            let _repo = Repo::clone_from(&url, &config)?;
+    let result = run(cli.command, config);
+    let required = result
+        .as_ref()
+        .err()
+        .and_then(|e| e.downcast_ref::<git2::Error>())
+        .and_then(CredentialsRequired::from_error);
+    if let Some(required) = required {
+        eprintln!(
+            "hint: the remote accepts {}, set SSH_KEY to a private key or use a --profile with \
+             a password or ssh_key",
+            required.accepted_methods.join(", ")
+        );
+    }
+    result
+}
+
+fn run(command: Commands, config: Config) -> Result<(), Box<dyn Error>> {
+    match command {
+        Commands::Clone(CloneArgs { url, mirror }) => {
+            let _repo = if mirror {
+                Repo::clone_mirror(&url, &config)?
+            } else {
+                Repo::clone_from(&url, &config)?
+            };
             config
                 .path
                 .read_dir()?
                 .flatten()
                 .for_each(|file| println!("{}", file.file_name().to_string_lossy()));
         },
+        Commands::Init(InitArgs {
+            template,
+            message,
+            initial_branch,
+        }) => {
+            let template = template.map(|name| {
+                let mut template = InitTemplate::named(&name);
+                if let Some(message) = message {
+                    template.message = message;
+                }
+                template
+            });
+            let config = Config {
+                default_branch: initial_branch.or(config.default_branch),
+                ..config
+            };
+            let _repo = Repo::init(&config, template.as_ref())?;
+            println!("initialized {}", config.path.display());
+        },
         Commands::Add(AddArgs { files }) => {
             let repo = Repo::open(&config).expect("failed to open repository");
-            let _index = repo.add(files)?;
-            println!("files added");
+            let staged = repo.add(files)?;
+            println!("{} files added", staged.len());
+            for path in staged {
+                println!("  {path}");
+            }
+        },
+        Commands::Discard(AddArgs { files }) => {
+            let repo = Repo::open(&config).expect("failed to open repository");
+            let files: Vec<&str> = files.iter().map(String::as_str).collect();
+            repo.discard_changes(&files)?;
+            println!("changes discarded");
         },
-        Commands::Commit(CommitArgs { message }) => {
+        Commands::Commit(CommitArgs { message, encoding, signoff }) => {
+            let config = Config {
+                commit_encoding: encoding.or(config.commit_encoding),
+                ..config
+            };
             let repo = Repo::open(&config).expect("failed to open repository");
-            let oid = repo.commit(&message)?;
-            println!("made commit {oid}");
+            let oid =
+                if signoff { repo.commit_signed_off(&message)? } else { repo.commit(&message)? };
+            println!("made commit {}", repo.short_id(oid)?);
         },
-        Commands::Status => {
+        Commands::Amend(AmendArgs { paths }) => {
             let repo = Repo::open(&config).expect("failed to open repository");
-            let summary = repo.status()?;
-            print_status_summary(&summary);
+            let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+            let oid = repo.add_and_amend(&paths)?;
+            println!("amended commit {}", repo.short_id(oid)?);
+        },
+        Commands::Status(StatusArgs {
+            report: ReportArgs { output, format },
+            ignored,
+            conflicts,
+            no_untracked,
+            mode,
+            watch,
+            interval,
+        }) => {
+            let config = Config {
+                status_perf_mode: mode,
+                ..config
+            };
+            let repo = Repo::open(&config).expect("failed to open repository");
+            let policy = StatusPolicy {
+                include_untracked: !no_untracked,
+                include_ignored: ignored,
+                detect_conflicts: conflicts,
+                ..StatusPolicy::default()
+            };
+            if watch {
+                // Ctrl-C ends the process, the sender lives as long
+                let (_stop, stop) = std::sync::mpsc::channel();
+                let interval = Duration::from_secs_f64(interval.max(0.1));
+                let clear = std::io::stdout().is_terminal();
+                watch::watch(&repo, policy, interval, &stop, |summary| {
+                    if clear {
+                        print!("\x1b[2J\x1b[H");
+                    } else {
+                        println!("----");
+                    }
+                    match summary {
+                        Ok(summary) => print_status_summary(&summary),
+                        Err(e) => println!("{e}"),
+                    }
+                    if let Ok(line) = last_fetched(&repo) {
+                        println!("{line}");
+                    }
+                })?;
+                return Ok(());
+            }
+            let summary = repo.status_with(policy)?;
+            match output {
+                Some(output) => {
+                    let written = report::write_status(&summary, format, File::create(&output)?)?;
+                    println!("{written} bytes written to {}", output.display());
+                },
+                None => {
+                    print_status_summary(&summary);
+                    println!("{}", last_fetched(&repo)?);
+                },
+            }
             // println!("{summary:?}");
         },
         Commands::Branches => {
             let repo = Repo::open(&config).expect("failed to open repository");
-            repo.branches()?.for_each(|(branch, branch_type)| {
-                let branch_name = branch_name(&branch);
-                println!("{:6} -- {branch_name}", format!("{branch_type:?}"))
-            });
+            println!("{}", repo.branch_list()?);
+        },
+        Commands::Branch(BranchArgs {
+            orphan: Some(name),
+            message,
+            files,
+            checkout,
+            ..
+        }) => {
+            let repo = Repo::open(&config).expect("failed to open repository");
+            let contents = files
+                .iter()
+                .map(|path| std::fs::read(path).map(|contents| (path.replace('\\', "/"), contents)))
+                .collect::<Result<Vec<_>, _>>()?;
+            let files = contents
+                .iter()
+                .map(|(path, contents)| (path.as_str(), contents.as_slice()))
+                .collect::<Vec<_>>();
+            let id = repo.branch_create_orphan(
+                &name,
+                message.as_deref().unwrap_or("Initial commit"),
+                &files,
+                checkout,
+            )?;
+            println!("{name} {id}");
+        },
+        Commands::Branch(BranchArgs { delete, .. }) => {
+            let repo = Repo::open(&config).expect("failed to open repository");
+            for pruned in repo.prune_report()? {
+                println!("pruned {pruned}");
+            }
+            if delete {
+                for name in repo.delete_stale_branches(false)? {
+                    println!("deleted {name}");
+                }
+            } else {
+                for name in repo.stale_local_branches()? {
+                    println!("{name}");
+                }
+            }
         },
         Commands::CurrentBranch => {
             let repo = Repo::open(&config).expect("failed to open repository");
             let current_branch = repo.current_branch()?;
 
             let local = current_branch.local_name();
-            let upstream = current_branch
-                .upstream_name()
-                .unwrap_or_else(|| "[No upstream branch tracked]".to_string());
+            let upstream = match current_branch.upstream {
+                Upstream::Configured(name) => name,
+                Upstream::Gone(name) => format!("[upstream {name} is gone]"),
+                Upstream::None => "[No upstream branch tracked]".to_string(),
+            };
 
             println!("{local}:{upstream}");
         },
-        Commands::Checkout(CheckoutArgs { branch_name }) => {
+        Commands::Checkout(CheckoutArgs { branch_name, paths }) if !paths.is_empty() => {
+            let repo = Repo::open(&config).expect("failed to open repository");
+            let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+            let taken = repo.checkout_paths(&branch_name, &paths)?;
+            println!("{taken} files checked out from {branch_name}");
+        },
+        Commands::Checkout(CheckoutArgs { branch_name, .. }) => {
             let repo = Repo::open(&config).expect("failed to open repository");
             let res = repo.checkout(&branch_name);
             println!("{res:?}");
         },
-        Commands::Push => {
+        Commands::Restore(RestoreArgs { source, paths }) => {
             let repo = Repo::open(&config).expect("failed to open repository");
-            let res = repo.push();
-            println!("{res:?}")
+            let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+            for restored in repo.checkout_paths_from(&source, &paths)? {
+                match restored.files {
+                    0 => println!("{}: not found at {source}", restored.path),
+                    files => println!("{}: {files} files checked out", restored.path),
+                }
+            }
         },
-        Commands::Pull(PullArgs { branch_name }) => {
+        Commands::Stash(StashArgs { message, paths }) => {
+            let mut repo = Repo::open(&config).expect("failed to open repository");
+            let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+            let oid = repo.stash_save_paths(message.as_deref(), &paths)?;
+            println!("stashed as {}", repo.short_id(oid)?);
+        },
+        Commands::Push(PushArgs { refspecs, remote, atomic, .. }) if !refspecs.is_empty() => {
             let repo = Repo::open(&config).expect("failed to open repository");
-            let res = repo.pull(&branch_name);
-            println!("{res:?}");
+            let refspecs: Vec<&str> = refspecs.iter().map(String::as_str).collect();
+            let remote = remote.as_deref().unwrap_or("origin");
+            let atomicity = if atomic { PushAtomicity::Atomic } else { PushAtomicity::NonAtomic };
+            for updated in repo.push_refspecs(remote, &refspecs, atomicity)? {
+                println!("updated {updated}");
+            }
+        },
+        Commands::Push(PushArgs {
+            to: Some(remote_branch),
+            remote,
+            ..
+        }) => {
+            let repo = Repo::open(&config).expect("failed to open repository");
+            let local = repo.current_branch()?.local_name();
+            repo.push_to(&local, &remote_branch, remote.as_deref())?;
+            println!("pushed {local} to {remote_branch}");
+        },
+        Commands::Push(PushArgs {
+            to: None,
+            verbose,
+            pack_threads,
+            big_file_threshold,
+            push_remote,
+            ..
+        }) => {
+            let config = Config {
+                pack_threads: pack_threads.unwrap_or(config.pack_threads),
+                big_file_threshold: big_file_threshold.or(config.big_file_threshold),
+                ..config
+            };
+            let repo = Repo::open(&config).expect("failed to open repository");
+            if let Some(remote) = push_remote {
+                let branch = repo.current_branch()?.local_name();
+                repo.set_push_remote(&branch, &remote)?;
+            }
+            let report = repo.push()?;
+            println!("pushed");
+            if verbose {
+                println!("{report}");
+            }
+        },
+        Commands::Pull(PullArgs { branch_name, verbose }) => {
+            let repo = Repo::open(&config).expect("failed to open repository");
+            match repo.pull(&branch_name)? {
+                PullResult::FastForwarded {
+                    old_id,
+                    new_id,
+                    files,
+                    truncated,
+                } => {
+                    let (old_id, new_id) = (repo.short_id(old_id)?, repo.short_id(new_id)?);
+                    println!("fast-forwarded {branch_name}, {old_id} -> {new_id}");
+                    let insertions: usize = files.iter().map(|file| file.insertions).sum();
+                    let deletions: usize = files.iter().map(|file| file.deletions).sum();
+                    let more = if truncated { " or more" } else { "" };
+                    println!("{}{more} files changed, +{insertions} -{deletions}", files.len());
+                    if verbose {
+                        for file in files {
+                            println!(
+                                "\t{}: {} (+{} -{})",
+                                file.change, file.path, file.insertions, file.deletions
+                            );
+                        }
+                    }
+                },
+                PullResult::UpToDate => println!("{branch_name} already up to date"),
+                PullResult::None => println!("no merge of {branch_name} possible"),
+                PullResult::Normal =>
+                    println!("local and remote {branch_name} diverged, merge them"),
+                PullResult::Unborn => println!("HEAD doesn't point to a valid commit"),
+                PullResult::UpstreamGone { upstream } => println!(
+                    "upstream {upstream} is gone, push {branch_name} to track it again or \
+                     delete it with branch --stale --delete"
+                ),
+            }
+        },
+        Commands::Diff(DiffArgs {
+            from,
+            to,
+            unified,
+            report: ReportArgs { output, format },
+        }) => {
+            let config = Config {
+                diff_context_lines: unified,
+                ..config
+            };
+            let repo = Repo::open(&config).expect("failed to open repository");
+            match output {
+                Some(output) => {
+                    let file = File::create(&output)?;
+                    let written = repo.write_diff(from.as_deref(), to.as_deref(), format, file)?;
+                    println!("{written} bytes written to {}", output.display());
+                },
+                None => {
+                    repo.write_diff(from.as_deref(), to.as_deref(), format, std::io::stdout())?;
+                },
+            }
+        },
+        Commands::Incoming(PullArgs { branch_name, .. }) => {
+            let repo = Repo::open(&config).expect("failed to open repository");
+            for commit in repo.incoming(&branch_name)? {
+                println!("{} {} ({})", commit.id, commit.summary, commit.author);
+            }
+        },
+        Commands::Outgoing(PullArgs { branch_name, .. }) => {
+            let repo = Repo::open(&config).expect("failed to open repository");
+            for commit in repo.outgoing(&branch_name)? {
+                println!("{} {} ({})", commit.id, commit.summary, commit.author);
+            }
+        },
+        Commands::RevParse(RevParseArgs { revspec }) => {
+            let repo = Repo::open(&config).expect("failed to open repository");
+            let rev = repo.resolve(&revspec)?;
+            println!("{} {}", rev.id, rev.kind);
+            if let Some(ref_name) = rev.ref_name {
+                println!("ref {ref_name}");
+            }
+            if let Some(summary) = rev.summary {
+                println!("{summary}");
+            }
+        },
+        Commands::LsRemote(LsRemoteArgs { remote, branch }) => {
+            let repo = Repo::open(&config).expect("failed to open repository");
+            println!("{}", repo.ls_remote_head(&remote, &branch)?);
+        },
+        Commands::FileLog(FileLogArgs { path, max }) => {
+            let repo = Repo::open(&config).expect("failed to open repository");
+            for commit in repo.file_log(&path, max)? {
+                println!("{} {} ({})", commit.short_id, commit.summary, commit.author);
+            }
+        },
+        Commands::Compare(CompareArgs { base, head }) => {
+            let repo = Repo::open(&config).expect("failed to open repository");
+            let compare = repo.compare_refs(&base, &head)?;
+            match compare.merge_base {
+                Some(merge_base) => println!("merge base {merge_base}"),
+                None => println!("no common history"),
+            }
+            println!("{} ahead, {} behind", compare.ahead, compare.behind);
+            for file in compare.files {
+                let path = match file.old_path {
+                    Some(old_path) => format!("{old_path} -> {}", file.path),
+                    None => file.path,
+                };
+                println!("\t{}: {path} (+{} -{})", file.change, file.insertions, file.deletions);
+            }
+        },
+        Commands::Bisect(BisectArgs { command }) => {
+            let repo = Repo::open(&config).expect("failed to open repository");
+            let step = match command {
+                BisectCommand::Start { good, bad } => repo.bisect_start(&good, &bad)?,
+                BisectCommand::Good { commit } => bisect_mark(&repo, commit.as_deref(), true)?,
+                BisectCommand::Bad { commit } => bisect_mark(&repo, commit.as_deref(), false)?,
+                BisectCommand::Reset => {
+                    repo.bisect_reset()?;
+                    println!("bisect reset");
+                    return Ok(());
+                },
+            };
+            match step {
+                BisectStep::Next { commit, remaining } => {
+                    let commit = repo.short_id(commit)?;
+                    println!("testing {commit}, {remaining} commits left to check");
+                },
+                BisectStep::Found(commit) => {
+                    println!("{} is the first bad commit", repo.short_id(commit)?);
+                },
+            }
+        },
+        Commands::Notes(NotesArgs { namespace, command }) => {
+            let repo = Repo::open(&config).expect("failed to open repository");
+            match command {
+                NotesCommand::Add { commit, text, force } => {
+                    let oid = repo.note_add(&commit, &namespace, &text, force)?;
+                    println!("added note {}", repo.short_id(oid)?);
+                },
+                NotesCommand::Show { commit } => match repo.note_read(&commit, &namespace)? {
+                    Some(note) => print!("{note}"),
+                    None => println!("no note"),
+                },
+                NotesCommand::Remove { commit } => {
+                    repo.note_remove(&commit, &namespace)?;
+                    println!("note removed");
+                },
+            }
+        },
+        Commands::Bundle(command) => {
+            let repo = Repo::open(&config).expect("failed to open repository");
+            match command {
+                BundleCommand::Create { path, range } => {
+                    let objects = repo.bundle_create(&path, &range)?;
+                    println!("{objects} objects written to {}", path.display());
+                },
+                BundleCommand::Import { path } =>
+                    for refname in repo.bundle_import(&path)? {
+                        println!("imported {refname}");
+                    },
+            }
+        },
+        Commands::SparseCheckout(command) => {
+            let repo = Repo::open(&config).expect("failed to open repository");
+            let directories = match command {
+                SparseCheckoutCommand::List => None,
+                SparseCheckoutCommand::Set { directories } => Some(directories),
+                SparseCheckoutCommand::Disable => Some(Vec::new()),
+            };
+            if let Some(directories) = directories {
+                for kept in repo.set_sparse_checkout(&directories)? {
+                    println!("kept {kept}, it has local changes");
+                }
+            } else {
+                for directory in repo.sparse_checkout()? {
+                    println!("{directory}");
+                }
+            }
+        },
+        Commands::Verify(VerifyArgs {
+            range,
+            allowed_signers,
+            gpg_home,
+        }) => {
+            let repo = Repo::open(&config).expect("failed to open repository");
+            let policy = VerifyPolicy {
+                allowed_signers,
+                gpg_home,
+                ..VerifyPolicy::default()
+            };
+            let results = repo.verify_commits(&range, &policy)?;
+            for result in &results {
+                println!("{} {}: {}", result.commit, result.summary, result.status);
+            }
+            if !results.iter().all(VerificationResult::is_valid) {
+                std::process::exit(1);
+            }
+        },
+        Commands::Preflight(PreflightArgs { operation }) => {
+            let repo = Repo::open(&config).expect("failed to open repository");
+            let issues = repo.preflight(operation);
+            if issues.is_empty() {
+                println!("ready");
+            }
+            for issue in issues {
+                let kind = if issue.blocking { "blocking" } else { "warning" };
+                println!("{kind}[{}]: {}", issue.code, issue.message);
+            }
+        },
+        Commands::Doctor(DoctorArgs { fix_index, clear_locks }) => {
+            let repo = Repo::open(&config).expect("failed to open repository");
+            if clear_locks {
+                for lock in repo.clear_locks()? {
+                    println!("deleted {}", lock.display());
+                }
+            }
+            if fix_index {
+                let files = repo.rebuild_index()?;
+                println!("index rebuilt from HEAD with {files} files, staged changes were dropped");
+            }
+            let issues = repo.health_check();
+            if issues.is_empty() {
+                println!("no problems found");
+            }
+            for issue in issues {
+                println!("{}[{}]: {}", issue.severity, issue.code, issue.description);
+                println!("\tfix: {}", issue.suggested_fix);
+            }
+        },
+        Commands::Mirror(MirrorArgs { manifest, jobs, report }) => {
+            let entries = mirror::read_manifest(&manifest)?;
+            let outcomes = mirror::run(&entries, &config, jobs);
+            std::fs::write(&report, mirror::report(&entries, &outcomes).to_string())?;
+            let failed = outcomes.iter().filter(|outcome| outcome.error.is_some()).count();
+            println!(
+                "{} of {} repositories up to date, report written to {}",
+                entries.len() - failed,
+                entries.len(),
+                report.display()
+            );
+            if failed > 0 {
+                return Err(format!("{failed} of {} repositories failed", entries.len()).into());
+            }
         },
     }
 
@@ -87,6 +585,15 @@ fn main() -> Result<(), Box<dyn Error>> {
     help_template = "usage: {usage}"
 )]
 struct Cli {
+    /// Profile from the JSON file in GIT_CLI_PROFILES to use instead of the defaults
+    #[arg(long, global = true)]
+    profile: Option<String>,
+    /// Extra `Name: value` header for HTTP remotes, can be repeated
+    #[arg(long = "header", global = true)]
+    headers: Vec<String>,
+    /// Extra gitignore pattern, e.g. '*.tmp', can be repeated
+    #[arg(long, global = true)]
+    ignore: Vec<String>,
     #[command(subcommand)]
     command: Commands,
 }
@@ -94,15 +601,69 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     Clone(CloneArgs),
+    Init(InitArgs),
     Add(AddArgs),
+    Discard(AddArgs),
     Commit(CommitArgs),
-    Status,
+    Amend(AmendArgs),
+    Status(StatusArgs),
+    Diff(DiffArgs),
     Branches,
+    Branch(BranchArgs),
     #[command(name = "current-branch")]
     CurrentBranch,
     Checkout(CheckoutArgs),
-    Push,
+    Restore(RestoreArgs),
+    Stash(StashArgs),
+    Push(PushArgs),
     Pull(PullArgs),
+    Incoming(PullArgs),
+    Outgoing(PullArgs),
+    #[command(name = "ls-remote")]
+    LsRemote(LsRemoteArgs),
+    #[command(name = "rev-parse")]
+    RevParse(RevParseArgs),
+    #[command(name = "file-log")]
+    FileLog(FileLogArgs),
+    Compare(CompareArgs),
+    Bisect(BisectArgs),
+    Notes(NotesArgs),
+    #[command(subcommand)]
+    Bundle(BundleCommand),
+    #[command(subcommand, name = "sparse-checkout")]
+    SparseCheckout(SparseCheckoutCommand),
+    Verify(VerifyArgs),
+    Preflight(PreflightArgs),
+    Doctor(DoctorArgs),
+    Mirror(MirrorArgs),
+}
+
+#[derive(Args)]
+struct MirrorArgs {
+    /// TOML file with a [[repo]] table per repository: url, path and optionally branch
+    manifest: PathBuf,
+    /// Repositories cloned or fetched at once
+    #[arg(long, default_value_t = 4)]
+    jobs: usize,
+    /// Write the JSON summary of the run to this file
+    #[arg(long, default_value = "mirror-report.json")]
+    report: PathBuf,
+}
+
+#[derive(Args)]
+struct DoctorArgs {
+    /// Rebuild a corrupt or missing index from HEAD, dropping whatever was staged
+    #[arg(long)]
+    fix_index: bool,
+    /// Delete lock files left behind by a crashed git operation
+    #[arg(long)]
+    clear_locks: bool,
+}
+
+#[derive(Args)]
+struct PreflightArgs {
+    /// commit, push or commit_and_push
+    operation: OperationKind,
 }
 
 #[derive(Args)]
@@ -113,49 +674,327 @@ struct AddArgs {
 #[derive(Args)]
 struct CommitArgs {
     message: String,
+    /// Write the commit in this encoding, e.g. windows-1251
+    #[arg(long)]
+    encoding: Option<String>,
+    /// Add a Signed-off-by trailer, as `git commit -s`
+    #[arg(short, long)]
+    signoff: bool,
+}
+
+#[derive(Args)]
+struct AmendArgs {
+    #[arg(required = true)]
+    paths: Vec<String>,
+}
+
+#[derive(Args)]
+struct BranchArgs {
+    /// List local branches whose upstream is gone
+    #[arg(long, required_unless_present = "orphan", conflicts_with = "orphan")]
+    stale: bool,
+    /// Delete the stale branches instead of listing them
+    #[arg(long, requires = "stale")]
+    delete: bool,
+    /// Create a branch of this name with no history, see --file
+    #[arg(long, value_name = "NAME")]
+    orphan: Option<String>,
+    /// Message of the orphan branch's commit, "Initial commit" by default
+    #[arg(short, long, conflicts_with = "stale")]
+    message: Option<String>,
+    /// File to put on the orphan branch, under the same relative path
+    #[arg(long = "file", value_name = "PATH", conflicts_with = "stale")]
+    files: Vec<String>,
+    /// Check the orphan branch out once created
+    #[arg(long, conflicts_with = "stale")]
+    checkout: bool,
 }
 
 #[derive(Args)]
 struct CheckoutArgs {
     branch_name: String,
+    /// Take only these paths from the branch or commit, without switching to it
+    #[arg(last = true)]
+    paths: Vec<String>,
+}
+
+#[derive(Args)]
+struct RestoreArgs {
+    /// Branch or commit to take the paths from
+    #[arg(long)]
+    source: String,
+    /// Files or directories to take
+    #[arg(last = true, required = true)]
+    paths: Vec<String>,
+}
+
+#[derive(Args)]
+struct StashArgs {
+    #[arg(short, long)]
+    message: Option<String>,
+    /// Files or directories whose changes are stashed
+    #[arg(last = true, required = true)]
+    paths: Vec<String>,
+}
+
+#[derive(Args)]
+struct PushArgs {
+    /// Push the current branch to this remote branch instead of its namesake
+    #[arg(long)]
+    to: Option<String>,
+    /// Remote to push to with --to or --refspec, origin by default
+    #[arg(long)]
+    remote: Option<String>,
+    /// Push this refspec instead of the current branch, e.g. a branch and its tag together
+    #[arg(long = "refspec", value_name = "REFSPEC", conflicts_with = "to")]
+    refspecs: Vec<String>,
+    /// With --refspec, push nothing unless every ref is a fast-forward
+    #[arg(long)]
+    atomic: bool,
+    /// Print how long negotiation, packing and transfer took
+    #[arg(long, short, conflicts_with = "to")]
+    verbose: bool,
+    /// Threads compressing the pack, 0 for one per CPU
+    #[arg(long, conflicts_with = "to")]
+    pack_threads: Option<u32>,
+    /// Send blobs larger than this many bytes without delta compression
+    #[arg(long, conflicts_with = "to")]
+    big_file_threshold: Option<u64>,
+    /// Remember this remote as where the current branch is pushed, then push there
+    #[arg(long, conflicts_with = "to")]
+    push_remote: Option<String>,
 }
 
 #[derive(Args)]
 struct PullArgs {
     branch_name: String,
+    /// With pull, list the files it changed
+    #[arg(short, long)]
+    verbose: bool,
+}
+
+#[derive(Args)]
+struct FileLogArgs {
+    path: String,
+    /// Show at most this many commits
+    #[arg(short = 'n', long)]
+    max: Option<usize>,
+}
+
+#[derive(Args)]
+struct RevParseArgs {
+    revspec: String,
+}
+
+#[derive(Args)]
+struct LsRemoteArgs {
+    remote: String,
+    branch: String,
+}
+
+#[derive(Args)]
+struct ReportArgs {
+    /// Write the report to this file instead of the terminal
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// text, json or html
+    #[arg(long, default_value = "text")]
+    format: ReportFormat,
+}
+
+#[derive(Args)]
+struct StatusArgs {
+    #[command(flatten)]
+    report: ReportArgs,
+    /// List ignored files too
+    #[arg(long)]
+    ignored: bool,
+    /// List conflicted files in their own section
+    #[arg(long)]
+    conflicts: bool,
+    /// Skip untracked files, the working tree is only scanned for tracked ones
+    #[arg(long)]
+    no_untracked: bool,
+    /// How to scan the working tree: conservative or fast, which stats files in parallel
+    /// first and records touched but unchanged files in the index
+    #[arg(long, default_value = "conservative")]
+    mode: StatusPerfMode,
+    /// Keep showing the status, again whenever it changes, until Ctrl-C
+    #[arg(long, conflicts_with = "output")]
+    watch: bool,
+    /// Seconds between checks for changes while watching
+    #[arg(long, default_value_t = 1.0)]
+    interval: f64,
+}
+
+#[derive(Args)]
+struct DiffArgs {
+    from: Option<String>,
+    to: Option<String>,
+    /// Unchanged lines shown around each change
+    #[arg(short = 'U', long, default_value_t = 3)]
+    unified: u32,
+    #[command(flatten)]
+    report: ReportArgs,
+}
+
+#[derive(Args)]
+struct CompareArgs {
+    base: String,
+    head: String,
+}
+
+#[derive(Args)]
+struct BisectArgs {
+    #[command(subcommand)]
+    command: BisectCommand,
+}
+
+#[derive(Subcommand)]
+enum BisectCommand {
+    Start {
+        good: String,
+        bad: String,
+    },
+    /// Mark the checked out commit, or `commit`, as good
+    Good {
+        commit: Option<String>,
+    },
+    /// Mark the checked out commit, or `commit`, as bad
+    Bad {
+        commit: Option<String>,
+    },
+    Reset,
+}
+
+#[derive(Args)]
+struct NotesArgs {
+    /// Notes namespace under refs/notes/
+    #[arg(long = "ref", default_value = "commits", global = true)]
+    namespace: String,
+    #[command(subcommand)]
+    command: NotesCommand,
+}
+
+#[derive(Subcommand)]
+enum NotesCommand {
+    Add {
+        commit: String,
+        text: String,
+        /// Replace an existing note
+        #[arg(short, long)]
+        force: bool,
+    },
+    Show {
+        commit: String,
+    },
+    Remove {
+        commit: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum SparseCheckoutCommand {
+    /// Show the directories checked out
+    List,
+    /// Check out only these directories, the files in the root and in their parents
+    Set {
+        #[arg(required = true)]
+        directories: Vec<String>,
+    },
+    /// Check out the whole tree again
+    Disable,
+}
+
+#[derive(Subcommand)]
+enum BundleCommand {
+    /// Write the commits of a range to a bundle file
+    Create {
+        path: PathBuf,
+        /// `from..to`, or a branch or tag for its whole history
+        range: String,
+    },
+    /// Import a bundle file, its branches appear under the bundle remote
+    Import { path: PathBuf },
+}
+
+#[derive(Args)]
+struct VerifyArgs {
+    /// `from..to`, or a single revision to check its whole history
+    range: String,
+    #[arg(long)]
+    allowed_signers: Option<PathBuf>,
+    #[arg(long)]
+    gpg_home: Option<PathBuf>,
 }
 
 #[derive(Args)]
 struct CloneArgs {
     url: String,
+    /// Make a bare mirror holding every ref of the remote, e.g. for backups
+    #[arg(long)]
+    mirror: bool,
 }
 
-fn print_status_summary(summary: &StatusSummary) {
-    let StatusSummary {
-        branch_name,
-        staged,
-        not_staged,
-        untracked,
-    } = summary;
+#[derive(Args)]
+struct InitArgs {
+    /// Built-in template name (e.g. 1c-config) or a template directory
+    #[arg(long)]
+    template: Option<String>,
+    /// Message of the initial commit made from the template
+    #[arg(short, long, requires = "template")]
+    message: Option<String>,
+    /// Name of the first branch, e.g. main
+    #[arg(short = 'b', long)]
+    initial_branch: Option<String>,
+}
+
+/// Marks `commit`, the checked out one when omitted.
+fn bisect_mark(repo: &Repo, commit: Option<&str>, good: bool) -> Result<BisectStep, git2::Error> {
+    repo.bisect_mark(repo.resolve_commit(commit.unwrap_or("HEAD"))?, good)
+}
+
+/// Reads the profile `name` from the file in `GIT_CLI_PROFILES`, a JSON object with a
+/// profile per name.
+fn load_profile(name: &str) -> Result<Profile, Box<dyn Error>> {
+    let path = std::env::var("GIT_CLI_PROFILES").map_err(|_| "GIT_CLI_PROFILES is not set")?;
+    let profiles = json::parse(&std::fs::read_to_string(&path)?)?;
+    let profile = profiles.get(name).ok_or_else(|| format!("no profile '{name}' in {path}"))?;
+    Ok(Profile::from_value(profile)?)
+}
+
+/// "last fetched 5 minutes ago" for the newest fetch from any remote.
+fn last_fetched(repo: &Repo) -> Result<String, git2::Error> {
+    let Some(fetched) = repo.sync_state()?.last_fetch() else {
+        return Ok("never fetched".to_string());
+    };
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |now| now.as_secs());
+    let elapsed = now.saturating_sub(u64::try_from(fetched).unwrap_or_default());
+    let ago = match elapsed {
+        0..60 => "less than a minute".to_string(),
+        60..120 => "a minute".to_string(),
+        120..7200 => format!("{} minutes", elapsed / 60),
+        7200..172_800 => format!("{} hours", elapsed / 3600),
+        _ => format!("{} days", elapsed / 86_400),
+    };
+    Ok(format!("last fetched {ago} ago"))
+}
 
-    println!("on branch {branch_name}");
+fn print_status_summary(summary: &StatusSummary) {
+    println!("on branch {}", summary.branch_name);
+    if let Some(upstream) = &summary.upstream_gone {
+        println!("upstream {upstream} is gone");
+    }
 
-    if staged.is_empty() && not_staged.is_empty() && untracked.is_empty() {
+    let sections = summary.sections();
+    if sections[..4].iter().all(|(_, files)| files.is_empty()) {
         println!("nothing to commit, working tree clean");
-        return;
     }
 
-    let print_section = |header, contents: &[_]| {
-        if contents.is_empty() {
-            return;
-        }
-        println!("{header}");
-        for file in contents {
+    for (header, files) in sections.into_iter().filter(|(_, files)| !files.is_empty()) {
+        println!("{header}:");
+        for file in files {
             println!("\t{file}");
         }
-    };
-
-    print_section("Changes to be committed:", staged);
-    print_section("Changes not staged for commit:", not_staged);
-    print_section("Untracked files:", untracked);
+    }
 }
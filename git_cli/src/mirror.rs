@@ -0,0 +1,264 @@
+//! `mirror`: keeping local copies of many repositories up to date in one run, cloning the
+//! missing ones and fetching the rest a few at a time.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        Mutex,
+        PoisonError,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use git_core::{Config, Repo, json};
+
+/// A repository of the manifest.
+pub struct Entry {
+    pub url: String,
+    pub path: PathBuf,
+    /// Checked out after cloning, later runs only fetch.
+    pub branch: Option<String>,
+}
+
+/// What became of an entry.
+pub struct Outcome {
+    pub status: &'static str,
+    pub duration: Duration,
+    pub error: Option<String>,
+}
+
+/// Reads the manifest at `path`, a TOML file with a `[[repo]]` table per repository holding
+/// the string keys `url`, `path` and optionally `branch`. Relative paths are taken from the
+/// manifest's directory. Only this much of TOML is understood: comments, `[[repo]]` headers
+/// and `key = "string"` lines.
+pub fn read_manifest(path: &Path) -> Result<Vec<Entry>, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("failed to read '{}': {e}", path.display()))?;
+    let base = path.parent().unwrap_or(Path::new(""));
+    let error =
+        |number: usize, message: &str| format!("{}:{}: {message}", path.display(), number + 1);
+
+    let mut tables: Vec<(usize, Vec<(String, String)>)> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line == "[[repo]]" {
+            tables.push((number, Vec::new()));
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| error(number, "expected '[[repo]]' or 'key = \"value\"'"))?;
+        let (_, table) =
+            tables.last_mut().ok_or_else(|| error(number, "keys must follow a '[[repo]]'"))?;
+        let value = string_value(value.trim()).map_err(|message| error(number, &message))?;
+        table.push((key.trim().to_string(), value));
+    }
+
+    tables
+        .into_iter()
+        .map(|(number, table)| {
+            if let Some((key, _)) =
+                table.iter().find(|(key, _)| !["url", "path", "branch"].contains(&key.as_str()))
+            {
+                return Err(error(number, &format!("unknown key '{key}'")));
+            }
+            let get = |name| table.iter().rev().find(|(key, _)| key == name).map(|(_, v)| v);
+            let missing = |name| error(number, &format!("the repo has no '{name}'"));
+            Ok(Entry {
+                url: get("url").ok_or_else(|| missing("url"))?.clone(),
+                path: base.join(get("path").ok_or_else(|| missing("path"))?),
+                branch: get("branch").cloned(),
+            })
+        })
+        .collect()
+}
+
+/// A TOML basic or literal string up to an optional trailing comment.
+fn string_value(text: &str) -> Result<String, String> {
+    let mut chars = text.chars();
+    let quote = chars.next().filter(|c| ['"', '\''].contains(c));
+    let Some(quote) = quote else {
+        return Err("values must be quoted strings".to_string());
+    };
+    let mut value = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c == quote => {
+                let rest = chars.as_str().trim();
+                if !rest.is_empty() && !rest.starts_with('#') {
+                    return Err(format!("unexpected '{rest}' after the value"));
+                }
+                return Ok(value);
+            },
+            '\\' if quote == '"' => match chars.next() {
+                Some('\\') => value.push('\\'),
+                Some('"') => value.push('"'),
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                other => return Err(format!("unsupported escape '\\{}'", other.unwrap_or(' '))),
+            },
+            c => value.push(c),
+        }
+    }
+    Err("unterminated string".to_string())
+}
+
+/// Clones or fetches every entry with up to `jobs` at once, printing a line per finished
+/// one. A failing entry doesn't stop the others. No one is asked for credentials, as
+/// prompts of parallel jobs would mix, so `config` must carry them.
+pub fn run(entries: &[Entry], config: &Config, jobs: usize) -> Vec<Outcome> {
+    let next = AtomicUsize::new(0);
+    let finished = AtomicUsize::new(0);
+    let outcomes = Mutex::new((0..entries.len()).map(|_| None).collect::<Vec<_>>());
+    let config = Config {
+        credential_provider: None,
+        ..config.clone()
+    };
+
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, entries.len().max(1)) {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(entry) = entries.get(index) else { break };
+                    let outcome = update(entry, &config);
+                    let done = finished.fetch_add(1, Ordering::SeqCst) + 1;
+                    eprintln!(
+                        "[{done}/{}] {}: {} in {:.1?}{}",
+                        entries.len(),
+                        entry.path.display(),
+                        outcome.status,
+                        outcome.duration,
+                        outcome.error.as_ref().map(|e| format!(", {e}")).unwrap_or_default()
+                    );
+                    outcomes.lock().unwrap_or_else(PoisonError::into_inner)[index] = Some(outcome);
+                }
+            });
+        }
+    });
+
+    outcomes.into_inner().unwrap_or_else(PoisonError::into_inner).into_iter().flatten().collect()
+}
+
+/// Fetches `entry` when its path holds a repository, otherwise clones it.
+fn update(entry: &Entry, config: &Config) -> Outcome {
+    let started = Instant::now();
+    let config = Config {
+        path: entry.path.clone(),
+        ..config.clone()
+    };
+    let result = match Repo::open(&config) {
+        Ok(repo) => repo.prune_report().map(|_| "fetched"),
+        Err(_) => Repo::clone_from(&entry.url, &config).and_then(|repo| {
+            entry.branch.as_ref().map_or(Ok(()), |branch| repo.checkout_local(branch))?;
+            Ok("cloned")
+        }),
+    };
+    Outcome {
+        status: result.as_ref().map_or("failed", |status| status),
+        duration: started.elapsed(),
+        error: result.err().map(|e| e.message().to_string()),
+    }
+}
+
+/// The report of a run, the outcomes matching `entries` by position.
+pub fn report(entries: &[Entry], outcomes: &[Outcome]) -> json::Object {
+    let repos = entries.iter().zip(outcomes).map(|(entry, outcome)| {
+        json::Object::new()
+            .str("url", &entry.url)
+            .str("path", &entry.path.to_string_lossy())
+            .opt_str("branch", entry.branch.as_deref())
+            .str("status", outcome.status)
+            .raw("duration_ms", outcome.duration.as_millis())
+            .opt_str("error", outcome.error.as_deref())
+    });
+    json::Object::new()
+        .raw("total", outcomes.len())
+        .raw("failed", outcomes.iter().filter(|outcome| outcome.error.is_some()).count())
+        .raw("repos", json::array(repos))
+}
+
+#[cfg(test)]
+mod tests {
+    use git_core::{
+        Config,
+        Repo,
+        testing::{SimulatedRemote, TempDir},
+    };
+
+    use super::{read_manifest, report, run};
+
+    #[test]
+    fn a_failing_repository_leaves_the_others_mirrored() -> Result<(), git2::Error> {
+        let catalog = SimulatedRemote::new("main")?;
+        let reports = SimulatedRemote::new("main")?;
+        reports.push_commit("dev", "Report.xml", "<Report/>\n", "report")?;
+        let dir = TempDir::new("git-cli-mirror")?;
+        let manifest = dir.path().join("mirror.toml");
+        let missing = dir.path().join("no-such-remote");
+        std::fs::write(
+            &manifest,
+            format!(
+                "# three of them\n[[repo]]\nurl = \"{}\"\npath = \"catalog\"\n\n[[repo]]\nurl = \
+                 \"file://{}\"\npath = \"broken\"\n\n[[repo]]\nurl = '{}'\npath = \"reports\"\n\
+                 branch = \"dev\" # the one to work on\n",
+                catalog.url(),
+                missing.display(),
+                reports.url()
+            ),
+        )
+        .unwrap();
+        let entries = read_manifest(&manifest).unwrap();
+        let config = Config {
+            username: "Test Developer".to_string(),
+            email: "test@example.com".to_string(),
+            ..Config::default()
+        };
+
+        let outcomes = run(&entries, &config, 2);
+
+        let statuses: Vec<_> = outcomes.iter().map(|outcome| outcome.status).collect();
+        assert_eq!(statuses, ["cloned", "failed", "cloned"]);
+        assert!(outcomes[1].error.is_some());
+        let cloned = |name: &str| Config {
+            path: dir.path().join(name),
+            ..config.clone()
+        };
+        let reports_clone = cloned("reports");
+        assert_eq!(
+            Repo::open(&reports_clone)?.head_ref()?.full_name.as_deref(),
+            Some("refs/heads/dev")
+        );
+        let summary = report(&entries, &outcomes).to_string();
+        assert!(summary.starts_with("{\"total\":3,\"failed\":1,"), "{summary}");
+
+        // a second run fetches what was pushed meanwhile
+        let pushed = catalog.push_commit("main", "Catalog.xml", "<Catalog/>\n", "catalog")?;
+        let outcomes = run(&entries, &config, 3);
+
+        let statuses: Vec<_> = outcomes.iter().map(|outcome| outcome.status).collect();
+        assert_eq!(statuses, ["fetched", "failed", "fetched"]);
+        assert_eq!(Repo::open(&cloned("catalog"))?.resolve_commit("origin/main")?, pushed);
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_keys_are_named_with_their_line() -> Result<(), git2::Error> {
+        let dir = TempDir::new("git-cli-mirror")?;
+        let manifest = dir.path().join("mirror.toml");
+        std::fs::write(
+            &manifest,
+            "[[repo]]\nurl = \"a\"\npath = \"a\"\n\n[[repo]]\nurl = \"b\"\nbrnach = \"dev\"\n",
+        )
+        .unwrap();
+
+        let e = read_manifest(&manifest).err().expect("the key is misspelt");
+
+        assert_eq!(e, format!("{}:5: unknown key 'brnach'", manifest.display()));
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+//! Secrets asked on the terminal when a remote wants them, read without echo so they don't
+//! stay on the screen or in a recording of the session.
+
+use std::io::{BufRead, IsTerminal, Write};
+
+use git_core::{CredentialKind, CredentialProvider};
+use git2::{ErrorClass, ErrorCode};
+
+/// Asks for missing secrets on the terminal, an empty answer cancels the operation.
+pub struct TtyPrompt;
+
+impl CredentialProvider for TtyPrompt {
+    fn request(&self, url: &str, kind: CredentialKind) -> Result<String, git2::Error> {
+        let not_provided = || {
+            git2::Error::new(
+                ErrorCode::User,
+                ErrorClass::Callback,
+                format!("{kind} for '{url}' was not provided"),
+            )
+        };
+        eprint!("{kind} for {url}: ");
+        std::io::stderr().flush().map_err(|_| not_provided())?;
+
+        let stdin = std::io::stdin();
+        let mut secret = String::new();
+        if stdin.is_terminal() {
+            let read = {
+                let _hidden = echo::Hidden::new();
+                stdin.lock().read_line(&mut secret)
+            };
+            // the Enter typed wasn't echoed either
+            eprintln!();
+            read.map_err(|_| not_provided())?;
+        } else {
+            stdin.lock().read_line(&mut secret).map_err(|_| not_provided())?;
+        }
+        let secret = secret.trim_end_matches(['\r', '\n']);
+        (!secret.is_empty()).then(|| secret.to_string()).ok_or_else(not_provided)
+    }
+}
+
+#[cfg(unix)]
+mod echo {
+    use std::mem::MaybeUninit;
+
+    use libc::{ECHO, STDIN_FILENO, TCSANOW, tcgetattr, tcsetattr, termios};
+
+    /// Echo of the terminal turned off until dropped. Does nothing when the terminal
+    /// settings can't be read.
+    pub struct Hidden {
+        saved: Option<termios>,
+    }
+
+    impl Hidden {
+        pub fn new() -> Self {
+            let mut settings = MaybeUninit::<termios>::uninit();
+            // SAFETY: tcgetattr fills `settings` when it returns 0
+            if unsafe { tcgetattr(STDIN_FILENO, settings.as_mut_ptr()) } != 0 {
+                return Self { saved: None };
+            }
+            // SAFETY: initialized by the successful tcgetattr above
+            let saved = unsafe { settings.assume_init() };
+            let mut hidden = saved;
+            hidden.c_lflag &= !ECHO;
+            // SAFETY: `hidden` is a valid termios read from the same terminal
+            unsafe { tcsetattr(STDIN_FILENO, TCSANOW, &hidden) };
+            Self { saved: Some(saved) }
+        }
+    }
+
+    impl Drop for Hidden {
+        fn drop(&mut self) {
+            if let Some(saved) = &self.saved {
+                // SAFETY: `saved` is the termios read from this terminal before
+                unsafe { tcsetattr(STDIN_FILENO, TCSANOW, saved) };
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+mod echo {
+    use std::ffi::c_void;
+
+    const STD_INPUT_HANDLE: u32 = (-10_i32).cast_unsigned();
+    const ENABLE_ECHO_INPUT: u32 = 0x4;
+
+    unsafe extern "system" {
+        fn GetStdHandle(std_handle: u32) -> *mut c_void;
+        fn GetConsoleMode(console: *mut c_void, mode: *mut u32) -> i32;
+        fn SetConsoleMode(console: *mut c_void, mode: u32) -> i32;
+    }
+
+    /// Echo of the console turned off until dropped. Does nothing when the console mode
+    /// can't be read.
+    pub struct Hidden {
+        saved: Option<(*mut c_void, u32)>,
+    }
+
+    impl Hidden {
+        pub fn new() -> Self {
+            let mut mode = 0;
+            // SAFETY: plain console calls, `mode` outlives them
+            unsafe {
+                let console = GetStdHandle(STD_INPUT_HANDLE);
+                if GetConsoleMode(console, &mut mode) == 0 {
+                    return Self { saved: None };
+                }
+                SetConsoleMode(console, mode & !ENABLE_ECHO_INPUT);
+                Self { saved: Some((console, mode)) }
+            }
+        }
+    }
+
+    impl Drop for Hidden {
+        fn drop(&mut self) {
+            if let Some((console, mode)) = self.saved {
+                // SAFETY: restores the mode read from the same console handle
+                unsafe { SetConsoleMode(console, mode) };
+            }
+        }
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod echo {
+    /// No way to turn echo off here, the secret shows as typed.
+    pub struct Hidden;
+
+    impl Hidden {
+        pub fn new() -> Self {
+            Self
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! `status --watch`: the status shown again whenever it changes, for a terminal kept open
+//! next to the editor. Changes are found by polling [`Repo::status_fingerprint`], which
+//! works on network shares too and costs little between changes.
+
+use std::{
+    sync::mpsc::{Receiver, RecvTimeoutError},
+    time::Duration,
+};
+
+use git_core::{Repo, StatusPolicy, StatusSummary};
+
+/// Passes the status of `repo` to `render` at once and then whenever it changed, checking
+/// every `interval`. A change is rendered once the fingerprint held still for another
+/// `interval`, so a checkout writing many files renders once. Failures of later rounds,
+/// e.g. while git rewrites the index, are passed on and watching goes on. Returns once
+/// `stop` receives or its sender is gone.
+pub fn watch(
+    repo: &Repo,
+    policy: StatusPolicy,
+    interval: Duration,
+    stop: &Receiver<()>,
+    mut render: impl FnMut(Result<StatusSummary, git2::Error>),
+) -> Result<(), git2::Error> {
+    render(Ok(repo.status_with(policy)?));
+    // taken after the status, which may refresh the index in the fast mode
+    let mut shown = repo.status_fingerprint().ok();
+    let mut settling = None;
+    loop {
+        match stop.recv_timeout(interval) {
+            Err(RecvTimeoutError::Timeout) => {},
+            Ok(()) | Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+        let fingerprint = repo.status_fingerprint().ok();
+        if fingerprint == shown {
+            settling = None;
+            continue;
+        }
+        if settling != Some(fingerprint.clone()) {
+            settling = Some(fingerprint);
+            continue;
+        }
+        render(repo.status_with(policy));
+        shown = repo.status_fingerprint().ok();
+        settling = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::mpsc::{self, Receiver, RecvTimeoutError},
+        thread,
+        time::Duration,
+    };
+
+    use git_core::{Repo, StatusPolicy, testing::SimulatedRemote};
+
+    use super::watch;
+
+    const INTERVAL: Duration = Duration::from_millis(20);
+    /// Long enough for many rounds, the renders of the tests arrive well within it.
+    const WAIT: Duration = Duration::from_secs(10);
+
+    fn next(renders: &Receiver<String>) -> String {
+        renders.recv_timeout(WAIT).expect("no status rendered")
+    }
+
+    fn assert_quiet(renders: &Receiver<String>) {
+        let rendered = renders.recv_timeout(INTERVAL * 10);
+        assert_eq!(rendered, Err(RecvTimeoutError::Timeout), "rendered without a change");
+    }
+
+    #[test]
+    fn renders_once_per_change_until_stopped() -> Result<(), git2::Error> {
+        let remote = SimulatedRemote::new("main")?;
+        let local = remote.clone_workspace()?;
+        let config = local.config.clone();
+        let (stop_sender, stop) = mpsc::channel();
+        let (sender, renders) = mpsc::channel();
+        let watching = thread::spawn(move || {
+            let repo = Repo::open(&config)?;
+            watch(&repo, StatusPolicy::default(), INTERVAL, &stop, |summary| {
+                let _ = sender.send(summary.map_or_else(|e| e.to_string(), |s| s.to_string()));
+            })
+        });
+
+        assert!(next(&renders).contains("working tree clean"));
+        assert_quiet(&renders);
+
+        // several files written in a row render once
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            std::fs::write(local.config.path.join(name), "new\n").unwrap();
+        }
+        let changed = next(&renders);
+        assert!(changed.contains("untracked: c.txt"), "{changed}");
+        assert_quiet(&renders);
+
+        std::fs::write(local.config.path.join("a.txt"), "changed\n").unwrap();
+        std::fs::remove_file(local.config.path.join("b.txt")).unwrap();
+        let changed = next(&renders);
+        assert!(!changed.contains("b.txt"), "{changed}");
+
+        stop_sender.send(()).unwrap();
+        watching.join().unwrap()?;
+        Ok(())
+    }
+
+    #[test]
+    fn returns_when_the_stop_sender_is_gone() -> Result<(), git2::Error> {
+        let remote = SimulatedRemote::new("main")?;
+        let local = remote.clone_workspace()?;
+        let repo = Repo::open(&local.config)?;
+        let (stop_sender, stop) = mpsc::channel::<()>();
+        drop(stop_sender);
+        let mut rendered = 0;
+
+        watch(&repo, StatusPolicy::default(), INTERVAL, &stop, |_| rendered += 1)?;
+
+        assert_eq!(rendered, 1);
+        Ok(())
+    }
+}
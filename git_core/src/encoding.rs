@@ -0,0 +1,90 @@
+//! Commit message encodings other than UTF-8, for legacy repositories whose commits carry
+//! an `encoding` header. Only Windows-1251, the one our Russian-language repositories
+//! use, is supported besides UTF-8.
+
+/// Characters of the Windows-1251 bytes 0x80..=0xFF, 0x98 is unassigned.
+const CP1251_HIGH: [char; 128] = [
+    '\u{0402}', '\u{0403}', '\u{201A}', '\u{0453}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{20AC}', '\u{2030}', '\u{0409}', '\u{2039}', '\u{040A}', '\u{040C}', '\u{040B}', '\u{040F}',
+    '\u{0452}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{FFFD}', '\u{2122}', '\u{0459}', '\u{203A}', '\u{045A}', '\u{045C}', '\u{045B}', '\u{045F}',
+    '\u{00A0}', '\u{040E}', '\u{045E}', '\u{0408}', '\u{00A4}', '\u{0490}', '\u{00A6}', '\u{00A7}',
+    '\u{0401}', '\u{00A9}', '\u{0404}', '\u{00AB}', '\u{00AC}', '\u{00AD}', '\u{00AE}', '\u{0407}',
+    '\u{00B0}', '\u{00B1}', '\u{0406}', '\u{0456}', '\u{0491}', '\u{00B5}', '\u{00B6}', '\u{00B7}',
+    '\u{0451}', '\u{2116}', '\u{0454}', '\u{00BB}', '\u{0458}', '\u{0405}', '\u{0455}', '\u{0457}',
+    '\u{0410}', '\u{0411}', '\u{0412}', '\u{0413}', '\u{0414}', '\u{0415}', '\u{0416}', '\u{0417}',
+    '\u{0418}', '\u{0419}', '\u{041A}', '\u{041B}', '\u{041C}', '\u{041D}', '\u{041E}', '\u{041F}',
+    '\u{0420}', '\u{0421}', '\u{0422}', '\u{0423}', '\u{0424}', '\u{0425}', '\u{0426}', '\u{0427}',
+    '\u{0428}', '\u{0429}', '\u{042A}', '\u{042B}', '\u{042C}', '\u{042D}', '\u{042E}', '\u{042F}',
+    '\u{0430}', '\u{0431}', '\u{0432}', '\u{0433}', '\u{0434}', '\u{0435}', '\u{0436}', '\u{0437}',
+    '\u{0438}', '\u{0439}', '\u{043A}', '\u{043B}', '\u{043C}', '\u{043D}', '\u{043E}', '\u{043F}',
+    '\u{0440}', '\u{0441}', '\u{0442}', '\u{0443}', '\u{0444}', '\u{0445}', '\u{0446}', '\u{0447}',
+    '\u{0448}', '\u{0449}', '\u{044A}', '\u{044B}', '\u{044C}', '\u{044D}', '\u{044E}', '\u{044F}',
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Utf8,
+    Cp1251,
+}
+
+impl Encoding {
+    /// Encoding names as git and iconv spell them, case-insensitively.
+    fn named(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(Self::Utf8),
+            "windows-1251" | "cp1251" => Some(Self::Cp1251),
+            _ => None,
+        }
+    }
+}
+
+/// Whether `name` needs an `encoding` header, i.e. names a supported encoding other than
+/// UTF-8. Unsupported names are an error.
+pub fn needs_header(name: &str) -> Result<bool, git2::Error> {
+    match Encoding::named(name) {
+        Some(encoding) => Ok(encoding != Encoding::Utf8),
+        None => Err(unsupported(name)),
+    }
+}
+
+/// `text` in the encoding `name`, failing on characters it can't represent.
+pub fn encode(name: &str, text: &str) -> Result<Vec<u8>, git2::Error> {
+    match Encoding::named(name).ok_or_else(|| unsupported(name))? {
+        Encoding::Utf8 => Ok(text.as_bytes().to_vec()),
+        Encoding::Cp1251 => text
+            .chars()
+            .map(|c| match u8::try_from(c) {
+                Ok(byte) if byte.is_ascii() => Ok(byte),
+                _ => (0x80..=0xff)
+                    .zip(CP1251_HIGH)
+                    .find(|&(_, high)| high == c && c != char::REPLACEMENT_CHARACTER)
+                    .map(|(byte, _)| byte)
+                    .ok_or_else(|| {
+                        git2::Error::from_str(&format!("'{c}' can't be encoded in {name}"))
+                    }),
+            })
+            .collect(),
+    }
+}
+
+/// `bytes` decoded from the encoding `name`, UTF-8 when it is `None` or not supported.
+/// Invalid sequences become U+FFFD.
+pub fn decode(name: Option<&str>, bytes: &[u8]) -> String {
+    match name.and_then(Encoding::named) {
+        Some(Encoding::Cp1251) => bytes
+            .iter()
+            .map(|&byte| match byte {
+                0..=0x7f => char::from(byte),
+                _ => CP1251_HIGH[usize::from(byte - 0x80)],
+            })
+            .collect(),
+        Some(Encoding::Utf8) | None => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+fn unsupported(name: &str) -> git2::Error {
+    git2::Error::from_str(&format!(
+        "unsupported commit encoding '{name}', use UTF-8 or windows-1251"
+    ))
+}
@@ -1,199 +1,1307 @@
-use std::path::PathBuf;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ffi::{OsStr, OsString},
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
 
 use git2::{
+    AttrCheckFlags,
+    AttrValue,
+    AutotagOption,
     Branch,
     BranchType,
+    CheckoutNotificationType,
+    ConfigLevel,
     Cred,
+    CredentialType,
+    DiffOptions,
+    Direction,
+    ErrorClass,
+    ErrorCode,
     FetchOptions,
     FetchPrune,
     IndexAddOption,
     IntoCString,
     ObjectType,
     Oid,
+    Pathspec,
+    PathspecFlags,
     PushOptions,
+    PushUpdate,
+    Remote,
     RemoteCallbacks,
     Repository,
+    RepositoryOpenFlags,
     Signature,
     StatusOptions,
     build::{CheckoutBuilder, RepoBuilder},
 };
 
-use crate::{INVALID_UTF8, git_status::StatusSummary};
+use crate::{
+    encoding,
+    git_branches::Upstream,
+    git_compare::FileDiff,
+    git_notes::NOTES_REFSPEC,
+    git_pack::{PushReport, PushTimer},
+    git_preflight::detached_head,
+    git_status::{StatusBuilder, StatusPerfMode, StatusPolicy, StatusSummary, prewarm_metadata},
+    git_sync::SyncKind,
+    git_verify::VerifyPolicy,
+    http,
+    path,
+    report::ReportFormat,
+    url,
+};
+
+/// Fetch refspec of a mirror, every ref of the remote under the same name.
+const MIRROR_REFSPEC: &str = "+refs/*:refs/*";
 
 #[derive(Clone, Default)]
 pub enum AuthType {
     Password(String),
+    SshKey {
+        private_key: PathBuf,
+        passphrase: Option<String>,
+    },
     #[default]
     None,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CredentialKind {
+    Password,
+    SshPassphrase,
+}
 
-#[derive(Clone, Default)]
+impl std::fmt::Display for CredentialKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CredentialKind::Password => write!(f, "password"),
+            CredentialKind::SshPassphrase => write!(f, "ssh key passphrase"),
+        }
+    }
+}
+
+/// A remote asked for credentials while none are configured ([`AuthType::None`]). The
+/// credentials callback fails with it as a [`git2::Error`], recognize it in the error of
+/// the network operation with [`CredentialsRequired::from_error`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CredentialsRequired {
+    pub url: String,
+    /// What the remote accepts, e.g. "password" and "ssh key".
+    pub accepted_methods: Vec<&'static str>,
+}
+
+impl CredentialsRequired {
+    const MARKER: &'static str = "' requires authentication (";
+
+    fn new(url: &str, allowed_types: CredentialType) -> Self {
+        let mut accepted_methods = Vec::new();
+        if allowed_types.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            accepted_methods.push("password");
+        }
+        if allowed_types.intersects(CredentialType::SSH_KEY | CredentialType::SSH_MEMORY) {
+            accepted_methods.push("ssh key");
+        }
+        Self {
+            url: url.to_string(),
+            accepted_methods,
+        }
+    }
+
+    /// libgit2 passes only the code, class and message of a callback error on, so the
+    /// fields are read back from the message, which may come prefixed, e.g. by
+    /// [`Repo::prune_report`] with the remote that failed.
+    pub fn from_error(error: &git2::Error) -> Option<Self> {
+        if error.code() != ErrorCode::Auth || error.class() != ErrorClass::Callback {
+            return None;
+        }
+        let (quoted, rest) = error.message().rsplit_once(Self::MARKER)?;
+        let (_, url) = quoted.rsplit_once('\'')?;
+        let (methods, _) = rest.split_once(')')?;
+        let accepted_methods = ["password", "ssh key"]
+            .into_iter()
+            .filter(|method| methods.split(", ").any(|accepted| accepted == *method))
+            .collect();
+        Some(Self {
+            url: url.to_string(),
+            accepted_methods,
+        })
+    }
+}
+
+impl std::fmt::Display for CredentialsRequired {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "'{}{}{}) but no credentials are configured",
+            self.url,
+            Self::MARKER,
+            self.accepted_methods.join(", ")
+        )
+    }
+}
+
+impl From<CredentialsRequired> for git2::Error {
+    fn from(required: CredentialsRequired) -> Self {
+        git2::Error::new(ErrorCode::Auth, ErrorClass::Callback, required.to_string())
+    }
+}
+
+/// Whether [`Repo::push_refspecs`] updates all the refs or none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PushAtomicity {
+    /// The server keeps the refs it accepts when it rejects others.
+    #[default]
+    NonAtomic,
+    /// All or none as far as we can tell before sending: once the server listed its refs,
+    /// every update has to be a fast-forward of a commit we have and a tag can't move,
+    /// otherwise nothing is sent and the error lists each ref refused. libgit2 can't ask
+    /// the server for git's `atomic` capability, so a hook rejecting some refs on the
+    /// server still keeps the others.
+    Atomic,
+}
+
+/// An operation refused by a [`Config::read_only`] clone. Its code is [`ErrorCode::Locked`]
+/// like that of a held lock file, recognize it with [`ReadOnlyRefusal::from_error`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadOnlyRefusal;
+
+impl ReadOnlyRefusal {
+    const MESSAGE: &'static str = "operation refused, the repository is opened read-only";
+
+    pub fn from_error(error: &git2::Error) -> Option<Self> {
+        let refused = error.code() == ErrorCode::Locked
+            && error.class() == ErrorClass::Repository
+            && error.message().starts_with(Self::MESSAGE);
+        refused.then_some(Self)
+    }
+}
+
+impl std::fmt::Display for ReadOnlyRefusal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(Self::MESSAGE)
+    }
+}
+
+impl From<ReadOnlyRefusal> for git2::Error {
+    fn from(refusal: ReadOnlyRefusal) -> Self {
+        git2::Error::new(ErrorCode::Locked, ErrorClass::Repository, refusal.to_string())
+    }
+}
+
+/// A ref no server has, which [`Repo::connected`] asks for to connect without a transfer.
+const CONNECT_ONLY_REF: &str = "refs/git-core/connect-only";
+
+/// Supplies secrets interactively when the configured ones are missing or were rejected,
+/// e.g. a TTY prompt in the CLI or a form in 1C.
+pub trait CredentialProvider: Send + Sync {
+    /// Returns the secret of `kind` for `url`. An error, e.g. when the user cancelled, aborts
+    /// the running operation with it.
+    fn request(&self, url: &str, kind: CredentialKind) -> Result<String, git2::Error>;
+}
+
+
+#[derive(Clone)]
 pub struct Config {
     pub username: String,
     pub auth: AuthType,
     pub email: String,
     pub path: PathBuf,
+    pub credential_provider: Option<Arc<dyn CredentialProvider>>,
+    /// Set from another thread to abort a running clone or fetch at its next progress
+    /// report. A cancelled clone leaves nothing behind.
+    pub cancel: Option<Arc<AtomicBool>>,
+    /// Expected `core.autocrlf`, reported by the health check when the repository differs.
+    pub autocrlf: Option<bool>,
+    /// Refuse to commit staged files locked by someone else instead of only reporting them.
+    pub block_locked_commits: bool,
+    /// Refuse to pull commits without a valid signature into the protected branches.
+    pub verify_policy: Option<VerifyPolicy>,
+    /// Push and fetch `refs/notes/*` along with the branches.
+    pub sync_notes: bool,
+    /// Refuse every operation that changes the repository, see [`Config::ensure_writable`].
+    pub read_only: bool,
+    /// Branch new repositories start on and the first commit and push expect, e.g. "main"
+    /// when the server's default differs from libgit2's "master".
+    pub default_branch: Option<String>,
+    /// Encoding of new commits, e.g. "windows-1251" for legacy repositories. Anything but
+    /// UTF-8 is recorded in the commit's `encoding` header.
+    pub commit_encoding: Option<String>,
+    /// `Name: value` headers sent to HTTP remotes on clone, fetch and push, validated with
+    /// [`crate::http::validate_header`].
+    pub custom_headers: Vec<String>,
+    /// Report renamed files in the status as renames rather than a deletion and an addition.
+    /// Costly on large change sets, on by default.
+    pub detect_renames: bool,
+    /// Unchanged lines shown around each change in unified diffs, 3 as in git.
+    pub diff_context_lines: u32,
+    /// How the working tree is scanned for the status, see [`StatusPerfMode`].
+    pub status_perf_mode: StatusPerfMode,
+    /// Threads compressing the pack on push, 0 for one per CPU. Pushing large XML files is
+    /// bound by this compression.
+    pub pack_threads: u32,
+    /// Blobs larger than this many bytes are sent without delta compression, see
+    /// [`Repo::push`]. The repository's own setting is kept when unset.
+    pub big_file_threshold: Option<u64>,
+    /// Suggest Latin spellings for Cyrillic branch names refused as invalid, see
+    /// [`crate::refname::suggest`].
+    pub transliterate_ref_names: bool,
+    /// gitignore patterns added to the repository's and the user's ignore files, e.g.
+    /// `*.tmp`, so a deployment can hide noise in the status without touching any file.
+    /// They live in memory only.
+    pub extra_ignore_patterns: Vec<String>,
+    /// User for SSH URLs that don't name one, like `ssh://host/repo.git`. Unset, it is
+    /// `git`, which hosting servers expect; [`Config::username`] is the author's name and
+    /// rarely an SSH login.
+    pub ssh_user: Option<String>,
+    /// Times a checkout failing on a file another program holds open, e.g. a dump file the
+    /// 1C Designer keeps, is tried again. Such locks are often gone within a second. Only
+    /// Windows sharing violations are retried, any other failure is reported at once.
+    pub checkout_retries: u32,
+    /// Pause before each of the [`Config::checkout_retries`].
+    pub checkout_retry_delay: Duration,
+    /// Files a pull lists as changed at most, counting lines in huge pulls takes long.
+    pub pull_file_limit: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            username: String::new(),
+            auth: AuthType::default(),
+            email: String::new(),
+            path: PathBuf::new(),
+            credential_provider: None,
+            cancel: None,
+            autocrlf: None,
+            block_locked_commits: false,
+            verify_policy: None,
+            sync_notes: false,
+            read_only: false,
+            default_branch: None,
+            commit_encoding: None,
+            custom_headers: Vec::new(),
+            detect_renames: true,
+            diff_context_lines: 3,
+            status_perf_mode: StatusPerfMode::Conservative,
+            pack_threads: 1,
+            big_file_threshold: None,
+            transliterate_ref_names: false,
+            extra_ignore_patterns: Vec::new(),
+            ssh_user: None,
+            checkout_retries: 3,
+            checkout_retry_delay: Duration::from_millis(500),
+            pull_file_limit: 1000,
+        }
+    }
+}
+
+impl Config {
+    /// [`Config::custom_headers`] that are safe to hand to git2, which panics on a NUL.
+    /// The others are dropped, they are refused where the headers are entered.
+    fn http_headers(&self) -> Vec<&str> {
+        self.custom_headers
+            .iter()
+            .filter(|header| http::validate_header(header).is_ok())
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Adds [`Config::extra_ignore_patterns`] to the in-memory ignore rules of `repo`.
+    pub(crate) fn apply_ignore_rules(&self, repo: &Repository) -> Result<(), git2::Error> {
+        if self.extra_ignore_patterns.is_empty() {
+            return Ok(());
+        }
+        repo.add_ignore_rule(&self.extra_ignore_patterns.join("\n"))
+    }
+
+    /// The check every mutating operation makes before touching the repository. Cloning
+    /// and fetching stay allowed, they only update the local copy of the server's state.
+    pub(crate) fn ensure_writable(&self) -> Result<(), git2::Error> {
+        if self.read_only {
+            return Err(ReadOnlyRefusal.into());
+        }
+        Ok(())
+    }
 }
 
 pub struct Repo<'a> {
-    repo: Repository,
-    config: &'a Config,
+    pub(crate) repo: Repository,
+    pub(crate) config: &'a Config,
 }
 
 impl<'a> Repo<'a> {
+    /// Opens exactly `config.path` without searching parent directories. Linked worktrees
+    /// and submodule checkouts, whose `.git` is a file pointing elsewhere, open like a
+    /// clone: their HEAD, index and operation state are their own, while what they share
+    /// with the main repository is found under [`Repo::common_dir`].
     pub fn open(config: &'a Config) -> Result<Self, git2::Error> {
-        Ok(Self {
-            repo: Repository::open(&config.path)?,
-            config,
+        let path = path::normalize(&config.path);
+        let repo = Repository::open_ext(
+            &path,
+            RepositoryOpenFlags::NO_SEARCH,
+            std::iter::empty::<&OsStr>(),
+        )
+        .map_err(|e| path::with_network_hint(&path, e))?;
+        if !config.read_only {
+            path::apply_fs_options(&repo)?;
+        }
+        config.apply_ignore_rules(&repo)?;
+
+        Ok(Self { repo, config })
+    }
+
+    /// Directory holding the object store, refs, config and the crate's own state shared
+    /// by every worktree. For a plain clone this is the same as the `.git` directory.
+    pub fn common_dir(&self) -> &Path {
+        self.repo.commondir()
+    }
+
+    pub fn is_worktree(&self) -> bool {
+        self.repo.is_worktree()
+    }
+
+    /// Fetch URL of the remote `name` with embedded credentials removed, `None` when the
+    /// remote has no URL.
+    pub fn remote_url(&self, name: &str) -> Result<Option<String>, git2::Error> {
+        let remote = self.find_remote(name)?;
+        Ok(remote.url().map(url::strip_credentials))
+    }
+
+    /// Adds the remote `name` fetching from `url` with the default refspec, e.g. `origin`
+    /// for a repository made with [`Repo::init`].
+    pub fn add_remote(&self, name: &str, url: &str) -> Result<(), git2::Error> {
+        self.config.ensure_writable()?;
+        self.repo.remote(name, url).map(drop)
+    }
+
+    /// The remote `name`, failing with a hint on adding it when it doesn't exist: a
+    /// repository made with `init` has no `origin`.
+    pub(crate) fn find_remote(&self, name: &str) -> Result<git2::Remote<'_>, git2::Error> {
+        self.repo.find_remote(name).map_err(|e| {
+            if e.code() != ErrorCode::NotFound {
+                return e;
+            }
+            git2::Error::new(
+                ErrorCode::NotFound,
+                ErrorClass::Config,
+                format!("no remote '{name}' configured; add one with AddRemote"),
+            )
         })
     }
 
+    /// Makes pushes to `remote` go to `url` while fetches keep using its URL, e.g. to fetch
+    /// from a read-only HTTPS mirror and push over SSH. An empty `url` removes the push URL.
+    ///
+    /// `url` must point at a server: libgit2's local transport pushes to the fetch URL
+    /// whatever the push URL, so a local path is refused.
+    pub fn set_remote_push_url(&self, remote: &str, url: &str) -> Result<(), git2::Error> {
+        self.config.ensure_writable()?;
+        // libgit2 would write the setting for a remote that doesn't exist
+        self.find_remote(remote)?;
+        if !url.is_empty() && url::is_local(url) {
+            return Err(git2::Error::new(
+                ErrorCode::Invalid,
+                ErrorClass::Net,
+                format!("push URL '{url}' is a local path, only server URLs are supported"),
+            ));
+        }
+        self.repo.remote_set_pushurl(remote, Some(url).filter(|url| !url.is_empty()))
+    }
+
+    /// Name of the project: the last segment of the origin URL, or the directory name
+    /// when there is no origin.
+    pub fn name(&self) -> String {
+        let origin = self.remote_url("origin").ok().flatten();
+        let dir = self.repo.workdir().unwrap_or_else(|| self.repo.path());
+        origin
+            .as_deref()
+            .and_then(url::repository_name)
+            .or_else(|| dir.to_str().and_then(url::repository_name))
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    /// Clones into a staging directory next to `config.path` and moves it into place only
+    /// once the clone has fully succeeded, so a failed clone never leaves a half-populated
+    /// destination behind.
     pub fn clone_from(url: &str, config: &'a Config) -> Result<Self, git2::Error> {
-        let repo = RepoBuilder::new()
+        let mut builder = RepoBuilder::new();
+        // before the checkout, which needs them for long paths
+        builder.remote_create(|repo, name, url| {
+            path::apply_fs_options(repo)?;
+            repo.remote(name, url)
+        });
+        Self::clone_with(url, config, builder)
+    }
+
+    /// Clones a bare mirror of `url` into `config.path`, every ref of the remote under the
+    /// same name as with `git clone --mirror`, e.g. for backups. Fetching it again updates
+    /// all refs and drops those deleted on the remote. Staged like [`Repo::clone_from`].
+    pub fn clone_mirror(url: &str, config: &'a Config) -> Result<Self, git2::Error> {
+        let mut builder = RepoBuilder::new();
+        builder.bare(true).remote_create(|repo, name, url| {
+            let remote = repo.remote_with_fetch(name, url, MIRROR_REFSPEC)?;
+            // what `git clone --mirror` sets, making `git push` mirror too
+            repo.config()?.set_bool(&format!("remote.{name}.mirror"), true)?;
+            Ok(remote)
+        });
+        let repo = Self::clone_with(url, config, builder)?;
+        // libgit2 records the remote's HEAD as for a regular clone, dangling in a mirror
+        if let Ok(mut head) = repo.repo.find_reference("refs/remotes/origin/HEAD") {
+            head.delete()?;
+        }
+        Ok(repo)
+    }
+
+    fn clone_with(
+        url: &str,
+        config: &'a Config,
+        mut builder: RepoBuilder<'a>,
+    ) -> Result<Self, git2::Error> {
+        let dest = path::normalize(&config.path);
+        // refused before anything is downloaded
+        destination_exists(&dest)?;
+        let staging = staging_path(&dest)?;
+        create_staging(&staging)?;
+
+        let cloned = builder
             .fetch_options(Self::fetch_options(config))
-            .clone(url, &config.path)?;
+            .clone(url, &staging)
+            .and_then(|repo| {
+                // the handle points to the staging directory, reopen after the move
+                drop(repo);
+                move_into_place(&staging, &dest)
+            })
+            .map_err(|e| path::with_network_hint(&dest, e));
 
-        Ok(Self { repo, config })
+        // the staging directory is this clone's own, see `create_staging`
+        if let Err(e) = cloned {
+            return Err(match std::fs::remove_dir_all(&staging) {
+                Ok(()) => e,
+                Err(io) if io.kind() == std::io::ErrorKind::NotFound => e,
+                Err(io) => git2::Error::from_str(&format!(
+                    "{e}; additionally failed to remove partial clone at '{}': {io}",
+                    staging.display()
+                )),
+            });
+        }
+
+        Self::open(config)
     }
 
     pub fn branches(
         &self,
-    ) -> Result<impl Iterator<Item = (git2::Branch, git2::BranchType)>, git2::Error> {
+    ) -> Result<impl Iterator<Item = (git2::Branch<'_>, git2::BranchType)>, git2::Error> {
         self.fetch_all()?;
         Ok(self.repo.branches(None)?.flatten())
     }
 
-    pub fn current_branch(&self) -> Result<TrackedBranch, git2::Error> {
+    /// The full ref HEAD points at and the commit it resolves to, telling a detached HEAD
+    /// and a branch without commits apart from a checked out branch.
+    pub fn head_ref(&self) -> Result<HeadRef, git2::Error> {
+        let head = self.repo.find_reference("HEAD")?;
+        let full_name = head.symbolic_target().map(str::to_string);
+        let oid = match head.resolve() {
+            Ok(resolved) => resolved.target(),
+            Err(e) if e.code() == ErrorCode::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        Ok(HeadRef {
+            detached: full_name.is_none(),
+            full_name,
+            oid,
+        })
+    }
+
+    /// Whether `branch`, the current one by default, has an upstream configured. Only the
+    /// configuration is read, so the upstream may not be fetched yet. A detached HEAD has
+    /// none.
+    pub fn has_upstream(&self, branch: Option<&str>) -> Result<bool, git2::Error> {
+        let refname = match branch {
+            Some(branch) => {
+                let branch = self.repo.find_branch(branch, BranchType::Local)?;
+                branch.get().name().map(str::to_string)
+            },
+            None => self.repo.find_reference("HEAD")?.symbolic_target().map(str::to_string),
+        };
+        let Some(refname) = refname else {
+            return Ok(false);
+        };
+        match self.repo.branch_upstream_name(&refname) {
+            Ok(_) => Ok(true),
+            Err(e) if e.code() == ErrorCode::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn current_branch(&self) -> Result<TrackedBranch<'_>, git2::Error> {
         let head = self.repo.head()?;
         let head_shorthand = head.shorthand().unwrap_or("HEAD");
 
         let local = self.repo.find_branch(head_shorthand, BranchType::Local)?;
-        let upstream = local.upstream().ok();
+        let upstream = self.upstream_of(&local)?;
 
         Ok(TrackedBranch { local, upstream })
     }
 
     pub fn status(&self) -> Result<StatusSummary, git2::Error> {
-        let branch_name = self
-            .repo
-            .head()?
-            .shorthand()
-            .ok_or_else(|| {
-                git2::Error::from_str(&format!("Current branch name is {INVALID_UTF8}"))
-            })?
-            .to_string();
+        self.status_with(StatusPolicy::default())
+    }
+
+    /// [`Repo::status`] with the entries sorted by `policy`.
+    pub fn status_with(&self, policy: StatusPolicy) -> Result<StatusSummary, git2::Error> {
+        let head = self.repo.head()?;
+        let branch_name = String::from_utf8_lossy(head.shorthand_bytes()).into_owned();
+        let upstream =
+            if head.is_branch() { self.upstream_of(&Branch::wrap(head))? } else { Upstream::None };
 
+        let fast = self.config.status_perf_mode == StatusPerfMode::Fast;
+        if let Some(workdir) = self.repo.workdir().filter(|_| fast) {
+            let index = self.repo.index()?;
+            let paths = index
+                .iter()
+                .map(|entry| PathBuf::from(String::from_utf8_lossy(&entry.path).as_ref()))
+                .collect::<Vec<_>>();
+            prewarm_metadata(workdir, &paths);
+        }
+
+        let update_index = fast && !self.config.read_only;
         let mut options = StatusOptions::new();
         options
-            .renames_from_rewrites(true) // not sure if this line is needed
-            .include_untracked(true)
-            .renames_head_to_index(true);
-
-        let summary = self.repo.statuses(Some(&mut options))?.iter().fold(
-            StatusSummary::new(branch_name),
-            |mut summary, entry| {
-                summary.add_entry(&entry);
-                summary
-            },
-        );
+            .renames_from_rewrites(self.config.detect_renames) // not sure if this line is needed
+            .include_untracked(policy.include_untracked)
+            .include_ignored(policy.include_ignored)
+            .renames_head_to_index(self.config.detect_renames)
+            .update_index(update_index);
+        let statuses = match self.repo.statuses(Some(&mut options)) {
+            // someone else is writing the index, the refresh waits for the next status
+            Err(e) if update_index && e.code() == ErrorCode::Locked =>
+                self.repo.statuses(Some(options.update_index(false)))?,
+            statuses => statuses?,
+        };
+
+        let mut builder = StatusBuilder::new(branch_name, policy);
+        builder.add_statuses(&statuses);
+        let mut summary = builder.build();
+        summary.detect_lfs_pointers(&self.repo);
+        // the locks only annotate the status, which is still worth showing without them
+        let locks = self.foreign_locks().unwrap_or_else(|e| {
+            log::warn!("status shows no locks, reading them failed: {e}");
+            Vec::new()
+        });
+        summary.annotate_locks(&locks);
+        self.hide_sparse_files(&mut summary)?;
+        if let Upstream::Gone(upstream) = upstream {
+            summary.upstream_gone = Some(upstream);
+        }
 
         Ok(summary)
     }
 
-    pub fn add<T, I>(&self, pathspecks: I) -> Result<git2::Index, git2::Error>
+    /// Cheap opaque value that changes whenever [`Repo::status`] would, meant for polling.
+    ///
+    /// Combines the HEAD ref name and target (so branch switches and commits always change
+    /// it), the checksum trailer of the on-disk index (any staging), and the number and
+    /// latest mtime of the files differing between index and working tree.
+    ///
+    /// Known blind spots, where a full status may differ under an unchanged fingerprint:
+    /// - files inside untracked directories, which aren't recursed into;
+    /// - an already modified file edited again without its mtime passing the latest one,
+    ///   i.e. within the file system's mtime resolution or with the clock set back.
+    ///
+    /// It may also change while the status doesn't: touching an unmodified file leaves it
+    /// as it is, but touching a modified or untracked one changes it, as the file's content
+    /// isn't hashed.
+    pub fn status_fingerprint(&self) -> Result<String, git2::Error> {
+        let mut hasher = DefaultHasher::new();
+
+        match self.repo.head() {
+            Ok(head) => {
+                head.name().hash(&mut hasher);
+                head.target().hash(&mut hasher);
+            },
+            Err(e) if e.code() == ErrorCode::UnbornBranch => "unborn".hash(&mut hasher),
+            Err(e) => return Err(e),
+        }
+
+        index_checksum(&self.repo.path().join("index")).hash(&mut hasher);
+
+        let mut options = DiffOptions::new();
+        options.include_untracked(true).recurse_untracked_dirs(false);
+        let diff = self.repo.diff_index_to_workdir(None, Some(&mut options))?;
+
+        let workdir = self.repo.workdir();
+        let latest_mtime = diff
+            .deltas()
+            .filter_map(|delta| {
+                let path = workdir?.join(delta.new_file().path()?);
+                path.symlink_metadata().and_then(|metadata| metadata.modified()).ok()
+            })
+            .max();
+
+        diff.deltas().len().hash(&mut hasher);
+        latest_mtime.hash(&mut hasher);
+
+        Ok(format!("{:016x}", hasher.finish()))
+    }
+
+    /// Stages the files matching `pathspecks` and returns the paths newly staged or
+    /// updated in the index, files already staged as they are left out.
+    pub fn add<T, I>(&self, pathspecks: I) -> Result<Vec<String>, git2::Error>
     where
         T: IntoCString,
         I: IntoIterator<Item = T>,
     {
+        self.config.ensure_writable()?;
         let mut index = self.repo.index()?;
+        let before = index_entries(&index);
         index.add_all(pathspecks, IndexAddOption::DEFAULT, None)?;
         index.write()?;
-        Ok(index)
+        Ok(staged_since(&before, &index))
     }
 
-    pub fn add_all(&self) -> Result<git2::Index, git2::Error> {
-        self.add(["."])
+    /// Stages every change except new commits checked out in submodules, which are only
+    /// staged when [`Repo::add`] names the submodule. Returns the paths as [`Repo::add`].
+    pub fn add_all(&self) -> Result<Vec<String>, git2::Error> {
+        self.config.ensure_writable()?;
+        let submodules: Vec<PathBuf> = self
+            .repo
+            .submodules()?
+            .iter()
+            .map(|submodule| submodule.path().to_path_buf())
+            .collect();
+
+        let mut index = self.repo.index()?;
+        let before = index_entries(&index);
+        index.add_all(
+            ["."],
+            IndexAddOption::DEFAULT,
+            Some(&mut |path: &Path, _matched: &[u8]| {
+                i32::from(submodules.iter().any(|s| s == path))
+            }),
+        )?;
+        index.write()?;
+        Ok(staged_since(&before, &index))
     }
 
     pub fn commit(&self, message: &str) -> Result<Oid, git2::Error> {
+        self.config.ensure_writable()?;
+        self.check_commit_locks()?;
+        self.check_no_conflicts()?;
+
         let mut index = self.repo.index()?;
         let tree_oid = index.write_tree()?;
         let tree = self.repo.find_tree(tree_oid)?;
-        let parent_commit = self.find_last_commit()?;
+        let parent_commit = self.commit_parent()?;
+        self.write_commit(message, &tree, parent_commit.as_ref())
+    }
+
+    /// The commit a new one goes on top of, `None` for the first commit, which creates the
+    /// branch HEAD names.
+    pub(crate) fn commit_parent(&self) -> Result<Option<git2::Commit<'_>>, git2::Error> {
+        match self.find_last_commit() {
+            Ok(commit) => Ok(Some(commit)),
+            Err(e) if e.code() == ErrorCode::UnbornBranch => {
+                self.check_first_commit_branch()?;
+                Ok(None)
+            },
+            Err(e) => Err(e),
+        }
+    }
 
+    /// Creates the commit of `tree` in [`Config::commit_encoding`] and moves HEAD to it.
+    pub(crate) fn write_commit(
+        &self,
+        message: &str,
+        tree: &git2::Tree<'_>,
+        parent_commit: Option<&git2::Commit<'_>>,
+    ) -> Result<Oid, git2::Error> {
         let author = Signature::now(&self.config.username, &self.config.email)?;
-        self.repo.commit(Some("HEAD"), &author, &author, message, &tree, &[&parent_commit])
+        let parents = parent_commit.into_iter().collect::<Vec<_>>();
+        match &self.config.commit_encoding {
+            Some(name) if encoding::needs_header(name)? =>
+                self.commit_encoded(name, &author, message, tree, &parents),
+            _ => self.repo.commit(Some("HEAD"), &author, &author, message, tree, &parents),
+        }
+    }
+
+    /// Writes the whole commit in the encoding `name` with an `encoding` header, as git does
+    /// with `i18n.commitEncoding`, and moves HEAD like [`Repository::commit`].
+    fn commit_encoded(
+        &self,
+        name: &str,
+        author: &Signature<'_>,
+        message: &str,
+        tree: &git2::Tree<'_>,
+        parents: &[&git2::Commit<'_>],
+    ) -> Result<Oid, git2::Error> {
+        let buffer = self.repo.commit_create_buffer(author, author, "", tree, parents)?;
+        let headers = buffer
+            .as_str()
+            .ok_or_else(|| git2::Error::from_str("commit headers are not valid UTF-8"))?
+            .trim_end_matches('\n');
+        let commit = encoding::encode(name, &format!("{headers}\nencoding {name}\n\n{message}"))?;
+        let id = self.repo.odb()?.write(ObjectType::Commit, &commit)?;
+
+        let summary = message.lines().next().unwrap_or_default();
+        let log_message = match parents {
+            [] => format!("commit (initial): {summary}"),
+            _ => format!("commit: {summary}"),
+        };
+        self.move_head(id, &log_message)?;
+        Ok(id)
+    }
+
+    /// Moves the branch HEAD names, or a detached HEAD, to `id`.
+    fn move_head(&self, id: Oid, log_message: &str) -> Result<(), git2::Error> {
+        match self.repo.find_reference("HEAD")?.symbolic_target() {
+            Some(branch) => self.repo.reference(branch, id, true, log_message).map(drop),
+            None => self.repo.set_head_detached(id),
+        }
+    }
+
+    /// Commits with a `Co-authored-by: Name <email>` trailer per co-author.
+    pub fn commit_with_coauthors(
+        &self,
+        message: &str,
+        coauthors: &[(String, String)],
+    ) -> Result<Oid, git2::Error> {
+        let trailers = coauthors
+            .iter()
+            .map(|(name, email)| format!("Co-authored-by: {name} <{email}>"))
+            .collect::<Vec<_>>();
+
+        self.commit(&append_trailers(message, &trailers))
+    }
+
+    /// Commits with a `Signed-off-by: Name <email>` trailer for the configured identity, as
+    /// `git commit -s` does for projects following the Developer Certificate of Origin.
+    /// The trailer joins a trailer block ending the message and isn't repeated when it is
+    /// already the last one.
+    pub fn commit_signed_off(&self, message: &str) -> Result<Oid, git2::Error> {
+        let signoff = format!("Signed-off-by: {} <{}>", self.config.username, self.config.email);
+        let message = message.trim_end();
+        match message.rsplit_once("\n\n").map(|(_, last)| last) {
+            Some(last) if last.lines().last() == Some(signoff.as_str()) =>
+                self.commit(&format!("{message}\n")),
+            Some(last) if last.lines().all(is_trailer) =>
+                self.commit(&format!("{message}\n{signoff}\n")),
+            _ => self.commit(&append_trailers(message, &[signoff])),
+        }
+    }
+
+    /// Stages the files matching `paths` and amends HEAD to include them, keeping its author
+    /// and message, for the file forgotten in the commit just made. The committer becomes
+    /// the configured identity. Returns the amended commit.
+    pub fn add_and_amend(&self, paths: &[&str]) -> Result<Oid, git2::Error> {
+        self.config.ensure_writable()?;
+        let head = self.find_last_commit().map_err(|e| match e.code() {
+            ErrorCode::UnbornBranch => git2::Error::new(
+                ErrorCode::UnbornBranch,
+                ErrorClass::Reference,
+                "nothing to amend, the branch has no commits yet",
+            ),
+            _ => e,
+        })?;
+        self.add(paths)?;
+        self.check_commit_locks()?;
+        self.check_no_conflicts()?;
+
+        let tree = self.repo.find_tree(self.repo.index()?.write_tree()?)?;
+        let committer = Signature::now(&self.config.username, &self.config.email)?;
+        let id = head.amend(None, None, Some(&committer), None, None, Some(&tree))?;
+        // libgit2 would log the amend as a plain commit
+        let summary = String::from_utf8_lossy(head.summary_bytes().unwrap_or_default());
+        self.move_head(id, &format!("commit (amend): {summary}"))?;
+        Ok(id)
+    }
+
+    /// Stores `user.name` and `user.email` in the repository's own config, so command-line
+    /// git commits under the same identity as [`Repo::commit`].
+    pub fn write_identity(&self, name: &str, email: &str) -> Result<(), git2::Error> {
+        self.config.ensure_writable()?;
+        let mut config = self.repo.config()?.open_level(ConfigLevel::Local)?;
+        config.set_str("user.name", name)?;
+        config.set_str("user.email", email)
     }
 
     pub fn checkout(&self, branch_name: &str) -> Result<(), git2::Error> {
+        self.config.ensure_writable()?;
         self.fetch_all()?;
+        self.switch_branch(branch_name)
+    }
 
-        let remote_branch_name = format!("origin/{branch_name}");
-
-        let (branch, brach_type) = self
-            .repo
-            .branches(None)?
-            .flatten()
-            .find(|(branch, branch_type)| match branch_type {
-                BranchType::Local => Ok(Some(branch_name)) == branch.name(),
-                BranchType::Remote => Ok(Some(remote_branch_name.as_str())) == branch.name(),
-            })
-            .ok_or(git2::Error::from_str("no branch with this name"))?;
+    /// Like [`Repo::checkout`] without fetching first, so only branches known from earlier
+    /// fetches can be switched to.
+    pub fn checkout_local(&self, branch_name: &str) -> Result<(), git2::Error> {
+        self.config.ensure_writable()?;
+        self.switch_branch(branch_name).map_err(|e| match e.code() {
+            ErrorCode::NotFound => git2::Error::new(
+                ErrorCode::NotFound,
+                ErrorClass::Reference,
+                format!("branch '{branch_name}' not found locally; fetch first"),
+            ),
+            _ => e,
+        })
+    }
 
-        let commit = branch
-            .get()
-            .resolve()?
-            .peel(ObjectType::Commit)?
-            .into_commit()
-            .map_err(|_e| git2::Error::from_str("Failed to obtain commit"))?;
+    fn switch_branch(&self, branch_name: &str) -> Result<(), git2::Error> {
+        let (commit, brach_type) = self.find_branch_commit(branch_name)?;
 
         if let BranchType::Remote = brach_type {
             self.repo
                 .branch(branch_name, &commit, false)?
-                .set_upstream(Some(&remote_branch_name))?;
+                .set_upstream(Some(&format!("origin/{branch_name}")))?;
         }
 
         self.repo.set_head(&format!("refs/heads/{branch_name}"))?;
-        self.repo.checkout_head(Some(CheckoutBuilder::default().allow_conflicts(true).force()))?;
+        self.retry_checkout(|| {
+            self.repo.checkout_head(Some(CheckoutBuilder::default().allow_conflicts(true).force()))
+        })?;
+        self.reapply_sparse_checkout()
+    }
 
-        Ok(())
+    /// Paths whose local changes a checkout of `branch_name` would overwrite, without
+    /// touching the working tree. An empty result means the checkout is safe.
+    pub fn checkout_dry_run(&self, branch_name: &str) -> Result<Vec<String>, git2::Error> {
+        let (commit, _branch_type) = self.find_branch_commit(branch_name)?;
+
+        let mut conflicts = Vec::new();
+        let mut builder = CheckoutBuilder::new();
+        builder.dry_run().notify_on(CheckoutNotificationType::CONFLICT).notify(
+            |_notification, path, _baseline, _target, _workdir| {
+                if let Some(path) = path {
+                    conflicts.push(path.to_string_lossy().into_owned());
+                }
+                true
+            },
+        );
+        self.repo.checkout_tree(commit.as_object(), Some(&mut builder))?;
+        drop(builder);
+
+        Ok(conflicts)
+    }
+
+    /// Replaces `paths` in the index and working tree with their version at `revspec`,
+    /// like `git checkout <revspec> -- <paths>`, without switching branches. Paths are
+    /// pathspecs, so a directory or glob takes every file under it, and tracked files under
+    /// it that `revspec` lacks are removed. Returns the number of files taken; a path
+    /// matching no file at `revspec` fails before anything changes.
+    pub fn checkout_paths(&self, revspec: &str, paths: &[&str]) -> Result<usize, git2::Error> {
+        self.config.ensure_writable()?;
+        let tree = self.repo.revparse_single(revspec)?.peel_to_tree()?;
+
+        let pathspec = Pathspec::new(paths)?;
+        let matches = pathspec.match_tree(&tree, PathspecFlags::FIND_FAILURES)?;
+        if let Some(missing) = matches.failed_entries().next() {
+            return Err(git2::Error::new(
+                ErrorCode::NotFound,
+                ErrorClass::Checkout,
+                format!(
+                    "'{}' did not match any file in {revspec}",
+                    String::from_utf8_lossy(missing)
+                ),
+            ));
+        }
+
+        self.retry_checkout(|| {
+            let mut builder = CheckoutBuilder::new();
+            builder.force();
+            for path in paths {
+                builder.path(path);
+            }
+            self.repo.checkout_tree(tree.as_object(), Some(&mut builder))
+        })?;
+        Ok(matches.entries().len())
+    }
+
+    /// Like [`Repo::checkout_paths`], but takes the paths found at `revspec` and reports
+    /// the rest instead of failing, e.g. to bring a few modules over from another branch
+    /// without merging it. Returns one result per path, in order.
+    pub fn checkout_paths_from(
+        &self,
+        revspec: &str,
+        paths: &[&str],
+    ) -> Result<Vec<RestoredPath>, git2::Error> {
+        self.config.ensure_writable()?;
+        let tree = self.repo.revparse_single(revspec)?.peel_to_tree()?;
+
+        let mut restored = Vec::with_capacity(paths.len());
+        for path in paths {
+            let files =
+                Pathspec::new([path])?.match_tree(&tree, PathspecFlags::DEFAULT)?.entries().len();
+            restored.push(RestoredPath {
+                path: (*path).to_string(),
+                files,
+            });
+        }
+        // without paths the checkout would take the whole tree
+        if restored.iter().any(|path| path.files > 0) {
+            self.retry_checkout(|| {
+                let mut builder = CheckoutBuilder::new();
+                builder.force();
+                for path in restored.iter().filter(|path| path.files > 0) {
+                    builder.path(&path.path);
+                }
+                self.repo.checkout_tree(tree.as_object(), Some(&mut builder))
+            })?;
+        }
+        Ok(restored)
+    }
+
+    /// Throws away the unstaged changes to `paths`, restoring them from the index. Symlinks
+    /// are restored as links and submodule checkouts are left alone.
+    pub fn discard_changes(&self, paths: &[&str]) -> Result<(), git2::Error> {
+        self.config.ensure_writable()?;
+        self.retry_checkout(|| {
+            let mut builder = CheckoutBuilder::new();
+            builder.force().disable_pathspec_match(true);
+            for path in paths {
+                builder.path(path);
+            }
+            self.repo.checkout_index(None, Some(&mut builder))
+        })
+    }
+
+    /// Runs the checkout `operation` again, up to [`Config::checkout_retries`] times, while
+    /// it fails on a file another program holds open, see [`is_sharing_violation`]. A file
+    /// still held then fails with [`ErrorCode::Locked`] and a message naming it, libgit2's
+    /// only names the system call. Other failures return at once as libgit2 reports them.
+    fn retry_checkout<T>(
+        &self,
+        mut operation: impl FnMut() -> Result<T, git2::Error>,
+    ) -> Result<T, git2::Error> {
+        let mut retries = 0;
+        loop {
+            match operation() {
+                Err(e) if held_file(&e).is_some() && retries < self.config.checkout_retries => {
+                    retries += 1;
+                    thread::sleep(self.config.checkout_retry_delay);
+                },
+                Err(e) => {
+                    let Some(path) = held_file(&e) else {
+                        return Err(e);
+                    };
+                    return Err(git2::Error::new(
+                        ErrorCode::Locked,
+                        ErrorClass::Checkout,
+                        format!(
+                            "{path} is held open by another program, e.g. the 1C Designer, close \
+                             it and try again ({})",
+                            e.message()
+                        ),
+                    ));
+                },
+                result => return result,
+            }
+        }
+    }
+
+    /// Checks out the commit the tag `tag` points at, annotated or lightweight, with HEAD
+    /// detached, e.g. to look at a release. Local changes it would overwrite make it fail.
+    pub fn checkout_tag(&self, tag: &str) -> Result<Oid, git2::Error> {
+        self.config.ensure_writable()?;
+        let reference = match self.repo.find_reference(&format!("refs/tags/{tag}")) {
+            Err(e) if e.code() == ErrorCode::NotFound => {
+                return Err(git2::Error::new(
+                    ErrorCode::NotFound,
+                    ErrorClass::Tag,
+                    format!("tag '{tag}' not found, fetch first if it was created elsewhere"),
+                ));
+            },
+            result => result?,
+        };
+        let commit = reference.peel_to_commit()?.id();
+        self.checkout_detached(commit)?;
+        Ok(commit)
     }
 
-    pub fn push(&self) -> Result<(), git2::Error> {
-        let mut origin = self.repo.find_remote("origin")?;
+    /// Pushes the current branch to its [`Repo::push_remote`] and reports how long the
+    /// phases took. It goes to its namesake there unless `push.default` says otherwise, see
+    /// [`crate::PushDefault`]. The first push of a new repository sets the upstream.
+    pub fn push(&self) -> Result<PushReport, git2::Error> {
+        self.config.ensure_writable()?;
         let repo_head = self.repo.head()?;
+        if !repo_head.is_branch() {
+            return Err(detached_head());
+        }
         let branch_name =
             repo_head.name().ok_or_else(|| git2::Error::from_str("no branch name"))?;
-        let mut options = Self::push_options(self.config);
-        origin.push(&[branch_name], Some(&mut options))?;
+        let remote_name = self.push_remote(branch_name)?;
+        let target = self.push_target(branch_name, &remote_name)?;
+        let mut origin = self.find_remote(&remote_name)?;
+        let first_push = self.is_first_push(branch_name)?;
+        self.check_first_push(&mut origin, branch_name)?;
+        let mut refspecs = vec![format!("{branch_name}:{target}")];
+        if self.config.sync_notes {
+            refspecs.extend(self.notes_push_refspecs()?);
+        }
+        let threshold = self.apply_big_file_threshold()?;
+
+        let timer = PushTimer::start();
+        let mut callbacks = RemoteCallbacks::new();
+        timer.register(&mut callbacks);
+        let mut options = Self::push_options_with(self.config, callbacks);
+        origin.push(&refspecs, Some(&mut options))?;
+        drop(options);
+        drop(threshold);
+        self.record_sync(&remote_name, SyncKind::Push);
+        if first_push {
+            // as `git push -u`, the published branch tracks where it went
+            let name = branch_name.trim_start_matches("refs/heads/");
+            let mut config = self.repo.config()?;
+            config.set_str(&format!("branch.{name}.remote"), &remote_name)?;
+            config.set_str(&format!("branch.{name}.merge"), &target)?;
+        }
 
+        Ok(timer.finish())
+    }
+
+    /// Pushes the local branch `local` to `remote_branch` on `remote`, by default the
+    /// branch's [`Repo::push_remote`], e.g. `feature` to `review/feature`. A full `refs/...`
+    /// name is pushed to as is.
+    pub fn push_to(
+        &self,
+        local: &str,
+        remote_branch: &str,
+        remote: Option<&str>,
+    ) -> Result<(), git2::Error> {
+        self.config.ensure_writable()?;
+        let source = self.repo.find_branch(local, BranchType::Local)?.into_reference();
+        let source = source.name().ok_or_else(|| git2::Error::from_str("no branch name"))?;
+        let mut remote = match remote {
+            Some(remote) => self.find_remote(remote)?,
+            None => self.find_remote(&self.push_remote(source)?)?,
+        };
+        let target = if remote_branch.starts_with("refs/") {
+            remote_branch.to_string()
+        } else {
+            format!("refs/heads/{remote_branch}")
+        };
+
+        let mut refspecs = vec![format!("{source}:{target}")];
+        if self.config.sync_notes {
+            refspecs.extend(self.notes_push_refspecs()?);
+        }
+        let mut options = Self::push_options(self.config);
+        remote.push(&refspecs, Some(&mut options))?;
+        if let Some(name) = remote.name() {
+            self.record_sync(name, SyncKind::Push);
+        }
         Ok(())
     }
 
+    /// Pushes `refspecs` like `release/1.2`, `v1.2` or `+main:refs/heads/review` to
+    /// `remote` in one go and returns the remote refs updated. A name without `refs/`
+    /// stands for the local branch or tag of that name, a `+` forces the update and
+    /// `:refs/tags/name` deletes the remote ref.
+    ///
+    /// Before anything is sent, libgit2 refuses the whole push when a source is missing or
+    /// an update isn't a fast-forward, [`PushAtomicity::Atomic`] checks every ref first and
+    /// names all that would be refused. The server may still reject some refs, e.g. in a
+    /// hook, and keeps the others. The error lists what was rejected and what was updated.
+    pub fn push_refspecs(
+        &self,
+        remote: &str,
+        refspecs: &[&str],
+        atomicity: PushAtomicity,
+    ) -> Result<Vec<String>, git2::Error> {
+        self.config.ensure_writable()?;
+        if refspecs.is_empty() {
+            return Err(git2::Error::from_str("no refspecs to push"));
+        }
+        let specs =
+            refspecs.iter().map(|spec| self.push_spec(spec)).collect::<Result<Vec<_>, _>>()?;
+        let mut found = self.find_remote(remote)?;
+
+        let rejected = RefCell::new(Vec::new());
+        let updated = RefCell::new(Vec::new());
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.push_update_reference(|reference, status| {
+            match status {
+                Some(reason) => rejected.borrow_mut().push(format!("{reference} ({reason})")),
+                None => updated.borrow_mut().push(reference.to_string()),
+            }
+            Ok(())
+        });
+        if atomicity == PushAtomicity::Atomic {
+            // called with the refs the server listed, before the pack is built
+            callbacks.push_negotiation(|updates| {
+                let refused = self.atomic_refusals(updates, &specs);
+                if refused.is_empty() {
+                    return Ok(());
+                }
+                Err(git2::Error::new(
+                    ErrorCode::NotFastForward,
+                    ErrorClass::Reference,
+                    format!(
+                        "atomic push to '{remote}' refused, nothing was pushed: {}",
+                        refused.join(", ")
+                    ),
+                ))
+            });
+        }
+        let mut options = Self::push_options_with(self.config, callbacks);
+        let pushed = specs.iter().map(PushSpec::refspec).collect::<Vec<_>>();
+        found.push(&pushed, Some(&mut options))?;
+        drop(options);
+        let (rejected, updated) = (rejected.into_inner(), updated.into_inner());
+        if rejected.is_empty() {
+            self.record_sync(remote, SyncKind::Push);
+            return Ok(updated);
+        }
+
+        let mut message = format!("the remote rejected {}", rejected.join(", "));
+        if !updated.is_empty() {
+            message.push_str(&format!("; updated {}", updated.join(", ")));
+        }
+        Err(git2::Error::new(ErrorCode::GenericError, ErrorClass::Reference, message))
+    }
+
+    /// The `updates` of an atomic [`Repo::push_refspecs`] that can't go through, each as
+    /// "ref (reason)".
+    fn atomic_refusals(&self, updates: &[PushUpdate<'_>], specs: &[PushSpec]) -> Vec<String> {
+        let mut refused = Vec::new();
+        for update in updates {
+            let Some(dst) = update.dst_refname() else { continue };
+            let (old, new) = (update.src(), update.dst());
+            let force = specs.iter().any(|spec| spec.force && spec.dst == dst);
+            if force || old.is_zero() || new.is_zero() || old == new {
+                continue;
+            }
+            let reason = if self.repo.find_object(old, None).is_err() {
+                "the remote has commits we don't, fetch first"
+            } else if dst.starts_with("refs/tags/") {
+                "the tag already exists"
+            } else if !self.fast_forward(old, new) {
+                "non-fast-forward"
+            } else {
+                continue;
+            };
+            refused.push(format!("{dst} ({reason})"));
+        }
+        refused
+    }
+
+    /// Whether the commit `new` peels to contains `old`.
+    fn fast_forward(&self, old: Oid, new: Oid) -> bool {
+        let Ok(new) = self.repo.find_object(new, None).and_then(|new| new.peel_to_commit()) else {
+            return false;
+        };
+        new.id() == old || self.repo.graph_descendant_of(new.id(), old).unwrap_or(false)
+    }
+
+    /// `spec` of [`Repo::push_refspecs`] with full ref names and the object it pushes.
+    fn push_spec(&self, spec: &str) -> Result<PushSpec, git2::Error> {
+        let (force, spec) = match spec.strip_prefix('+') {
+            Some(spec) => (true, spec),
+            None => (false, spec),
+        };
+        let (src, dst) = spec.split_once(':').unwrap_or((spec, ""));
+        let src = match self.repo.resolve_reference_from_short_name(src) {
+            _ if src.is_empty() => String::new(),
+            Ok(reference) =>
+                reference.name().ok_or_else(|| git2::Error::from_str("no ref name"))?.to_string(),
+            Err(_) => self.repo.revparse_single(src)?.id().to_string(),
+        };
+        let dst = if dst.is_empty() {
+            src.clone()
+        } else if dst.starts_with("refs/") || src.is_empty() {
+            dst.to_string()
+        } else if src.starts_with("refs/tags/") {
+            format!("refs/tags/{dst}")
+        } else {
+            format!("refs/heads/{dst}")
+        };
+        if !dst.starts_with("refs/") {
+            // a remote branch and tag may share the name, a deletion has to say which
+            let example = if src.is_empty() {
+                format!(":refs/heads/{dst} or :refs/tags/{dst}")
+            } else {
+                format!("{src}:refs/heads/name")
+            };
+            return Err(git2::Error::new(
+                ErrorCode::InvalidSpec,
+                ErrorClass::Reference,
+                format!("'{spec}' needs a full destination like {example}"),
+            ));
+        }
+        Ok(PushSpec { force, src, dst })
+    }
+
     pub fn pull(&self, branch_name: &str) -> Result<PullResult, git2::Error> {
+        let result = self.pull_branch(branch_name)?;
+        if let PullResult::UpToDate | PullResult::FastForwarded { .. } = result {
+            self.record_pull(branch_name);
+        }
+        Ok(result)
+    }
+
+    fn pull_branch(&self, branch_name: &str) -> Result<PullResult, git2::Error> {
         let mut local_branch = self.repo.find_branch(branch_name, BranchType::Local)?;
+        if let Upstream::Gone(upstream) = self.upstream_of(&local_branch)? {
+            return Ok(PullResult::UpstreamGone { upstream });
+        }
         let remote_branch = local_branch.upstream()?;
         let old_id = local_branch.get().peel_to_commit()?.id();
 
         let remote_commit = remote_branch.get().peel_to_commit()?;
+        if let Some(policy) = &self.config.verify_policy
+            && policy.protects(branch_name)
+        {
+            self.require_verified(&format!("{old_id}..{}", remote_commit.id()), policy)?;
+        }
+
         let annotated_commit = self.repo.find_annotated_commit(remote_commit.id())?;
         let (analisis, _preference) =
             self.repo.merge_analysis_for_ref(local_branch.get(), &[&annotated_commit])?;
 
+        // libgit2 flags a fast-forward as normal too, so it has to be checked first
         if analisis.is_none() {
             Ok(PullResult::None)
-        } else if analisis.is_normal() {
-            Ok(PullResult::Normal)
         } else if analisis.is_up_to_date() {
             Ok(PullResult::UpToDate)
         } else if analisis.is_fast_forward() {
+            self.config.ensure_writable()?;
+            if local_branch.is_head() {
+                // fails before the branch moves when local changes would be overwritten
+                self.retry_checkout(|| {
+                    self.repo.checkout_tree(
+                        remote_commit.as_object(),
+                        Some(CheckoutBuilder::default().safe()),
+                    )
+                })?;
+            }
             let referense = local_branch.get_mut().set_target(
                 remote_commit.id(),
                 &format!("fast forward branch '{branch_name}' tip"),
             )?;
             let new_id = referense.peel_to_commit()?.id();
-            Ok(PullResult::FastForwarded { old_id, new_id })
+            if local_branch.is_head() {
+                self.reapply_sparse_checkout()?;
+            }
+            let old_tree = self.repo.find_commit(old_id)?.tree()?;
+            let (files, truncated) =
+                self.file_diffs(&old_tree, &remote_commit.tree()?, self.config.pull_file_limit)?;
+            Ok(PullResult::FastForwarded {
+                old_id,
+                new_id,
+                files,
+                truncated,
+            })
+        } else if analisis.is_normal() {
+            Ok(PullResult::Normal)
         } else if analisis.is_unborn() {
             Ok(PullResult::Unborn)
         } else {
@@ -202,61 +1310,455 @@ impl<'a> Repo<'a> {
     }
 
     pub fn merge(&self, _branch_from: &str, _branch_to: Option<&str>) -> Result<(), git2::Error> {
+        self.config.ensure_writable()?;
         // self.repo.
         // self.repo.merge(annotated_commits, merge_opts, checkout_opts)
 
         Ok(())
     }
 
-    fn fetch_all(&self) -> Result<(), git2::Error> {
-        for remote_name in self.repo.remotes()?.iter().flatten() {
-            let mut remote = self.repo.find_remote(remote_name)?;
-            let mut opts = Self::fetch_options(self.config);
-            remote.fetch(&[] as &[&str], Some(&mut opts), None)?;
+    /// Blame summary of `path` at HEAD: author names with the number of lines they last
+    /// touched, most lines first.
+    pub fn file_authors(&self, path: &str) -> Result<Vec<(String, usize)>, git2::Error> {
+        let blame = self.repo.blame_file(Path::new(path), None)?;
+
+        let mut lines = HashMap::new();
+        for hunk in blame.iter() {
+            let name = String::from_utf8_lossy(hunk.final_signature().name_bytes()).into_owned();
+            *lines.entry(name).or_insert(0) += hunk.lines_in_hunk();
         }
-        Ok(())
+        let mut authors = lines.into_iter().collect::<Vec<_>>();
+
+        authors.sort_by(|(a_name, a_lines), (b_name, b_lines)| {
+            b_lines.cmp(a_lines).then_with(|| a_name.cmp(b_name))
+        });
+
+        Ok(authors)
+    }
+
+    /// Parent ids of the commit `revspec` resolves to, several for a merge commit.
+    pub fn parents(&self, revspec: &str) -> Result<Vec<Oid>, git2::Error> {
+        let commit = self.repo.revparse_single(revspec)?.peel_to_commit()?;
+        Ok(commit.parent_ids().collect())
     }
 
-    fn push_options<'b>(config: &'a Config) -> PushOptions<'b>
+    /// The message of the commit `revspec` resolves to exactly as stored, trailers and
+    /// blank lines included, decoded from the commit's `encoding` header. Meant to prefill
+    /// the message when amending.
+    pub fn commit_raw_message(&self, revspec: &str) -> Result<String, git2::Error> {
+        let commit = self.repo.revparse_single(revspec)?.peel_to_commit()?;
+        Ok(encoding::decode(commit.message_encoding(), commit.message_raw_bytes()))
+    }
+
+    /// Whether `maybe_ancestor` is in the history of `descendant`, i.e. merging
+    /// `maybe_ancestor` into `descendant` is a no-op and the other way round a fast-forward.
+    /// Like `git merge-base --is-ancestor`, a commit counts as its own ancestor.
+    pub fn is_ancestor(&self, maybe_ancestor: &str, descendant: &str) -> Result<bool, git2::Error> {
+        let ancestor = self.resolve_commit(maybe_ancestor)?;
+        let descendant = self.resolve_commit(descendant)?;
+        Ok(ancestor == descendant || self.repo.graph_descendant_of(descendant, ancestor)?)
+    }
+
+    /// Id of the commit `revspec` resolves to, tags peeled.
+    pub fn resolve_commit(&self, revspec: &str) -> Result<Oid, git2::Error> {
+        Ok(self.repo.revparse_single(revspec)?.peel_to_commit()?.id())
+    }
+
+    /// Changed paths like `git diff --name-only`, see [`Repo::diff_between`] for the sides.
+    pub fn diff_names(
+        &self,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> Result<Vec<String>, git2::Error> {
+        let diff = self.diff_between(from, to, &mut DiffOptions::new())?;
+
+        let names = diff
+            .deltas()
+            .map(|delta| {
+                let path = delta.new_file().path().or_else(|| delta.old_file().path());
+                path.map(|path| path.to_string_lossy().into_owned()).unwrap_or_default()
+            })
+            .collect();
+
+        Ok(names)
+    }
+
+    /// Deletes remote-tracking refs of `remote_name` whose branches are gone from the server.
+    /// Only the advertised refs are read, nothing is downloaded.
+    pub fn prune(&self, remote_name: &str) -> Result<Vec<String>, git2::Error> {
+        self.config.ensure_writable()?;
+        let mut remote = self.find_remote(remote_name)?;
+
+        let pruned = RefCell::new(Vec::new());
+        self.connected(&mut remote, Direction::Fetch, |remote| {
+            let mut callbacks = RemoteCallbacks::new();
+            callbacks.update_tips(|reference, _old, new| {
+                if new.is_zero() {
+                    pruned.borrow_mut().push(reference.to_string());
+                }
+                true
+            });
+            remote.prune(Some(callbacks))
+        })?;
+
+        Ok(pruned.into_inner())
+    }
+
+    /// Unified diff between two revisions with [`Config::diff_context_lines`] of context,
+    /// see [`Repo::diff_between`] for the sides.
+    ///
+    /// Files that `.gitattributes` marks binary (`binary`, `-diff`) are reported as
+    /// "Binary files ... differ", Git LFS pointers as "LFS object" instead of their content.
+    pub fn diff(&self, from: Option<&str>, to: Option<&str>) -> Result<String, git2::Error> {
+        let mut patch = Vec::new();
+        self.write_diff(from, to, ReportFormat::Text, &mut patch)?;
+        Ok(String::from_utf8_lossy(&patch).into_owned())
+    }
+
+    /// Unified diff of the staged changes, HEAD to the index like `git diff --cached`, in
+    /// the format of [`Repo::diff`]. Before the first commit everything staged is added.
+    pub fn diff_staged(&self) -> Result<String, git2::Error> {
+        let head_tree = self.repo.head().and_then(|head| head.peel_to_tree()).ok();
+        let diff = self.repo.diff_tree_to_index(
+            head_tree.as_ref(),
+            None,
+            Some(&mut self.patch_options()),
+        )?;
+        let mut patch = Vec::new();
+        self.write_patch(&diff, ReportFormat::Text, &mut patch)?;
+        Ok(String::from_utf8_lossy(&patch).into_owned())
+    }
+
+    /// Value of the `.gitattributes` attribute `name` for `path`, checking the working tree
+    /// first and the index second like git does: "set", "unset", a value, or `None` when
+    /// unspecified.
+    pub fn check_attr(&self, path: &str, name: &str) -> Result<Option<String>, git2::Error> {
+        let value = self.repo.get_attr(Path::new(path), name, AttrCheckFlags::FILE_THEN_INDEX)?;
+
+        Ok(match AttrValue::from_string(value) {
+            AttrValue::True => Some("set".to_string()),
+            AttrValue::False => Some("unset".to_string()),
+            AttrValue::String(value) => Some(value.to_string()),
+            AttrValue::Bytes(value) => Some(String::from_utf8_lossy(value).into_owned()),
+            AttrValue::Unspecified => None,
+        })
+    }
+
+    /// The attributes affecting diff, merge and line endings of `path` that are specified.
+    pub fn file_attributes(&self, path: &str) -> Result<Vec<(&'static str, String)>, git2::Error> {
+        const ATTRIBUTES: [&str; 7] = ["text", "eol", "crlf", "binary", "diff", "merge", "filter"];
+
+        ATTRIBUTES
+            .iter()
+            .filter_map(|&name| {
+                self.check_attr(path, name).transpose().map(|value| Ok((name, value?)))
+            })
+            .collect()
+    }
+
+    pub(crate) fn fetch_all(&self) -> Result<(), git2::Error> {
+        self.prune_report().map(drop)
+    }
+
+    /// Fetches every remote and returns the remote-tracking refs the fetch pruned because
+    /// their branches are gone from the server. A remote failing, e.g. an unreachable fork,
+    /// doesn't keep the others from being fetched, the error naming each failed remote
+    /// comes after; [`Repo::fetch_all_report`] tells them apart.
+    pub fn prune_report(&self) -> Result<Vec<String>, git2::Error> {
+        let mut pruned = Vec::new();
+        let mut failed: Vec<(String, git2::Error)> = Vec::new();
+        for (remote_name, result) in self.fetch_all_report()? {
+            match result {
+                Ok(stats) => pruned.extend(stats.pruned),
+                Err(e) => failed.push((remote_name, e)),
+            }
+        }
+        let Some((_, first)) = failed.first() else {
+            return Ok(pruned);
+        };
+        let message = failed
+            .iter()
+            .map(|(remote_name, e)| format!("fetching '{remote_name}' failed: {}", e.message()))
+            .collect::<Vec<_>>()
+            .join("; ");
+        Err(git2::Error::new(first.code(), first.class(), message))
+    }
+
+    /// Fetches every remote, going on past the ones failing, and returns what each brought
+    /// or why it failed, in the order the remotes are configured.
+    pub fn fetch_all_report(&self) -> Result<FetchReport, git2::Error> {
+        let remotes = self.repo.remotes()?;
+        Ok(remotes
+            .iter()
+            .flatten()
+            .map(|remote_name| (remote_name.to_string(), self.fetch_remote(remote_name)))
+            .collect())
+    }
+
+    /// Fetches `remote_name` with pruning unless `fetch.prune` is off, and the notes when [`Config::sync_notes`] is set.
+    fn fetch_remote(&self, remote_name: &str) -> Result<FetchStats, git2::Error> {
+        let mut remote = self.repo.find_remote(remote_name)?;
+        let updated = RefCell::new(Vec::new());
+        let pruned = RefCell::new(Vec::new());
+        let mut callbacks = Self::register_credentials(self.config, RemoteCallbacks::new());
+        callbacks.update_tips(|reference, _old, new| {
+            let refs = if new.is_zero() { &pruned } else { &updated };
+            refs.borrow_mut().push(reference.to_string());
+            true
+        });
+        Self::register_cancel(self.config, &mut callbacks);
+        let mut opts = FetchOptions::new();
+        opts.remote_callbacks(callbacks)
+            .prune(self.fetch_prune()?)
+            .custom_headers(&self.config.http_headers());
+
+        // explicit refspecs replace the configured ones, so those are repeated
+        let mut refspecs = Vec::new();
+        if self.config.sync_notes {
+            let configured = remote.fetch_refspecs()?;
+            refspecs.extend(configured.iter().flatten().map(str::to_string));
+            refspecs.push(NOTES_REFSPEC.to_string());
+        }
+        remote.fetch(&refspecs, Some(&mut opts), None)?;
+        drop(opts);
+        self.record_sync(remote_name, SyncKind::Fetch);
+
+        let stats = remote.stats();
+        Ok(FetchStats {
+            received_objects: stats.received_objects(),
+            received_bytes: stats.received_bytes(),
+            updated: updated.into_inner(),
+            pruned: pruned.into_inner(),
+        })
+    }
+
+    pub(crate) fn push_options<'b>(config: &'a Config) -> PushOptions<'b>
+    where
+        'a: 'b,
+    {
+        Self::push_options_with(config, RemoteCallbacks::new())
+    }
+
+    /// [`Repo::push_options`] calling `callbacks` as well.
+    pub(crate) fn push_options_with<'b>(
+        config: &'a Config,
+        callbacks: RemoteCallbacks<'b>,
+    ) -> PushOptions<'b>
     where
         'a: 'b,
     {
-        let callbacks = Self::register_credentials(config, RemoteCallbacks::new());
+        let callbacks = Self::register_credentials(config, callbacks);
         let mut options = PushOptions::new();
-        options.remote_callbacks(callbacks);
+        options
+            .remote_callbacks(callbacks)
+            .custom_headers(&config.http_headers())
+            .packbuilder_parallelism(config.pack_threads);
         options
     }
 
-    fn fetch_options<'b>(config: &'a Config) -> FetchOptions<'b>
+    pub(crate) fn fetch_options<'b>(config: &'a Config) -> FetchOptions<'b>
     where
         'a: 'b,
     {
-        let callbacks = Self::register_credentials(config, RemoteCallbacks::new());
+        let mut callbacks = Self::register_credentials(config, RemoteCallbacks::new());
+        Self::register_cancel(config, &mut callbacks);
         let mut options = FetchOptions::new();
         options.remote_callbacks(callbacks);
         options.prune(FetchPrune::On);
+        options.custom_headers(&config.http_headers());
         options
     }
 
-    fn register_credentials<'b>(
+    /// Connects to `remote` like the transfers do, with the credentials and
+    /// [`Config::custom_headers`], without transferring anything. A fetch connection is
+    /// handed to `read` for what the server advertises. A push connection only proves the
+    /// server takes pushes from us, `read` gets the remote after it was closed.
+    pub(crate) fn connected<T>(
+        &self,
+        remote: &mut Remote<'_>,
+        direction: Direction,
+        read: impl FnOnce(&mut Remote<'_>) -> Result<T, git2::Error>,
+    ) -> Result<T, git2::Error> {
+        // git2 only connects without the headers, so a transfer connects that stops before
+        // sending anything: a download of a ref no server has, or a push that is called
+        // off once the server listed its refs
+        match direction {
+            Direction::Fetch => {
+                let mut options = Self::fetch_options(self.config);
+                options.download_tags(AutotagOption::None);
+                remote.download(&[CONNECT_ONLY_REF], Some(&mut options))?;
+                let result = read(remote);
+                remote.disconnect()?;
+                result
+            },
+            Direction::Push => {
+                let mut callbacks = RemoteCallbacks::new();
+                callbacks.push_negotiation(|_updates| {
+                    Err(git2::Error::new(ErrorCode::User, ErrorClass::Callback, CONNECT_ONLY_REF))
+                });
+                let mut options = Self::push_options_with(self.config, callbacks);
+                match remote.push::<&str>(&[], Some(&mut options)) {
+                    Err(e) if e.code() == ErrorCode::User && e.message() == CONNECT_ONLY_REF => {},
+                    Err(e) => return Err(e),
+                    Ok(()) => {},
+                }
+                read(remote)
+            },
+        }
+    }
+
+    /// Makes transfers check [`Config::cancel`].
+    fn register_cancel<'b>(config: &'a Config, callbacks: &mut RemoteCallbacks<'b>)
+    where
+        'a: 'b,
+    {
+        if let Some(cancel) = &config.cancel {
+            // returning false makes libgit2 abort the transfer
+            callbacks.transfer_progress(|_| !cancel.load(Ordering::Relaxed));
+        }
+    }
+
+    pub(crate) fn register_credentials<'b>(
         config: &'a Config,
         mut callbacks: RemoteCallbacks<'b>,
     ) -> RemoteCallbacks<'b>
     where
         'a: 'b,
     {
-        match &config.auth {
-            AuthType::Password(password) => {
-                callbacks.credentials(|_url, _username_from_url, _allowed_types| {
-                    Cred::userpass_plaintext(&config.username, password)
-                });
-            },
-            AuthType::None => {},
-        }
+        // libgit2 calls back again when the offered credentials are rejected,
+        // the configured secret is tried first and the provider is asked afterwards,
+        // the SSH agent and default (NTLM/Negotiate) credentials are only offered once
+        let mut attempt = 0;
+        let mut agent_tried = false;
+        let mut default_tried = false;
+        callbacks.credentials(move |url, username_from_url, allowed_types| {
+            // only SSH asks for a user name on its own, HTTP takes it with the password
+            let ssh_user = username_from_url.or(config.ssh_user.as_deref()).unwrap_or("git");
+            if allowed_types.contains(CredentialType::USERNAME) {
+                return Cred::username(ssh_user);
+            }
+
+            match &config.auth {
+                AuthType::Password(password)
+                    if allowed_types.contains(CredentialType::USER_PASS_PLAINTEXT) =>
+                {
+                    attempt += 1;
+                    let password = match attempt {
+                        1 => password.clone(),
+                        _ => Self::request_secret(config, url, CredentialKind::Password)?,
+                    };
+                    return Cred::userpass_plaintext(&config.username, &password);
+                },
+                AuthType::SshKey { private_key, passphrase }
+                    if allowed_types.contains(CredentialType::SSH_KEY) =>
+                {
+                    attempt += 1;
+                    let passphrase = match attempt {
+                        1 => passphrase.clone(),
+                        _ =>
+                            Some(Self::request_secret(config, url, CredentialKind::SshPassphrase)?),
+                    };
+                    return Cred::ssh_key(ssh_user, None, private_key, passphrase.as_deref());
+                },
+                _ => {},
+            }
+
+            if allowed_types.contains(CredentialType::SSH_KEY) && !agent_tried {
+                agent_tried = true;
+                return Cred::ssh_key_from_agent(ssh_user);
+            }
+            if allowed_types.contains(CredentialType::DEFAULT) && !default_tried {
+                default_tried = true;
+                return Cred::default();
+            }
+            if matches!(config.auth, AuthType::None) {
+                return Err(CredentialsRequired::new(url, allowed_types).into());
+            }
+
+            Err(git2::Error::new(
+                ErrorCode::Auth,
+                ErrorClass::Net,
+                format!("no configured credentials are accepted by '{url}' ({allowed_types:?})"),
+            ))
+        });
         callbacks
     }
 
-    fn find_last_commit(&self) -> Result<git2::Commit, git2::Error> {
+    fn request_secret(
+        config: &Config,
+        url: &str,
+        kind: CredentialKind,
+    ) -> Result<String, git2::Error> {
+        let provider = config.credential_provider.as_ref().ok_or_else(|| {
+            git2::Error::new(
+                ErrorCode::Auth,
+                ErrorClass::Net,
+                format!("{kind} rejected by '{url}'"),
+            )
+        })?;
+
+        provider.request(url, kind)
+    }
+
+    /// Diff between two revisions, where a missing side means:
+    /// - neither given: index to working tree, i.e. unstaged changes;
+    /// - only `from`: `from` to working tree, including staged changes;
+    /// - only `to`: HEAD to `to`.
+    pub(crate) fn diff_between(
+        &self,
+        from: Option<&str>,
+        to: Option<&str>,
+        options: &mut DiffOptions,
+    ) -> Result<git2::Diff<'_>, git2::Error> {
+        let tree = |revspec: &str| self.repo.revparse_single(revspec)?.peel_to_tree();
+
+        match (from, to) {
+            (None, None) => self.repo.diff_index_to_workdir(None, Some(options)),
+            (Some(from), None) =>
+                self.repo.diff_tree_to_workdir_with_index(Some(&tree(from)?), Some(options)),
+            (from, Some(to)) => self.repo.diff_tree_to_tree(
+                Some(&tree(from.unwrap_or("HEAD"))?),
+                Some(&tree(to)?),
+                Some(options),
+            ),
+        }
+    }
+
+    /// Tip of the local branch `branch_name`, or of `origin/branch_name` when there is no
+    /// local one yet.
+    fn find_branch_commit(
+        &self,
+        branch_name: &str,
+    ) -> Result<(git2::Commit<'_>, BranchType), git2::Error> {
+        let remote_branch_name = format!("origin/{branch_name}");
+
+        let (branch, brach_type) = self
+            .repo
+            .branches(None)?
+            .flatten()
+            .find(|(branch, branch_type)| match branch_type {
+                BranchType::Local => Ok(Some(branch_name)) == branch.name(),
+                BranchType::Remote => Ok(Some(remote_branch_name.as_str())) == branch.name(),
+            })
+            .ok_or_else(|| {
+                git2::Error::new(
+                    ErrorCode::NotFound,
+                    ErrorClass::Reference,
+                    "no branch with this name",
+                )
+            })?;
+
+        let commit = branch
+            .get()
+            .resolve()?
+            .peel(ObjectType::Commit)?
+            .into_commit()
+            .map_err(|_e| git2::Error::from_str("Failed to obtain commit"))?;
+
+        Ok((commit, brach_type))
+    }
+
+    fn find_last_commit(&self) -> Result<git2::Commit<'_>, git2::Error> {
         self.repo
             .head()?
             .resolve()?
@@ -266,17 +1768,133 @@ impl<'a> Repo<'a> {
     }
 }
 
+/// Appends `trailers` to `message` as a separate paragraph, one per line.
+fn append_trailers(message: &str, trailers: &[String]) -> String {
+    if trailers.is_empty() {
+        return message.to_string();
+    }
+    format!("{}\n\n{}\n", message.trim_end(), trailers.join("\n"))
+}
+
+/// Whether `line` reads as a `Token: value` trailer, the token having no spaces.
+fn is_trailer(line: &str) -> bool {
+    line.split_once(": ").is_some_and(|(token, _)| {
+        !token.is_empty() && token.chars().all(|c| c.is_alphanumeric() || c == '-')
+    })
+}
+
+/// Blob and mode of every entry of `index` outside of conflicts, by path.
+fn index_entries(index: &git2::Index) -> HashMap<Vec<u8>, (Oid, u32)> {
+    resolved_entries(index).map(|entry| (entry.path, (entry.id, entry.mode))).collect()
+}
+
+/// Paths whose entry in `index` is new or differs from the one in `before`, in index order.
+fn staged_since(before: &HashMap<Vec<u8>, (Oid, u32)>, index: &git2::Index) -> Vec<String> {
+    resolved_entries(index)
+        .filter(|entry| before.get(&entry.path) != Some(&(entry.id, entry.mode)))
+        .map(|entry| String::from_utf8_lossy(&entry.path).into_owned())
+        .collect()
+}
+
+/// Entries at stage 0, the conflict stages 1 to 3 are left out.
+fn resolved_entries(index: &git2::Index) -> impl Iterator<Item = git2::IndexEntry> + '_ {
+    index.iter().filter(|entry| (entry.flags >> 12) & 0x3 == 0)
+}
+
+/// Trailing checksum of the index file, which git rewrites on every index change.
+fn index_checksum(path: &Path) -> Option<(u64, [u8; 32])> {
+    let mut file = std::fs::File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+
+    // 20 bytes for SHA-1 repositories, 32 for SHA-256 ones
+    let mut checksum = [0; 32];
+    let checksum_len = checksum.len().min(usize::try_from(len).ok()?);
+    file.seek(SeekFrom::End(-(checksum_len as i64))).ok()?;
+    file.read_exact(&mut checksum[..checksum_len]).ok()?;
+
+    Some((len, checksum))
+}
+
+/// Sibling directory a clone is staged in, e.g. `repos/.project.clone` for `repos/project`.
+fn staging_path(path: &Path) -> Result<PathBuf, git2::Error> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| git2::Error::from_str("Catalog must name a directory to clone into"))?;
+
+    let mut staging_name = OsString::from(".");
+    staging_name.push(name);
+    staging_name.push(".clone");
+
+    Ok(path.with_file_name(staging_name))
+}
+
+/// Creates the directory a clone is staged in. It must not exist yet: it may belong to
+/// another clone still running, which removing it on failure would break.
+fn create_staging(staging: &Path) -> Result<(), git2::Error> {
+    let io_error = |e: std::io::Error| {
+        git2::Error::from_str(&format!("failed to create '{}': {e}", staging.display()))
+    };
+    if let Some(parent) = staging.parent() {
+        std::fs::create_dir_all(parent).map_err(io_error)?;
+    }
+    std::fs::create_dir(staging).map_err(|e| {
+        if e.kind() != std::io::ErrorKind::AlreadyExists {
+            return io_error(e);
+        }
+        git2::Error::new(
+            ErrorCode::Exists,
+            ErrorClass::Os,
+            format!(
+                "'{}' already exists, another clone may be running; remove it if none is",
+                staging.display()
+            ),
+        )
+    })
+}
+
+/// Whether `dest` exists as an empty directory. Anything else there is refused the same
+/// way `git clone` refuses a non-empty destination.
+fn destination_exists(dest: &Path) -> Result<bool, git2::Error> {
+    match dest.read_dir().map(|mut entries| entries.next().is_none()) {
+        Ok(true) => Ok(true),
+        Ok(false) => Err(git2::Error::new(
+            ErrorCode::Exists,
+            ErrorClass::Os,
+            format!(
+                "destination path '{}' already exists and is not an empty directory",
+                dest.display()
+            ),
+        )),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) =>
+            Err(git2::Error::from_str(&format!("failed to inspect '{}': {e}", dest.display()))),
+    }
+}
+
+/// Renames the finished clone onto `dest`, replacing an empty directory, checked again as
+/// something may have appeared there during the clone.
+fn move_into_place(staging: &Path, dest: &Path) -> Result<(), git2::Error> {
+    let io_error = |action: &str, e: std::io::Error| {
+        git2::Error::from_str(&format!("failed to {action} '{}': {e}", dest.display()))
+    };
+
+    if destination_exists(dest)? {
+        std::fs::remove_dir(dest).map_err(|e| io_error("replace empty directory", e))?;
+    }
+    std::fs::rename(staging, dest).map_err(|e| io_error("move the clone into", e))
+}
+
+/// Branch name with bytes that aren't valid UTF-8 replaced by U+FFFD.
 pub fn branch_name(branch: &git2::Branch) -> String {
-    match branch.name() {
-        Ok(Some(name)) => name.to_string(),
-        Ok(None) => INVALID_UTF8.to_string(),
+    match branch.name_bytes() {
+        Ok(name) => String::from_utf8_lossy(name).into_owned(),
         Err(e) => e.to_string(),
     }
 }
 
 pub struct TrackedBranch<'repo> {
     pub local: Branch<'repo>,
-    pub upstream: Option<Branch<'repo>>,
+    pub upstream: Upstream,
 }
 
 impl TrackedBranch<'_> {
@@ -284,12 +1902,63 @@ impl TrackedBranch<'_> {
         branch_name(&self.local)
     }
 
+    /// The remote-tracking branch followed, `None` when there is none or it is gone.
     pub fn upstream_name(&self) -> Option<String> {
-        self.upstream.as_ref().map(branch_name)
+        match &self.upstream {
+            Upstream::Configured(name) => Some(name.clone()),
+            Upstream::Gone(_) | Upstream::None => None,
+        }
+    }
+}
+
+/// One path given to [`Repo::checkout_paths_from`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestoredPath {
+    pub path: String,
+    /// Files taken from the revision, 0 when the path is not there.
+    pub files: usize,
+}
+
+/// Where HEAD points, see [`Repo::head_ref`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeadRef {
+    /// The full ref HEAD names, e.g. `refs/heads/main`, `None` when detached.
+    pub full_name: Option<String>,
+    /// The commit HEAD resolves to, `None` before the first commit.
+    pub oid: Option<Oid>,
+    pub detached: bool,
+}
+
+/// What fetching a remote brought, see [`Repo::fetch_all_report`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FetchStats {
+    pub received_objects: usize,
+    pub received_bytes: usize,
+    /// Remote-tracking refs created or moved.
+    pub updated: Vec<String>,
+    /// Remote-tracking refs deleted because their branches are gone from the server.
+    pub pruned: Vec<String>,
+}
+
+/// A refspec of [`Repo::push_refspecs`] spelled out.
+struct PushSpec {
+    force: bool,
+    /// Full ref name or object id, empty to delete `dst`.
+    src: String,
+    dst: String,
+}
+
+impl PushSpec {
+    fn refspec(&self) -> String {
+        let force = if self.force { "+" } else { "" };
+        format!("{force}{}:{}", self.src, self.dst)
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Each remote with what fetching it brought or why that failed.
+pub type FetchReport = Vec<(String, Result<FetchStats, git2::Error>)>;
+
+#[derive(Debug, Clone)]
 pub enum PullResult {
     /// No merge is possible.
     None,
@@ -301,9 +1970,51 @@ pub enum PullResult {
     UpToDate,
     /// The given merge input is a fast-forward from HEAD and no merge
     /// needs to be performed. Check out the given merge input.
-    FastForwarded { old_id: Oid, new_id: Oid },
+    FastForwarded {
+        old_id: Oid,
+        new_id: Oid,
+        /// The files the pull changed, at most [`Config::pull_file_limit`] of them.
+        files: Vec<FileDiff>,
+        /// More files changed than `files` lists.
+        truncated: bool,
+    },
     /// The HEAD of the current repository is "unborn" and does not point to
     /// a valid commit. No merge can be performed, but the caller may wish
     /// to simply set HEAD to the target commit(s).
     Unborn,
+    /// The upstream of the branch, e.g. `origin/feature`, is gone from the remote. Nothing
+    /// was pulled, the branch has to be pushed to track it again or deleted.
+    UpstreamGone { upstream: String },
+}
+
+/// The file a failed checkout could not write or remove because another program holds it
+/// open, quoted, or "a file" when libgit2 didn't name it. `None` for any other failure.
+fn held_file(e: &git2::Error) -> Option<String> {
+    if !matches!(e.class(), ErrorClass::Os | ErrorClass::Filesystem | ErrorClass::Checkout) {
+        return None;
+    }
+    if !is_sharing_violation(e.message()) {
+        return None;
+    }
+    let quoted = e.message().split('\'').nth(1).filter(|path| !path.is_empty());
+    Some(quoted.map_or_else(|| "a file".to_string(), |path| format!("'{path}'")))
+}
+
+/// Whether libgit2's `message` ends in the text Windows has for `ERROR_SHARING_VIOLATION`
+/// or `ERROR_LOCK_VIOLATION`, in the user's language like libgit2 formats it. A denied
+/// access, a read-only file or directory among them, isn't a file held open.
+#[cfg(windows)]
+fn is_sharing_violation(message: &str) -> bool {
+    const SHARING_VIOLATIONS: [i32; 2] = [32, 33];
+    SHARING_VIOLATIONS.iter().any(|&code| {
+        let text = std::io::Error::from_raw_os_error(code).to_string();
+        let text = text.split(" (os error").next().unwrap_or_default().trim();
+        !text.is_empty() && message.contains(text)
+    })
+}
+
+/// Only Windows keeps others from writing a file a program has open.
+#[cfg(not(windows))]
+fn is_sharing_violation(_message: &str) -> bool {
+    false
 }
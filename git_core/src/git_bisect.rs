@@ -0,0 +1,204 @@
+//! Binary search through history for the commit that introduced a regression.
+//!
+//! State lives where git keeps it: the branch to return to in `BISECT_START`, the bad
+//! commit in `refs/bisect/bad`, every good one in `refs/bisect/good-<id>` and the replayable
+//! commands in `BISECT_LOG`, so a bisect started here can be inspected or reset with the
+//! git command line.
+
+use git2::{ErrorClass, ErrorCode, Oid, Sort, build::CheckoutBuilder};
+
+use crate::git::Repo;
+
+const START_FILE: &str = "BISECT_START";
+const LOG_FILE: &str = "BISECT_LOG";
+const BAD_REF: &str = "refs/bisect/bad";
+const GOOD_PREFIX: &str = "refs/bisect/good-";
+
+/// Where a bisect stands after starting or marking a commit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BisectStep {
+    /// `commit` is checked out and waits to be tested, `remaining` commits are still
+    /// suspects.
+    Next { commit: Oid, remaining: usize },
+    /// `commit` is the first bad one.
+    Found(Oid),
+}
+
+impl Repo<'_> {
+    /// Starts a bisect between a known `good` and a known `bad` revision and checks out
+    /// the first commit to test detached. Local changes that the checkout would overwrite
+    /// make it fail.
+    pub fn bisect_start(&self, good: &str, bad: &str) -> Result<BisectStep, git2::Error> {
+        self.config.ensure_writable()?;
+        let start_file = self.repo.path().join(START_FILE);
+        if start_file.exists() {
+            return Err(git2::Error::new(
+                ErrorCode::Exists,
+                ErrorClass::Repository,
+                "a bisect is already in progress, reset it first",
+            ));
+        }
+
+        let good = self.resolve_commit(good)?;
+        let bad = self.resolve_commit(bad)?;
+
+        // git records the branch name, or the id when HEAD is already detached
+        let head = self.repo.head()?;
+        let original = match head.shorthand() {
+            Some(branch) if head.is_branch() => branch.to_string(),
+            _ => head.peel_to_commit()?.id().to_string(),
+        };
+        std::fs::write(&start_file, format!("{original}\n"))
+            .map_err(|e| git2::Error::from_str(&format!("failed to write {START_FILE}: {e}")))?;
+
+        self.repo.reference(BAD_REF, bad, true, "bisect: bad")?;
+        self.repo.reference(&format!("{GOOD_PREFIX}{good}"), good, true, "bisect: good")?;
+        self.bisect_log(&format!("git bisect start '{bad}' '{good}'"))?;
+        self.bisect_next()
+    }
+
+    /// Records the result of testing `commit` and checks out the next one, or reports
+    /// the first bad commit once a single suspect is left.
+    pub fn bisect_mark(&self, commit: Oid, good: bool) -> Result<BisectStep, git2::Error> {
+        self.config.ensure_writable()?;
+        self.bisect_bad()?;
+        if good {
+            self.repo.reference(&format!("{GOOD_PREFIX}{commit}"), commit, true, "bisect: good")?;
+        } else {
+            self.repo.reference(BAD_REF, commit, true, "bisect: bad")?;
+        }
+        self.bisect_log(&format!("git bisect {} {commit}", if good { "good" } else { "bad" }))?;
+        self.bisect_next()
+    }
+
+    /// The first bad commit once the bisect has converged, `None` while suspects remain.
+    pub fn bisect_result(&self) -> Result<Option<Oid>, git2::Error> {
+        let bad = self.bisect_bad()?;
+        let suspects = self.bisect_suspects(bad)?;
+        Ok((suspects.len() == 1).then_some(bad))
+    }
+
+    /// Ends the bisect, checks out the branch or commit it started from and removes the
+    /// bisect refs.
+    pub fn bisect_reset(&self) -> Result<(), git2::Error> {
+        self.config.ensure_writable()?;
+        let start_file = self.repo.path().join(START_FILE);
+        let original = std::fs::read_to_string(&start_file).map_err(|_| {
+            git2::Error::new(ErrorCode::NotFound, ErrorClass::Repository, "no bisect in progress")
+        })?;
+        let original = original.trim();
+
+        match self.repo.find_branch(original, git2::BranchType::Local) {
+            Ok(branch) => {
+                let tree = branch.get().peel_to_tree()?;
+                self.repo
+                    .checkout_tree(tree.as_object(), Some(CheckoutBuilder::default().safe()))?;
+                self.repo.set_head(&format!("refs/heads/{original}"))?;
+            },
+            Err(e) if e.code() == ErrorCode::NotFound => {
+                self.checkout_detached(Oid::from_str(original)?)?;
+            },
+            Err(e) => return Err(e),
+        }
+
+        for reference in self.repo.references_glob("refs/bisect/*")? {
+            reference?.delete()?;
+        }
+        for file in [START_FILE, LOG_FILE] {
+            match std::fs::remove_file(self.repo.path().join(file)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(git2::Error::from_str(&format!("failed to remove {file}: {e}")));
+                },
+                _ => {},
+            }
+        }
+        Ok(())
+    }
+
+    fn bisect_log(&self, command: &str) -> Result<(), git2::Error> {
+        use std::io::Write;
+
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.repo.path().join(LOG_FILE))
+            .and_then(|mut log| writeln!(log, "{command}"))
+            .map_err(|e| git2::Error::from_str(&format!("failed to write {LOG_FILE}: {e}")))
+    }
+
+    fn bisect_bad(&self) -> Result<Oid, git2::Error> {
+        match self.repo.refname_to_id(BAD_REF) {
+            Err(e) if e.code() == ErrorCode::NotFound => Err(git2::Error::new(
+                ErrorCode::NotFound,
+                ErrorClass::Repository,
+                "no bisect in progress",
+            )),
+            result => result,
+        }
+    }
+
+    fn bisect_next(&self) -> Result<BisectStep, git2::Error> {
+        let bad = self.bisect_bad()?;
+        let suspects = self.bisect_suspects(bad)?;
+        if suspects.len() <= 1 {
+            return Ok(BisectStep::Found(bad));
+        }
+
+        let commit = self.bisect_midpoint(&suspects)?;
+        self.checkout_detached(commit)?;
+        Ok(BisectStep::Next {
+            commit,
+            remaining: suspects.len(),
+        })
+    }
+
+    /// Commits reachable from `bad` but from no good commit, parents before children.
+    fn bisect_suspects(&self, bad: Oid) -> Result<Vec<Oid>, git2::Error> {
+        let mut walk = self.repo.revwalk()?;
+        walk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+        walk.push(bad)?;
+        for reference in self.repo.references_glob(&format!("{GOOD_PREFIX}*"))? {
+            if let Some(good) = reference?.target() {
+                walk.hide(good)?;
+            }
+        }
+        walk.collect()
+    }
+
+    /// The suspect whose ancestors among the suspects come closest to half of them, so
+    /// either answer rules out about half. Ancestor sets are bitsets built parents first.
+    fn bisect_midpoint(&self, suspects: &[Oid]) -> Result<Oid, git2::Error> {
+        let index: std::collections::HashMap<Oid, usize> =
+            suspects.iter().enumerate().map(|(i, &oid)| (oid, i)).collect();
+        let words = suspects.len().div_ceil(64);
+        let mut ancestors: Vec<Vec<u64>> = Vec::with_capacity(suspects.len());
+
+        let mut best = (suspects[0], 0);
+        for (i, &oid) in suspects.iter().enumerate() {
+            let mut reach = vec![0u64; words];
+            reach[i / 64] |= 1 << (i % 64);
+            for parent in self.repo.find_commit(oid)?.parent_ids() {
+                if let Some(&p) = index.get(&parent) {
+                    for (word, parent_word) in reach.iter_mut().zip(&ancestors[p]) {
+                        *word |= parent_word;
+                    }
+                }
+            }
+
+            let count = reach.iter().map(|word| word.count_ones() as usize).sum::<usize>();
+            let score = count.min(suspects.len() - count);
+            if score > best.1 {
+                best = (oid, score);
+            }
+            ancestors.push(reach);
+        }
+        Ok(best.0)
+    }
+
+    /// Checks out `commit` with HEAD detached, failing on local changes it would overwrite.
+    pub(crate) fn checkout_detached(&self, commit: Oid) -> Result<(), git2::Error> {
+        let commit = self.repo.find_commit(commit)?;
+        self.repo.checkout_tree(commit.as_object(), Some(CheckoutBuilder::default().safe()))?;
+        self.repo.set_head_detached(commit.id())
+    }
+}
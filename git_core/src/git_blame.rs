@@ -0,0 +1,56 @@
+//! Blaming a single line, for an editor gutter that asks about the line under the cursor.
+
+use std::path::Path;
+
+use git2::{BlameOptions, ErrorClass, ErrorCode};
+
+use crate::{git::Repo, git_log::CommitInfo};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlameLine {
+    /// The line asked about, counting from 1.
+    pub line: u32,
+    /// The commit that last changed the line.
+    pub commit: CommitInfo,
+    /// Number of the line in that commit's version of the file, before later commits
+    /// moved it.
+    pub original_line: u32,
+}
+
+impl Repo<'_> {
+    /// Who last changed `line`, counting from 1, of `path` as committed at HEAD. Only that
+    /// line is blamed, which is much cheaper than [`Repo::file_authors`] for long files.
+    /// With `ignore_whitespace` a commit changing only whitespace, e.g. a reformat of the
+    /// whole module, doesn't count as changing the line.
+    pub fn blame_line(
+        &self,
+        path: &str,
+        line: u32,
+        ignore_whitespace: bool,
+    ) -> Result<BlameLine, git2::Error> {
+        let out_of_range = || {
+            git2::Error::new(
+                ErrorCode::Invalid,
+                ErrorClass::Invalid,
+                format!("'{path}' has no line {line}"),
+            )
+        };
+        let lineno = usize::try_from(line).map_err(|_| out_of_range())?;
+        if lineno == 0 {
+            return Err(out_of_range());
+        }
+
+        let mut options = BlameOptions::new();
+        options.min_line(lineno).max_line(lineno).ignore_whitespace(ignore_whitespace);
+        let blame = self.repo.blame_file(Path::new(path), Some(&mut options))?;
+        let hunk = blame.get_line(lineno).ok_or_else(out_of_range)?;
+
+        let commit = self.repo.find_commit(hunk.final_commit_id())?;
+        let original_line = hunk.orig_start_line() + (lineno - hunk.final_start_line());
+        Ok(BlameLine {
+            line,
+            commit: CommitInfo::from_commit(&commit),
+            original_line: u32::try_from(original_line).unwrap_or(u32::MAX),
+        })
+    }
+}
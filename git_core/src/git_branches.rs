@@ -0,0 +1,534 @@
+//! Branches: listing them with the remote-tracking branches grouped under the local ones,
+//! creating and renaming them, where they are pushed to, and housekeeping for local
+//! branches whose upstream was deleted on the server.
+
+use std::collections::BTreeMap;
+
+use git2::{
+    Branch,
+    BranchType,
+    ConfigLevel,
+    Direction,
+    ErrorClass,
+    ErrorCode,
+    FileMode,
+    Oid,
+    Remote,
+    Repository,
+    Signature,
+    build::CheckoutBuilder,
+};
+
+use crate::{
+    CommitInfo,
+    git::Repo,
+    git_locks::LOCKS_TRACKING,
+    git_preflight::detached_head,
+    refname,
+};
+
+/// The branches of a repository, remote-tracking branches grouped under their local
+/// counterpart, see [`Repo::branch_list`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BranchList {
+    pub local: Vec<LocalBranch>,
+    /// Remote-tracking branches without a local counterpart, e.g. `origin/feature/x`.
+    pub remote_only: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalBranch {
+    pub name: String,
+    /// Whether HEAD points at this branch.
+    pub is_head: bool,
+    /// Its upstream and its namesakes on the remotes, e.g. `origin/feature/x`.
+    pub remote_names: Vec<String>,
+}
+
+/// A branch with its newest commit, see [`Repo::branches_with_last_commit`].
+#[derive(Debug, Clone)]
+pub struct BranchActivity {
+    /// The local name, or for a remote-only branch the remote one, e.g. `origin/feature/x`.
+    pub name: String,
+    pub is_head: bool,
+    /// Whether there is no local branch, only the remote-tracking one.
+    pub remote_only: bool,
+    /// For a local branch, as in [`LocalBranch::remote_names`].
+    pub remote_names: Vec<String>,
+    pub last_commit: CommitInfo,
+}
+
+/// The upstream of a local branch, see [`Repo::upstream_of`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Upstream {
+    /// The remote-tracking branch it follows, e.g. `origin/main`.
+    Configured(String),
+    /// An upstream still configured whose remote-tracking branch doesn't exist, typically
+    /// pruned by a fetch after the branch was deleted or renamed on the server.
+    Gone(String),
+    /// A purely local branch.
+    None,
+}
+
+impl LocalBranch {
+    pub fn has_remote(&self) -> bool {
+        !self.remote_names.is_empty()
+    }
+}
+
+/// One line per branch, `* ` marking the current one and the remotes that have it in
+/// parentheses, e.g. `* feature/x (origin)`. A remote branch named unlike the local one
+/// is shown in full. The remote-only branches follow under `remote only:`.
+impl std::fmt::Display for BranchList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut lines = Vec::new();
+        for branch in &self.local {
+            let marker = if branch.is_head { '*' } else { ' ' };
+            let mut line = format!("{marker} {}", branch.name);
+            if branch.has_remote() {
+                let suffix = format!("/{}", branch.name);
+                let remotes = branch
+                    .remote_names
+                    .iter()
+                    .map(|remote| remote.strip_suffix(&suffix).unwrap_or(remote))
+                    .collect::<Vec<_>>();
+                line.push_str(&format!(" ({})", remotes.join(", ")));
+            }
+            lines.push(line);
+        }
+        if !self.remote_only.is_empty() {
+            lines.push("remote only:".to_string());
+            lines.extend(self.remote_only.iter().map(|name| format!("  {name}")));
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl Repo<'_> {
+    /// Fetches like [`Repo::branches`] and lists the branches sorted case-insensitively. A
+    /// remote-tracking branch goes under the local branch it is the upstream of, or else
+    /// under the local branch of the same name. The remote HEADs and the locks are left out.
+    pub fn branch_list(&self) -> Result<BranchList, git2::Error> {
+        self.fetch_all()?;
+        self.local_branch_list()
+    }
+
+    /// [`Repo::branch_list`] from the refs as they are, without fetching.
+    pub(crate) fn local_branch_list(&self) -> Result<BranchList, git2::Error> {
+        // (short name, refname, name without the remote)
+        let mut remotes = Vec::new();
+        for branch in self.repo.branches(Some(BranchType::Remote))? {
+            let (branch, _) = branch?;
+            let reference = branch.get();
+            let Some(refname) = reference.name() else {
+                continue;
+            };
+            // the remote HEADs, and the locks of `git_locks` that are no branch to work on
+            if reference.symbolic_target().is_some() || refname == LOCKS_TRACKING {
+                continue;
+            }
+            let short = refname.trim_start_matches("refs/remotes/").to_string();
+            let remote = self.repo.branch_remote_name(refname)?;
+            let remote = remote.as_str().unwrap_or_default();
+            let name = short.strip_prefix(&format!("{remote}/")).unwrap_or(&short).to_string();
+            remotes.push((short, refname.to_string(), name));
+        }
+
+        let mut local = Vec::new();
+        let mut claimed = vec![false; remotes.len()];
+        for branch in self.repo.branches(Some(BranchType::Local))? {
+            let (branch, _) = branch?;
+            let name = crate::git::branch_name(&branch);
+            let upstream = branch
+                .get()
+                .name()
+                .and_then(|refname| self.repo.branch_upstream_name(refname).ok())
+                .and_then(|upstream| upstream.as_str().map(str::to_string));
+
+            let mut remote_names = Vec::new();
+            for (i, (short, refname, remote_name)) in remotes.iter().enumerate() {
+                if upstream.as_deref() == Some(refname.as_str()) || *remote_name == name {
+                    remote_names.push(short.clone());
+                    claimed[i] = true;
+                }
+            }
+            remote_names.sort_by_key(|remote| remote.to_lowercase());
+            local.push(LocalBranch {
+                name,
+                is_head: branch.is_head(),
+                remote_names,
+            });
+        }
+        local.sort_by(|a, b| sort_key(&a.name).cmp(&sort_key(&b.name)));
+
+        let mut remote_only: Vec<String> = remotes
+            .into_iter()
+            .zip(claimed)
+            .filter(|(_, claimed)| !claimed)
+            .map(|((short, ..), _)| short)
+            .collect();
+        remote_only.sort_by(|a, b| sort_key(a).cmp(&sort_key(b)));
+
+        Ok(BranchList { local, remote_only })
+    }
+
+    /// Fetches and lists the branches of [`Repo::branch_list`] in one list with the commit
+    /// each points at, the most recently committed to first, for a picker showing the
+    /// active branches on top. Ties are sorted by name.
+    pub fn branches_with_last_commit(&self) -> Result<Vec<BranchActivity>, git2::Error> {
+        self.fetch_all()?;
+        let list = self.local_branch_list()?;
+
+        let mut branches = Vec::new();
+        for branch in list.local {
+            let commit =
+                self.repo.find_branch(&branch.name, BranchType::Local)?.get().peel_to_commit()?;
+            branches.push(BranchActivity {
+                name: branch.name,
+                is_head: branch.is_head,
+                remote_only: false,
+                remote_names: branch.remote_names,
+                last_commit: CommitInfo::from_commit(&commit),
+            });
+        }
+        for name in list.remote_only {
+            let commit =
+                self.repo.find_branch(&name, BranchType::Remote)?.get().peel_to_commit()?;
+            branches.push(BranchActivity {
+                name,
+                is_head: false,
+                remote_only: true,
+                remote_names: Vec::new(),
+                last_commit: CommitInfo::from_commit(&commit),
+            });
+        }
+        branches.sort_by(|a, b| {
+            b.last_commit
+                .time
+                .cmp(&a.last_commit.time)
+                .then_with(|| sort_key(&a.name).cmp(&sort_key(&b.name)))
+        });
+        Ok(branches)
+    }
+
+    /// Local branches with a configured upstream that no longer exists, typically because
+    /// it was merged and deleted on the server. Only local refs are read, fetch first (e.g.
+    /// with [`Repo::prune_report`]) to see the server's current state.
+    pub fn stale_local_branches(&self) -> Result<Vec<String>, git2::Error> {
+        let mut stale = Vec::new();
+        for branch in self.repo.branches(Some(BranchType::Local))? {
+            let (branch, _) = branch?;
+            if let Upstream::Gone(_) = self.upstream_of(&branch)? {
+                stale.push(crate::git::branch_name(&branch));
+            }
+        }
+        Ok(stale)
+    }
+
+    /// The upstream of the local `branch` and whether it still exists. Only local refs are
+    /// read, so an upstream deleted on the server is gone once a fetch pruned it.
+    pub fn upstream_of(&self, branch: &Branch) -> Result<Upstream, git2::Error> {
+        // libgit2 only looks up upstreams by UTF-8 names
+        let Some(refname) = branch.get().name() else {
+            return Ok(Upstream::None);
+        };
+        let upstream = match self.repo.branch_upstream_name(refname) {
+            Ok(upstream) => upstream,
+            Err(e) if e.code() == ErrorCode::NotFound => return Ok(Upstream::None),
+            Err(e) => return Err(e),
+        };
+        let Some(upstream) = upstream.as_str() else {
+            return Ok(Upstream::None);
+        };
+
+        // "refs/heads/x" for an upstream that is a local branch
+        let short = upstream.trim_start_matches("refs/remotes/").trim_start_matches("refs/heads/");
+        match self.repo.find_reference(upstream) {
+            Ok(_) => Ok(Upstream::Configured(short.to_string())),
+            Err(e) if e.code() == ErrorCode::NotFound => Ok(Upstream::Gone(short.to_string())),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The remote-tracking branch the local `branch` follows, failing with a message that
+    /// says what to do when its upstream is gone.
+    pub(crate) fn tracked_upstream<'b>(
+        &self,
+        branch: &Branch<'b>,
+    ) -> Result<Branch<'b>, git2::Error> {
+        if let Upstream::Gone(upstream) = self.upstream_of(branch)? {
+            return Err(upstream_gone(&crate::git::branch_name(branch), &upstream));
+        }
+        branch.upstream()
+    }
+
+    /// Deletes the branches [`Repo::stale_local_branches`] reports, except the checked out
+    /// one, and returns their names. With `dry_run` nothing is deleted.
+    pub fn delete_stale_branches(&self, dry_run: bool) -> Result<Vec<String>, git2::Error> {
+        if !dry_run {
+            self.config.ensure_writable()?;
+        }
+        let mut deleted = Vec::new();
+        for name in self.stale_local_branches()? {
+            let mut branch = self.repo.find_branch(&name, BranchType::Local)?;
+            if branch.is_head() {
+                continue;
+            }
+            if !dry_run {
+                branch.delete()?;
+            }
+            deleted.push(name);
+        }
+        Ok(deleted)
+    }
+
+    /// The commit a branch points at, without enumerating the other branches. Remote
+    /// branches are named like `origin/main`.
+    pub fn branch_tip(&self, name: &str, branch_type: BranchType) -> Result<Oid, git2::Error> {
+        let branch = self.repo.find_branch(name, branch_type)?;
+        branch.get().peel_to_commit().map(|commit| commit.id())
+    }
+
+    /// Renames the checked out branch, also before its first commit. HEAD follows it and
+    /// the `branch.<name>` config, including the upstream, moves to the new name.
+    pub fn rename_current_branch(&self, new_name: &str) -> Result<(), git2::Error> {
+        self.config.ensure_writable()?;
+        refname::check(new_name, self.config.transliterate_ref_names)?;
+        let old_name = self.head_branch()?;
+        match self.repo.find_branch(&old_name, BranchType::Local) {
+            Ok(mut branch) => {
+                branch.rename(new_name, false)?;
+            },
+            // nothing committed yet, only HEAD names the branch
+            Err(e) if e.code() == ErrorCode::NotFound => {
+                self.repo.set_head(&format!("refs/heads/{new_name}"))?;
+            },
+            Err(e) => return Err(e),
+        }
+        Ok(())
+    }
+
+    /// Creates the branch `name` with a history of its own, one parentless commit with
+    /// `message` holding just `files`, given as paths with `/` and their contents, e.g. for
+    /// release notes kept apart from the code. Nothing is read from or written to the
+    /// working tree unless `checkout` is set, then the branch is checked out like any other
+    /// and later commits go on top of it. Uncommitted changes the checkout would overwrite
+    /// make it fail without creating the branch, untracked files stay. Returns the commit.
+    pub fn branch_create_orphan(
+        &self,
+        name: &str,
+        message: &str,
+        files: &[(&str, &[u8])],
+        checkout: bool,
+    ) -> Result<Oid, git2::Error> {
+        self.config.ensure_writable()?;
+        refname::check(name, self.config.transliterate_ref_names)?;
+        let refname = format!("refs/heads/{name}");
+        if self.repo.find_reference(&refname).is_ok() {
+            return Err(git2::Error::new(
+                ErrorCode::Exists,
+                ErrorClass::Reference,
+                format!("branch '{name}' already exists"),
+            ));
+        }
+
+        let mut root = TreeNode::default();
+        for (path, contents) in files {
+            root.insert(path, self.repo.blob(contents)?)?;
+        }
+        let tree = self.repo.find_tree(root.write(&self.repo)?)?;
+        let author = Signature::now(&self.config.username, &self.config.email)?;
+        let id = self.repo.commit(None, &author, &author, message, &tree, &[])?;
+
+        // checked out before the branch exists, so a failing checkout leaves no branch behind
+        if checkout {
+            self.repo.checkout_tree(tree.as_object(), Some(CheckoutBuilder::new().safe()))?;
+        }
+        let summary = message.lines().next().unwrap_or_default();
+        self.repo.reference(&refname, id, false, &format!("commit (initial): {summary}"))?;
+        if checkout {
+            self.repo.set_head(&refname)?;
+        }
+        Ok(id)
+    }
+
+    /// The remote a push of the local branch `refname` goes to, for teams fetching from
+    /// one remote and pushing to their fork: `branch.<name>.pushRemote`, else
+    /// `remote.pushDefault`, else the remote the branch fetches from, else origin.
+    pub fn push_remote(&self, refname: &str) -> Result<String, git2::Error> {
+        let name = refname.trim_start_matches("refs/heads/");
+        let config = self.repo.config()?.snapshot()?;
+        let keys = [
+            format!("branch.{name}.pushRemote"),
+            "remote.pushDefault".to_string(),
+            format!("branch.{name}.remote"),
+        ];
+        for key in keys {
+            match config.get_string(&key) {
+                // "." is the repository itself, for an upstream that is a local branch
+                Ok(remote) if !remote.is_empty() && remote != "." => return Ok(remote),
+                Ok(_) => {},
+                Err(e) if e.code() == ErrorCode::NotFound => {},
+                Err(e) => return Err(e),
+            }
+        }
+        Ok("origin".to_string())
+    }
+
+    /// Makes pushes of the local `branch` go to `remote` while it keeps fetching from its
+    /// upstream, see [`Repo::push_remote`]. An empty `remote` removes the setting.
+    pub fn set_push_remote(&self, branch: &str, remote: &str) -> Result<(), git2::Error> {
+        self.config.ensure_writable()?;
+        self.repo.find_branch(branch, BranchType::Local)?;
+        let key = format!("branch.{branch}.pushRemote");
+        let mut config = self.repo.config()?.open_level(ConfigLevel::Local)?;
+        if remote.is_empty() {
+            return match config.remove(&key) {
+                Err(e) if e.code() == ErrorCode::NotFound => Ok(()),
+                result => result,
+            };
+        }
+        self.find_remote(remote)?;
+        config.set_str(&key, remote)
+    }
+
+    /// Name of the branch HEAD points at, which may not exist yet in a new repository.
+    pub(crate) fn head_branch(&self) -> Result<String, git2::Error> {
+        let head = self.repo.find_reference("HEAD")?;
+        head.symbolic_target()
+            .and_then(|target| target.strip_prefix("refs/heads/"))
+            .map(str::to_string)
+            .ok_or_else(detached_head)
+    }
+
+    /// Refuses to start history on `branch` when another name is `expected`, so the first
+    /// commit or push doesn't create a branch next to the server's default.
+    pub(crate) fn ensure_default_branch(
+        &self,
+        branch: &str,
+        expected: Option<&str>,
+    ) -> Result<(), git2::Error> {
+        match expected {
+            Some(expected) if expected != branch => Err(git2::Error::new(
+                ErrorCode::Invalid,
+                ErrorClass::Reference,
+                format!(
+                    "the current branch is '{branch}' but the default branch is '{expected}', \
+                     rename it first"
+                ),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// A push of the only local branch, which has no upstream yet: the push that
+    /// publishes a new repository. Before the first commit there is no branch at all.
+    pub(crate) fn is_first_push(&self, refname: &str) -> Result<bool, git2::Error> {
+        match self.repo.branch_upstream_name(refname) {
+            Ok(_) => return Ok(false),
+            Err(e) if e.code() == ErrorCode::NotFound => {},
+            Err(e) => return Err(e),
+        }
+        Ok(self.repo.branches(Some(BranchType::Local))?.count() <= 1)
+    }
+
+    /// The commit `branch` points at on `remote`, read from the refs the server advertises
+    /// as `git ls-remote` does, without fetching. Differing from the local remote-tracking
+    /// branch, it tells cheaply that the remote has new commits. Fails with
+    /// [`ErrorCode::NotFound`] when the remote has no such branch.
+    pub fn ls_remote_head(&self, remote: &str, branch: &str) -> Result<Oid, git2::Error> {
+        let branch = branch.trim_start_matches("refs/heads/");
+        let refname = format!("refs/heads/{branch}");
+        let mut found = self.find_remote(remote)?;
+        let head = self.connected(&mut found, Direction::Fetch, |connected| {
+            let heads = connected.list()?;
+            Ok(heads.iter().find(|head| head.name() == refname).map(git2::RemoteHead::oid))
+        })?;
+        head.ok_or_else(|| {
+            git2::Error::new(
+                ErrorCode::NotFound,
+                ErrorClass::Reference,
+                format!("remote '{remote}' has no branch '{branch}'"),
+            )
+        })
+    }
+
+    /// The branch the remote's HEAD points at, `None` for an empty remote which doesn't
+    /// advertise one. Only the fetch side advertises HEAD, as for `git ls-remote`.
+    pub(crate) fn remote_default_branch(
+        &self,
+        remote: &mut Remote<'_>,
+    ) -> Result<Option<String>, git2::Error> {
+        self.connected(remote, Direction::Fetch, |connected| match connected.default_branch() {
+            Ok(refname) => Ok(refname
+                .as_str()
+                .map(|refname| refname.trim_start_matches("refs/heads/").to_string())),
+            Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+            Err(e) => Err(e),
+        })
+    }
+}
+
+/// Case-insensitive order, names differing only in case in their byte order.
+fn sort_key(name: &str) -> (String, &str) {
+    (name.to_lowercase(), name)
+}
+
+/// A directory of the tree [`Repo::branch_create_orphan`] builds.
+#[derive(Default)]
+struct TreeNode {
+    blobs: BTreeMap<String, Oid>,
+    dirs: BTreeMap<String, TreeNode>,
+}
+
+impl TreeNode {
+    fn insert(&mut self, path: &str, blob: Oid) -> Result<(), git2::Error> {
+        let invalid = || {
+            git2::Error::new(
+                ErrorCode::InvalidSpec,
+                ErrorClass::Tree,
+                format!("'{path}' is not a relative file path"),
+            )
+        };
+        let mut parts = path.split('/').collect::<Vec<_>>();
+        if parts.iter().any(|part| ["", ".", "..", ".git"].contains(part)) {
+            return Err(invalid());
+        }
+        let file = parts.pop().ok_or_else(invalid)?;
+        let mut dir = self;
+        for part in parts {
+            if dir.blobs.contains_key(part) {
+                return Err(invalid());
+            }
+            dir = dir.dirs.entry(part.to_string()).or_default();
+        }
+        if dir.dirs.contains_key(file) {
+            return Err(invalid());
+        }
+        dir.blobs.insert(file.to_string(), blob);
+        Ok(())
+    }
+
+    fn write(&self, repo: &Repository) -> Result<Oid, git2::Error> {
+        let mut builder = repo.treebuilder(None)?;
+        for (name, blob) in &self.blobs {
+            builder.insert(name, *blob, FileMode::Blob.into())?;
+        }
+        for (name, dir) in &self.dirs {
+            builder.insert(name, dir.write(repo)?, FileMode::Tree.into())?;
+        }
+        builder.write()
+    }
+}
+
+/// The error of operations comparing `branch` with its upstream that is gone.
+fn upstream_gone(branch: &str, upstream: &str) -> git2::Error {
+    git2::Error::new(
+        ErrorCode::NotFound,
+        ErrorClass::Reference,
+        format!(
+            "upstream {upstream} of '{branch}' is gone, push the branch to track it again or \
+             delete it with DeleteStaleBranches"
+        ),
+    )
+}
@@ -0,0 +1,187 @@
+//! Carrying commits between repositories that can't reach each other, e.g. on a flash
+//! drive to a server without a route to the git server. Bundles are in git's own v2
+//! format, so `git bundle verify` and `git fetch` read the ones made here, and bundles made
+//! by `git bundle create` import here.
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use git2::{ErrorClass, ErrorCode, Oid, RevparseMode};
+
+use crate::git::Repo;
+
+const SIGNATURE: &str = "# v2 git bundle";
+
+/// Remote the imported branches appear under, as if fetched from a remote of that name.
+pub const BUNDLE_REMOTE: &str = "bundle";
+
+impl Repo<'_> {
+    /// Writes the commits of `range` to the bundle file `path`. `range` is either
+    /// `from..to`, for what `to` has on top of `from`, which the importing repository must
+    /// already have, or `to` alone for its whole history. `to` must be a branch or tag, the
+    /// bundle carries it. Returns the number of objects written.
+    pub fn bundle_create(&self, path: &Path, range: &str) -> Result<usize, git2::Error> {
+        let spec = self.repo.revparse(range)?;
+        if spec.mode().contains(RevparseMode::MERGE_BASE) {
+            return Err(invalid_range(range));
+        }
+        let (tip_name, tip, base) = if spec.mode().contains(RevparseMode::RANGE) {
+            let tip_name = range.rsplit("..").next().filter(|name| !name.is_empty());
+            (tip_name.unwrap_or("HEAD"), spec.to(), spec.from())
+        } else {
+            (range, spec.from(), None)
+        };
+        let tip = tip.ok_or_else(|| invalid_range(range))?.peel_to_commit()?;
+        let base = base.map(git2::Object::peel_to_commit).transpose()?;
+
+        let reference = self
+            .repo
+            .resolve_reference_from_short_name(tip_name)
+            .and_then(|reference| reference.resolve())
+            .map_err(|_| {
+                git2::Error::new(
+                    ErrorCode::Invalid,
+                    ErrorClass::Reference,
+                    format!("'{tip_name}' is not a branch or tag, a bundle carries one"),
+                )
+            })?;
+        let refname = reference.name().ok_or_else(|| git2::Error::from_str("no ref name"))?;
+        let target = reference.target().ok_or_else(|| git2::Error::from_str("no ref target"))?;
+
+        let mut walk = self.repo.revwalk()?;
+        walk.push(tip.id())?;
+        if let Some(base) = &base {
+            walk.hide(base.id())?;
+        }
+        let mut builder = self.repo.packbuilder()?;
+        builder.set_threads(self.config.pack_threads);
+        builder.insert_walk(&mut walk)?;
+        if builder.object_count() == 0 {
+            return Err(git2::Error::new(
+                ErrorCode::Invalid,
+                ErrorClass::Invalid,
+                format!("nothing to bundle, '{range}' holds no commits"),
+            ));
+        }
+        // an annotated tag points at the tag object, which the walk doesn't add
+        if target != tip.id() {
+            builder.insert_object(target, None)?;
+        }
+
+        let mut header = format!("{SIGNATURE}\n");
+        if let Some(base) = &base {
+            header.push_str(&format!("-{} {}\n", base.id(), base.summary().unwrap_or_default()));
+        }
+        header.push_str(&format!("{target} {refname}\n\n"));
+
+        let mut file = BufWriter::new(File::create(path).map_err(|e| write_error(path, &e))?);
+        file.write_all(header.as_bytes()).map_err(|e| write_error(path, &e))?;
+        let mut written = Ok(());
+        let packed = builder.foreach(|chunk| {
+            written = file.write_all(chunk);
+            written.is_ok()
+        });
+        written.and_then(|()| file.flush()).map_err(|e| write_error(path, &e))?;
+        packed?;
+        Ok(builder.object_count())
+    }
+
+    /// Imports the bundle file `path`, as if fetching from it: its objects are added, its
+    /// branches appear as remote-tracking branches of [`BUNDLE_REMOTE`] ready to be merged,
+    /// and its tags are created unless a different tag of the name exists. Fails before
+    /// changing anything when the repository lacks the commits the bundle builds on.
+    /// Returns the refs written.
+    pub fn bundle_import(&self, path: &Path) -> Result<Vec<String>, git2::Error> {
+        self.config.ensure_writable()?;
+        let mut reader = BufReader::new(File::open(path).map_err(|e| read_error(path, &e))?);
+
+        let mut next_line = || {
+            let mut line = String::new();
+            match reader.read_line(&mut line) {
+                Ok(0) => Err(not_a_bundle(path)),
+                Ok(_) => Ok(line.trim_end_matches('\n').to_string()),
+                Err(e) => Err(read_error(path, &e)),
+            }
+        };
+        if next_line()? != SIGNATURE {
+            return Err(not_a_bundle(path));
+        }
+        let odb = self.repo.odb()?;
+        let mut refs = Vec::new();
+        loop {
+            let line = next_line()?;
+            if line.is_empty() {
+                break;
+            }
+            if let Some(prerequisite) = line.strip_prefix('-') {
+                let id = parse_id(prerequisite, path)?;
+                if !odb.exists(id) {
+                    return Err(git2::Error::new(
+                        ErrorCode::NotFound,
+                        ErrorClass::Odb,
+                        format!("the bundle builds on commit {id}, which this repository lacks"),
+                    ));
+                }
+            } else {
+                let (_, refname) = line.split_once(' ').ok_or_else(|| not_a_bundle(path))?;
+                refs.push((parse_id(&line, path)?, refname.to_string()));
+            }
+        }
+
+        let mut writer = odb.packwriter()?;
+        std::io::copy(&mut reader, &mut writer).map_err(|e| read_error(path, &e))?;
+        writer.commit()?;
+
+        let mut written = Vec::new();
+        for (id, refname) in refs {
+            let target = if let Some(branch) = refname.strip_prefix("refs/heads/") {
+                format!("refs/remotes/{BUNDLE_REMOTE}/{branch}")
+            } else if refname.starts_with("refs/tags/") {
+                if self.repo.find_reference(&refname).is_ok() {
+                    continue;
+                }
+                refname
+            } else {
+                continue;
+            };
+            if self.repo.refname_to_id(&target).ok() != Some(id) {
+                self.repo.reference(&target, id, true, "bundle: import")?;
+                written.push(target);
+            }
+        }
+        Ok(written)
+    }
+}
+
+/// The id at the start of a bundle header line.
+fn parse_id(line: &str, path: &Path) -> Result<Oid, git2::Error> {
+    let id = line.split(' ').next().unwrap_or_default();
+    Oid::from_str(id).map_err(|_| not_a_bundle(path))
+}
+
+fn invalid_range(range: &str) -> git2::Error {
+    git2::Error::new(
+        ErrorCode::Invalid,
+        ErrorClass::Invalid,
+        format!("'{range}' is not a revision or a from..to range"),
+    )
+}
+
+fn not_a_bundle(path: &Path) -> git2::Error {
+    git2::Error::new(
+        ErrorCode::Invalid,
+        ErrorClass::Invalid,
+        format!("'{}' is not a v2 git bundle", path.display()),
+    )
+}
+
+fn read_error(path: &Path, e: &std::io::Error) -> git2::Error {
+    git2::Error::from_str(&format!("failed to read '{}': {e}", path.display()))
+}
+
+fn write_error(path: &Path, e: &std::io::Error) -> git2::Error {
+    git2::Error::from_str(&format!("failed to write '{}': {e}", path.display()))
+}
@@ -0,0 +1,129 @@
+//! File-level comparison of two branches, like the summary of a pull request.
+
+use git2::{Delta, DiffFindOptions, ErrorCode, Oid, Patch, Tree};
+
+use crate::git::Repo;
+
+#[derive(Debug, Clone)]
+pub struct CompareResult {
+    /// Commits on `head` that `base` doesn't have.
+    pub ahead: usize,
+    /// Commits on `base` that `head` doesn't have.
+    pub behind: usize,
+    /// `None` for unrelated histories, the files are then compared to `base` itself.
+    pub merge_base: Option<Oid>,
+    pub files: Vec<FileDiff>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileDiff {
+    pub path: String,
+    /// The path on the base side for renamed and copied files.
+    pub old_path: Option<String>,
+    pub change: ChangeKind,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Deleted,
+    Modified,
+    Renamed,
+    Copied,
+    TypeChanged,
+}
+
+impl std::fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Deleted => "deleted",
+            ChangeKind::Modified => "modified",
+            ChangeKind::Renamed => "renamed",
+            ChangeKind::Copied => "copied",
+            ChangeKind::TypeChanged => "type changed",
+        };
+        f.write_str(kind)
+    }
+}
+
+impl Repo<'_> {
+    /// What merging `head` into `base` would bring: the files changed on `head` since the
+    /// branches diverged, with renames detected, and how far apart the branches are.
+    pub fn compare_refs(&self, base: &str, head: &str) -> Result<CompareResult, git2::Error> {
+        let base = self.repo.revparse_single(base)?.peel_to_commit()?;
+        let head = self.repo.revparse_single(head)?.peel_to_commit()?;
+
+        let merge_base = match self.repo.merge_base(base.id(), head.id()) {
+            Ok(merge_base) => Some(merge_base),
+            Err(e) if e.code() == ErrorCode::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        let (ahead, behind) = self.repo.graph_ahead_behind(head.id(), base.id())?;
+
+        let from = match merge_base {
+            Some(merge_base) => self.repo.find_commit(merge_base)?.tree()?,
+            None => base.tree()?,
+        };
+        let (files, _) = self.file_diffs(&from, &head.tree()?, usize::MAX)?;
+
+        Ok(CompareResult {
+            ahead,
+            behind,
+            merge_base,
+            files,
+        })
+    }
+
+    /// The files changed from `from` to `to`, renames detected, at most `limit` of them.
+    /// Also tells whether there were more.
+    pub(crate) fn file_diffs(
+        &self,
+        from: &Tree<'_>,
+        to: &Tree<'_>,
+        limit: usize,
+    ) -> Result<(Vec<FileDiff>, bool), git2::Error> {
+        let mut diff = self.repo.diff_tree_to_tree(Some(from), Some(to), None)?;
+        diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+
+        let mut files = Vec::new();
+        for (idx, delta) in diff.deltas().enumerate().take(limit) {
+            let change = match delta.status() {
+                Delta::Added => ChangeKind::Added,
+                Delta::Deleted => ChangeKind::Deleted,
+                Delta::Renamed => ChangeKind::Renamed,
+                Delta::Copied => ChangeKind::Copied,
+                Delta::Typechange => ChangeKind::TypeChanged,
+                _ => ChangeKind::Modified,
+            };
+            let path = |file: git2::DiffFile| {
+                file.path().map(|path| path.to_string_lossy().into_owned()).unwrap_or_default()
+            };
+            let (insertions, deletions) = match Patch::from_diff(&diff, idx)? {
+                Some(patch) => patch.line_stats().map(|(_, ins, del)| (ins, del))?,
+                None => (0, 0),
+            };
+
+            files.push(FileDiff {
+                path: path(match change {
+                    ChangeKind::Deleted => delta.old_file(),
+                    _ => delta.new_file(),
+                }),
+                old_path: matches!(change, ChangeKind::Renamed | ChangeKind::Copied)
+                    .then(|| path(delta.old_file())),
+                change,
+                insertions,
+                deletions,
+            });
+        }
+        Ok((files, diff.deltas().len() > limit))
+    }
+
+    /// The best common ancestor of `a` and `b`, where their histories diverged. Unrelated
+    /// histories fail with [`ErrorCode::NotFound`].
+    pub fn merge_base(&self, a: &str, b: &str) -> Result<Oid, git2::Error> {
+        self.repo.merge_base(self.resolve_commit(a)?, self.resolve_commit(b)?)
+    }
+}
@@ -0,0 +1,41 @@
+//! Conflicts left in the index by a merge that couldn't be completed automatically.
+
+use git2::{IndexEntry, Oid};
+
+use crate::git::Repo;
+
+/// The three versions of a conflicted path, a side is missing when the file didn't exist
+/// there, e.g. `base` for a file added on both sides.
+#[derive(Debug, Clone)]
+pub struct ConflictInfo {
+    pub path: String,
+    pub base: Option<Oid>,
+    pub ours: Option<Oid>,
+    pub theirs: Option<Oid>,
+}
+
+impl Repo<'_> {
+    pub fn conflicts(&self) -> Result<Vec<ConflictInfo>, git2::Error> {
+        let index = self.repo.index()?;
+        index
+            .conflicts()?
+            .map(|conflict| {
+                let conflict = conflict?;
+                let path = [&conflict.our, &conflict.their, &conflict.ancestor]
+                    .into_iter()
+                    .flatten()
+                    .map(|entry| String::from_utf8_lossy(&entry.path).into_owned())
+                    .next()
+                    .unwrap_or_default();
+                let id = |entry: Option<IndexEntry>| entry.map(|entry| entry.id);
+
+                Ok(ConflictInfo {
+                    path,
+                    base: id(conflict.ancestor),
+                    ours: id(conflict.our),
+                    theirs: id(conflict.their),
+                })
+            })
+            .collect()
+    }
+}
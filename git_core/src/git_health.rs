@@ -0,0 +1,297 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use git2::{ErrorClass, ErrorCode, ObjectType, TreeWalkMode, TreeWalkResult};
+
+use crate::git::Repo;
+
+/// In-progress markers older than this are assumed to be left over from a crashed operation.
+const STALE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Lock files younger than this may belong to a git process still running.
+const LOCK_MIN_AGE: Duration = Duration::from_mins(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct HealthIssue {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub description: String,
+    pub suggested_fix: String,
+}
+
+impl HealthIssue {
+    fn new(
+        severity: Severity,
+        code: &'static str,
+        description: impl Into<String>,
+        suggested_fix: impl Into<String>,
+    ) -> Self {
+        Self {
+            severity,
+            code,
+            description: description.into(),
+            suggested_fix: suggested_fix.into(),
+        }
+    }
+}
+
+impl Repo<'_> {
+    /// Looks for the usual causes of a broken clone. An empty result means nothing was found,
+    /// the checks never modify the repository.
+    pub fn health_check(&self) -> Vec<HealthIssue> {
+        let mut issues = Vec::new();
+
+        self.check_head(&mut issues);
+        self.check_index(&mut issues);
+        self.check_remotes(&mut issues);
+        self.check_upstream(&mut issues);
+        self.check_stale_file("index.lock", "stale_index_lock", &mut issues);
+        self.check_stale_file("MERGE_HEAD", "stale_merge_head", &mut issues);
+        self.check_autocrlf(&mut issues);
+        self.check_head_objects(&mut issues);
+
+        issues.sort_by_key(|issue| issue.severity);
+        issues
+    }
+
+    /// Replaces a corrupt or missing index with one holding HEAD's tree, the fix for
+    /// [`Repo::health_check`]'s `index_corrupt` and `index_missing`. Nothing staged
+    /// survives: staged changes show as unstaged afterwards, staged new files as untracked.
+    /// Returns the number of files in the new index.
+    pub fn rebuild_index(&self) -> Result<usize, git2::Error> {
+        self.config.ensure_writable()?;
+        let path = self.repo.path().join("index");
+        match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(git2::Error::from_str(&format!(
+                    "failed to delete '{}': {e}",
+                    path.display()
+                )));
+            },
+            _ => {},
+        }
+
+        let mut index = self.repo.index()?;
+        index.read(true)?;
+        match self.repo.head() {
+            Ok(head) => index.read_tree(&head.peel_to_tree()?)?,
+            Err(e) if e.code() == ErrorCode::UnbornBranch => index.clear()?,
+            Err(e) => return Err(e),
+        }
+        index.write()?;
+        Ok(index.len())
+    }
+
+    /// Deletes the `*.lock` files a crashed operation left in the git directory, which make
+    /// every later commit or checkout fail with "locked". A lock younger than a minute may
+    /// belong to a git process still running, then nothing is deleted and the error names
+    /// it. Returns the deleted files.
+    pub fn clear_locks(&self) -> Result<Vec<PathBuf>, git2::Error> {
+        self.config.ensure_writable()?;
+        let mut locks = Vec::new();
+        find_locks(self.repo.path(), &mut locks);
+        // a worktree keeps the refs and config it shares with the main one there
+        if self.is_worktree() {
+            find_locks(self.common_dir(), &mut locks);
+        }
+
+        if let Some(fresh) = locks.iter().find(|lock| !is_older_than(lock, LOCK_MIN_AGE)) {
+            return Err(git2::Error::new(
+                ErrorCode::Locked,
+                ErrorClass::Repository,
+                format!(
+                    "'{}' is less than a minute old, a git operation may still be running; \
+                     try again later",
+                    fresh.display()
+                ),
+            ));
+        }
+        for lock in &locks {
+            std::fs::remove_file(lock).map_err(|e| {
+                git2::Error::from_str(&format!("failed to delete '{}': {e}", lock.display()))
+            })?;
+        }
+        Ok(locks)
+    }
+
+    fn check_head(&self, issues: &mut Vec<HealthIssue>) {
+        if let Err(e) = self.repo.head().and_then(|head| head.peel_to_commit()) {
+            issues.push(HealthIssue::new(
+                Severity::Error,
+                "head_unresolved",
+                format!("HEAD doesn't resolve to a commit: {e}"),
+                "check out an existing branch",
+            ));
+        }
+    }
+
+    fn check_index(&self, issues: &mut Vec<HealthIssue>) {
+        if let Err(e) = self.repo.index().and_then(|mut index| index.read(true)) {
+            issues.push(HealthIssue::new(
+                Severity::Error,
+                "index_corrupt",
+                format!("the index can't be read: {e}"),
+                "rebuild the index from HEAD, staged changes are lost",
+            ));
+        } else if !self.repo.path().join("index").exists()
+            && self
+                .repo
+                .head()
+                .and_then(|head| head.peel_to_tree())
+                .is_ok_and(|tree| !tree.is_empty())
+        {
+            // without an index every committed file shows as deleted and untracked
+            issues.push(HealthIssue::new(
+                Severity::Error,
+                "index_missing",
+                "the index is missing",
+                "rebuild the index from HEAD",
+            ));
+        }
+    }
+
+    fn check_remotes(&self, issues: &mut Vec<HealthIssue>) {
+        let Ok(remotes) = self.repo.remotes() else { return };
+
+        for name in remotes.iter().flatten() {
+            // git2 reads a missing URL as an empty one
+            let has_url = self
+                .repo
+                .find_remote(name)
+                .is_ok_and(|remote| remote.url().is_some_and(|url| !url.is_empty()));
+            if !has_url {
+                issues.push(HealthIssue::new(
+                    Severity::Error,
+                    "remote_without_url",
+                    format!("remote '{name}' has no URL"),
+                    format!("set remote.{name}.url or remove the remote"),
+                ));
+            }
+        }
+    }
+
+    fn check_upstream(&self, issues: &mut Vec<HealthIssue>) {
+        let Ok(head) = self.repo.head() else { return };
+        let Some(head_name) = head.name().filter(|_| head.is_branch()) else { return };
+        let Ok(upstream) = self.repo.branch_upstream_name(head_name) else { return };
+        let Some(upstream) = upstream.as_str() else { return };
+
+        if self.repo.find_reference(upstream).is_err() {
+            issues.push(HealthIssue::new(
+                Severity::Warning,
+                "upstream_missing",
+                format!("upstream '{upstream}' of the current branch doesn't exist"),
+                "fetch, set another upstream or push the branch",
+            ));
+        }
+    }
+
+    fn check_stale_file(&self, name: &str, code: &'static str, issues: &mut Vec<HealthIssue>) {
+        let path = self.repo.path().join(name);
+        if is_older_than(&path, STALE_AFTER) {
+            issues.push(HealthIssue::new(
+                Severity::Warning,
+                code,
+                format!("'{}' is more than a day old", path.display()),
+                format!("make sure no git process is running and delete {name}"),
+            ));
+        }
+    }
+
+    fn check_autocrlf(&self, issues: &mut Vec<HealthIssue>) {
+        let Some(expected) = self.config.autocrlf else { return };
+        let Ok(config) = self.repo.config() else { return };
+
+        // git takes yes, on, 1 and the like for true, unset is false
+        let actual = match config.get_bool("core.autocrlf") {
+            Ok(actual) if actual == expected => return,
+            Err(e) if e.code() == ErrorCode::NotFound && !expected => return,
+            Ok(actual) => actual.to_string(),
+            Err(e) if e.code() == ErrorCode::NotFound => "unset".to_string(),
+            // "input" converts on commit only, neither of the modes expected
+            Err(_) => config.get_string("core.autocrlf").unwrap_or_default(),
+        };
+        let description = if actual.eq_ignore_ascii_case("input") {
+            format!(
+                "core.autocrlf is 'input', line endings are converted on commit but not on \
+                 checkout, expected '{expected}'"
+            )
+        } else {
+            format!("core.autocrlf is '{actual}', expected '{expected}'")
+        };
+        issues.push(HealthIssue::new(
+            Severity::Warning,
+            "autocrlf_mismatch",
+            description,
+            format!("set core.autocrlf to {expected}"),
+        ));
+    }
+
+    fn check_head_objects(&self, issues: &mut Vec<HealthIssue>) {
+        let Ok(tree) = self.repo.head().and_then(|head| head.peel_to_tree()) else { return };
+        let Ok(odb) = self.repo.odb() else { return };
+
+        let mut missing = Vec::new();
+        let walked = tree.walk(TreeWalkMode::PreOrder, |root, entry| {
+            if entry.kind() == Some(ObjectType::Blob) && !odb.exists(entry.id()) {
+                missing.push(format!("{root}{}", entry.name().unwrap_or_default()));
+            }
+            TreeWalkResult::Ok
+        });
+
+        let description = match walked {
+            Err(e) => format!("the HEAD tree can't be walked: {e}"),
+            Ok(()) if !missing.is_empty() =>
+                format!("objects missing for {} files, e.g. '{}'", missing.len(), missing[0]),
+            Ok(()) => return,
+        };
+
+        issues.push(HealthIssue::new(
+            Severity::Error,
+            "missing_objects",
+            description,
+            "fetch from the remote again or re-clone the repository",
+        ));
+    }
+}
+
+/// Collects the `*.lock` files under `dir`. The object database is skipped, git locks
+/// nothing there.
+fn find_locks(dir: &Path, locks: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() && entry.file_name() != "objects" =>
+                find_locks(&path, locks),
+            Ok(file_type) if file_type.is_file() && path.extension() == Some("lock".as_ref()) =>
+                locks.push(path),
+            _ => {},
+        }
+    }
+}
+
+fn is_older_than(path: &Path, age: Duration) -> bool {
+    path.metadata().and_then(|metadata| metadata.modified()).is_ok_and(|modified| {
+        SystemTime::now().duration_since(modified).is_ok_and(|elapsed| elapsed > age)
+    })
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
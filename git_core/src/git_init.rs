@@ -0,0 +1,167 @@
+//! Creating new repositories, optionally seeded with the files of a template.
+
+use std::path::{Path, PathBuf};
+
+use git2::{ErrorClass, ErrorCode, IndexAddOption, Repository, RepositoryInitOptions, Signature};
+
+use crate::{
+    git::{Config, Repo},
+    path,
+    refname,
+};
+
+/// Templates shipped with the crate, by name. Dotfiles are stored without the dot so
+/// they don't affect this repository.
+const BUILTIN_TEMPLATES: &[(&str, &[(&str, &str)])] = &[(
+    "1c-config",
+    &[
+        (".gitignore", include_str!("../templates/1c-config/gitignore")),
+        (".gitattributes", include_str!("../templates/1c-config/gitattributes")),
+        ("README.md", include_str!("../templates/1c-config/README.md")),
+    ],
+)];
+
+pub struct InitTemplate {
+    pub source: TemplateSource,
+    /// Message of the initial commit.
+    pub message: String,
+    /// Name the initial branch `main` instead of the libgit2 default, unless
+    /// [`Config::default_branch`] names another.
+    pub main_branch: bool,
+}
+
+pub enum TemplateSource {
+    /// One of the templates shipped with the crate, e.g. "1c-config".
+    Builtin(String),
+    /// Every file under the directory except `.git`, keeping the relative paths.
+    Directory(PathBuf),
+}
+
+impl InitTemplate {
+    /// A built-in template when `name` is one, otherwise the directory at `name`.
+    pub fn named(name: &str) -> Self {
+        let source = if BUILTIN_TEMPLATES.iter().any(|(builtin, _)| *builtin == name) {
+            TemplateSource::Builtin(name.to_string())
+        } else {
+            TemplateSource::Directory(name.into())
+        };
+
+        Self {
+            source,
+            message: "Initial commit".to_string(),
+            main_branch: true,
+        }
+    }
+
+    /// The template files as paths relative to the repository root with their contents.
+    fn files(&self) -> Result<Vec<(PathBuf, Vec<u8>)>, git2::Error> {
+        match &self.source {
+            TemplateSource::Builtin(name) => {
+                let (_, files) =
+                    BUILTIN_TEMPLATES.iter().find(|(builtin, _)| builtin == name).ok_or_else(
+                        || git2::Error::from_str(&format!("no built-in template '{name}'")),
+                    )?;
+                Ok(files
+                    .iter()
+                    .map(|(path, contents)| (path.into(), contents.as_bytes().to_vec()))
+                    .collect())
+            },
+            TemplateSource::Directory(dir) => {
+                let mut files = Vec::new();
+                read_template_dir(dir, Path::new(""), &mut files).map_err(|e| {
+                    git2::Error::from_str(&format!(
+                        "failed to read template '{}': {e}",
+                        dir.display()
+                    ))
+                })?;
+                Ok(files)
+            },
+        }
+    }
+}
+
+impl<'a> Repo<'a> {
+    /// Creates a repository at `config.path`. With a `template`, its files are written
+    /// to the working tree and committed as the initial commit; a file already there is
+    /// not overwritten, the init is refused before anything is created.
+    pub fn init(config: &'a Config, template: Option<&InitTemplate>) -> Result<Self, git2::Error> {
+        config.ensure_writable()?;
+        let dest = path::normalize(&config.path);
+        let files = template.map(InitTemplate::files).transpose()?.unwrap_or_default();
+        if let Some((existing, _)) = files.iter().find(|(path, _)| dest.join(path).exists()) {
+            return Err(git2::Error::new(
+                ErrorCode::Exists,
+                ErrorClass::Filesystem,
+                format!(
+                    "'{}' already exists, the template would overwrite it",
+                    dest.join(existing).display()
+                ),
+            ));
+        }
+
+        let mut options = RepositoryInitOptions::new();
+        options.mkpath(true);
+        if let Some(branch) = &config.default_branch {
+            refname::check(branch, config.transliterate_ref_names)?;
+            options.initial_head(branch);
+        } else if template.is_some_and(|template| template.main_branch) {
+            options.initial_head("main");
+        }
+        let repo = Repository::init_opts(&dest, &options)
+            .map_err(|e| path::with_network_hint(&dest, e))?;
+
+        if let Some(template) = template {
+            // `Repository::is_empty` only recognizes an unborn `master`
+            if repo.head().is_ok() {
+                return Err(git2::Error::from_str(&format!(
+                    "'{}' already has commits, templates only apply to new repositories",
+                    dest.display()
+                )));
+            }
+
+            for (path, contents) in &files {
+                let target = dest.join(path);
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent).map_err(|e| write_error(&target, &e))?;
+                }
+                std::fs::write(&target, contents).map_err(|e| write_error(&target, &e))?;
+            }
+
+            let mut index = repo.index()?;
+            index.add_all(files.iter().map(|(path, _)| path), IndexAddOption::FORCE, None)?;
+            index.write()?;
+            let tree = repo.find_tree(index.write_tree()?)?;
+
+            let author = Signature::now(&config.username, &config.email)?;
+            repo.commit(Some("HEAD"), &author, &author, &template.message, &tree, &[])?;
+        }
+        config.apply_ignore_rules(&repo)?;
+
+        Ok(Self { repo, config })
+    }
+}
+
+fn read_template_dir(
+    dir: &Path,
+    relative: &Path,
+    files: &mut Vec<(PathBuf, Vec<u8>)>,
+) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir.join(relative))? {
+        let entry = entry?;
+        if entry.file_name() == ".git" {
+            continue;
+        }
+
+        let path = relative.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            read_template_dir(dir, &path, files)?;
+        } else {
+            files.push((path, std::fs::read(entry.path())?));
+        }
+    }
+    Ok(())
+}
+
+fn write_error(path: &Path, e: &std::io::Error) -> git2::Error {
+    git2::Error::from_str(&format!("failed to write '{}': {e}", path.display()))
+}
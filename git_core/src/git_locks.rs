@@ -0,0 +1,271 @@
+//! Advisory locks for files that can't be merged, like binary 1C artifacts.
+//!
+//! Locks live in the `locks` file of a dedicated `locks` branch on origin, one
+//! `owner<TAB>locked_at<TAB>path` line each. Changes are committed on top of the freshly
+//! fetched branch and pushed without force; when someone else pushed in between, the
+//! change is reapplied to their version and pushed again. The clone keeps its own change
+//! under `refs/locks`, out of the branches users see.
+
+use std::{
+    cell::RefCell,
+    path::Path,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use git2::{Commit, ErrorCode, Oid, Remote, Repository, Signature};
+
+use crate::{git::Repo, url};
+
+const LOCKS_BRANCH: &str = "refs/heads/locks";
+/// The last change of this clone, pushed to [`LOCKS_BRANCH`].
+const LOCKS_LOCAL: &str = "refs/locks/local";
+pub(crate) const LOCKS_TRACKING: &str = "refs/remotes/origin/locks";
+const LOCKS_REFSPEC: &str = "+refs/heads/locks:refs/remotes/origin/locks";
+const LOCKS_FILE: &str = "locks";
+const UPDATE_ATTEMPTS: usize = 5;
+/// How often and how long to wait for another clone storing its locks to let go of the branch.
+const HELD_REF_RETRIES: u32 = 50;
+const HELD_REF_DELAY: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileLock {
+    pub path: String,
+    pub owner: String,
+    /// Unix time in seconds.
+    pub locked_at: i64,
+}
+
+impl Repo<'_> {
+    /// Locks `path` for `owner`. Locking a file the same owner already holds is a no-op,
+    /// a lock held by someone else is an error.
+    pub fn lock_file(&self, path: &str, owner: &str) -> Result<(), git2::Error> {
+        self.update_locks(&format!("lock {path}"), |locks| {
+            match locks.iter().find(|lock| lock.path == path) {
+                Some(lock) if lock.owner == owner => Ok(false),
+                Some(lock) => Err(git2::Error::new(
+                    ErrorCode::Locked,
+                    git2::ErrorClass::Reference,
+                    format!("'{path}' is already locked by {}", lock.owner),
+                )),
+                None => {
+                    locks.push(FileLock {
+                        path: path.to_string(),
+                        owner: owner.to_string(),
+                        locked_at: unix_now(),
+                    });
+                    Ok(true)
+                },
+            }
+        })
+    }
+
+    pub fn unlock_file(&self, path: &str) -> Result<(), git2::Error> {
+        self.update_locks(&format!("unlock {path}"), |locks| {
+            let before = locks.len();
+            locks.retain(|lock| lock.path != path);
+            Ok(locks.len() != before)
+        })
+    }
+
+    /// Locks as of the last fetch, no network access.
+    pub fn list_locks(&self) -> Result<Vec<FileLock>, git2::Error> {
+        let tip = self
+            .locks_tip(LOCKS_TRACKING)
+            .transpose()
+            .or_else(|| self.locks_tip(LOCKS_LOCAL).transpose())
+            .transpose()?;
+
+        tip.map_or_else(|| Ok(Vec::new()), |commit| self.read_locks(&commit))
+    }
+
+    /// Locks held by someone other than the configured user on currently staged files.
+    pub fn staged_locks(&self) -> Result<Vec<FileLock>, git2::Error> {
+        let locks = self.foreign_locks()?;
+        if locks.is_empty() {
+            return Ok(locks);
+        }
+
+        let head_tree = self.repo.head().and_then(|head| head.peel_to_tree()).ok();
+        let staged = self.repo.diff_tree_to_index(head_tree.as_ref(), None, None)?;
+        let staged_paths = staged
+            .deltas()
+            .filter_map(|delta| delta.new_file().path().or_else(|| delta.old_file().path()))
+            .filter_map(|path| path.to_str().map(str::to_string))
+            .collect::<Vec<_>>();
+
+        Ok(locks.into_iter().filter(|lock| staged_paths.contains(&lock.path)).collect())
+    }
+
+    /// Locks held by someone other than the configured user.
+    pub(crate) fn foreign_locks(&self) -> Result<Vec<FileLock>, git2::Error> {
+        let mut locks = self.list_locks()?;
+        locks.retain(|lock| lock.owner != self.config.username);
+        Ok(locks)
+    }
+
+    /// Applies `change` to the current remote locks and pushes the result, retrying on top
+    /// of the new remote state when the push is rejected. `change` returns whether it
+    /// modified anything.
+    fn update_locks(
+        &self,
+        message: &str,
+        mut change: impl FnMut(&mut Vec<FileLock>) -> Result<bool, git2::Error>,
+    ) -> Result<(), git2::Error> {
+        self.config.ensure_writable()?;
+        let mut origin = self.find_remote("origin")?;
+
+        for _ in 0..UPDATE_ATTEMPTS {
+            let mut options = Self::fetch_options(self.config);
+            origin.fetch(&[LOCKS_REFSPEC], Some(&mut options), None)?;
+
+            let parent = self.locks_tip(LOCKS_TRACKING)?;
+            let mut locks = parent
+                .as_ref()
+                .map(|parent| self.read_locks(parent))
+                .transpose()?
+                .unwrap_or_default();
+            if !change(&mut locks)? {
+                return Ok(());
+            }
+
+            let oid = self.write_locks(&locks, message, parent.as_ref())?;
+            self.repo.reference(LOCKS_LOCAL, oid, true, message)?;
+
+            let pushed = match origin.url().filter(|url| url::is_local(url)) {
+                Some(url) =>
+                    self.store_locks(&url::local_path(url), oid, parent.as_ref(), message)?,
+                None => self.push_locks(&mut origin)?,
+            };
+            if pushed {
+                self.repo.reference(LOCKS_TRACKING, oid, true, message)?;
+                return Ok(());
+            }
+        }
+
+        Err(git2::Error::from_str(&format!(
+            "the locks branch kept changing on the remote, gave up after {UPDATE_ATTEMPTS} attempts"
+        )))
+    }
+
+    fn read_locks(&self, commit: &Commit) -> Result<Vec<FileLock>, git2::Error> {
+        let tree = commit.tree()?;
+        let Some(entry) = tree.get_name(LOCKS_FILE) else { return Ok(Vec::new()) };
+        let blob = self.repo.find_blob(entry.id())?;
+
+        let locks = String::from_utf8_lossy(blob.content())
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, '\t');
+                let owner = fields.next()?.to_string();
+                let locked_at = fields.next()?.parse().ok()?;
+                let path = fields.next()?.to_string();
+                Some(FileLock { path, owner, locked_at })
+            })
+            .collect();
+
+        Ok(locks)
+    }
+
+    fn locks_tip(&self, reference: &str) -> Result<Option<Commit<'_>>, git2::Error> {
+        match self.repo.find_reference(reference) {
+            Ok(reference) => reference.peel_to_commit().map(Some),
+            Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn write_locks(
+        &self,
+        locks: &[FileLock],
+        message: &str,
+        parent: Option<&Commit>,
+    ) -> Result<Oid, git2::Error> {
+        let content = locks
+            .iter()
+            .map(|lock| format!("{}\t{}\t{}\n", lock.owner, lock.locked_at, lock.path))
+            .collect::<String>();
+
+        let blob = self.repo.blob(content.as_bytes())?;
+        let mut builder = self.repo.treebuilder(None)?;
+        builder.insert(LOCKS_FILE, blob, 0o100_644)?;
+        let tree = self.repo.find_tree(builder.write()?)?;
+
+        let signature = Signature::now(&self.config.username, &self.config.email)?;
+        let parents = parent.into_iter().collect::<Vec<_>>();
+        self.repo.commit(None, &signature, &signature, message, &tree, &parents)
+    }
+
+    /// Stores the locks commit `oid` as the locks branch of the repository at `path`,
+    /// `false` when the branch moved from `parent` meanwhile. libgit2's local transport
+    /// overwrites the remote branch whatever it points at by then, so a change of another
+    /// clone pushed in between would be lost.
+    fn store_locks(
+        &self,
+        path: &Path,
+        oid: Oid,
+        parent: Option<&Commit>,
+        message: &str,
+    ) -> Result<bool, git2::Error> {
+        let remote = Repository::open(path)?;
+        let (local_odb, remote_odb) = (self.repo.odb()?, remote.odb()?);
+        let tree = self.repo.find_commit(oid)?.tree()?;
+        let objects = [oid, tree.id()].into_iter().chain(tree.iter().map(|entry| entry.id()));
+        for id in objects {
+            let object = local_odb.read(id)?;
+            remote_odb.write(object.kind(), object.data())?;
+        }
+
+        let mut held = 0;
+        loop {
+            let stored = match parent {
+                Some(parent) =>
+                    remote.reference_matching(LOCKS_BRANCH, oid, true, parent.id(), message),
+                None => remote.reference(LOCKS_BRANCH, oid, false, message),
+            };
+            match stored {
+                Ok(_) => return Ok(true),
+                // another clone is storing its change right now, whether it moved the branch
+                // is only known once it let go; giving up at once could have both fail
+                Err(e) if e.code() == ErrorCode::Locked && held < HELD_REF_RETRIES => {
+                    held += 1;
+                    thread::sleep(HELD_REF_DELAY);
+                },
+                Err(e)
+                    if matches!(
+                        e.code(),
+                        ErrorCode::Modified | ErrorCode::Exists | ErrorCode::Locked
+                    ) =>
+                    return Ok(false),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Pushes the local change of the locks, `false` when the remote rejected it as outdated.
+    fn push_locks(&self, origin: &mut Remote) -> Result<bool, git2::Error> {
+        let rejected = RefCell::new(None);
+
+        let mut callbacks = git2::RemoteCallbacks::new();
+        callbacks.push_update_reference(|_reference, status| {
+            *rejected.borrow_mut() = status.map(str::to_string);
+            Ok(())
+        });
+        let mut options = Self::push_options_with(self.config, callbacks);
+
+        match origin.push(&[format!("{LOCKS_LOCAL}:{LOCKS_BRANCH}")], Some(&mut options)) {
+            // libgit2 finds the remote branch moved since the fetch before sending anything
+            Err(e) if e.code() == ErrorCode::NotFastForward => return Ok(false),
+            result => result?,
+        }
+        drop(options);
+
+        Ok(rejected.into_inner().is_none())
+    }
+}
+
+pub(crate) fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| i64::try_from(elapsed.as_secs()).unwrap_or(i64::MAX))
+}
@@ -0,0 +1,170 @@
+//! Listing commits, e.g. the ones a pull would bring in or a push would send.
+
+use std::{
+    collections::{BinaryHeap, HashSet},
+    path::Path,
+};
+
+use git2::{BranchType, ErrorCode, Oid, Sort};
+
+use crate::{encoding, git::Repo};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitInfo {
+    /// The full hex id, the one to store and compare.
+    pub id: Oid,
+    /// The shortest prefix of `id` unambiguous in the repository, for showing to people.
+    pub short_id: String,
+    pub parents: Vec<Oid>,
+    pub author: String,
+    pub email: String,
+    /// Author time in seconds since the Unix epoch.
+    pub time: i64,
+    /// First line of the message.
+    pub summary: String,
+    pub message: String,
+}
+
+impl CommitInfo {
+    /// Names and messages are decoded from the commit's `encoding` header, UTF-8 without
+    /// one. What isn't valid in the encoding is converted lossily.
+    pub(crate) fn from_commit(commit: &git2::Commit<'_>) -> Self {
+        let encoding = commit.message_encoding();
+        let author = commit.author();
+        let message = encoding::decode(encoding, commit.message_bytes());
+        Self {
+            id: commit.id(),
+            short_id: short_id(commit.as_object()),
+            parents: commit.parent_ids().collect(),
+            author: encoding::decode(encoding, author.name_bytes()),
+            email: encoding::decode(encoding, author.email_bytes()),
+            time: author.when().seconds(),
+            summary: message.lines().next().unwrap_or_default().to_string(),
+            message,
+        }
+    }
+}
+
+/// The shortest unambiguous prefix of the id of `object`, the full id if that fails.
+fn short_id(object: &git2::Object<'_>) -> String {
+    object
+        .short_id()
+        .ok()
+        .and_then(|buf| buf.as_str().map(str::to_string))
+        .unwrap_or_else(|| object.id().to_string())
+}
+
+impl Repo<'_> {
+    /// The shortest prefix of `id` unambiguous in the repository, at least 7 characters,
+    /// for messages. Ids meant to be stored keep the full hex of [`Oid::to_string`].
+    pub fn short_id(&self, id: Oid) -> Result<String, git2::Error> {
+        Ok(short_id(&self.repo.find_object(id, None)?))
+    }
+
+    /// Fetches and lists the commits on the upstream of the local `branch` that the branch
+    /// doesn't have yet, newest first: what pulling it would bring in.
+    pub fn incoming(&self, branch: &str) -> Result<Vec<CommitInfo>, git2::Error> {
+        // fail before the fetch when there is nothing to compare with
+        self.tracked_upstream(&self.repo.find_branch(branch, BranchType::Local)?)?;
+        self.prune_report()?;
+
+        let local = self.repo.find_branch(branch, BranchType::Local)?;
+        let upstream = self.tracked_upstream(&local)?;
+        self.commits_between(
+            local.get().peel_to_commit()?.id(),
+            upstream.get().peel_to_commit()?.id(),
+        )
+    }
+
+    /// The commits on the local `branch` that its upstream doesn't have, newest first: what
+    /// pushing it would send. Compares with the last fetched state of the upstream.
+    pub fn outgoing(&self, branch: &str) -> Result<Vec<CommitInfo>, git2::Error> {
+        let local = self.repo.find_branch(branch, BranchType::Local)?;
+        let upstream = self.tracked_upstream(&local)?;
+        self.commits_between(
+            upstream.get().peel_to_commit()?.id(),
+            local.get().peel_to_commit()?.id(),
+        )
+    }
+
+    /// How many commits the local `branch` has that its upstream lacks and the other way
+    /// round: the counts of [`Repo::outgoing`] and of what pulling would bring in, as of the
+    /// last fetch.
+    pub fn ahead_behind(&self, branch: &str) -> Result<(usize, usize), git2::Error> {
+        let local = self.repo.find_branch(branch, BranchType::Local)?;
+        let upstream = self.tracked_upstream(&local)?;
+        self.repo.graph_ahead_behind(
+            local.get().peel_to_commit()?.id(),
+            upstream.get().peel_to_commit()?.id(),
+        )
+    }
+
+    /// Number of commits `to` has that `from` lacks, like `git rev-list --count from..to`,
+    /// for any two revisions. Swap the sides for the other direction.
+    pub fn rev_count(&self, from: &str, to: &str) -> Result<usize, git2::Error> {
+        let (from, to) = (self.resolve_commit(from)?, self.resolve_commit(to)?);
+        Ok(self.repo.graph_ahead_behind(to, from)?.0)
+    }
+
+    /// The commits from HEAD back that changed `path`, a file or directory, newest first and
+    /// at most `max` of them, like `git log -- <path>`. History is simplified as git does:
+    /// at a merge that took `path` from one parent unchanged only that parent is followed,
+    /// so changes the merge dropped aren't listed. Renames aren't followed, the history
+    /// ends where the path was created.
+    pub fn file_log(&self, path: &str, max: Option<usize>) -> Result<Vec<CommitInfo>, git2::Error> {
+        let path = Path::new(path.trim_end_matches('/'));
+        let entry_id = |commit: &git2::Commit<'_>| -> Result<Option<Oid>, git2::Error> {
+            match commit.tree()?.get_path(path) {
+                Ok(entry) => Ok(Some(entry.id())),
+                Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+                Err(e) => Err(e),
+            }
+        };
+
+        // newest commit time first, as the revision walk sorts
+        let head = self.repo.head()?.peel_to_commit()?;
+        let mut queue = BinaryHeap::from([(head.time().seconds(), head.id())]);
+        let mut seen = HashSet::from([head.id()]);
+        let mut commits = Vec::new();
+        while let Some((_, id)) = queue.pop() {
+            if max.is_some_and(|max| commits.len() >= max) {
+                break;
+            }
+            let commit = self.repo.find_commit(id)?;
+            let entry = entry_id(&commit)?;
+            let mut parents = Vec::new();
+            let mut unchanged_from = None;
+            for parent in commit.parents() {
+                if entry_id(&parent)? == entry {
+                    unchanged_from = Some(parent);
+                    break;
+                }
+                parents.push(parent);
+            }
+            // a root commit changed the path when it has it
+            if unchanged_from.is_none() && (commit.parent_count() > 0 || entry.is_some()) {
+                commits.push(CommitInfo::from_commit(&commit));
+            }
+            // a parent with the path unchanged explains it alone
+            if let Some(parent) = unchanged_from {
+                parents = vec![parent];
+            }
+            for parent in parents {
+                if seen.insert(parent.id()) {
+                    queue.push((parent.time().seconds(), parent.id()));
+                }
+            }
+        }
+        Ok(commits)
+    }
+
+    /// Commits reachable from `to` but not from `from`, like `git log from..to`.
+    fn commits_between(&self, from: Oid, to: Oid) -> Result<Vec<CommitInfo>, git2::Error> {
+        let mut walk = self.repo.revwalk()?;
+        walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+        walk.push(to)?;
+        walk.hide(from)?;
+
+        walk.map(|id| Ok(CommitInfo::from_commit(&self.repo.find_commit(id?)?))).collect()
+    }
+}
@@ -0,0 +1,66 @@
+//! Git notes, metadata like "reviewed-by" attached to commits without rewriting them.
+//!
+//! Each namespace is kept under `refs/notes/<namespace>`, "commits" being git's default.
+//! With [`Config::sync_notes`](crate::git::Config::sync_notes) set, push and fetch carry
+//! every `refs/notes/*` ref along.
+
+use git2::{ErrorCode, Oid, Signature};
+
+use crate::git::Repo;
+
+const DEFAULT_NAMESPACE: &str = "commits";
+pub(crate) const NOTES_REFSPEC: &str = "+refs/notes/*:refs/notes/*";
+
+impl Repo<'_> {
+    /// Attaches `text` to the commit `revspec` resolves to. An existing note in the same
+    /// namespace is an error unless `force` is set, which replaces it.
+    pub fn note_add(
+        &self,
+        revspec: &str,
+        namespace: &str,
+        text: &str,
+        force: bool,
+    ) -> Result<Oid, git2::Error> {
+        self.config.ensure_writable()?;
+        let commit = self.repo.revparse_single(revspec)?.peel_to_commit()?;
+        let author = Signature::now(&self.config.username, &self.config.email)?;
+
+        self.repo.note(&author, &author, Some(&notes_ref(namespace)), commit.id(), text, force)
+    }
+
+    /// The note on the commit `revspec` resolves to, `None` when it has none.
+    pub fn note_read(&self, revspec: &str, namespace: &str) -> Result<Option<String>, git2::Error> {
+        let commit = self.repo.revparse_single(revspec)?.peel_to_commit()?;
+
+        match self.repo.find_note(Some(&notes_ref(namespace)), commit.id()) {
+            Ok(note) => Ok(Some(String::from_utf8_lossy(note.message_bytes()).into_owned())),
+            Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn note_remove(&self, revspec: &str, namespace: &str) -> Result<(), git2::Error> {
+        self.config.ensure_writable()?;
+        let commit = self.repo.revparse_single(revspec)?.peel_to_commit()?;
+        let author = Signature::now(&self.config.username, &self.config.email)?;
+
+        self.repo.note_delete(commit.id(), Some(&notes_ref(namespace)), &author, &author)
+    }
+
+    /// Push refspecs for every local notes ref, libgit2 can't push wildcard refspecs.
+    pub(crate) fn notes_push_refspecs(&self) -> Result<Vec<String>, git2::Error> {
+        let mut refspecs = Vec::new();
+        for reference in self.repo.references_glob("refs/notes/*")? {
+            if let Some(name) = reference?.name() {
+                refspecs.push(format!("{name}:{name}"));
+            }
+        }
+        Ok(refspecs)
+    }
+}
+
+/// `refs/notes/<namespace>`, the default namespace for an empty one.
+fn notes_ref(namespace: &str) -> String {
+    let namespace = if namespace.is_empty() { DEFAULT_NAMESPACE } else { namespace };
+    format!("refs/notes/{namespace}")
+}
@@ -0,0 +1,62 @@
+//! Finishing merges, cherry-picks and reverts that stopped on conflicts.
+
+use git2::{ErrorClass, ErrorCode, Oid, RepositoryState, Signature};
+
+use crate::git::Repo;
+
+impl Repo<'_> {
+    /// Commits the resolved state of the merge, cherry-pick or revert in progress and
+    /// clears it: a merge commit with HEAD and every MERGE_HEAD as parents, or a single
+    /// parent commit keeping the picked commit's author. The message is the one git
+    /// prepared in MERGE_MSG without comments.
+    pub fn continue_operation(&self) -> Result<Oid, git2::Error> {
+        self.config.ensure_writable()?;
+
+        let state = self.repo.state();
+        if !matches!(
+            state,
+            RepositoryState::Merge | RepositoryState::CherryPick | RepositoryState::Revert
+        ) {
+            return Err(git2::Error::new(
+                ErrorCode::Invalid,
+                ErrorClass::Repository,
+                match state {
+                    RepositoryState::Clean =>
+                        "no merge, cherry-pick or revert in progress".to_string(),
+                    state => format!("can't continue {state:?}"),
+                },
+            ));
+        }
+
+        self.check_no_conflicts()?;
+        let mut index = self.repo.index()?;
+        let tree = self.repo.find_tree(index.write_tree()?)?;
+
+        let head = self.repo.head()?.peel_to_commit()?;
+        let mut parents = vec![head];
+        if state == RepositoryState::Merge {
+            // `Repository::mergehead_foreach` needs `&mut`, the file holds one id per line
+            let merge_heads = std::fs::read_to_string(self.repo.path().join("MERGE_HEAD"))
+                .map_err(|e| git2::Error::from_str(&format!("failed to read MERGE_HEAD: {e}")))?;
+            for id in merge_heads.lines().filter(|line| !line.is_empty()) {
+                parents.push(self.repo.find_commit(Oid::from_str(id)?)?);
+            }
+        }
+
+        let committer = Signature::now(&self.config.username, &self.config.email)?;
+        let author = match state {
+            RepositoryState::CherryPick => {
+                let picked = self.repo.revparse_single("CHERRY_PICK_HEAD")?.peel_to_commit()?;
+                picked.author().to_owned()
+            },
+            _ => committer.clone(),
+        };
+        // git strips the commented conflict list it appends to the prepared message
+        let message = git2::message_prettify(self.repo.message()?, Some(b'#'))?;
+
+        let parents = parents.iter().collect::<Vec<_>>();
+        let oid = self.repo.commit(Some("HEAD"), &author, &committer, &message, &tree, &parents)?;
+        self.repo.cleanup_state()?;
+        Ok(oid)
+    }
+}
@@ -0,0 +1,135 @@
+//! Building the pack a push sends: the tuning taken from [`crate::Config`] and where the time of
+//! a push goes.
+
+use std::{
+    cell::RefCell,
+    time::{Duration, Instant},
+};
+
+use git2::{ConfigLevel, RemoteCallbacks};
+
+use crate::git::Repo;
+
+/// How long the phases of a push took, measured with libgit2's progress callbacks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PushReport {
+    /// Connecting, listing the remote refs and working out what to send.
+    pub negotiation: Duration,
+    /// Collecting the objects and compressing them into a pack.
+    pub pack: Duration,
+    /// Sending the pack and waiting for the remote to accept it.
+    pub transfer: Duration,
+    /// Objects sent.
+    pub objects: usize,
+    /// Bytes sent.
+    pub bytes: usize,
+}
+
+impl std::fmt::Display for PushReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "negotiation {:.2?}, pack {:.2?}, transfer {:.2?} ({} objects, {} bytes)",
+            self.negotiation, self.pack, self.transfer, self.objects, self.bytes
+        )
+    }
+}
+
+/// Records the moments a push moves from one phase to the next.
+pub(crate) struct PushTimer {
+    started: Instant,
+    marks: RefCell<Marks>,
+}
+
+#[derive(Default)]
+struct Marks {
+    negotiated: Option<Instant>,
+    transfer_started: Option<Instant>,
+    objects: usize,
+    bytes: usize,
+}
+
+impl PushTimer {
+    pub(crate) fn start() -> Self {
+        Self {
+            started: Instant::now(),
+            marks: RefCell::default(),
+        }
+    }
+
+    /// Hooks the timer into `callbacks`. libgit2 calls back on negotiation once it knows
+    /// what to send, and reports the transfer from the first bytes of the pack written.
+    pub(crate) fn register<'b>(&'b self, callbacks: &mut RemoteCallbacks<'b>) {
+        callbacks.push_negotiation(|_updates| {
+            self.marks.borrow_mut().negotiated.get_or_insert_with(Instant::now);
+            Ok(())
+        });
+        callbacks.push_transfer_progress(|_current, total, bytes| {
+            let mut marks = self.marks.borrow_mut();
+            marks.transfer_started.get_or_insert_with(Instant::now);
+            marks.objects = total;
+            marks.bytes = bytes;
+        });
+    }
+
+    /// The report of a push that just ended, phases never reported took no time.
+    pub(crate) fn finish(self) -> PushReport {
+        let finished = Instant::now();
+        let marks = self.marks.into_inner();
+        let negotiated = marks.negotiated.unwrap_or(finished);
+        let transfer_started = marks.transfer_started.unwrap_or(finished).max(negotiated);
+        PushReport {
+            negotiation: negotiated - self.started,
+            pack: transfer_started - negotiated,
+            transfer: finished - transfer_started,
+            objects: marks.objects,
+            bytes: marks.bytes,
+        }
+    }
+}
+
+/// The pack settings [`Repo::apply_big_file_threshold`] wrote, put back as they were on
+/// drop, so they only last for the push.
+pub(crate) struct BigFileThreshold {
+    local: git2::Config,
+    previous: Vec<(&'static str, Option<i64>)>,
+}
+
+impl Drop for BigFileThreshold {
+    fn drop(&mut self) {
+        for (key, previous) in &self.previous {
+            let restored = match previous {
+                Some(value) => self.local.set_i64(key, *value),
+                None => self.local.remove(key),
+            };
+            if let Err(e) = restored {
+                log::warn!("failed to restore {key} after the push: {e}");
+            }
+        }
+    }
+}
+
+impl Repo<'_> {
+    /// Writes [`crate::Config::big_file_threshold`] to the repository configuration, where the
+    /// pack builder reads it from, until the returned guard is dropped. libgit2 1.9 takes the
+    /// threshold from `pack.deltaCacheSize` rather than `core.bigFileThreshold`, so both are
+    /// set, which also caps the delta cache at the threshold.
+    pub(crate) fn apply_big_file_threshold(&self) -> Result<Option<BigFileThreshold>, git2::Error> {
+        let Some(threshold) = self.config.big_file_threshold else {
+            return Ok(None);
+        };
+        let threshold = i64::try_from(threshold).unwrap_or(i64::MAX);
+        let mut applied = BigFileThreshold {
+            local: self.repo.config()?.open_level(ConfigLevel::Local)?,
+            previous: Vec::new(),
+        };
+        for key in ["core.bigFileThreshold", "pack.deltaCacheSize"] {
+            let previous = applied.local.get_i64(key).ok();
+            if previous != Some(threshold) {
+                applied.local.set_i64(key, threshold)?;
+                applied.previous.push((key, previous));
+            }
+        }
+        Ok(Some(applied))
+    }
+}
@@ -0,0 +1,230 @@
+//! Checking in advance whether a commit or push would go through, e.g. to disable the
+//! save button in 1C. The operations run the same checks, so a blocking issue carries the
+//! message the operation would fail with.
+
+use std::str::FromStr;
+
+use git2::{Direction, ErrorClass, ErrorCode, Remote, RepositoryState, Signature};
+
+use crate::git::Repo;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperationKind {
+    Commit,
+    Push,
+    /// A commit followed by pushing it, the 1C save flow.
+    CommitAndPush,
+}
+
+impl FromStr for OperationKind {
+    type Err = git2::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "commit" => Ok(Self::Commit),
+            "push" => Ok(Self::Push),
+            "commit_and_push" | "commit+push" => Ok(Self::CommitAndPush),
+            _ => Err(git2::Error::from_str(&format!(
+                "unknown operation '{s}', expected commit, push or commit_and_push"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreflightIssue {
+    /// The operation would fail, otherwise it would go through but likely not as intended.
+    pub blocking: bool,
+    pub code: &'static str,
+    pub message: String,
+}
+
+#[derive(Default)]
+struct Issues(Vec<PreflightIssue>);
+
+impl Issues {
+    /// Records the error of a check the operation makes as blocking.
+    fn check<T>(&mut self, code: &'static str, result: Result<T, git2::Error>) -> Option<T> {
+        result
+            .map_err(|e| {
+                self.0.push(PreflightIssue {
+                    blocking: true,
+                    code,
+                    message: e.message().to_string(),
+                });
+            })
+            .ok()
+    }
+
+    fn warn(&mut self, code: &'static str, message: impl Into<String>) {
+        self.0.push(PreflightIssue {
+            blocking: false,
+            code,
+            message: message.into(),
+        });
+    }
+}
+
+impl Repo<'_> {
+    /// Runs the cheap checks of `operation` without performing it. Pushing connects to
+    /// the remote, nothing is fetched or sent.
+    pub fn preflight(&self, operation: OperationKind) -> Vec<PreflightIssue> {
+        let mut issues = Issues::default();
+        issues.check("read_only", self.config.ensure_writable());
+        if matches!(operation, OperationKind::Commit | OperationKind::CommitAndPush) {
+            self.preflight_commit(&mut issues);
+        }
+        if matches!(operation, OperationKind::Push | OperationKind::CommitAndPush) {
+            self.preflight_push(&mut issues, operation == OperationKind::CommitAndPush);
+        }
+        issues.0
+    }
+
+    fn preflight_commit(&self, issues: &mut Issues) {
+        issues.check("identity", Signature::now(&self.config.username, &self.config.email));
+        issues.check("conflicts", self.check_no_conflicts());
+        issues.check("default_branch", self.check_first_commit_branch());
+        if issues.check("locked", self.check_commit_locks()).is_some()
+            && let Ok(locks) = self.staged_locks()
+        {
+            for lock in locks {
+                issues.warn("locked", format!("'{}' is locked by {}", lock.path, lock.owner));
+            }
+        }
+
+        if self.repo.state() != RepositoryState::Clean {
+            issues.warn(
+                "operation_in_progress",
+                format!(
+                    "{:?} in progress, finish it with continue_operation instead of a commit",
+                    self.repo.state()
+                ),
+            );
+        }
+        let head_tree = self.repo.head().and_then(|head| head.peel_to_tree()).ok();
+        let staged = self.repo.diff_tree_to_index(head_tree.as_ref(), None, None);
+        if staged.is_ok_and(|staged| staged.deltas().len() == 0) {
+            issues.warn("nothing_staged", "nothing staged, the commit would be empty");
+        }
+    }
+
+    /// With `after_commit` the push follows a commit, which creates the branch when there
+    /// is none yet.
+    fn preflight_push(&self, issues: &mut Issues, after_commit: bool) {
+        let refname = match self.repo.head() {
+            Ok(head) if !head.is_branch() => {
+                issues.check("detached_head", Err::<(), _>(detached_head()));
+                return;
+            },
+            Ok(head) => head.name().map(str::to_string),
+            Err(e) if e.code() == ErrorCode::UnbornBranch && after_commit =>
+                self.head_branch().ok().map(|branch| format!("refs/heads/{branch}")),
+            Err(e) => {
+                issues.check("no_commits", Err::<(), _>(e));
+                return;
+            },
+        };
+        let remote = match &refname {
+            Some(refname) => self.push_remote(refname),
+            None => Ok("origin".to_string()),
+        };
+        let origin = remote.and_then(|remote| self.find_remote(&remote));
+        let Some(mut origin) = issues.check("no_remote", origin) else {
+            return;
+        };
+
+        let connected = self.connected(&mut origin, Direction::Push, |_| Ok(()));
+        if issues.check("unreachable", connected).is_none() {
+            return;
+        }
+        if let Some(refname) = refname {
+            issues.check("default_branch", self.check_first_push(&mut origin, &refname));
+            self.warn_behind_upstream(issues, &refname);
+        }
+    }
+
+    /// The last fetched upstream has commits the branch lacks, the server rejects the push
+    /// unless someone force-pushed in between.
+    fn warn_behind_upstream(&self, issues: &mut Issues, refname: &str) {
+        let Ok(upstream) = self.repo.branch_upstream_name(refname) else {
+            return;
+        };
+        let (Some(upstream), Ok(local)) = (upstream.as_str(), self.repo.refname_to_id(refname))
+        else {
+            return;
+        };
+        let Ok(upstream_id) = self.repo.refname_to_id(upstream) else {
+            return;
+        };
+        if let Ok((_, behind)) = self.repo.graph_ahead_behind(local, upstream_id)
+            && behind > 0
+        {
+            issues.warn(
+                "behind_upstream",
+                format!("the upstream has {behind} commits the branch lacks, pull first"),
+            );
+        }
+    }
+
+    /// Refuses a commit including files someone else locked when
+    /// [`crate::Config::block_locked_commits`] is set.
+    pub(crate) fn check_commit_locks(&self) -> Result<(), git2::Error> {
+        if self.config.block_locked_commits
+            && let Some(lock) = self.staged_locks()?.first()
+        {
+            return Err(git2::Error::new(
+                ErrorCode::Locked,
+                ErrorClass::Index,
+                format!("'{}' is locked by {}", lock.path, lock.owner),
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_no_conflicts(&self) -> Result<(), git2::Error> {
+        if self.repo.index()?.has_conflicts() {
+            return Err(git2::Error::new(
+                ErrorCode::Unmerged,
+                ErrorClass::Index,
+                "resolve and stage all conflicts first",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Before the first commit, HEAD has to name the default branch.
+    pub(crate) fn check_first_commit_branch(&self) -> Result<(), git2::Error> {
+        match self.repo.head() {
+            Err(e) if e.code() == ErrorCode::UnbornBranch => {
+                let expected = self.config.default_branch.as_deref();
+                self.ensure_default_branch(&self.head_branch()?, expected)
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// On the first push, the branch has to be the remote's default, or the configured
+    /// one for an empty remote.
+    pub(crate) fn check_first_push(
+        &self,
+        origin: &mut Remote<'_>,
+        refname: &str,
+    ) -> Result<(), git2::Error> {
+        if !self.is_first_push(refname)? {
+            return Ok(());
+        }
+        let expected = match self.remote_default_branch(origin)? {
+            Some(remote_default) => Some(remote_default),
+            None => self.config.default_branch.clone(),
+        };
+        self.ensure_default_branch(refname.trim_start_matches("refs/heads/"), expected.as_deref())
+    }
+}
+
+pub(crate) fn detached_head() -> git2::Error {
+    git2::Error::new(
+        ErrorCode::Invalid,
+        ErrorClass::Reference,
+        "HEAD is detached, check out a branch first",
+    )
+}
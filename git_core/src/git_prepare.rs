@@ -0,0 +1,72 @@
+//! Committing in two steps, so 1C code can inspect a commit and veto it before it is made.
+//! Preparing writes only the tree, the index stays unlocked and usable in between.
+
+use git2::{ErrorClass, ErrorCode, Oid};
+
+use crate::git::Repo;
+
+/// A commit that passed the checks of [`Repo::commit`] and waits for
+/// [`Repo::finish_commit`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreparedCommit {
+    /// The tree the index held when the commit was prepared.
+    pub tree: Oid,
+    /// The commit HEAD was at, `None` for the first commit.
+    pub parent: Option<Oid>,
+    pub message: String,
+    /// Paths the commit changes.
+    pub files: Vec<String>,
+}
+
+impl Repo<'_> {
+    /// Runs the checks of [`Repo::commit`] and records what it would commit, without
+    /// committing.
+    pub fn prepare_commit(&self, message: &str) -> Result<PreparedCommit, git2::Error> {
+        self.config.ensure_writable()?;
+        self.check_commit_locks()?;
+        self.check_no_conflicts()?;
+
+        let tree = self.repo.find_tree(self.repo.index()?.write_tree()?)?;
+        let parent = self.commit_parent()?;
+        let parent_tree = parent.as_ref().map(git2::Commit::tree).transpose()?;
+        let diff = self.repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+        let files = diff
+            .deltas()
+            .filter_map(|delta| delta.new_file().path().or_else(|| delta.old_file().path()))
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+
+        Ok(PreparedCommit {
+            tree: tree.id(),
+            parent: parent.map(|commit| commit.id()),
+            message: message.to_string(),
+            files,
+        })
+    }
+
+    /// Makes the `prepared` commit. Fails with [`ErrorCode::Modified`] when the index or
+    /// HEAD changed since, the commit would then not be the one inspected.
+    pub fn finish_commit(&self, prepared: &PreparedCommit) -> Result<Oid, git2::Error> {
+        self.config.ensure_writable()?;
+        self.check_commit_locks()?;
+
+        if self.repo.index()?.write_tree()? != prepared.tree {
+            return Err(git2::Error::new(
+                ErrorCode::Modified,
+                ErrorClass::Index,
+                "the index changed since the commit was prepared, prepare it again",
+            ));
+        }
+        let parent = self.commit_parent()?;
+        if parent.as_ref().map(git2::Commit::id) != prepared.parent {
+            return Err(git2::Error::new(
+                ErrorCode::Modified,
+                ErrorClass::Reference,
+                "HEAD moved since the commit was prepared, prepare it again",
+            ));
+        }
+
+        let tree = self.repo.find_tree(prepared.tree)?;
+        self.write_commit(&prepared.message, &tree, parent.as_ref())
+    }
+}
@@ -0,0 +1,110 @@
+//! A repository opened for reporting: the type only has methods that read, so a reporting
+//! component can't commit, push or check out by mistake, whatever its [`Config`] says.
+
+use std::io::Write;
+
+use git2::Oid;
+
+use crate::{
+    BranchList,
+    CommitInfo,
+    HeadRef,
+    ReportFormat,
+    git::{Config, Repo, TrackedBranch},
+    git_status::StatusSummary,
+};
+
+/// A [`Repo`] restricted to reading. Nothing goes over the network either, so the
+/// credentials of the [`Config`] are never used and one with only the path set will do.
+///
+/// Exposes the status, HEAD and the branches, the log of outgoing commits, diffs and
+/// blame. Anything else needs a [`Repo`].
+pub struct ReadOnlyRepo<'a> {
+    repo: Repo<'a>,
+}
+
+impl<'a> ReadOnlyRepo<'a> {
+    pub fn open(config: &'a Config) -> Result<Self, git2::Error> {
+        Ok(Self { repo: Repo::open(config)? })
+    }
+
+    /// See [`Repo::status`].
+    pub fn status(&self) -> Result<StatusSummary, git2::Error> {
+        self.repo.status()
+    }
+
+    /// See [`Repo::status_fingerprint`].
+    pub fn status_fingerprint(&self) -> Result<String, git2::Error> {
+        self.repo.status_fingerprint()
+    }
+
+    /// See [`Repo::head_ref`].
+    pub fn head_ref(&self) -> Result<HeadRef, git2::Error> {
+        self.repo.head_ref()
+    }
+
+    /// See [`Repo::current_branch`].
+    pub fn current_branch(&self) -> Result<TrackedBranch<'_>, git2::Error> {
+        self.repo.current_branch()
+    }
+
+    /// [`Repo::branch_list`] as of the last fetch, it doesn't fetch.
+    pub fn branches(&self) -> Result<BranchList, git2::Error> {
+        self.repo.local_branch_list()
+    }
+
+    /// See [`Repo::outgoing`].
+    pub fn outgoing(&self, branch: &str) -> Result<Vec<CommitInfo>, git2::Error> {
+        self.repo.outgoing(branch)
+    }
+
+    /// See [`Repo::diff_names`].
+    pub fn diff_names(
+        &self,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> Result<Vec<String>, git2::Error> {
+        self.repo.diff_names(from, to)
+    }
+
+    /// See [`Repo::diff`].
+    pub fn diff(&self, from: Option<&str>, to: Option<&str>) -> Result<String, git2::Error> {
+        self.repo.diff(from, to)
+    }
+
+    /// See [`Repo::diff_staged`].
+    pub fn diff_staged(&self) -> Result<String, git2::Error> {
+        self.repo.diff_staged()
+    }
+
+    /// See [`Repo::write_diff`].
+    pub fn write_diff(
+        &self,
+        from: Option<&str>,
+        to: Option<&str>,
+        format: ReportFormat,
+        out: impl Write,
+    ) -> Result<u64, git2::Error> {
+        self.repo.write_diff(from, to, format, out)
+    }
+
+    /// See [`Repo::file_authors`].
+    pub fn file_authors(&self, path: &str) -> Result<Vec<(String, usize)>, git2::Error> {
+        self.repo.file_authors(path)
+    }
+
+    /// See [`Repo::commit_raw_message`].
+    pub fn commit_raw_message(&self, revspec: &str) -> Result<String, git2::Error> {
+        self.repo.commit_raw_message(revspec)
+    }
+
+    /// See [`Repo::rev_count`].
+    pub fn rev_count(&self, from: &str, to: &str) -> Result<usize, git2::Error> {
+        self.repo.rev_count(from, to)
+    }
+
+    /// See [`Repo::resolve_commit`].
+    pub fn resolve_commit(&self, revspec: &str) -> Result<Oid, git2::Error> {
+        self.repo.resolve_commit(revspec)
+    }
+}
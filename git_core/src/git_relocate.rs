@@ -0,0 +1,145 @@
+//! Finding a repository again after an administrator moved its directory. A repository is
+//! recognized by its [`Repo::repository_id`], so a move can be told apart from pointing the
+//! catalog at some other repository.
+
+use std::path::{Path, PathBuf};
+
+use git2::{ErrorClass, ErrorCode, Oid, Repository, RepositoryOpenFlags, Sort};
+
+use crate::{
+    git::{Config, Repo},
+    path,
+};
+
+/// Surviving ancestors of the old path searched, nearest first.
+const SEARCH_LEVELS: usize = 3;
+/// Directories below each ancestor searched for the moved repository.
+const SEARCH_DEPTH: usize = 2;
+/// Entries read at most while searching, the ancestors may be large network shares.
+const SEARCH_LIMIT: usize = 2000;
+
+/// The repository at `old` is gone. `candidates` are repositories found near it that may
+/// be where it was moved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepositoryMoved {
+    pub old: PathBuf,
+    pub candidates: Vec<PathBuf>,
+}
+
+impl RepositoryMoved {
+    /// Searches for the repository that was at `old`, going up through the ancestors of
+    /// `old` that still exist until one has candidates: the repository containing the
+    /// ancestor and those in its subdirectories. With the `expected_id` of the repository,
+    /// only the ones having it are candidates.
+    pub fn search(old: &Path, expected_id: Option<Oid>) -> Self {
+        let old = path::normalize(old);
+        let mut budget = SEARCH_LIMIT;
+        let mut candidates = Vec::new();
+        for ancestor in old.ancestors().skip(1).filter(|dir| dir.is_dir()).take(SEARCH_LEVELS) {
+            if let Ok(repo) = Repository::discover(ancestor)
+                && let Some(workdir) = repo.workdir()
+            {
+                candidates.push(workdir.components().collect());
+            }
+            find_repositories(ancestor, SEARCH_DEPTH, &mut budget, &mut candidates);
+            candidates.sort();
+            candidates.dedup();
+            if let Some(expected_id) = expected_id {
+                candidates.retain(|candidate| open_id(candidate) == Some(expected_id));
+            }
+            if !candidates.is_empty() || budget == 0 {
+                break;
+            }
+        }
+        Self { old, candidates }
+    }
+}
+
+impl std::fmt::Display for RepositoryMoved {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "repository '{}' was moved or deleted", self.old.display())?;
+        match self.candidates.as_slice() {
+            [] => write!(f, ", no repository found nearby"),
+            candidates => {
+                let candidates = candidates.iter().map(|path| format!("'{}'", path.display()));
+                write!(f, ", it may now be at {}", candidates.collect::<Vec<_>>().join(", "))
+            },
+        }
+    }
+}
+
+impl From<RepositoryMoved> for git2::Error {
+    fn from(moved: RepositoryMoved) -> Self {
+        git2::Error::new(ErrorCode::NotFound, ErrorClass::Repository, moved.to_string())
+    }
+}
+
+impl<'a> Repo<'a> {
+    /// Identifies the repository across moves and copies: the root commit its HEAD's
+    /// history starts from, `None` before the first commit.
+    pub fn repository_id(&self) -> Result<Option<Oid>, git2::Error> {
+        match self.repo.head() {
+            Err(e) if e.code() == ErrorCode::UnbornBranch => return Ok(None),
+            result => result?,
+        };
+        let mut walk = self.repo.revwalk()?;
+        walk.set_sorting(Sort::TOPOLOGICAL)?;
+        walk.simplify_first_parent()?;
+        walk.push_head()?;
+        walk.last().transpose()
+    }
+
+    /// Opens the repository moved to `new_path` and checks it is the one with
+    /// `expected_id`, its [`Repo::repository_id`] from before the move. `config.path` is
+    /// left to the caller to update.
+    pub fn relocate(
+        config: &'a Config,
+        new_path: &Path,
+        expected_id: Option<Oid>,
+    ) -> Result<Self, git2::Error> {
+        let repo = open_at(config, new_path)?;
+        if repo.repository_id()? != expected_id {
+            return Err(git2::Error::new(
+                ErrorCode::Invalid,
+                ErrorClass::Repository,
+                format!("'{}' holds a different repository", new_path.display()),
+            ));
+        }
+        Ok(repo)
+    }
+}
+
+fn open_at<'a>(config: &'a Config, path: &Path) -> Result<Repo<'a>, git2::Error> {
+    let repo = Repository::open_ext(
+        path::normalize(path),
+        RepositoryOpenFlags::NO_SEARCH,
+        std::iter::empty::<&std::ffi::OsStr>(),
+    )?;
+    Ok(Repo { repo, config })
+}
+
+/// The [`Repo::repository_id`] of the repository at `path`, if there is one with commits.
+fn open_id(path: &Path) -> Option<Oid> {
+    open_at(&Config::default(), path).ok()?.repository_id().ok().flatten()
+}
+
+/// Collects the working directories of repositories in `dir` and below, breadth limited
+/// by `budget` entries.
+fn find_repositories(dir: &Path, depth: usize, budget: &mut usize, found: &mut Vec<PathBuf>) {
+    let Ok(entries) = dir.read_dir() else { return };
+    for entry in entries.flatten() {
+        if *budget == 0 {
+            return;
+        }
+        *budget -= 1;
+        let path = entry.path();
+        if !entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+            continue;
+        }
+        if path.join(".git").exists() {
+            found.push(path);
+        } else if depth > 1 {
+            find_repositories(&path, depth - 1, budget, found);
+        }
+    }
+}
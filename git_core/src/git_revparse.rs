@@ -0,0 +1,77 @@
+//! Revisions as users type them, `HEAD~3`, `v1.2` or a short id, checked and resolved
+//! before they are handed to the heavier operations.
+
+use git2::{ErrorClass, ErrorCode, ObjectType, Oid, RevparseMode};
+
+use crate::{encoding, git::Repo};
+
+/// What a revision resolves to, see [`Repo::resolve`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedRev {
+    pub id: Oid,
+    /// [`ObjectType::Commit`], [`ObjectType::Tag`] for an annotated tag, or
+    /// [`ObjectType::Tree`] and [`ObjectType::Blob`] for specs like `HEAD:path`.
+    pub kind: ObjectType,
+    /// The ref when the revision is one, e.g. `refs/tags/v1.2` for `v1.2` but none for
+    /// `v1.2^{}`.
+    pub ref_name: Option<String>,
+    /// First line of the commit or tag message.
+    pub summary: Option<String>,
+}
+
+impl Repo<'_> {
+    /// Resolves the single revision `revspec` as `git rev-parse` does. An ambiguous short
+    /// id and a revision that names nothing fail with [`ErrorCode::Ambiguous`] and
+    /// [`ErrorCode::NotFound`] and a message saying so. Ranges like `a..b` are refused,
+    /// they go to the operations taking two revisions, e.g. [`Repo::compare_refs`].
+    pub fn resolve(&self, revspec: &str) -> Result<ResolvedRev, git2::Error> {
+        let spec = self.repo.revparse(revspec).map_err(|e| resolve_error(revspec, e))?;
+        if !spec.mode().contains(RevparseMode::SINGLE) {
+            return Err(git2::Error::new(
+                ErrorCode::InvalidSpec,
+                ErrorClass::Invalid,
+                format!(
+                    "'{revspec}' is a range, give a single revision or use an operation \
+                     comparing two, like CompareBranches"
+                ),
+            ));
+        }
+
+        let (object, reference) =
+            self.repo.revparse_ext(revspec).map_err(|e| resolve_error(revspec, e))?;
+        let kind = object.kind().unwrap_or(ObjectType::Any);
+        let summary = match kind {
+            ObjectType::Commit => object
+                .as_commit()
+                .map(|commit| encoding::decode(commit.message_encoding(), commit.message_bytes())),
+            ObjectType::Tag => object.as_tag().map(|tag| {
+                String::from_utf8_lossy(tag.message_bytes().unwrap_or_default()).into_owned()
+            }),
+            _ => None,
+        };
+        Ok(ResolvedRev {
+            id: object.id(),
+            kind,
+            ref_name: reference.and_then(|reference| reference.name().map(str::to_string)),
+            summary: summary.map(|message| message.lines().next().unwrap_or_default().to_string()),
+        })
+    }
+}
+
+/// libgit2's errors for `revspec` with a message a user can act on.
+fn resolve_error(revspec: &str, e: git2::Error) -> git2::Error {
+    let message = match (e.code(), e.class()) {
+        (ErrorCode::Ambiguous, ErrorClass::Odb) => {
+            format!("'{revspec}' is a short id of several objects, type more of it")
+        },
+        (ErrorCode::NotFound, ErrorClass::Reference | ErrorClass::Odb) => {
+            format!("'{revspec}' names no commit, branch or tag")
+        },
+        // `:/text` finding no commit message comes without a message
+        (ErrorCode::NotFound, ErrorClass::None) => format!("'{revspec}' matches nothing"),
+        // a missing parent or path
+        (ErrorCode::NotFound, _) => format!("'{revspec}': {}", e.message()),
+        _ => return e,
+    };
+    git2::Error::new(e.code(), e.class(), message)
+}
@@ -0,0 +1,169 @@
+//! The settings of the repository's own git config the crate follows, so that it behaves
+//! like command-line git in the same repository rather than on defaults of its own.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use git2::{ErrorClass, ErrorCode, FetchPrune};
+
+use crate::git::Repo;
+
+/// The settings [`Repo::effective_config`] reports, each `None` when the repository leaves
+/// it unset.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigSnapshot {
+    /// `core.hooksPath`. libgit2 runs no hooks, so it is only reported.
+    pub hooks_path: Option<PathBuf>,
+    /// `commit.template`, read by [`Repo::commit_template`].
+    pub commit_template: Option<PathBuf>,
+    /// `push.default`, which remote branch [`Repo::push`] updates.
+    pub push_default: Option<PushDefault>,
+    /// `fetch.prune`, turning off the pruning fetches do by default.
+    pub fetch_prune: Option<bool>,
+}
+
+/// Values of `push.default` as git documents them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushDefault {
+    /// Refuse pushes that don't name the remote branch.
+    Nothing,
+    /// Push to the branch of the same name, which is also what happens when unset.
+    Current,
+    /// Push to the upstream branch, whatever its name.
+    Upstream,
+    /// Like `Current`, refusing when the upstream branch is named differently.
+    Simple,
+    /// Git pushes every branch with a namesake on the remote, here like `Current`.
+    Matching,
+}
+
+impl FromStr for PushDefault {
+    type Err = git2::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nothing" => Ok(Self::Nothing),
+            "current" => Ok(Self::Current),
+            // "tracking" is the deprecated name
+            "upstream" | "tracking" => Ok(Self::Upstream),
+            "simple" => Ok(Self::Simple),
+            "matching" => Ok(Self::Matching),
+            _ => Err(git2::Error::new(
+                ErrorCode::Invalid,
+                ErrorClass::Config,
+                format!("unknown push.default '{s}'"),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for PushDefault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            Self::Nothing => "nothing",
+            Self::Current => "current",
+            Self::Upstream => "upstream",
+            Self::Simple => "simple",
+            Self::Matching => "matching",
+        };
+        f.write_str(value)
+    }
+}
+
+impl Repo<'_> {
+    /// The git config settings the operations follow, as set for this repository at any
+    /// level, for finding out why the component behaves as it does.
+    pub fn effective_config(&self) -> Result<ConfigSnapshot, git2::Error> {
+        let config = self.repo.config()?.snapshot()?;
+        // `~` is expanded by libgit2, relative paths are taken from the working tree
+        let path = |key| -> Result<Option<PathBuf>, git2::Error> {
+            Ok(found(config.get_path(key))?.map(|path| self.workdir_relative(&path)))
+        };
+        Ok(ConfigSnapshot {
+            hooks_path: path("core.hooksPath")?,
+            commit_template: path("commit.template")?,
+            push_default: found(config.get_string("push.default"))?
+                .map(|value| value.parse())
+                .transpose()?,
+            fetch_prune: found(config.get_bool("fetch.prune"))?,
+        })
+    }
+
+    /// Contents of the `commit.template` file to start commit messages from, `None` when
+    /// none is configured.
+    pub fn commit_template(&self) -> Result<Option<String>, git2::Error> {
+        let Some(path) = self.effective_config()?.commit_template else {
+            return Ok(None);
+        };
+        let template = std::fs::read(&path).map_err(|e| {
+            git2::Error::from_str(&format!(
+                "failed to read commit.template '{}': {e}",
+                path.display()
+            ))
+        })?;
+        Ok(Some(String::from_utf8_lossy(&template).into_owned()))
+    }
+
+    /// Pruning for fetches: on, unless `fetch.prune` turns it off.
+    pub(crate) fn fetch_prune(&self) -> Result<FetchPrune, git2::Error> {
+        if self.effective_config()?.fetch_prune == Some(false) {
+            Ok(FetchPrune::Off)
+        } else {
+            Ok(FetchPrune::On)
+        }
+    }
+
+    /// The ref on `remote` a push of the local branch `refname` updates following
+    /// `push.default`, its namesake when unset.
+    pub(crate) fn push_target(&self, refname: &str, remote: &str) -> Result<String, git2::Error> {
+        let name = refname.trim_start_matches("refs/heads/");
+        let refused = |reason: String| {
+            git2::Error::new(
+                ErrorCode::Invalid,
+                ErrorClass::Config,
+                format!("{reason}, use PushTo"),
+            )
+        };
+        let upstream = || -> Result<Option<String>, git2::Error> {
+            let config = self.repo.config()?.snapshot()?;
+            let merge = found(config.get_string(&format!("branch.{name}.merge")))?;
+            let from = found(config.get_string(&format!("branch.{name}.remote")))?;
+            Ok(merge.filter(|_| from.as_deref() == Some(remote)))
+        };
+
+        match self.effective_config()?.push_default {
+            Some(PushDefault::Nothing) =>
+                Err(refused("push.default is 'nothing', the remote branch must be named".into())),
+            Some(PushDefault::Upstream) => upstream()?.ok_or_else(|| {
+                refused(format!("push.default is 'upstream' and '{name}' has none on '{remote}'"))
+            }),
+            Some(PushDefault::Simple) => match upstream()? {
+                Some(merge) if merge != refname => Err(refused(format!(
+                    "push.default is 'simple' and the upstream of '{name}' is named differently, \
+                     {merge}"
+                ))),
+                _ => Ok(refname.to_string()),
+            },
+            Some(PushDefault::Current | PushDefault::Matching) | None => Ok(refname.to_string()),
+        }
+    }
+
+    fn workdir_relative(&self, path: &Path) -> PathBuf {
+        match self.repo.workdir() {
+            Some(workdir) if path.is_relative() => workdir.join(path),
+            _ => path.to_path_buf(),
+        }
+    }
+}
+
+/// A config value, `None` when unset.
+fn found<T>(result: Result<T, git2::Error>) -> Result<Option<T>, git2::Error> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,254 @@
+//! Sparse checkouts, a working tree holding only some directories of a big repository, in
+//! the cone mode of `git sparse-checkout`. libgit2 knows nothing of them, so the
+//! directories are kept in `info/sparse-checkout` for command-line git and applied here by
+//! marking the other files skip-worktree in the index and removing them from the disk.
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use git2::{
+    ConfigLevel,
+    ErrorClass,
+    ErrorCode,
+    IndexEntryExtendedFlag,
+    Status,
+    StatusOptions,
+    build::CheckoutBuilder,
+};
+
+use crate::{git::Repo, git_status::StatusSummary};
+
+const SPARSE_CHECKOUT: &str = "core.sparseCheckout";
+const SPARSE_CHECKOUT_CONE: &str = "core.sparseCheckoutCone";
+
+impl Repo<'_> {
+    /// Directories of the sparse checkout, empty when the whole tree is checked out.
+    pub fn sparse_checkout(&self) -> Result<Vec<String>, git2::Error> {
+        if !self.sparse_checkout_enabled()? {
+            return Ok(Vec::new());
+        }
+        let patterns = fs::read_to_string(self.sparse_checkout_file()).unwrap_or_default();
+        Ok(cone_directories(&patterns))
+    }
+
+    /// Checks out only the files under `directories`, e.g. `src/Catalogs/Goods`, and the
+    /// files directly in their parents and in the root, as `git sparse-checkout set --cone`
+    /// does. The other files are removed from the working tree and left out of the status,
+    /// but stay in the index and in commits. Gitignore-like patterns are refused, only
+    /// directories are supported. No directories check the whole tree out again.
+    ///
+    /// Files with local changes are never removed, they are returned and stay checked out
+    /// until the changes are committed or discarded and the checkout is set again. Branch
+    /// switches and pulls restore the sparse checkout after libgit2 wrote the whole tree.
+    pub fn set_sparse_checkout(&self, directories: &[String]) -> Result<Vec<String>, git2::Error> {
+        self.config.ensure_writable()?;
+        let mut cone = Vec::new();
+        for directory in directories {
+            let directory = directory.trim().trim_matches('/');
+            if directory.contains(['*', '?', '[', '!', '\\']) || directory.contains("//") {
+                return Err(git2::Error::new(
+                    ErrorCode::InvalidSpec,
+                    ErrorClass::Invalid,
+                    format!("'{directory}' is not a directory, only directories can be sparse"),
+                ));
+            }
+            if !directory.is_empty() {
+                cone.push(directory.to_string());
+            }
+        }
+        // a directory under another one listed adds nothing
+        cone.sort();
+        cone.dedup();
+        let nested = |directory: &String| {
+            cone.iter().any(|other| directory.starts_with(&format!("{other}/")))
+        };
+        let cone = cone.iter().filter(|directory| !nested(directory)).cloned().collect::<Vec<_>>();
+
+        let mut config = self.repo.config()?.open_level(ConfigLevel::Local)?;
+        if cone.is_empty() {
+            config.set_bool(SPARSE_CHECKOUT, false)?;
+        } else {
+            let file = self.sparse_checkout_file();
+            if let Some(info) = file.parent() {
+                fs::create_dir_all(info).map_err(|e| io_error(info, &e))?;
+            }
+            fs::write(&file, cone_patterns(&cone)).map_err(|e| io_error(&file, &e))?;
+            config.set_bool(SPARSE_CHECKOUT, true)?;
+            config.set_bool(SPARSE_CHECKOUT_CONE, true)?;
+        }
+        self.apply_sparse_checkout(&cone)
+    }
+
+    /// Applies the sparse checkout again after a checkout wrote the whole tree, nothing
+    /// to do without one.
+    pub(crate) fn reapply_sparse_checkout(&self) -> Result<(), git2::Error> {
+        let cone = self.sparse_checkout()?;
+        if !cone.is_empty() {
+            self.apply_sparse_checkout(&cone)?;
+        }
+        Ok(())
+    }
+
+    /// Drops the files the sparse checkout removed from `summary`, which libgit2 reports
+    /// as deleted.
+    pub(crate) fn hide_sparse_files(&self, summary: &mut StatusSummary) -> Result<(), git2::Error> {
+        if !self.sparse_checkout_enabled()? {
+            return Ok(());
+        }
+        let skipped = self
+            .repo
+            .index()?
+            .iter()
+            .filter(|entry| {
+                entry.flags_extended & IndexEntryExtendedFlag::SKIP_WORKTREE.bits() != 0
+            })
+            .map(|entry| String::from_utf8_lossy(&entry.path).into_owned())
+            .collect::<HashSet<_>>();
+        summary.not_staged.retain(|file| {
+            file.status != git2::Delta::Deleted || !skipped.contains(&file.old_file)
+        });
+        Ok(())
+    }
+
+    /// Marks the files outside `cone` skip-worktree and removes them, or all files back
+    /// when `cone` is empty. Returns the files kept for their local changes.
+    fn apply_sparse_checkout(&self, cone: &[String]) -> Result<Vec<String>, git2::Error> {
+        let mut options = StatusOptions::new();
+        options.include_untracked(false);
+        let changed = self
+            .repo
+            .statuses(Some(&mut options))?
+            .iter()
+            .filter(|entry| entry.status() != Status::CURRENT)
+            .filter_map(|entry| entry.path().map(str::to_string))
+            .collect::<HashSet<_>>();
+
+        let workdir = self.repo.workdir().ok_or_else(|| {
+            git2::Error::new(ErrorCode::BareRepo, ErrorClass::Repository, "no working tree")
+        })?;
+        let skip_worktree = IndexEntryExtendedFlag::SKIP_WORKTREE.bits();
+        let mut index = self.repo.index()?;
+        let mut kept = Vec::new();
+        let mut removed = Vec::new();
+        let mut restored = Vec::new();
+        let entries = index.iter().collect::<Vec<_>>();
+        for mut entry in entries {
+            let path = String::from_utf8_lossy(&entry.path).into_owned();
+            let skipped = entry.flags_extended & skip_worktree != 0;
+            let wanted = cone.is_empty() || in_cone(cone, &path);
+            if wanted && skipped {
+                entry.flags_extended &= !skip_worktree;
+                index.add(&entry)?;
+                restored.push(path);
+            } else if !wanted && !skipped {
+                if changed.contains(&path) {
+                    kept.push(path);
+                    continue;
+                }
+                entry.flags_extended |= skip_worktree;
+                index.add(&entry)?;
+                removed.push(path);
+            }
+        }
+        index.write()?;
+
+        for path in &removed {
+            let file = workdir.join(path);
+            match fs::remove_file(&file) {
+                Ok(()) => remove_empty_parents(workdir, &file),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {},
+                Err(e) => return Err(io_error(&file, &e)),
+            }
+        }
+        if !restored.is_empty() {
+            let mut checkout = CheckoutBuilder::new();
+            checkout.force().disable_pathspec_match(true);
+            for path in &restored {
+                checkout.path(path.as_str());
+            }
+            self.repo.checkout_index(Some(&mut index), Some(&mut checkout))?;
+        }
+        Ok(kept)
+    }
+
+    fn sparse_checkout_enabled(&self) -> Result<bool, git2::Error> {
+        match self.repo.config()?.get_bool(SPARSE_CHECKOUT) {
+            Ok(enabled) => Ok(enabled),
+            Err(e) if e.code() == ErrorCode::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Each worktree has its own, as with git.
+    fn sparse_checkout_file(&self) -> PathBuf {
+        self.repo.path().join("info").join("sparse-checkout")
+    }
+}
+
+/// Whether the file `path` is checked out with the directories `cone`: it is in the root,
+/// under one of them, or directly in one of their parents.
+fn in_cone(cone: &[String], path: &str) -> bool {
+    let Some((parent, _)) = path.rsplit_once('/') else {
+        return true;
+    };
+    cone.iter().any(|directory| {
+        path.starts_with(&format!("{directory}/")) || directory.starts_with(&format!("{parent}/"))
+    })
+}
+
+/// The patterns `git sparse-checkout set --cone` writes for `cone`, sorted directories
+/// none of which is under another: every parent is taken without its subdirectories.
+fn cone_patterns(cone: &[String]) -> String {
+    let mut parents = cone
+        .iter()
+        .flat_map(|directory| {
+            directory.match_indices('/').map(|(end, _)| directory[..end].to_string())
+        })
+        .collect::<Vec<_>>();
+    parents.sort();
+    parents.dedup();
+
+    let mut lines = vec!["/*".to_string(), "!/*/".to_string()];
+    let mut entries = parents.iter().map(|parent| (parent, true)).collect::<Vec<_>>();
+    entries.extend(cone.iter().map(|directory| (directory, false)));
+    entries.sort();
+    for (directory, parent) in entries {
+        lines.push(format!("/{directory}/"));
+        if parent {
+            lines.push(format!("!/{directory}/*/"));
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+/// The directories of cone `patterns`: those included without their subdirectories
+/// being excluded again.
+fn cone_directories(patterns: &str) -> Vec<String> {
+    let lines = patterns.lines().map(str::trim).collect::<Vec<_>>();
+    lines
+        .iter()
+        .filter_map(|line| line.strip_prefix('/')?.strip_suffix('/'))
+        .filter(|directory| {
+            *directory != "*" && !lines.contains(&format!("!/{directory}/*/").as_str())
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+/// Removes the directories left empty between `file` and `workdir`.
+fn remove_empty_parents(workdir: &Path, file: &Path) {
+    let mut directory = file.parent();
+    while let Some(current) = directory.filter(|current| *current != workdir) {
+        if fs::remove_dir(current).is_err() {
+            break;
+        }
+        directory = current.parent();
+    }
+}
+
+fn io_error(path: &Path, e: &std::io::Error) -> git2::Error {
+    git2::Error::from_str(&format!("failed to update '{}': {e}", path.display()))
+}
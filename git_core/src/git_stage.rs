@@ -0,0 +1,109 @@
+//! Staging part of a file's changes.
+
+use std::path::Path;
+
+use git2::{DiffLineType, DiffOptions, IndexEntry, IndexTime, Oid, Patch};
+
+use crate::git::Repo;
+
+const REGULAR_FILE_MODE: u32 = 0o100_644;
+
+impl Repo<'_> {
+    /// Stages the changes of `path` that touch the working tree lines in `ranges`, leaving
+    /// the rest unstaged, and returns the number of hunks staged.
+    ///
+    /// Ranges are 1-based and inclusive. Changes are staged a whole hunk at a time, with
+    /// hunks split as finely as git does without context lines, so one range touching any
+    /// line of a hunk stages all of it. A hunk that only deletes lines sits at the working
+    /// tree line right before the deletion. Overlapping or repeated ranges are merged, a hunk
+    /// is staged at most once whatever the number of ranges touching it.
+    pub fn stage_lines(&self, path: &str, ranges: &[(u32, u32)]) -> Result<usize, git2::Error> {
+        self.config.ensure_writable()?;
+        if let Some((start, end)) = ranges.iter().find(|(start, end)| start > end || *start == 0) {
+            return Err(git2::Error::from_str(&format!("invalid line range {start}-{end}")));
+        }
+
+        let mut index = self.repo.index()?;
+        let staged = index.get_path(Path::new(path), 0);
+        let old = match &staged {
+            Some(entry) => self.repo.find_blob(entry.id)?.content().to_vec(),
+            None => Vec::new(),
+        };
+        // hashing the file applies the same filters (e.g. autocrlf) as `git add`
+        let new_id = self.repo.blob_path(&self.workdir()?.join(path))?;
+        let new = self.repo.find_blob(new_id)?.content().to_vec();
+
+        let mut options = DiffOptions::new();
+        options.context_lines(0);
+        let patch = Patch::from_buffers(
+            &old,
+            Some(Path::new(path)),
+            &new,
+            Some(Path::new(path)),
+            Some(&mut options),
+        )?;
+
+        let old_lines = old.split_inclusive(|byte| *byte == b'\n').collect::<Vec<_>>();
+        let mut staged_content = Vec::with_capacity(new.len().max(old.len()));
+        let mut copied = 0;
+        let mut hunks_staged = 0;
+        for hunk_idx in 0..patch.num_hunks() {
+            let (hunk, line_count) = patch.hunk(hunk_idx)?;
+            let first = hunk.new_start().max(1);
+            let last = first + hunk.new_lines().saturating_sub(1);
+            if !ranges.iter().any(|(start, end)| *start <= last && first <= *end) {
+                continue;
+            }
+
+            // a hunk without old lines inserts after `old_start`, otherwise replaces from it
+            let (keep_until, skip) = match hunk.old_lines() {
+                0 => (hunk.old_start() as usize, 0),
+                removed => (hunk.old_start() as usize - 1, removed as usize),
+            };
+            old_lines[copied..keep_until].iter().for_each(|line| staged_content.extend(*line));
+            copied = keep_until + skip;
+
+            for line_idx in 0..line_count {
+                let line = patch.line_in_hunk(hunk_idx, line_idx)?;
+                if line.origin_value() == DiffLineType::Addition {
+                    staged_content.extend(line.content());
+                }
+            }
+            hunks_staged += 1;
+        }
+        old_lines[copied..].iter().for_each(|line| staged_content.extend(*line));
+
+        if hunks_staged > 0 {
+            let mode = staged.map_or(REGULAR_FILE_MODE, |entry| entry.mode);
+            index.add_frombuffer(&partial_entry(path, mode), &staged_content)?;
+            index.write()?;
+        }
+        Ok(hunks_staged)
+    }
+
+    fn workdir(&self) -> Result<&Path, git2::Error> {
+        self.repo
+            .workdir()
+            .ok_or_else(|| git2::Error::from_str("bare repository has no working tree"))
+    }
+}
+
+/// Index entry for partially staged content, the id and size are filled in when the
+/// content is added. The stat data stays empty since the content no longer matches the
+/// file on disk, otherwise git could take the file for unchanged and hide the rest of it.
+fn partial_entry(path: &str, mode: u32) -> IndexEntry {
+    IndexEntry {
+        ctime: IndexTime::new(0, 0),
+        mtime: IndexTime::new(0, 0),
+        dev: 0,
+        ino: 0,
+        mode,
+        uid: 0,
+        gid: 0,
+        file_size: 0,
+        id: Oid::zero(),
+        flags: 0,
+        flags_extended: 0,
+        path: path.as_bytes().to_vec(),
+    }
+}
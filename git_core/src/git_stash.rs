@@ -0,0 +1,105 @@
+//! Setting changes aside in the stash, as `git stash push` does.
+
+use git2::{
+    ErrorClass,
+    ErrorCode,
+    Oid,
+    Signature,
+    StashFlags,
+    StashSaveOptions,
+    StatusOptions,
+    build::CheckoutBuilder,
+};
+
+use crate::git::Repo;
+
+const STASH_REF: &str = "refs/stash";
+
+impl Repo<'_> {
+    /// Stashes the staged and unstaged changes to `paths`, files or directories, and
+    /// reverts them in the working tree and index, leaving changes to other files alone, as
+    /// `git stash push -- <paths>` does. Untracked files stay. Without a `message` the stash
+    /// is described by the branch and commit it was made on. Returns the stash commit.
+    pub fn stash_save_paths(
+        &mut self,
+        message: Option<&str>,
+        paths: &[&str],
+    ) -> Result<Oid, git2::Error> {
+        self.config.ensure_writable()?;
+        if paths.is_empty() {
+            return Err(git2::Error::from_str("no paths to stash"));
+        }
+        self.check_no_conflicts()?;
+        let files = self.changed_files(paths)?;
+        if files.is_empty() {
+            return Err(git2::Error::new(
+                ErrorCode::NotFound,
+                ErrorClass::Stash,
+                format!("nothing to stash in {}", paths.join(", ")),
+            ));
+        }
+
+        // libgit2 takes files only and, once stashed, resets the whole working tree, so the
+        // directories are expanded here and only the stashed files are reset below
+        let stasher = Signature::now(&self.config.username, &self.config.email)?;
+        let mut options = StashSaveOptions::new(stasher.clone());
+        options.flags(Some(StashFlags::KEEP_ALL));
+        for file in &files {
+            options.pathspec(file.as_str());
+        }
+        let id = self.repo.stash_save_ext(Some(&mut options))?;
+
+        let head = self.repo.head()?.peel_to_commit()?;
+        self.repo.reset_default(Some(head.as_object()), &files)?;
+        let mut checkout = CheckoutBuilder::new();
+        checkout.force().remove_untracked(true).disable_pathspec_match(true);
+        for file in &files {
+            checkout.path(file.as_str());
+        }
+        self.repo.checkout_head(Some(&mut checkout))?;
+
+        match message {
+            Some(message) => self.describe_stash(id, message, &stasher),
+            None => Ok(id),
+        }
+    }
+
+    /// Tracked files under `paths` with staged or unstaged changes.
+    fn changed_files(&self, paths: &[&str]) -> Result<Vec<String>, git2::Error> {
+        let mut options = StatusOptions::new();
+        options.include_untracked(false);
+        for path in paths {
+            options.pathspec(path.trim_end_matches('/'));
+        }
+        let statuses = self.repo.statuses(Some(&mut options))?;
+        Ok(statuses.iter().filter_map(|entry| entry.path().map(str::to_string)).collect())
+    }
+
+    /// Replaces the stash `id` just made with one described by `message` as git does,
+    /// `On <branch>: <message>`. git2's stash options take paths but no message.
+    fn describe_stash(
+        &self,
+        id: Oid,
+        message: &str,
+        stasher: &Signature<'_>,
+    ) -> Result<Oid, git2::Error> {
+        let head = self.repo.head()?;
+        let branch =
+            if head.is_branch() { head.shorthand().unwrap_or("HEAD") } else { "(no branch)" };
+        let message = format!("On {branch}: {message}");
+        let described =
+            self.repo.find_commit(id)?.amend(None, None, None, None, Some(&message), None)?;
+
+        // the ref moves with libgit2's default identity in the log, both entries are
+        // replaced by one of the stasher as if the stash had been made so
+        self.repo.reference(STASH_REF, described, true, &message)?;
+        let mut reflog = self.repo.reflog(STASH_REF)?;
+        reflog.remove(0, false)?;
+        if reflog.get(0).map(|entry| entry.id_new()) == Some(id) {
+            reflog.remove(0, false)?;
+        }
+        reflog.append(described, stasher, Some(&message))?;
+        reflog.write()?;
+        Ok(described)
+    }
+}
@@ -1,37 +1,273 @@
-use std::path::Path;
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
-use git2::{Delta, DiffDelta, StatusEntry};
+use git2::{Delta, DiffDelta, FileMode, Oid, Repository, StatusEntry, Statuses};
 
-use crate::INVALID_UTF8;
+use crate::git_locks::FileLock;
+
+/// Git LFS pointer files are at most this big, see <https://github.com/git-lfs/git-lfs/blob/main/docs/spec.md>.
+pub const LFS_POINTER_MAX_SIZE: usize = 1024;
+const LFS_POINTER_HEADER: &[u8] = b"version https://git-lfs";
+
+/// Whether `content` is a Git LFS pointer standing in for the real file.
+pub fn is_lfs_pointer(content: &[u8]) -> bool {
+    content.len() <= LFS_POINTER_MAX_SIZE && content.starts_with(LFS_POINTER_HEADER)
+}
+
+/// Checks the blob `id`, or the working tree file at `path` when the blob isn't in the object
+/// database (not hashed yet), for being an LFS pointer.
+pub(crate) fn is_lfs_pointer_at(repo: &Repository, id: Oid, path: &Path) -> bool {
+    let from_odb = || {
+        // the header alone rules out big blobs without reading them
+        let (size, _) = repo.odb().ok()?.read_header(id).ok()?;
+        if size > LFS_POINTER_MAX_SIZE {
+            return Some(false);
+        }
+        Some(is_lfs_pointer(repo.find_blob(id).ok()?.content()))
+    };
+    let from_workdir = || {
+        let path = repo.workdir()?.join(path);
+        let too_big = path.metadata().ok()?.len() > LFS_POINTER_MAX_SIZE as u64;
+        Some(!too_big && is_lfs_pointer(&std::fs::read(path).ok()?))
+    };
+
+    from_odb().or_else(from_workdir).unwrap_or(false)
+}
+
+/// What a status entry is, from its git file mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Executable,
+    /// The content is the link target.
+    Symlink,
+    /// A submodule checkout, its content is the commit it is at.
+    Submodule,
+}
+
+impl EntryKind {
+    fn from_mode(mode: FileMode) -> Self {
+        match mode {
+            FileMode::Link => Self::Symlink,
+            FileMode::Commit => Self::Submodule,
+            FileMode::BlobExecutable => Self::Executable,
+            _ => Self::File,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct FileStatus {
     pub status: Delta,
     pub old_file: String,
     pub new_file: String,
+    /// Kind of the new entry, of the old one for a deletion.
+    pub entry_kind: EntryKind,
+    /// Git file modes of both sides, [`FileMode::Unreadable`] for the side an addition or
+    /// deletion lacks.
+    pub old_mode: FileMode,
+    pub new_mode: FileMode,
+    /// The new content is a Git LFS pointer, not the object itself.
+    pub lfs_pointer: bool,
+    /// Owner of an advisory lock someone else holds on the file.
+    pub locked_by: Option<String>,
+    new_id: Oid,
 }
 
 impl FileStatus {
-    fn from_delta(delta: &DiffDelta) -> Self {
+    fn from_delta(delta: &DiffDelta, policy: StatusPolicy) -> Self {
+        let side = match delta.status() {
+            Delta::Deleted => delta.old_file(),
+            _ => delta.new_file(),
+        };
         Self {
             status: delta.status(),
-            old_file: delta
-                .old_file()
-                .path()
-                .and_then(Path::to_str)
-                .unwrap_or(INVALID_UTF8)
-                .to_string(),
-            new_file: delta
-                .new_file()
-                .path()
-                .and_then(Path::to_str)
-                .unwrap_or(INVALID_UTF8)
-                .to_string(),
+            entry_kind: if policy.classify_kinds {
+                EntryKind::from_mode(side.mode())
+            } else {
+                EntryKind::File
+            },
+            old_mode: delta.old_file().mode(),
+            new_mode: delta.new_file().mode(),
+            lfs_pointer: false,
+            locked_by: None,
+            new_id: delta.new_file().id(),
+            old_file: lossy_path(delta.old_file().path()),
+            new_file: lossy_path(delta.new_file().path()),
+        }
+    }
+
+    /// The file modes before and after as git prints them, e.g. `100644` and `100755` for
+    /// a file made executable, when both sides exist and their modes differ.
+    pub fn mode_change(&self) -> Option<(String, String)> {
+        let unreadable = [self.old_mode, self.new_mode].contains(&FileMode::Unreadable);
+        if unreadable || self.old_mode == self.new_mode {
+            return None;
         }
+        let octal = |mode: FileMode| format!("{:06o}", u32::from(mode));
+        Some((octal(self.old_mode), octal(self.new_mode)))
+    }
+
+    /// Reads the new content from the object database, or from the working tree when it
+    /// hasn't been hashed yet, and checks it for the LFS pointer header.
+    fn detect_lfs_pointer(&mut self, repo: &Repository) {
+        if Delta::Deleted == self.status
+            || matches!(self.entry_kind, EntryKind::Symlink | EntryKind::Submodule)
+        {
+            return;
+        }
+        self.lfs_pointer = is_lfs_pointer_at(repo, self.new_id, Path::new(&self.new_file));
     }
 }
 
 
+/// How [`Repo::status`](crate::git::Repo::status) scans the working tree, see
+/// [`Config::status_perf_mode`](crate::git::Config::status_perf_mode). Both modes report
+/// the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatusPerfMode {
+    /// libgit2's scan alone, the index is only read.
+    #[default]
+    Conservative,
+    /// For large working trees on file systems with slow metadata, like NTFS with an
+    /// antivirus filter, where a status is mostly stat calls:
+    /// - the files of the index are stat'ed by one thread per CPU first, so libgit2's one
+    ///   by one stat calls are answered from the file system's cache. This is an extra pass
+    ///   that only pays off while that cache is cold, e.g. for the first status of the day;
+    /// - files touched but not changed, which libgit2 hashes on every status, are recorded
+    ///   in the index as unchanged, as `git status` does. A status then writes the index,
+    ///   so [`Repo::status_fingerprint`](crate::git::Repo::status_fingerprint) changes once.
+    ///   This part is left out when the repository is read-only or the index is locked.
+    Fast,
+}
+
+impl FromStr for StatusPerfMode {
+    type Err = git2::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "" | "conservative" => Ok(Self::Conservative),
+            "fast" => Ok(Self::Fast),
+            _ => Err(git2::Error::from_str(&format!(
+                "unknown status mode '{s}', expected conservative or fast"
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for StatusPerfMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StatusPerfMode::Conservative => write!(f, "conservative"),
+            StatusPerfMode::Fast => write!(f, "fast"),
+        }
+    }
+}
+
+/// Stats the files at `paths`, relative to `workdir`, with one thread per CPU, for
+/// [`StatusPerfMode::Fast`]. Only the file system's cache is of interest, so failures are
+/// ignored.
+pub(crate) fn prewarm_metadata(workdir: &Path, paths: &[PathBuf]) {
+    let threads = std::thread::available_parallelism().map_or(1, usize::from);
+    let chunk = paths.len().div_ceil(threads).max(1);
+    std::thread::scope(|scope| {
+        for paths in paths.chunks(chunk) {
+            scope.spawn(move || {
+                for path in paths {
+                    let _ = std::fs::symlink_metadata(workdir.join(path));
+                }
+            });
+        }
+    });
+}
+
+/// How [`StatusBuilder`] sorts status entries into a [`StatusSummary`]. The default keeps
+/// the sections [`Repo::status`](crate::git::Repo::status) always had.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatusPolicy {
+    /// List untracked files in [`StatusSummary::untracked`]. Without them the working tree
+    /// is only scanned for the files of the index, much quicker when there are many
+    /// untracked ones.
+    pub include_untracked: bool,
+    /// List ignored files in [`StatusSummary::ignored`].
+    pub include_ignored: bool,
+    /// List a file that was staged and changed again once, under its staged change.
+    pub merge_staged_unstaged: bool,
+    /// List files with merge conflicts once in [`StatusSummary::conflicted`] rather than
+    /// under both staged and not staged changes.
+    pub detect_conflicts: bool,
+    /// Tell symlinks, executables and submodules from plain files, see [`EntryKind`].
+    pub classify_kinds: bool,
+}
+
+impl Default for StatusPolicy {
+    fn default() -> Self {
+        Self {
+            include_untracked: true,
+            include_ignored: false,
+            merge_staged_unstaged: false,
+            detect_conflicts: false,
+            classify_kinds: true,
+        }
+    }
+}
+
+/// Builds a [`StatusSummary`] from libgit2's status entries following a [`StatusPolicy`].
+pub struct StatusBuilder {
+    policy: StatusPolicy,
+    summary: StatusSummary,
+}
+
+impl StatusBuilder {
+    pub fn new(branch_name: String, policy: StatusPolicy) -> Self {
+        Self {
+            policy,
+            summary: StatusSummary::new(branch_name),
+        }
+    }
+
+    /// Sorts every entry of `statuses` into the summary.
+    pub fn add_statuses(&mut self, statuses: &Statuses) {
+        for entry in statuses.iter() {
+            self.add_entry(&entry);
+        }
+    }
+
+    pub fn add_entry(&mut self, entry: &StatusEntry) {
+        let policy = self.policy;
+        let summary = &mut self.summary;
+        let file = |delta: DiffDelta| FileStatus::from_delta(&delta, policy);
+
+        if policy.include_ignored && entry.status().is_ignored() {
+            summary.ignored.extend(entry.index_to_workdir().map(file));
+            return;
+        }
+        if policy.detect_conflicts && entry.status().is_conflicted() {
+            let delta = entry.index_to_workdir().or_else(|| entry.head_to_index());
+            summary.conflicted.extend(delta.map(file));
+            return;
+        }
+
+        let staged = entry.head_to_index().map(file);
+        let has_staged = staged.is_some();
+        summary.staged.extend(staged);
+
+        match entry.index_to_workdir().map(file) {
+            Some(status @ FileStatus { status: Delta::Untracked, .. }) =>
+                summary.untracked.push(status),
+            Some(_) if policy.merge_staged_unstaged && has_staged => {},
+            Some(status) => summary.not_staged.push(status),
+            None => {},
+        }
+    }
+
+    pub fn build(self) -> StatusSummary {
+        self.summary
+    }
+}
+
 /// From <https://git-scm.com/docs/git-status> :
 ///
 /// Displays paths that have differences between the index file and the current HEAD commit,
@@ -45,6 +281,13 @@ pub struct StatusSummary {
     pub staged: Vec<FileStatus>,
     pub not_staged: Vec<FileStatus>,
     pub untracked: Vec<FileStatus>,
+    /// Files with merge conflicts, when [`StatusPolicy::detect_conflicts`] is set.
+    pub conflicted: Vec<FileStatus>,
+    /// Ignored files, when [`StatusPolicy::include_ignored`] is set.
+    pub ignored: Vec<FileStatus>,
+    /// The upstream of the branch when it is gone, e.g. pruned after it was deleted on the
+    /// server.
+    pub upstream_gone: Option<String>,
 }
 
 impl StatusSummary {
@@ -54,21 +297,55 @@ impl StatusSummary {
             staged: Vec::new(),
             not_staged: Vec::new(),
             untracked: Vec::new(),
+            conflicted: Vec::new(),
+            ignored: Vec::new(),
+            upstream_gone: None,
         }
     }
 
+    /// Sorts `entry` with the default [`StatusPolicy`], see [`StatusBuilder`].
     pub fn add_entry(&mut self, entry: &StatusEntry) {
-        if let Some(ref delta) = entry.head_to_index() {
-            self.staged.push(FileStatus::from_delta(delta));
-        }
+        let mut builder = StatusBuilder {
+            policy: StatusPolicy::default(),
+            summary: std::mem::replace(self, Self::new(String::new())),
+        };
+        builder.add_entry(entry);
+        *self = builder.build();
+    }
 
-        match entry.index_to_workdir().as_ref().map(FileStatus::from_delta) {
-            Some(status @ FileStatus { status: Delta::Untracked, .. }) =>
-                self.untracked.push(status),
-            Some(status) => self.not_staged.push(status),
-            None => {},
+    /// The file lists with their headings, in the order git shows them. Empty ones are
+    /// included.
+    pub fn sections(&self) -> [(&'static str, &[FileStatus]); 5] {
+        [
+            ("Changes to be committed", &self.staged),
+            ("Unmerged paths", &self.conflicted),
+            ("Changes not staged for commit", &self.not_staged),
+            ("Untracked files", &self.untracked),
+            ("Ignored files", &self.ignored),
+        ]
+    }
+
+    pub fn annotate_locks(&mut self, locks: &[FileLock]) {
+        for file in self.files_mut() {
+            file.locked_by = locks
+                .iter()
+                .find(|lock| lock.path == file.new_file || lock.path == file.old_file)
+                .map(|lock| lock.owner.clone());
         }
     }
+
+    pub fn detect_lfs_pointers(&mut self, repo: &Repository) {
+        self.files_mut().for_each(|file| file.detect_lfs_pointer(repo));
+    }
+
+    fn files_mut(&mut self) -> impl Iterator<Item = &mut FileStatus> {
+        self.staged
+            .iter_mut()
+            .chain(&mut self.conflicted)
+            .chain(&mut self.not_staged)
+            .chain(&mut self.untracked)
+            .chain(&mut self.ignored)
+    }
 }
 
 
@@ -77,9 +354,52 @@ impl std::fmt::Display for FileStatus {
         let status = format!("{:10?}", self.status).to_lowercase();
 
         if Delta::Renamed == self.status {
-            write!(f, "{status}: {} --> {}", self.old_file, self.new_file)
+            write!(f, "{status}: {} --> {}", self.old_file, self.new_file)?;
         } else {
-            write!(f, "{status}: {}", self.old_file)
+            write!(f, "{status}: {}", self.old_file)?;
+        }
+
+        match self.entry_kind {
+            EntryKind::Symlink => write!(f, " (symlink)")?,
+            EntryKind::Submodule => write!(f, " (submodule)")?,
+            EntryKind::File | EntryKind::Executable => {},
+        }
+        if let Some((old, new)) = self.mode_change() {
+            write!(f, " (mode changed {old} -> {new})")?;
+        }
+        if self.lfs_pointer {
+            write!(f, " (LFS object)")?;
         }
+        if let Some(owner) = &self.locked_by {
+            write!(f, " (locked by {owner})")?;
+        }
+        Ok(())
     }
 }
+
+impl std::fmt::Display for StatusSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "on branch {}", self.branch_name)?;
+        if let Some(upstream) = &self.upstream_gone {
+            write!(f, "\nupstream {upstream} is gone")?;
+        }
+        let sections = self.sections();
+        // ignored files leave the working tree clean
+        if sections[..4].iter().all(|(_, files)| files.is_empty()) {
+            write!(f, "\nnothing to commit, working tree clean")?;
+        }
+
+        for (header, files) in sections.into_iter().filter(|(_, files)| !files.is_empty()) {
+            write!(f, "\n{header}:")?;
+            for file in files {
+                write!(f, "\n\t{file}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Paths that aren't valid UTF-8 keep their readable parts, the rest becomes U+FFFD.
+fn lossy_path(path: Option<&Path>) -> String {
+    path.map(|path| path.to_string_lossy().into_owned()).unwrap_or_default()
+}
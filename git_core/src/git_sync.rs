@@ -0,0 +1,168 @@
+//! When the clone last talked to each remote, for admins asking why a deployment is stuck.
+//! The times are kept in `addin-state.json` in the git directory shared by all worktrees,
+//! a missing or unreadable file counting as never synced.
+
+use std::{fs, path::PathBuf};
+
+use git2::BranchType;
+
+use crate::{git::Repo, git_branches::Upstream, git_locks::unix_now, json};
+
+const STATE_FILE: &str = "addin-state.json";
+
+/// An exchange with a remote whose time is recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SyncKind {
+    Fetch,
+    Pull,
+    Push,
+}
+
+/// The last successful exchanges with a remote in seconds since the Unix epoch, `None`
+/// when there was none.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RemoteSync {
+    pub remote: String,
+    pub last_fetch: Option<i64>,
+    /// A pull only merges what was fetched, so this is when a branch tracking the remote
+    /// was last brought up to date with it.
+    pub last_pull: Option<i64>,
+    pub last_push: Option<i64>,
+}
+
+/// See [`Repo::sync_state`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncState {
+    /// Every configured remote, in the order they are configured.
+    pub remotes: Vec<RemoteSync>,
+    /// Commits the current branch has that its upstream lacks and the other way round, as
+    /// of the last fetch. `None` without an upstream.
+    pub ahead_behind: Option<(usize, usize)>,
+}
+
+impl SyncState {
+    /// The newest fetch from any remote.
+    pub fn last_fetch(&self) -> Option<i64> {
+        self.remotes.iter().filter_map(|sync| sync.last_fetch).max()
+    }
+}
+
+impl Repo<'_> {
+    /// When this clone last fetched from, pulled from and pushed to each remote, and how far
+    /// the current branch is from its upstream. Only the operations of this crate are
+    /// recorded, not those of command-line git.
+    pub fn sync_state(&self) -> Result<SyncState, git2::Error> {
+        let recorded = self.recorded_syncs();
+        let remotes = self
+            .repo
+            .remotes()?
+            .iter()
+            .flatten()
+            .map(|remote| {
+                recorded.iter().find(|sync| sync.remote == remote).cloned().unwrap_or_else(|| {
+                    RemoteSync {
+                        remote: remote.to_string(),
+                        ..RemoteSync::default()
+                    }
+                })
+            })
+            .collect();
+
+        let head = self.head_ref()?;
+        // a detached HEAD and a branch without commits have no upstream to compare with
+        let ahead_behind = match head.full_name.filter(|_| head.oid.is_some()) {
+            Some(refname) => {
+                let name = refname.trim_start_matches("refs/heads/");
+                let branch = self.repo.find_branch(name, BranchType::Local)?;
+                match self.upstream_of(&branch)? {
+                    Upstream::Configured(_) => Some(self.ahead_behind(name)?),
+                    Upstream::Gone(_) | Upstream::None => None,
+                }
+            },
+            None => None,
+        };
+        Ok(SyncState { remotes, ahead_behind })
+    }
+
+    /// Records a successful exchange with `remote` now. The operation succeeded already, so
+    /// failing to write the state is ignored, as is the rare update lost to a concurrent
+    /// one.
+    pub(crate) fn record_sync(&self, remote: &str, kind: SyncKind) {
+        let mut syncs = self.recorded_syncs();
+        let index = match syncs.iter().position(|sync| sync.remote == remote) {
+            Some(index) => index,
+            None => {
+                syncs.push(RemoteSync {
+                    remote: remote.to_string(),
+                    ..RemoteSync::default()
+                });
+                syncs.len() - 1
+            },
+        };
+        let now = Some(unix_now());
+        match kind {
+            SyncKind::Fetch => syncs[index].last_fetch = now,
+            SyncKind::Pull => syncs[index].last_pull = now,
+            SyncKind::Push => syncs[index].last_push = now,
+        }
+
+        let fields = syncs.iter().map(|sync| {
+            let time = |time: Option<i64>| time.map_or("null".into(), |time| time.to_string());
+            let times = json::Object::new()
+                .raw("fetch", time(sync.last_fetch))
+                .raw("pull", time(sync.last_pull))
+                .raw("push", time(sync.last_push));
+            format!("{}:{times}", json::string(&sync.remote))
+        });
+        let text = format!("{{{}}}", fields.collect::<Vec<_>>().join(","));
+        // written aside and renamed, so a reader never sees half a file
+        let file = self.sync_state_file();
+        let staging = file.with_extension("json.tmp");
+        if fs::write(&staging, text).is_ok() && fs::rename(&staging, &file).is_err() {
+            let _ = fs::remove_file(&staging);
+        }
+    }
+
+    /// Records a pull of the local `branch` from the remote it tracks.
+    pub(crate) fn record_pull(&self, branch: &str) {
+        if let Ok(remote) = self.repo.branch_upstream_remote(&format!("refs/heads/{branch}"))
+            && let Some(remote) = remote.as_str()
+        {
+            self.record_sync(remote, SyncKind::Pull);
+        }
+    }
+
+    /// The syncs the state file holds, none when it is missing or corrupt. A value that
+    /// isn't a time counts as never synced.
+    fn recorded_syncs(&self) -> Vec<RemoteSync> {
+        let Ok(text) = fs::read_to_string(self.sync_state_file()) else {
+            return Vec::new();
+        };
+        let Ok(json::Value::Object(remotes)) = json::parse(&text) else {
+            return Vec::new();
+        };
+        remotes
+            .iter()
+            .map(|(remote, times)| RemoteSync {
+                remote: remote.clone(),
+                last_fetch: seconds(times.get("fetch")),
+                last_pull: seconds(times.get("pull")),
+                last_push: seconds(times.get("push")),
+            })
+            .collect()
+    }
+
+    fn sync_state_file(&self) -> PathBuf {
+        // one clone talks to the server for all its worktrees
+        self.common_dir().join(STATE_FILE)
+    }
+}
+
+/// Whole seconds since the Unix epoch.
+fn seconds(value: Option<&json::Value>) -> Option<i64> {
+    match value {
+        Some(json::Value::Number(seconds)) if *seconds >= 0.0 && seconds.fract() == 0.0 =>
+            Some(*seconds as i64),
+        _ => None,
+    }
+}
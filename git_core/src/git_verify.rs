@@ -0,0 +1,228 @@
+//! Signature checks for commits coming from the server.
+//!
+//! SSH signatures are checked with `ssh-keygen -Y verify` against an allowed signers file,
+//! GPG signatures with `gpg --verify` against a keyring, the same tools git itself uses.
+
+use std::{
+    fmt::Display,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+};
+
+use git2::{ErrorCode, Oid};
+
+use crate::git::Repo;
+
+const SSH_SIGNATURE: &str = "-----BEGIN SSH SIGNATURE-----";
+const PGP_SIGNATURE: &str = "-----BEGIN PGP SIGNATURE-----";
+
+#[derive(Debug, Clone, Default)]
+pub struct VerifyPolicy {
+    /// `gpg.ssh.allowedSignersFile` style file trusted for SSH signatures.
+    pub allowed_signers: Option<PathBuf>,
+    /// GnuPG home directory whose keyring is trusted for GPG signatures, the user's
+    /// default keyring when not set.
+    pub gpg_home: Option<PathBuf>,
+    /// Branches whose pulls are verified, every branch when empty.
+    pub protected_branches: Vec<String>,
+}
+
+impl VerifyPolicy {
+    pub fn protects(&self, branch_name: &str) -> bool {
+        self.protected_branches.is_empty()
+            || self.protected_branches.iter().any(|branch| branch == branch_name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerificationStatus {
+    Valid {
+        signer: String,
+    },
+    Unsigned,
+    /// Signed with a key that is neither in the allowed signers file nor in the keyring.
+    UnknownKey,
+    /// The signature doesn't match the commit or couldn't be checked.
+    Invalid(String),
+}
+
+impl Display for VerificationStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerificationStatus::Valid { signer } => write!(f, "valid ({signer})"),
+            VerificationStatus::Unsigned => write!(f, "unsigned"),
+            VerificationStatus::UnknownKey => write!(f, "unknown key"),
+            VerificationStatus::Invalid(reason) => write!(f, "invalid: {reason}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VerificationResult {
+    pub commit: Oid,
+    pub summary: String,
+    pub status: VerificationStatus,
+}
+
+impl VerificationResult {
+    pub fn is_valid(&self) -> bool {
+        matches!(self.status, VerificationStatus::Valid { .. })
+    }
+}
+
+impl Repo<'_> {
+    /// Checks the signature of every commit in `range`, either `from..to` or a single
+    /// revision meaning all of its history, newest first.
+    pub fn verify_commits(
+        &self,
+        range: &str,
+        policy: &VerifyPolicy,
+    ) -> Result<Vec<VerificationResult>, git2::Error> {
+        let mut revwalk = self.repo.revwalk()?;
+        if range.contains("..") {
+            revwalk.push_range(range)?;
+        } else {
+            revwalk.push(self.repo.revparse_single(range)?.peel_to_commit()?.id())?;
+        }
+
+        revwalk
+            .map(|oid| {
+                let oid = oid?;
+                let commit = self.repo.find_commit(oid)?;
+                let status = match self.repo.extract_signature(&oid, None) {
+                    Ok((signature, data)) => verify_signature(oid, &signature, &data, policy),
+                    Err(e) if e.code() == ErrorCode::NotFound => VerificationStatus::Unsigned,
+                    Err(e) => return Err(e),
+                };
+
+                Ok(VerificationResult {
+                    commit: oid,
+                    summary: String::from_utf8_lossy(commit.summary_bytes().unwrap_or_default())
+                        .into_owned(),
+                    status,
+                })
+            })
+            .collect()
+    }
+
+    /// Fails listing the offending commits when `range` holds any commit without a valid
+    /// signature.
+    pub(crate) fn require_verified(
+        &self,
+        range: &str,
+        policy: &VerifyPolicy,
+    ) -> Result<(), git2::Error> {
+        let failed = self
+            .verify_commits(range, policy)?
+            .into_iter()
+            .filter(|result| !result.is_valid())
+            .map(|result| format!("{} {}: {}", result.commit, result.summary, result.status))
+            .collect::<Vec<_>>();
+
+        if failed.is_empty() {
+            return Ok(());
+        }
+        Err(git2::Error::new(
+            ErrorCode::Certificate,
+            git2::ErrorClass::Object,
+            format!("commits without a valid signature:\n{}", failed.join("\n")),
+        ))
+    }
+}
+
+fn verify_signature(
+    oid: Oid,
+    signature: &[u8],
+    data: &[u8],
+    policy: &VerifyPolicy,
+) -> VerificationStatus {
+    let signature_path =
+        std::env::temp_dir().join(format!("git-addin-{}-{oid}.sig", std::process::id()));
+    if let Err(e) = std::fs::write(&signature_path, signature) {
+        return VerificationStatus::Invalid(format!("failed to write signature: {e}"));
+    }
+
+    let status = if signature.starts_with(SSH_SIGNATURE.as_bytes()) {
+        match &policy.allowed_signers {
+            Some(allowed_signers) => verify_ssh(&signature_path, data, allowed_signers),
+            None => VerificationStatus::Invalid("no allowed signers file configured".to_string()),
+        }
+    } else if signature.starts_with(PGP_SIGNATURE.as_bytes()) {
+        verify_gpg(&signature_path, data, policy.gpg_home.as_deref())
+    } else {
+        VerificationStatus::Invalid("unsupported signature format".to_string())
+    };
+
+    let _ = std::fs::remove_file(&signature_path);
+    status
+}
+
+fn verify_ssh(signature: &Path, data: &[u8], allowed_signers: &Path) -> VerificationStatus {
+    let principals = Command::new("ssh-keygen")
+        .args(["-Y", "find-principals", "-f"])
+        .arg(allowed_signers)
+        .arg("-s")
+        .arg(signature)
+        .output();
+    let principal = match principals {
+        Ok(output) if output.status.success() =>
+            String::from_utf8_lossy(&output.stdout).lines().next().unwrap_or_default().to_string(),
+        Ok(_) => return VerificationStatus::UnknownKey,
+        Err(e) => return VerificationStatus::Invalid(format!("failed to run ssh-keygen: {e}")),
+    };
+
+    let mut verify = Command::new("ssh-keygen");
+    verify
+        .args(["-Y", "verify", "-n", "git", "-f"])
+        .arg(allowed_signers)
+        .arg("-I")
+        .arg(&principal)
+        .arg("-s")
+        .arg(signature);
+    match run_with_input(verify, data) {
+        Ok(output) if output.status.success() => VerificationStatus::Valid { signer: principal },
+        Ok(output) =>
+            VerificationStatus::Invalid(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+        Err(e) => VerificationStatus::Invalid(format!("failed to run ssh-keygen: {e}")),
+    }
+}
+
+fn verify_gpg(signature: &Path, data: &[u8], gpg_home: Option<&Path>) -> VerificationStatus {
+    let mut verify = Command::new("gpg");
+    if let Some(gpg_home) = gpg_home {
+        verify.arg("--homedir").arg(gpg_home);
+    }
+    verify.args(["--batch", "--status-fd", "1", "--verify"]).arg(signature).arg("-");
+
+    let output = match run_with_input(verify, data) {
+        Ok(output) => output,
+        Err(e) => return VerificationStatus::Invalid(format!("failed to run gpg: {e}")),
+    };
+
+    // https://github.com/gpg/gnupg/blob/master/doc/DETAILS, "Format of the status FD output"
+    let status = String::from_utf8_lossy(&output.stdout);
+    let mut status = status.lines().filter_map(|line| line.strip_prefix("[GNUPG:] "));
+    match status
+        .find(|line| ["GOODSIG ", "BADSIG ", "NO_PUBKEY "].iter().any(|s| line.starts_with(s)))
+    {
+        Some(line) if line.starts_with("GOODSIG ") && output.status.success() => {
+            // GOODSIG <long keyid> <user id>
+            let signer = line.splitn(3, ' ').nth(2).unwrap_or_default().to_string();
+            VerificationStatus::Valid { signer }
+        },
+        Some(line) if line.starts_with("NO_PUBKEY ") => VerificationStatus::UnknownKey,
+        Some(_) => VerificationStatus::Invalid("bad signature".to_string()),
+        None =>
+            VerificationStatus::Invalid(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+    }
+}
+
+fn run_with_input(mut command: Command, input: &[u8]) -> std::io::Result<Output> {
+    let mut child =
+        command.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+
+    let written = child.stdin.take().map_or(Ok(()), |mut stdin| stdin.write_all(input));
+    let output = child.wait_with_output()?;
+    written.map(|()| output)
+}
@@ -0,0 +1,44 @@
+//! Extra HTTP headers sent with every request to HTTPS remotes, e.g. a token a gateway in
+//! front of the git server requires, like git's `http.extraHeader`.
+//!
+//! Header values are secrets: errors name the header, never its value.
+
+/// Splits newline-separated `Name: value` pairs, as entered in 1C, and validates each.
+/// Empty lines are skipped, `\r\n` line ends are accepted.
+pub fn parse_headers(text: &str) -> Result<Vec<String>, git2::Error> {
+    text.split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| validate_header(line).map(|()| line.to_string()))
+        .collect()
+}
+
+/// Checks that `header` is a single `Name: value` pair: a token as the name and no line
+/// breaks or NUL that could smuggle in further headers.
+pub fn validate_header(header: &str) -> Result<(), git2::Error> {
+    let Some((name, value)) = header.split_once(':') else {
+        return Err(git2::Error::from_str("HTTP header must look like 'Name: value'"));
+    };
+    let is_token = |c: char| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c);
+    if name.is_empty() || !name.chars().all(is_token) {
+        // not echoed, a malformed line may well be a value
+        return Err(git2::Error::from_str("invalid HTTP header name"));
+    }
+    if value.contains(['\r', '\n', '\0']) {
+        return Err(git2::Error::from_str(&format!(
+            "value of HTTP header '{name}' contains a line break or NUL"
+        )));
+    }
+    Ok(())
+}
+
+/// `text` with the value of every header of `headers` replaced by `***`, for logs that may
+/// quote one.
+pub fn redact(text: &str, headers: &[String]) -> String {
+    headers
+        .iter()
+        .filter_map(|header| header.split_once(':'))
+        .map(|(_, value)| value.trim())
+        .filter(|value| !value.is_empty())
+        .fold(text.to_string(), |text, value| text.replace(value, "***"))
+}
@@ -1,7 +1,81 @@
+pub mod encoding;
 pub mod git;
+pub mod git_bisect;
+pub mod git_blame;
+pub mod git_branches;
+pub mod git_bundle;
+pub mod git_compare;
+pub mod git_conflicts;
+pub mod git_health;
+pub mod git_init;
+pub mod git_locks;
+pub mod git_log;
+pub mod git_notes;
+pub mod git_operation;
+pub mod git_pack;
+pub mod git_preflight;
+pub mod git_prepare;
+pub mod git_read_only;
+pub mod git_relocate;
+pub mod git_revparse;
+pub mod git_settings;
+pub mod git_sparse;
+pub mod git_stage;
+pub mod git_stash;
 pub mod git_status;
+pub mod git_sync;
+pub mod git_verify;
+pub mod http;
+pub mod json;
+pub mod path;
+pub mod profile;
+pub mod refname;
+pub mod report;
+#[cfg(feature = "test-util")]
+pub mod testing;
+pub mod url;
 
-pub use git::{AuthType, Config, Repo};
-pub use git_status::{FileStatus, StatusSummary};
-
-pub const INVALID_UTF8: &str = "INVALID UTF-8";
+pub use git::{
+    AuthType,
+    Config,
+    CredentialKind,
+    CredentialProvider,
+    CredentialsRequired,
+    FetchReport,
+    FetchStats,
+    HeadRef,
+    PullResult,
+    PushAtomicity,
+    ReadOnlyRefusal,
+    Repo,
+    RestoredPath,
+};
+pub use git_bisect::BisectStep;
+pub use git_blame::BlameLine;
+pub use git_branches::{BranchActivity, BranchList, LocalBranch, Upstream};
+pub use git_bundle::BUNDLE_REMOTE;
+pub use git_compare::{ChangeKind, CompareResult, FileDiff};
+pub use git_conflicts::ConflictInfo;
+pub use git_health::{HealthIssue, Severity};
+pub use git_init::{InitTemplate, TemplateSource};
+pub use git_locks::FileLock;
+pub use git_log::CommitInfo;
+pub use git_pack::PushReport;
+pub use git_preflight::{OperationKind, PreflightIssue};
+pub use git_prepare::PreparedCommit;
+pub use git_read_only::ReadOnlyRepo;
+pub use git_relocate::RepositoryMoved;
+pub use git_revparse::ResolvedRev;
+pub use git_settings::{ConfigSnapshot, PushDefault};
+pub use git_status::{
+    EntryKind,
+    FileStatus,
+    StatusBuilder,
+    StatusPerfMode,
+    StatusPolicy,
+    StatusSummary,
+};
+pub use git_sync::{RemoteSync, SyncState};
+pub use git_verify::{VerificationResult, VerificationStatus, VerifyPolicy};
+pub use profile::Profile;
+pub use report::ReportFormat;
@@ -0,0 +1,77 @@
+use std::path::{Path, PathBuf};
+
+use git2::{ConfigLevel, ErrorCode, Repository};
+
+const VERBATIM_PREFIX: &str = r"\\?\";
+
+/// Rewrites a catalog path entered in 1C into the form libgit2 expects on this platform.
+///
+/// On Windows separators are unified to `\`, which keeps `\\server\share` UNC paths and
+/// `\\?\` long-path prefixes valid whatever separator the user typed. The path is
+/// deliberately not canonicalized: that would resolve a mapped drive to its UNC target and
+/// turn plain UNC paths into `\\?\UNC\` ones. Elsewhere the path is returned unchanged.
+pub fn normalize(path: &Path) -> PathBuf {
+    match path.to_str() {
+        Some(path) if cfg!(windows) => PathBuf::from(path.replace('/', "\\")),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Turns on the settings libgit2 needs for catalogs on Windows: `core.longpaths`, so files
+/// deeper than 260 characters check out, and `core.protectNTFS`, so a tree can't write
+/// names NTFS or a share would alias to `.git`. A value the user configured at any level
+/// is kept, the rest are written to the repository's own config for command-line git to
+/// agree. Does nothing elsewhere.
+pub(crate) fn apply_fs_options(repo: &Repository) -> Result<(), git2::Error> {
+    if !cfg!(windows) {
+        return Ok(());
+    }
+    let config = repo.config()?;
+    let mut local = config.open_level(ConfigLevel::Local)?;
+    for name in ["core.longpaths", "core.protectNTFS"] {
+        match config.get_bool(name) {
+            Err(e) if e.code() == ErrorCode::NotFound => local.set_bool(name, true)?,
+            _ => {},
+        }
+    }
+    Ok(())
+}
+
+/// Whether `path` points to a network share, i.e. is a UNC path with or without the
+/// `\\?\UNC\` long-path prefix. Mapped drives can't be told apart from local ones by name.
+pub fn is_network_path(path: &Path) -> bool {
+    if !cfg!(windows) {
+        return false;
+    }
+
+    let Some(path) = path.to_str() else { return false };
+    let path = path.replace('/', "\\");
+
+    match path.strip_prefix(VERBATIM_PREFIX) {
+        Some(rest) => rest.starts_with("UNC\\"),
+        None => path.starts_with("\\\\"),
+    }
+}
+
+/// Adds a hint about share limitations to errors raised for a repository on a network share.
+pub fn with_network_hint(path: &Path, e: git2::Error) -> git2::Error {
+    if !is_network_path(path) {
+        return e;
+    }
+
+    let hint = match e.code() {
+        ErrorCode::Owner =>
+            "the share reports a different owner, add the catalog to git's safe.directory",
+        ErrorCode::Locked =>
+            "a lock file is left on the share or the share doesn't support locking",
+        _ =>
+            "the share must support hard links and exclusive file creation for git's lock files \
+             and index writes",
+    };
+
+    git2::Error::new(
+        e.code(),
+        e.class(),
+        format!("{} (network catalog '{}': {hint})", e.message(), path.display()),
+    )
+}
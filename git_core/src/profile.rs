@@ -0,0 +1,93 @@
+//! Named identities layered over a base [`Config`], e.g. one per client whose repositories
+//! are committed to with a different login.
+
+use std::path::PathBuf;
+
+use crate::{
+    git::{AuthType, Config},
+    json,
+};
+
+/// Settings that replace the base config's ones where set.
+#[derive(Clone, Default)]
+pub struct Profile {
+    pub username: Option<String>,
+    pub email: Option<String>,
+    pub auth: Option<AuthType>,
+    pub path: Option<PathBuf>,
+    pub ssh_user: Option<String>,
+}
+
+impl Profile {
+    /// Reads a profile from a JSON object with the optional string fields `username`,
+    /// `email`, `path`, and either `password` or `ssh_key` with `ssh_passphrase` and
+    /// `ssh_user`.
+    pub fn from_json(text: &str) -> Result<Self, git2::Error> {
+        let value = json::parse(text).map_err(|e| git2::Error::from_str(&e))?;
+        Self::from_value(&value)
+    }
+
+    pub fn from_value(value: &json::Value) -> Result<Self, git2::Error> {
+        let json::Value::Object(fields) = value else {
+            return Err(git2::Error::from_str("profile must be a JSON object"));
+        };
+        let field = |name: &str| -> Result<Option<String>, git2::Error> {
+            match value.get(name) {
+                None | Some(json::Value::Null) => Ok(None),
+                Some(json::Value::String(value)) => Ok(Some(value.clone())),
+                Some(_) =>
+                    Err(git2::Error::from_str(&format!("profile field '{name}' must be a string"))),
+            }
+        };
+
+        const FIELDS: [&str; 7] =
+            ["username", "email", "path", "password", "ssh_key", "ssh_passphrase", "ssh_user"];
+        if let Some((unknown, _)) = fields.iter().find(|(name, _)| !FIELDS.contains(&name.as_str()))
+        {
+            return Err(git2::Error::from_str(&format!("unknown profile field '{unknown}'")));
+        }
+
+        let auth = match (field("password")?, field("ssh_key")?) {
+            (Some(_), Some(_)) => {
+                return Err(git2::Error::from_str(
+                    "profile can't have both a password and an ssh key",
+                ));
+            },
+            (Some(password), None) => Some(AuthType::Password(password)),
+            (None, Some(private_key)) => Some(AuthType::SshKey {
+                private_key: private_key.into(),
+                passphrase: field("ssh_passphrase")?,
+            }),
+            (None, None) => None,
+        };
+
+        Ok(Self {
+            username: field("username")?,
+            email: field("email")?,
+            auth,
+            path: field("path")?.map(PathBuf::from),
+            ssh_user: field("ssh_user")?,
+        })
+    }
+
+    /// `base` with the settings this profile sets replaced.
+    pub fn apply(&self, base: &Config) -> Config {
+        let mut config = base.clone();
+        if let Some(username) = &self.username {
+            config.username.clone_from(username);
+        }
+        if let Some(email) = &self.email {
+            config.email.clone_from(email);
+        }
+        if let Some(auth) = &self.auth {
+            config.auth = auth.clone();
+        }
+        if let Some(path) = &self.path {
+            config.path.clone_from(path);
+        }
+        if let Some(ssh_user) = &self.ssh_user {
+            config.ssh_user = Some(ssh_user.clone());
+        }
+        config
+    }
+}
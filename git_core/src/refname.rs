@@ -0,0 +1,147 @@
+//! Branch and tag names as typed into a form, checked before a ref is created so the error
+//! says what is wrong with the name and offers one that works.
+
+use git2::{ErrorClass, ErrorCode, Reference};
+
+/// Characters git refuses anywhere in a ref name.
+const FORBIDDEN: &[char] = &['~', '^', ':', '?', '*', '[', '\\'];
+
+/// Fails with [`ErrorCode::InvalidSpec`] when `name` can't name a branch or tag, the
+/// message telling why and suggesting a valid name, see [`suggest`].
+pub fn check(name: &str, transliterate: bool) -> Result<(), git2::Error> {
+    let Some(problem) = problem(name) else {
+        return Ok(());
+    };
+    Err(git2::Error::new(
+        ErrorCode::InvalidSpec,
+        ErrorClass::Reference,
+        format!(
+            "'{name}' is not a valid branch name: {problem}, try '{}'",
+            suggest(name, transliterate)
+        ),
+    ))
+}
+
+/// What makes `name` unusable as a branch or tag name, `None` when it is fine. Besides
+/// git's rules, `HEAD` is refused as git's own commands would confuse it with HEAD.
+pub fn problem(name: &str) -> Option<String> {
+    let problem = if name.is_empty() {
+        "it is empty".to_string()
+    } else if name.contains(char::is_whitespace) {
+        "it contains spaces".to_string()
+    } else if name.contains(char::is_control) {
+        "it contains control characters".to_string()
+    } else if let Some(c) = name.chars().find(|c| FORBIDDEN.contains(c)) {
+        format!("it contains '{c}'")
+    } else if name.contains("..") {
+        "it contains '..'".to_string()
+    } else if name.contains("@{") {
+        "it contains '@{'".to_string()
+    } else if name == "@" || name == "HEAD" {
+        format!("'{name}' is reserved")
+    } else if name.starts_with('-') {
+        "it starts with '-'".to_string()
+    } else if name.starts_with('/') || name.ends_with('/') || name.contains("//") {
+        "it starts or ends with '/' or has an empty part between slashes".to_string()
+    } else if name.ends_with('.') {
+        "it ends with '.'".to_string()
+    } else if name.split('/').any(|part| part.starts_with('.')) {
+        "a part starts with '.'".to_string()
+    } else if name.split('/').any(|part| part.ends_with(".lock")) {
+        "a part ends with '.lock'".to_string()
+    } else if !Reference::is_valid_name(&format!("refs/heads/{name}")) {
+        "git refuses it".to_string()
+    } else {
+        return None;
+    };
+    Some(problem)
+}
+
+/// A valid name close to `name`, `name` itself when it is valid. Spaces and forbidden
+/// characters become dashes and dots and slashes in the wrong places are dropped.
+/// Cyrillic is valid in names, but is replaced with Latin letters when `transliterate`
+/// is set, for servers and tools that handle only ASCII.
+pub fn suggest(name: &str, transliterate: bool) -> String {
+    if (name.is_ascii() || !transliterate) && problem(name).is_none() {
+        return name.to_string();
+    }
+    let mut cleaned = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_whitespace() || c.is_control() || FORBIDDEN.contains(&c) {
+            cleaned.push('-');
+        } else if let Some(latin) = transliterate.then(|| latin(c)).flatten() {
+            cleaned.push_str(&latin);
+        } else {
+            cleaned.push(c);
+        }
+    }
+    let cleaned = cleaned.replace("@{", "-");
+
+    let parts: Vec<String> = cleaned
+        .split('/')
+        .map(|part| {
+            let mut part = part.to_string();
+            while part.contains("..") {
+                part = part.replace("..", ".");
+            }
+            while part.contains("--") {
+                part = part.replace("--", "-");
+            }
+            let mut part = part.trim_start_matches(['.', '-']);
+            while let Some(stripped) = part.strip_suffix(".lock") {
+                part = stripped;
+            }
+            part.trim_end_matches(['.', '-']).to_string()
+        })
+        .filter(|part| !part.is_empty())
+        .collect();
+    let suggestion = parts.join("/");
+    if problem(&suggestion).is_some() {
+        return "new-branch".to_string();
+    }
+    suggestion
+}
+
+/// Latin spelling of a Russian or Ukrainian letter, keeping its case.
+fn latin(c: char) -> Option<String> {
+    let lower = c.to_lowercase().next()?;
+    let latin = match lower {
+        'а' => "a",
+        'б' => "b",
+        'в' => "v",
+        'г' | 'ґ' => "g",
+        'д' => "d",
+        'е' | 'ё' | 'э' => "e",
+        'є' => "ye",
+        'ж' => "zh",
+        'з' => "z",
+        'и' | 'і' => "i",
+        'ї' => "yi",
+        'й' | 'ы' => "y",
+        'к' => "k",
+        'л' => "l",
+        'м' => "m",
+        'н' => "n",
+        'о' => "o",
+        'п' => "p",
+        'р' => "r",
+        'с' => "s",
+        'т' => "t",
+        'у' => "u",
+        'ф' => "f",
+        'х' => "kh",
+        'ц' => "ts",
+        'ч' => "ch",
+        'ш' => "sh",
+        'щ' => "shch",
+        'ъ' | 'ь' => "",
+        'ю' => "yu",
+        'я' => "ya",
+        _ => return None,
+    };
+    if lower == c {
+        return Some(latin.to_string());
+    }
+    let mut chars = latin.chars();
+    Some(chars.next().map(|first| first.to_ascii_uppercase()).into_iter().chain(chars).collect())
+}
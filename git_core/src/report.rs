@@ -0,0 +1,343 @@
+//! Status and diff reports streamed straight into a writer, so large diffs never have to
+//! be held in memory as one string.
+
+use std::{
+    io::{self, BufWriter, Write},
+    str::FromStr,
+};
+
+use git2::{Diff, DiffFormat, DiffLineType, DiffOptions};
+
+use crate::{
+    git::Repo,
+    git_status::{FileStatus, StatusSummary, is_lfs_pointer_at},
+    json,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReportFormat {
+    #[default]
+    Text,
+    Json,
+    Html,
+}
+
+impl FromStr for ReportFormat {
+    type Err = git2::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "" | "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "html" => Ok(Self::Html),
+            _ => Err(git2::Error::from_str(&format!(
+                "unknown report format '{s}', expected text, json or html"
+            ))),
+        }
+    }
+}
+
+/// One piece of a patch as libgit2 prints it.
+enum PatchEvent<'a> {
+    /// Start of a file, `header` is the `diff --git` block.
+    File {
+        path: &'a str,
+        header: &'a str,
+    },
+    Hunk(&'a str),
+    Line(char, &'a str),
+    /// "Binary files ... differ"
+    Binary(&'a str),
+    Lfs,
+}
+
+impl Repo<'_> {
+    /// Streams the diff between `from` and `to` (see [`Repo::diff`]) into `out`, returning the
+    /// number of bytes written.
+    pub fn write_diff(
+        &self,
+        from: Option<&str>,
+        to: Option<&str>,
+        format: ReportFormat,
+        out: impl Write,
+    ) -> Result<u64, git2::Error> {
+        let diff = self.diff_between(from, to, &mut self.patch_options())?;
+        self.write_patch(&diff, format, out)
+    }
+
+    /// Options for a diff printed as a patch, with [`Config::diff_context_lines`] of
+    /// context.
+    ///
+    /// [`Config::diff_context_lines`]: crate::Config::diff_context_lines
+    pub(crate) fn patch_options(&self) -> DiffOptions {
+        let mut options = DiffOptions::new();
+        options.context_lines(self.config.diff_context_lines);
+        options
+    }
+
+    /// Streams `diff` into `out` as a patch, returning the number of bytes written.
+    pub(crate) fn write_patch(
+        &self,
+        diff: &Diff,
+        format: ReportFormat,
+        out: impl Write,
+    ) -> Result<u64, git2::Error> {
+        let mut out = CountingWriter::new(BufWriter::new(out));
+        let mut writer = PatchWriter {
+            format,
+            out: &mut out,
+            files: 0,
+            lines: 0,
+        };
+
+        writer.begin().map_err(io_error)?;
+        self.walk_patch(diff, |event| writer.event(&event))?;
+        writer.end().map_err(io_error)?;
+
+        out.flush().map_err(io_error)?;
+        Ok(out.written)
+    }
+
+    fn walk_patch(
+        &self,
+        diff: &Diff,
+        mut on_event: impl FnMut(PatchEvent) -> io::Result<()>,
+    ) -> Result<(), git2::Error> {
+        let mut failure = None;
+        let mut lfs_delta = false;
+        let printed = diff.print(DiffFormat::Patch, |delta, _hunk, line| {
+            let content = String::from_utf8_lossy(line.content());
+            let event = match line.origin_value() {
+                DiffLineType::FileHeader => {
+                    lfs_delta = [delta.old_file(), delta.new_file()].iter().any(|file| {
+                        file.path()
+                            .is_some_and(|path| is_lfs_pointer_at(&self.repo, file.id(), path))
+                    });
+                    let path = delta.new_file().path().or_else(|| delta.old_file().path());
+                    let path = path.map(|path| path.to_string_lossy()).unwrap_or_default();
+                    let file = on_event(PatchEvent::File { path: &path, header: &content });
+                    match file {
+                        Ok(()) if lfs_delta => on_event(PatchEvent::Lfs),
+                        result => result,
+                    }
+                },
+                _ if lfs_delta => Ok(()),
+                DiffLineType::HunkHeader => on_event(PatchEvent::Hunk(&content)),
+                DiffLineType::Binary => on_event(PatchEvent::Binary(&content)),
+                _ => on_event(PatchEvent::Line(line.origin(), &content)),
+            };
+
+            event.map_err(|e| failure = Some(e)).is_ok()
+        });
+
+        match failure {
+            Some(e) => Err(io_error(e)),
+            None => printed,
+        }
+    }
+}
+
+/// Writes `summary` to `out` as a report, returning the number of bytes written.
+pub fn write_status(
+    summary: &StatusSummary,
+    format: ReportFormat,
+    out: impl Write,
+) -> Result<u64, git2::Error> {
+    let mut out = CountingWriter::new(BufWriter::new(out));
+
+    match format {
+        ReportFormat::Text => write!(out, "{summary}"),
+        ReportFormat::Json => write!(out, "{}", status_json(summary)),
+        ReportFormat::Html => write_status_html(summary, &mut out),
+    }
+    .and_then(|()| out.flush())
+    .map_err(io_error)?;
+
+    Ok(out.written)
+}
+
+fn status_json(summary: &StatusSummary) -> json::Object {
+    let files = |files: &[FileStatus]| {
+        json::array(files.iter().map(|file| {
+            let mode_change = file.mode_change();
+            json::Object::new()
+                .str("status", &format!("{:?}", file.status).to_lowercase())
+                .str("old_file", &file.old_file)
+                .str("new_file", &file.new_file)
+                .str("entry_kind", &format!("{:?}", file.entry_kind).to_lowercase())
+                .opt_str("old_mode", mode_change.as_ref().map(|(old, _)| old.as_str()))
+                .opt_str("new_mode", mode_change.as_ref().map(|(_, new)| new.as_str()))
+                .bool("lfs_pointer", file.lfs_pointer)
+                .opt_str("locked_by", file.locked_by.as_deref())
+        }))
+    };
+
+    let mut object = json::Object::new()
+        .str("branch_name", &summary.branch_name)
+        .raw("staged", files(&summary.staged))
+        .raw("not_staged", files(&summary.not_staged))
+        .raw("untracked", files(&summary.untracked));
+    // only collected by a non-default StatusPolicy, left out to keep the old shape
+    if !summary.conflicted.is_empty() {
+        object = object.raw("conflicted", files(&summary.conflicted));
+    }
+    if !summary.ignored.is_empty() {
+        object = object.raw("ignored", files(&summary.ignored));
+    }
+    if let Some(upstream) = &summary.upstream_gone {
+        object = object.str("upstream_gone", upstream);
+    }
+    object
+}
+
+fn write_status_html(summary: &StatusSummary, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "{HTML_HEAD}<h2>On branch {}</h2>", html_escape(&summary.branch_name))?;
+    if let Some(upstream) = &summary.upstream_gone {
+        writeln!(out, "<p>Upstream {} is gone</p>", html_escape(upstream))?;
+    }
+
+    for (header, files) in summary.sections().into_iter().filter(|(_, files)| !files.is_empty()) {
+        writeln!(out, "<section><h3>{header}</h3><ul>")?;
+        for file in files {
+            writeln!(out, "<li>{}</li>", html_escape(&file.to_string()))?;
+        }
+        writeln!(out, "</ul></section>")?;
+    }
+
+    writeln!(out, "{HTML_TAIL}")
+}
+
+struct PatchWriter<'w, W: Write> {
+    format: ReportFormat,
+    out: &'w mut W,
+    files: usize,
+    /// Lines written for the current file.
+    lines: usize,
+}
+
+impl<W: Write> PatchWriter<'_, W> {
+    fn begin(&mut self) -> io::Result<()> {
+        match self.format {
+            ReportFormat::Text => Ok(()),
+            ReportFormat::Json => write!(self.out, "["),
+            ReportFormat::Html => writeln!(self.out, "{HTML_HEAD}"),
+        }
+    }
+
+    fn event(&mut self, event: &PatchEvent) -> io::Result<()> {
+        match self.format {
+            ReportFormat::Text => self.text_event(event),
+            ReportFormat::Json => self.json_event(event),
+            ReportFormat::Html => self.html_event(event),
+        }
+    }
+
+    fn end(&mut self) -> io::Result<()> {
+        match self.format {
+            ReportFormat::Text => Ok(()),
+            ReportFormat::Json if self.files > 0 => write!(self.out, "]}}]"),
+            ReportFormat::Json => write!(self.out, "]"),
+            ReportFormat::Html if self.files > 0 =>
+                writeln!(self.out, "</pre></section>{HTML_TAIL}"),
+            ReportFormat::Html => writeln!(self.out, "{HTML_TAIL}"),
+        }
+    }
+
+    fn text_event(&mut self, event: &PatchEvent) -> io::Result<()> {
+        match event {
+            PatchEvent::File { header: text, .. }
+            | PatchEvent::Hunk(text)
+            | PatchEvent::Binary(text) => write!(self.out, "{text}"),
+            PatchEvent::Line(origin @ ('+' | '-' | ' '), text) =>
+                write!(self.out, "{origin}{text}"),
+            PatchEvent::Line(_, text) => write!(self.out, "{text}"),
+            PatchEvent::Lfs => writeln!(self.out, "LFS object"),
+        }
+    }
+
+    /// `[{"path": ..., "lines": [{"origin": "+", "content": ...}, ...]}, ...]`, where hunk
+    /// headers have the origin "@", binary notes "B" and LFS notes "L".
+    fn json_event(&mut self, event: &PatchEvent) -> io::Result<()> {
+        let (origin, content) = match event {
+            PatchEvent::File { path, .. } => {
+                if self.files > 0 {
+                    write!(self.out, "]}},")?;
+                }
+                self.files += 1;
+                self.lines = 0;
+                return write!(self.out, "{{\"path\":{},\"lines\":[", json::string(path));
+            },
+            PatchEvent::Hunk(text) => ('@', *text),
+            PatchEvent::Line(origin, text) => (*origin, *text),
+            PatchEvent::Binary(text) => ('B', *text),
+            PatchEvent::Lfs => ('L', "LFS object"),
+        };
+
+        if self.lines > 0 {
+            write!(self.out, ",")?;
+        }
+        self.lines += 1;
+        let line = json::Object::new()
+            .str("origin", &origin.to_string())
+            .str("content", content.trim_end_matches('\n'));
+        write!(self.out, "{line}")
+    }
+
+    fn html_event(&mut self, event: &PatchEvent) -> io::Result<()> {
+        match event {
+            PatchEvent::File { path, .. } => {
+                if self.files > 0 {
+                    writeln!(self.out, "</pre></section>")?;
+                }
+                self.files += 1;
+                write!(self.out, "<section><h3>{}</h3><pre>", html_escape(path))
+            },
+            PatchEvent::Hunk(text) =>
+                write!(self.out, "<span class=\"hunk\">{}</span>", html_escape(text)),
+            PatchEvent::Line(origin @ ('+' | '-'), text) => {
+                let class = if *origin == '+' { "add" } else { "del" };
+                write!(self.out, "<span class=\"{class}\">{origin}{}</span>", html_escape(text))
+            },
+            PatchEvent::Line(origin, text) => write!(self.out, "{origin}{}", html_escape(text)),
+            PatchEvent::Binary(text) => write!(self.out, "<i>{}</i>", html_escape(text)),
+            PatchEvent::Lfs => writeln!(self.out, "<i>LFS object</i>"),
+        }
+    }
+}
+
+const HTML_HEAD: &str = "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><style>\
+    body{font-family:sans-serif}pre{font-family:monospace;margin:0}\
+    .add{background:#e6ffec;color:#1a7f37}.del{background:#ffebe9;color:#cf222e}\
+    .hunk{color:#6e7781}</style></head><body>";
+const HTML_TAIL: &str = "</body></html>";
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn io_error(e: io::Error) -> git2::Error {
+    git2::Error::from_str(&format!("failed to write report: {e}"))
+}
+
+struct CountingWriter<W> {
+    inner: W,
+    written: u64,
+}
+
+impl<W> CountingWriter<W> {
+    fn new(inner: W) -> Self {
+        Self { inner, written: 0 }
+    }
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
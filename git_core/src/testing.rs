@@ -0,0 +1,200 @@
+//! Remotes for exercising network code without a server, behind the `test-util` feature.
+//!
+//! A [`SimulatedRemote`] is a bare repository in a temporary directory, reachable through
+//! the `file://` transport, so clone, fetch, pull and push take the same code paths as
+//! against a real server. [`SimulatedRemote::push_commit`] writes to it behind the
+//! client's back, like another developer pushing, and [`SimulatedRemote::ref_target`]
+//! tells what a push left there:
+//!
+//! ```ignore
+//! let remote = SimulatedRemote::new("main")?;
+//! let local = remote.clone_workspace()?;
+//! let repo = Repo::open(&local.config)?;
+//!
+//! let theirs = remote.push_commit("main", "a.txt", "theirs", "concurrent change")?;
+//! repo.prune_report()?; // fetch, pull only merges what was fetched
+//! repo.pull("main")?;
+//! assert_eq!(repo.resolve_commit("HEAD")?, theirs);
+//! ```
+//!
+//! The `file://` transport never asks for credentials, credential flows still need a real
+//! server. Code the local transport short-cuts is reached through [`SimulatedRemote::serve`].
+//! The integration tests in `git_core/tests` are written this way, and network features get
+//! theirs there too.
+
+use std::{
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use git2::{Oid, Repository, Signature};
+
+use crate::git::{Config, Repo};
+
+/// A directory under the system temp directory, removed with everything in it on drop.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(prefix: &str) -> Result<Self, git2::Error> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "{prefix}-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&path).map_err(|e| {
+            git2::Error::from_str(&format!("failed to create '{}': {e}", path.display()))
+        })?;
+        Ok(Self(path))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A bare repository standing in for the server, see the [module docs](self).
+pub struct SimulatedRemote {
+    dir: TempDir,
+    repo: Repository,
+}
+
+/// A [`SimulatedRemote`] served over `git://`, see [`SimulatedRemote::serve`].
+pub struct GitDaemon {
+    process: Child,
+    url: String,
+}
+
+impl GitDaemon {
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl Drop for GitDaemon {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+/// A [`Config`] for a local clone of a [`SimulatedRemote`] and the directory it lives in.
+pub struct Workspace {
+    pub config: Config,
+    _dir: TempDir,
+}
+
+impl SimulatedRemote {
+    /// A remote whose `branch` has one commit, with HEAD pointing at it as its default.
+    pub fn new(branch: &str) -> Result<Self, git2::Error> {
+        let remote = Self::empty(branch)?;
+        remote.push_commit(branch, "README.md", "initial\n", "initial commit")?;
+        Ok(remote)
+    }
+
+    /// A remote without commits, like a project just created on the server, whose HEAD
+    /// names `branch`.
+    pub fn empty(branch: &str) -> Result<Self, git2::Error> {
+        let dir = TempDir::new("git-core-remote")?;
+        let repo = Repository::init_bare(dir.path())?;
+        repo.set_head(&format!("refs/heads/{branch}"))?;
+        Ok(Self { dir, repo })
+    }
+
+    pub fn url(&self) -> String {
+        format!("file://{}", self.dir.path().display())
+    }
+
+    /// Commits `content` as the top-level file `path` on top of `branch`, creating the
+    /// branch when missing, as if another developer had pushed it.
+    pub fn push_commit(
+        &self,
+        branch: &str,
+        path: &str,
+        content: &str,
+        message: &str,
+    ) -> Result<Oid, git2::Error> {
+        let refname = format!("refs/heads/{branch}");
+        let parent = match self.repo.refname_to_id(&refname) {
+            Ok(id) => Some(self.repo.find_commit(id)?),
+            Err(_) => None,
+        };
+
+        let mut tree =
+            self.repo.treebuilder(parent.as_ref().map(|c| c.tree()).transpose()?.as_ref())?;
+        tree.insert(path, self.repo.blob(content.as_bytes())?, 0o100_644)?;
+        let tree = self.repo.find_tree(tree.write()?)?;
+
+        let author = Signature::now("Other Developer", "other@example.com")?;
+        let parents: Vec<_> = parent.iter().collect();
+        self.repo.commit(Some(&refname), &author, &author, message, &tree, &parents)
+    }
+
+    /// Deletes `branch` on the remote, as if another developer had removed it after a merge.
+    pub fn delete_branch(&self, branch: &str) -> Result<(), git2::Error> {
+        self.repo.find_reference(&format!("refs/heads/{branch}"))?.delete()
+    }
+
+    /// What `refname` points at on the remote, `None` when it doesn't exist.
+    pub fn ref_target(&self, refname: &str) -> Option<Oid> {
+        self.repo.refname_to_id(refname).ok()
+    }
+
+    /// Serves the remote over `git://` with a `git daemon`, for the code paths the `file://`
+    /// transport short-cuts. Needs command-line git; the daemon stops on drop.
+    pub fn serve(&self) -> Result<GitDaemon, git2::Error> {
+        let failed = |e: std::io::Error| git2::Error::from_str(&format!("git daemon: {e}"));
+        let port = TcpListener::bind("127.0.0.1:0")
+            .and_then(|free| free.local_addr())
+            .map_err(failed)?
+            .port();
+        let (base, name) =
+            (self.dir.path().parent().unwrap(), self.dir.path().file_name().unwrap());
+        // `git daemon` runs `git-daemon` as a child that outlives a killed `git`
+        let exec_path = Command::new("git").arg("--exec-path").output().map_err(failed)?.stdout;
+        let exec_path = String::from_utf8_lossy(&exec_path);
+        let process = Command::new(Path::new(exec_path.trim()).join("git-daemon"))
+            .args(["--export-all", "--enable=receive-pack", "--reuseaddr", "--listen=127.0.0.1"])
+            .arg(format!("--port={port}"))
+            .arg(format!("--base-path={}", base.display()))
+            .arg(self.dir.path())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(failed)?;
+        let daemon = GitDaemon {
+            process,
+            url: format!("git://127.0.0.1:{port}/{}", name.to_string_lossy()),
+        };
+        let started = Instant::now();
+        while TcpStream::connect(("127.0.0.1", port)).is_err() {
+            if started.elapsed() > Duration::from_secs(10) {
+                return Err(git2::Error::from_str("git daemon didn't start listening"));
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        Ok(daemon)
+    }
+
+    /// A fresh clone of the remote with a configured identity.
+    pub fn clone_workspace(&self) -> Result<Workspace, git2::Error> {
+        let dir = TempDir::new("git-core-workspace")?;
+        let config = Config {
+            username: "Test Developer".to_string(),
+            email: "test@example.com".to_string(),
+            path: dir.path().join("repo"),
+            ..Config::default()
+        };
+        Repo::clone_from(&self.url(), &config)?;
+        Ok(Workspace { config, _dir: dir })
+    }
+}
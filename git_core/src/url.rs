@@ -0,0 +1,56 @@
+//! Remote URLs as shown to users, which must not leak credentials embedded in them, and
+//! telling network remotes from local ones.
+
+use std::path::PathBuf;
+
+/// Removes the user info, e.g. `user:token@`, from a `scheme://` URL. A token often stands
+/// in the user name alone, so the whole user info goes. scp-like `git@host:path` addresses
+/// can't carry a password and are returned unchanged.
+pub fn strip_credentials(url: &str) -> String {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return url.to_string();
+    };
+    let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let (authority, path) = rest.split_at(authority_end);
+    let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    format!("{scheme}://{host}{path}")
+}
+
+/// The project name a URL or path ends in, without a `.git` suffix, e.g. "project" for
+/// `https://server/group/project.git`.
+pub fn repository_name(url: &str) -> Option<&str> {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let name = path.trim_end_matches(['/', '\\']).rsplit(['/', '\\', ':']).next()?;
+    let name = name.strip_suffix(".git").unwrap_or(name);
+    (!name.is_empty()).then_some(name)
+}
+
+/// The path of a repository [`is_local`] points at: `file:///srv/repo.git` is
+/// `/srv/repo.git`, `file:///C:/repos/project` is `C:/repos/project` and
+/// `file://server/share/project` the UNC path `//server/share/project`. Other URLs are
+/// paths already.
+pub fn local_path(url: &str) -> PathBuf {
+    let path = match url.split_once("://") {
+        Some((_, path)) => path,
+        None => return PathBuf::from(url),
+    };
+    match path.strip_prefix('/') {
+        Some(rest) if rest.get(1..2) == Some(":") => PathBuf::from(rest),
+        Some(_) => PathBuf::from(path),
+        None => PathBuf::from(format!("//{path}")),
+    }
+}
+
+/// Whether `url` points at a repository on the file system, a `file://` URL or a plain
+/// path such as `C:\repos\project` or `\\server\share\project`, rather than at a
+/// server. scp-like `git@host:path` addresses are remote.
+pub fn is_local(url: &str) -> bool {
+    if let Some((scheme, _)) = url.split_once("://") {
+        return scheme.eq_ignore_ascii_case("file");
+    }
+    match url.split_once(':') {
+        // a drive letter, or a colon after the first path separator
+        Some((host, _)) => host.len() == 1 || host.contains(['/', '\\']),
+        None => true,
+    }
+}
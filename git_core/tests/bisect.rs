@@ -0,0 +1,95 @@
+//! Bisecting a history for the commit that broke it, see `git_core::git_bisect`.
+
+use git_core::{BisectStep, Repo, testing::SimulatedRemote};
+use git2::{ErrorCode, Oid, Repository};
+
+/// `main` with eight more commits writing their number to `version.txt`, the fifth of them
+/// breaking it. Returns the commits, oldest first.
+fn history(remote: &SimulatedRemote) -> Result<Vec<Oid>, git2::Error> {
+    (1..=8)
+        .map(|n| {
+            let content = if n >= 5 { format!("{n} broken\n") } else { format!("{n}\n") };
+            remote.push_commit("main", "version.txt", &content, &format!("version {n}"))
+        })
+        .collect()
+}
+
+#[test]
+fn bisect_finds_the_first_bad_commit_and_resets() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let commits = history(&remote)?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    let raw = Repository::open(&local.config.path)?;
+
+    let mut step = repo.bisect_start(&commits[0].to_string(), "HEAD")?;
+    let mut tested = 0;
+    let found = loop {
+        match step {
+            BisectStep::Next { commit, remaining } => {
+                assert!(remaining > 1);
+                assert!(raw.head_detached()?);
+                assert_eq!(raw.head()?.target(), Some(commit));
+                assert_eq!(repo.bisect_result()?, None);
+                let version =
+                    std::fs::read_to_string(local.config.path.join("version.txt")).unwrap();
+                tested += 1;
+                step = repo.bisect_mark(commit, !version.contains("broken"))?;
+            },
+            BisectStep::Found(commit) => break commit,
+        }
+    };
+
+    assert_eq!(found, commits[4]);
+    assert!(tested <= 3, "seven suspects take three steps, took {tested}");
+    assert_eq!(repo.bisect_result()?, Some(commits[4]));
+    let log = std::fs::read_to_string(raw.path().join("BISECT_LOG")).unwrap();
+    assert!(log.starts_with(&format!("git bisect start '{}' '{}'\n", commits[7], commits[0])));
+    assert_eq!(log.lines().count(), 1 + tested);
+
+    repo.bisect_reset()?;
+
+    assert_eq!(raw.head()?.name(), Some("refs/heads/main"));
+    assert_eq!(raw.head()?.target(), Some(commits[7]));
+    assert_eq!(raw.references_glob("refs/bisect/*")?.count(), 0);
+    assert!(!raw.path().join("BISECT_START").exists());
+    assert!(!raw.path().join("BISECT_LOG").exists());
+    assert_eq!(
+        std::fs::read_to_string(local.config.path.join("version.txt")).unwrap(),
+        "8 broken\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn a_bisect_is_started_once_and_reset_once() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let commits = history(&remote)?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+
+    let e = repo.bisect_mark(commits[3], true).expect_err("nothing to mark");
+    assert_eq!(e.code(), ErrorCode::NotFound, "{e}");
+
+    repo.bisect_start(&commits[0].to_string(), "HEAD")?;
+    let e = repo.bisect_start(&commits[0].to_string(), "HEAD").expect_err("already bisecting");
+    assert_eq!(e.code(), ErrorCode::Exists, "{e}");
+
+    repo.bisect_reset()?;
+    let e = repo.bisect_reset().expect_err("nothing to reset");
+    assert_eq!(e.code(), ErrorCode::NotFound, "{e}");
+    Ok(())
+}
+
+#[test]
+fn adjacent_good_and_bad_commits_are_found_at_once() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let commits = history(&remote)?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+
+    let step = repo.bisect_start(&commits[3].to_string(), &commits[4].to_string())?;
+
+    assert_eq!(step, BisectStep::Found(commits[4]));
+    repo.bisect_reset()
+}
@@ -0,0 +1,48 @@
+//! Listing the branches, see `git_core::git_branches`.
+
+use git_core::{BranchList, LocalBranch, Repo, testing::SimulatedRemote};
+use git2::Repository;
+
+fn local(name: &str, is_head: bool, remote_names: &[&str]) -> LocalBranch {
+    LocalBranch {
+        name: name.to_string(),
+        is_head,
+        remote_names: remote_names.iter().map(|name| name.to_string()).collect(),
+    }
+}
+
+#[test]
+fn remote_branches_are_grouped_under_their_local_branch_and_sorted_ignoring_case()
+-> Result<(), git2::Error> {
+    let origin = SimulatedRemote::new("main")?;
+    origin.push_commit("feature/x", "x.txt", "x\n", "feature x")?;
+    origin.push_commit("Hotfix", "fix.txt", "fix\n", "hotfix")?;
+    let upstream = SimulatedRemote::new("main")?;
+    upstream.push_commit("other", "other.txt", "other\n", "other work")?;
+    let workspace = origin.clone_workspace()?;
+    let raw = Repository::open(&workspace.config.path)?;
+    let repo = Repo::open(&workspace.config)?;
+    repo.add_remote("upstream", &upstream.url())?;
+    repo.prune_report()?;
+    let head = raw.head()?.peel_to_commit()?;
+    // tracking a differently named branch
+    raw.branch("work", &head, false)?.set_upstream(Some("origin/feature/x"))?;
+    raw.branch("alpha", &head, false)?;
+    raw.branch("Beta", &head, false)?;
+
+    let branches = repo.branch_list()?;
+
+    assert_eq!(
+        branches,
+        BranchList {
+            local: vec![
+                local("alpha", false, &[]),
+                local("Beta", false, &[]),
+                local("main", true, &["origin/main", "upstream/main"]),
+                local("work", false, &["origin/feature/x"]),
+            ],
+            remote_only: vec!["origin/Hotfix".to_string(), "upstream/other".to_string()],
+        }
+    );
+    Ok(())
+}
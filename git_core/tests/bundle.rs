@@ -0,0 +1,133 @@
+//! Carrying commits between repositories that can't reach each other, see
+//! `git_core::git_bundle`.
+
+use std::{path::Path, process::Command};
+
+use git_core::{
+    BUNDLE_REMOTE,
+    Config,
+    Repo,
+    testing::{SimulatedRemote, TempDir, Workspace},
+};
+use git2::{ErrorCode, Oid, Repository};
+
+/// Runs git in `dir`, failing the test when it fails.
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output().unwrap();
+    assert!(output.status.success(), "git {args:?}: {}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// A repository of its own, without any remote.
+fn disconnected(dir: &TempDir) -> Result<Config, git2::Error> {
+    let config = Config {
+        path: dir.path().join("repo"),
+        username: "Test Developer".to_string(),
+        email: "test@example.com".to_string(),
+        ..Config::default()
+    };
+    Repository::init(&config.path)?;
+    Ok(config)
+}
+
+fn commit_file(local: &Workspace, path: &str, content: &str) -> Result<Oid, git2::Error> {
+    std::fs::write(local.config.path.join(path), content).unwrap();
+    let repo = Repo::open(&local.config)?;
+    repo.add([path])?;
+    repo.commit(&format!("change {path}"))
+}
+
+#[test]
+fn commits_travel_through_bundles() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let first = commit_file(&local, "Catalog.xml", "<Catalog/>\n")?;
+    let drive = TempDir::new("git-core-bundle")?;
+    let full = drive.path().join("full.bundle");
+    let server = TempDir::new("git-core-server")?;
+    let server = disconnected(&server)?;
+    let tracking = format!("refs/remotes/{BUNDLE_REMOTE}/main");
+
+    let objects = Repo::open(&local.config)?.bundle_create(&full, "main")?;
+    assert!(objects > 0);
+    git(&local.config.path, &["bundle", "verify", full.to_str().unwrap()]);
+    assert_eq!(Repo::open(&server)?.bundle_import(&full)?, std::slice::from_ref(&tracking));
+    assert_eq!(Repository::open(&server.path)?.refname_to_id(&tracking)?, first);
+
+    // what the server has builds on the first bundle
+    let second = commit_file(&local, "Form.xml", "<Form/>\n")?;
+    let update = drive.path().join("update.bundle");
+    Repo::open(&local.config)?.bundle_create(&update, &format!("{first}..main"))?;
+    git(&server.path, &["bundle", "verify", update.to_str().unwrap()]);
+    assert_eq!(Repo::open(&server)?.bundle_import(&update)?, std::slice::from_ref(&tracking));
+    let raw = Repository::open(&server.path)?;
+    assert_eq!(raw.refname_to_id(&tracking)?, second);
+    assert!(raw.find_commit(second)?.tree()?.get_name("Form.xml").is_some());
+
+    // importing again changes nothing
+    assert!(Repo::open(&server)?.bundle_import(&update)?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn git_clones_from_and_makes_the_same_bundles() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let head = commit_file(&local, "Catalog.xml", "<Catalog/>\n")?;
+    let drive = TempDir::new("git-core-bundle")?;
+    let ours = drive.path().join("ours.bundle");
+    Repo::open(&local.config)?.bundle_create(&ours, "main")?;
+
+    let clone = drive.path().join("clone");
+    git(drive.path(), &["clone", "-q", "-b", "main", ours.to_str().unwrap(), "clone"]);
+    assert_eq!(git(&clone, &["rev-parse", "HEAD"]).trim(), head.to_string());
+
+    let theirs = drive.path().join("theirs.bundle");
+    git(&local.config.path, &["bundle", "create", "-q", theirs.to_str().unwrap(), "main"]);
+    let server = TempDir::new("git-core-server")?;
+    let server = disconnected(&server)?;
+    Repo::open(&server)?.bundle_import(&theirs)?;
+    assert_eq!(
+        Repository::open(&server.path)?
+            .refname_to_id(&format!("refs/remotes/{BUNDLE_REMOTE}/main"))?,
+        head
+    );
+    Ok(())
+}
+
+#[test]
+fn a_bundle_building_on_missing_commits_changes_nothing() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let base = commit_file(&local, "Catalog.xml", "<Catalog/>\n")?;
+    commit_file(&local, "Form.xml", "<Form/>\n")?;
+    let drive = TempDir::new("git-core-bundle")?;
+    let update = drive.path().join("update.bundle");
+    Repo::open(&local.config)?.bundle_create(&update, &format!("{base}..main"))?;
+    let server = TempDir::new("git-core-server")?;
+    let server = disconnected(&server)?;
+
+    let e = Repo::open(&server)?.bundle_import(&update).expect_err("the base is missing");
+
+    assert_eq!(e.code(), ErrorCode::NotFound, "{e}");
+    assert!(e.message().contains(&base.to_string()), "{e}");
+    assert_eq!(Repository::open(&server.path)?.references()?.count(), 0);
+    Ok(())
+}
+
+#[test]
+fn empty_ranges_and_other_files_are_refused() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    let drive = TempDir::new("git-core-bundle")?;
+    let path = drive.path().join("empty.bundle");
+
+    let e = repo.bundle_create(&path, "main..main").expect_err("nothing to bundle");
+    assert!(e.message().starts_with("nothing to bundle"), "{e}");
+
+    std::fs::write(&path, "PK\u{3}\u{4}").unwrap();
+    let e = repo.bundle_import(&path).expect_err("not a bundle");
+    assert!(e.message().ends_with("is not a v2 git bundle"), "{e}");
+    Ok(())
+}
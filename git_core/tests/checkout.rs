@@ -0,0 +1,113 @@
+//! Checkouts retrying on a file another program holds, see `Config::checkout_retries`.
+//! Windows has 1C hold the file open, here a handle that shares nothing. Elsewhere nothing
+//! holds a file that way, so a read-only working directory has to fail at once.
+
+use std::time::Duration;
+
+use git_core::testing::{SimulatedRemote, Workspace};
+
+/// A clone on `main` with `feature` changing `Form.xml`, retrying `retries` times.
+fn form_checkout(remote: &SimulatedRemote, retries: u32) -> Result<Workspace, git2::Error> {
+    remote.push_commit("main", "Form.xml", "<Form/>\n", "form")?;
+    remote.push_commit("feature", "Form.xml", "<Form changed/>\n", "change the form")?;
+    let mut local = remote.clone_workspace()?;
+    local.config.checkout_retries = retries;
+    local.config.checkout_retry_delay = Duration::from_millis(50);
+    Ok(local)
+}
+
+#[cfg(windows)]
+mod held {
+    use std::{
+        fs::{File, OpenOptions},
+        os::windows::fs::OpenOptionsExt,
+        path::Path,
+        thread,
+        time::Duration,
+    };
+
+    use git_core::{Repo, testing::SimulatedRemote};
+    use git2::ErrorCode;
+
+    use super::form_checkout;
+
+    /// `path` opened like the 1C Designer holds a dump file: shared with no one.
+    fn hold(path: &Path) -> File {
+        OpenOptions::new().read(true).write(true).share_mode(0).open(path).unwrap()
+    }
+
+    #[test]
+    fn a_file_released_meanwhile_is_checked_out() -> Result<(), git2::Error> {
+        let remote = SimulatedRemote::new("main")?;
+        let local = form_checkout(&remote, 20)?;
+        let form = local.config.path.join("Form.xml");
+        let repo = Repo::open(&local.config)?;
+        let handle = hold(&form);
+        let releasing = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            drop(handle);
+        });
+
+        repo.checkout("feature")?;
+
+        releasing.join().unwrap();
+        assert_eq!(std::fs::read_to_string(&form).unwrap(), "<Form changed/>\n");
+        Ok(())
+    }
+
+    #[test]
+    fn a_file_still_held_is_named() -> Result<(), git2::Error> {
+        let remote = SimulatedRemote::new("main")?;
+        let local = form_checkout(&remote, 2)?;
+        let form = local.config.path.join("Form.xml");
+        let repo = Repo::open(&local.config)?;
+        let handle = hold(&form);
+
+        let e = repo.checkout("feature").expect_err("the form stays held");
+
+        drop(handle);
+        assert_eq!(e.code(), ErrorCode::Locked, "{e}");
+        assert!(e.message().contains("Form.xml' is held open by another program"), "{e}");
+        assert_eq!(std::fs::read_to_string(&form).unwrap(), "<Form/>\n");
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+mod read_only {
+    use std::{
+        fs::Permissions,
+        os::unix::fs::PermissionsExt,
+        time::{Duration, Instant},
+    };
+
+    use git_core::{Repo, testing::SimulatedRemote};
+
+    use super::form_checkout;
+
+    #[test]
+    fn a_denied_write_fails_at_once_as_libgit2_reports_it() -> Result<(), git2::Error> {
+        let remote = SimulatedRemote::new("main")?;
+        let mut local = form_checkout(&remote, 3)?;
+        local.config.checkout_retry_delay = Duration::from_secs(5);
+        let dir = local.config.path.clone();
+        std::fs::set_permissions(&dir, Permissions::from_mode(0o555)).unwrap();
+        if std::fs::write(dir.join("probe"), "").is_ok() {
+            // root writes anyway
+            std::fs::set_permissions(&dir, Permissions::from_mode(0o755)).unwrap();
+            std::fs::remove_file(dir.join("probe")).unwrap();
+            return Ok(());
+        }
+        let repo = Repo::open(&local.config)?;
+
+        let started = Instant::now();
+        let e = repo.checkout("feature").expect_err("the directory is read-only");
+
+        std::fs::set_permissions(&dir, Permissions::from_mode(0o755)).unwrap();
+        assert!(started.elapsed() < local.config.checkout_retry_delay, "retried: {e}");
+        assert!(!e.message().contains("held open"), "{e}");
+        assert!(e.message().to_lowercase().contains("permission denied"), "{e}");
+        assert_eq!(std::fs::read_to_string(dir.join("Form.xml")).unwrap(), "<Form/>\n");
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+//! Clones that fail leave no trace, see `Repo::clone_from`.
+
+use std::sync::{Arc, atomic::AtomicBool};
+
+use git_core::{
+    Config,
+    Repo,
+    testing::{SimulatedRemote, TempDir},
+};
+use git2::{ErrorClass, ErrorCode};
+
+fn config(dir: &TempDir) -> Config {
+    Config {
+        path: dir.path().join("repo"),
+        ..Config::default()
+    }
+}
+
+#[test]
+fn cancelled_clone_leaves_nothing_behind() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    remote.push_commit("main", "a.txt", "a\n", "second commit")?;
+    let dir = TempDir::new("git-core-clone")?;
+    let config = Config {
+        cancel: Some(Arc::new(AtomicBool::new(true))),
+        ..config(&dir)
+    };
+
+    let e = Repo::clone_from(&remote.url(), &config).err().expect("the clone was cancelled");
+
+    assert_eq!(e.class(), ErrorClass::Callback, "{e}");
+    assert!(!config.path.exists());
+    assert!(!dir.path().join(".repo.clone").exists());
+    Ok(())
+}
+
+#[test]
+fn staging_directory_of_another_clone_is_kept() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let dir = TempDir::new("git-core-clone")?;
+    let config = config(&dir);
+    let staging = dir.path().join(".repo.clone");
+    std::fs::create_dir(&staging).unwrap();
+    std::fs::write(staging.join("marker"), "other clone").unwrap();
+
+    let e = Repo::clone_from(&remote.url(), &config).err().expect("the staging directory is taken");
+
+    assert_eq!(e.code(), ErrorCode::Exists, "{e}");
+    assert!(staging.join("marker").exists());
+    assert!(!config.path.exists());
+    Ok(())
+}
+
+#[test]
+fn non_empty_destination_is_refused_before_fetching() -> Result<(), git2::Error> {
+    let dir = TempDir::new("git-core-clone")?;
+    let config = config(&dir);
+    std::fs::create_dir(&config.path).unwrap();
+    std::fs::write(config.path.join("notes.txt"), "keep me").unwrap();
+
+    // no remote at all: only the destination check can make the error
+    let e = Repo::clone_from("file:///nonexistent/remote.git", &config).err().expect("refused");
+
+    assert_eq!(e.code(), ErrorCode::Exists, "{e}");
+    assert!(e.message().contains("not an empty directory"), "{e}");
+    assert!(config.path.join("notes.txt").exists());
+    assert!(!dir.path().join(".repo.clone").exists());
+    Ok(())
+}
+
+#[test]
+fn clone_into_an_empty_directory() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let dir = TempDir::new("git-core-clone")?;
+    let config = config(&dir);
+    std::fs::create_dir(&config.path).unwrap();
+
+    let repo = Repo::clone_from(&remote.url(), &config)?;
+
+    assert_eq!(Some(repo.resolve_commit("HEAD")?), remote.ref_target("refs/heads/main"));
+    assert!(!dir.path().join(".repo.clone").exists());
+    Ok(())
+}
@@ -0,0 +1,97 @@
+//! What merging one branch into another would bring, see `Repo::compare_refs`.
+
+use git_core::{ChangeKind, FileDiff, Repo, testing::SimulatedRemote};
+use git2::{ErrorCode, Repository};
+
+/// A form big enough for a rename of it to be detected.
+const CATALOG: &str = "<Catalog>\n<Name>Goods</Name>\n<Code>9</Code>\n<Length>25</Length>\n\
+                       <Hierarchy>true</Hierarchy>\n<Owners/>\n</Catalog>\n";
+
+fn summary(files: &[FileDiff]) -> Vec<(String, Option<String>, ChangeKind, usize, usize)> {
+    let mut files: Vec<_> = files
+        .iter()
+        .map(|file| {
+            let FileDiff {
+                path,
+                old_path,
+                change,
+                insertions,
+                deletions,
+            } = file.clone();
+            (path, old_path, change, insertions, deletions)
+        })
+        .collect();
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    files
+}
+
+#[test]
+fn feature_files_since_the_branches_diverged() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    remote.push_commit("main", "Catalog.xml", CATALOG, "catalog")?;
+    let local = remote.clone_workspace()?;
+    let raw = Repository::open(&local.config.path)?;
+    let diverged = raw.head()?.peel_to_commit()?;
+    raw.branch("feature", &diverged, false)?;
+    let repo = Repo::open(&local.config)?;
+    repo.checkout_local("feature")?;
+    let path = |name: &str| local.config.path.join(name);
+    std::fs::rename(path("Catalog.xml"), path("Goods.xml")).unwrap();
+    std::fs::write(path("README.md"), "initial\nfeature notes\n").unwrap();
+    std::fs::write(path("Report.xml"), "<Report/>\n").unwrap();
+    repo.add_all()?;
+    repo.commit("rename the catalog, add a report")?;
+    std::fs::write(path("Report.xml"), "<Report>\n<Name>Sales</Name>\n</Report>\n").unwrap();
+    repo.add(["Report.xml"])?;
+    repo.commit("name the report")?;
+    // on main meanwhile, not part of what the feature brings
+    remote.push_commit("main", "Other.xml", "<Other/>\n", "their work")?;
+    repo.prune_report()?;
+
+    let compared = repo.compare_refs("origin/main", "feature")?;
+
+    assert_eq!((compared.ahead, compared.behind), (2, 1));
+    assert_eq!(compared.merge_base, Some(diverged.id()));
+    assert_eq!(
+        summary(&compared.files),
+        [
+            ("Goods.xml".to_string(), Some("Catalog.xml".to_string()), ChangeKind::Renamed, 0, 0),
+            ("README.md".to_string(), None, ChangeKind::Modified, 1, 0),
+            ("Report.xml".to_string(), None, ChangeKind::Added, 3, 0),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn unrelated_histories_are_compared_to_the_base() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    repo.branch_create_orphan("notes", "release notes", &[("NOTES.md", b"1.0\n")], false)?;
+
+    let compared = repo.compare_refs("main", "notes")?;
+
+    assert_eq!(compared.merge_base, None);
+    assert_eq!((compared.ahead, compared.behind), (1, 1));
+    assert_eq!(
+        summary(&compared.files),
+        [
+            ("NOTES.md".to_string(), None, ChangeKind::Added, 1, 0),
+            ("README.md".to_string(), None, ChangeKind::Deleted, 0, 1),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn an_unknown_ref_fails() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+
+    let e = repo.compare_refs("main", "no-such-branch").expect_err("the head doesn't exist");
+
+    assert_eq!(e.code(), ErrorCode::NotFound, "{e}");
+    Ok(())
+}
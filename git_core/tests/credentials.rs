@@ -0,0 +1,152 @@
+//! Credentials offered to a server that demands them, see `Config::auth` and
+//! `Config::credential_provider`. A local server answers every request with 401 and
+//! records the `Authorization` header libgit2 sent with it.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    sync::{
+        Arc,
+        Mutex,
+        mpsc::{self, Receiver},
+    },
+    thread,
+    time::Duration,
+};
+
+use git_core::{
+    AuthType,
+    Config,
+    CredentialKind,
+    CredentialProvider,
+    CredentialsRequired,
+    Repo,
+    testing::{SimulatedRemote, Workspace},
+};
+use git2::{ErrorClass, ErrorCode, Repository};
+
+/// The URL of a repository on a server refusing every request, sending the
+/// `Authorization` header of each, empty for none.
+fn refusing_server() -> (String, Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/repo.git", listener.local_addr().unwrap());
+    let (sender, requests) = mpsc::channel();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { return };
+            let mut authorization = String::new();
+            let mut reader = BufReader::new(&stream);
+            let mut line = String::new();
+            while reader.read_line(&mut line).is_ok_and(|read| read > 2) {
+                if let Some(value) = line.strip_prefix("Authorization: ") {
+                    authorization = value.trim_end().to_string();
+                }
+                line.clear();
+            }
+            let _ = stream.write_all(
+                b"HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: Basic realm=\"git\"\r\n\
+                  Content-Length: 0\r\nConnection: close\r\n\r\n",
+            );
+            if sender.send(authorization).is_err() {
+                return;
+            }
+        }
+    });
+    (url, requests)
+}
+
+/// Answers with `secrets` in turn and records every request, cancelling once they run out.
+struct RecordingProvider {
+    secrets: Mutex<Vec<&'static str>>,
+    requests: Mutex<Vec<(String, CredentialKind)>>,
+}
+
+impl RecordingProvider {
+    fn new(secrets: &[&'static str]) -> Arc<Self> {
+        Arc::new(Self {
+            secrets: Mutex::new(secrets.iter().rev().copied().collect()),
+            requests: Mutex::new(Vec::new()),
+        })
+    }
+}
+
+impl CredentialProvider for RecordingProvider {
+    fn request(&self, url: &str, kind: CredentialKind) -> Result<String, git2::Error> {
+        self.requests.lock().unwrap().push((url.to_string(), kind));
+        self.secrets.lock().unwrap().pop().map(str::to_string).ok_or_else(|| {
+            git2::Error::new(ErrorCode::User, ErrorClass::Callback, "the user cancelled")
+        })
+    }
+}
+
+/// A clone whose origin is the refusing server.
+fn behind_refusing_server(
+    remote: &SimulatedRemote,
+) -> Result<(Workspace, Receiver<String>, String), git2::Error> {
+    let (url, requests) = refusing_server();
+    let local = remote.clone_workspace()?;
+    Repository::open(&local.config.path)?.remote_set_url("origin", &url)?;
+    Ok((local, requests, url))
+}
+
+#[test]
+fn the_provider_is_asked_after_the_configured_password() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let (local, requests, url) = behind_refusing_server(&remote)?;
+    let provider = RecordingProvider::new(&["second"]);
+    let config = Config {
+        auth: AuthType::Password("first".to_string()),
+        credential_provider: Some(provider.clone()),
+        ..local.config.clone()
+    };
+
+    let e = Repo::open(&config)?.prune_report().expect_err("the server refuses everyone");
+
+    assert_eq!(e.code(), ErrorCode::User, "{e}");
+    assert!(e.message().contains("the user cancelled"), "{e}");
+    assert_eq!(
+        *provider.requests.lock().unwrap(),
+        [(url.clone(), CredentialKind::Password), (url, CredentialKind::Password)]
+    );
+    // "Test Developer" with "first", then with "second"
+    let sent: Vec<String> = requests.try_iter().filter(|auth| !auth.is_empty()).collect();
+    assert_eq!(sent, ["Basic VGVzdCBEZXZlbG9wZXI6Zmlyc3Q=", "Basic VGVzdCBEZXZlbG9wZXI6c2Vjb25k"]);
+    Ok(())
+}
+
+#[test]
+fn without_configured_credentials_the_provider_is_not_asked() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let (local, requests, url) = behind_refusing_server(&remote)?;
+    let provider = RecordingProvider::new(&["never used"]);
+    let config = Config {
+        credential_provider: Some(provider.clone()),
+        ..local.config.clone()
+    };
+
+    let e = Repo::open(&config)?.prune_report().expect_err("credentials are required");
+
+    let required = CredentialsRequired::from_error(&e).expect("credentials required");
+    assert_eq!(required.url, url);
+    assert_eq!(required.accepted_methods, ["password"]);
+    assert!(provider.requests.lock().unwrap().is_empty());
+    assert!(requests.recv_timeout(Duration::from_secs(10)).unwrap().is_empty());
+    assert!(requests.try_iter().all(|auth| auth.is_empty()));
+    Ok(())
+}
+
+#[test]
+fn a_local_remote_never_asks() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let provider = RecordingProvider::new(&[]);
+    let config = Config {
+        credential_provider: Some(provider.clone()),
+        ..local.config.clone()
+    };
+
+    Repo::open(&config)?.prune_report()?;
+
+    assert!(provider.requests.lock().unwrap().is_empty());
+    Ok(())
+}
@@ -0,0 +1,81 @@
+//! Patches as `Repo::diff` and `Repo::diff_staged` print them.
+
+use git_core::{Config, Repo, testing::SimulatedRemote};
+
+/// Lines of `patch` that are context, not the file header, a hunk header or a change.
+fn context_lines(patch: &str) -> usize {
+    patch.lines().filter(|line| line.starts_with(' ')).count()
+}
+
+#[test]
+fn context_lines_follow_the_config() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let lines: String = (1..=20).map(|line| format!("line {line}\n")).collect();
+    std::fs::write(local.config.path.join("a.txt"), &lines).unwrap();
+    let repo = Repo::open(&local.config)?;
+    repo.add(["a.txt"])?;
+    repo.commit("twenty lines")?;
+    std::fs::write(local.config.path.join("a.txt"), lines.replace("line 10\n", "ten\n")).unwrap();
+
+    let diff_with = |context_lines| {
+        let config = Config {
+            diff_context_lines: context_lines,
+            ..local.config.clone()
+        };
+        Repo::open(&config)?.diff(None, None)
+    };
+
+    assert_eq!(context_lines(&diff_with(0)?), 0);
+    assert_eq!(context_lines(&diff_with(3)?), 6);
+    assert_eq!(context_lines(&diff_with(5)?), 10);
+    Ok(())
+}
+
+#[test]
+fn staged_diff_leaves_out_unstaged_changes() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    std::fs::write(local.config.path.join("staged.txt"), "staged\n").unwrap();
+    repo.add(["staged.txt"])?;
+    std::fs::write(local.config.path.join("staged.txt"), "staged\nthen changed\n").unwrap();
+
+    let patch = repo.diff_staged()?;
+
+    assert!(patch.contains("+staged\n"), "{patch}");
+    assert!(!patch.contains("then changed"), "{patch}");
+    assert!(repo.diff(None, None)?.contains("+then changed\n"));
+    Ok(())
+}
+
+#[test]
+fn gitattributes_binary_files_get_no_text_diff() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    let write = |path: &str, content: &str| {
+        std::fs::write(local.config.path.join(path), content).unwrap();
+    };
+    write(".gitattributes", "Form.xml binary\nTemplate.xml -diff\n");
+    for twin in ["Form.xml", "Template.xml", "Module.xml"] {
+        write(twin, "<form>\n  <title>old</title>\n</form>\n");
+    }
+    repo.add([".gitattributes", "Form.xml", "Template.xml", "Module.xml"])?;
+    repo.commit("forms")?;
+    for twin in ["Form.xml", "Template.xml", "Module.xml"] {
+        write(twin, "<form>\n  <title>new</title>\n</form>\n");
+    }
+
+    let patch = repo.diff(None, None)?;
+
+    assert!(patch.contains("Binary files a/Form.xml and b/Form.xml differ"), "{patch}");
+    assert!(patch.contains("Binary files a/Template.xml and b/Template.xml differ"), "{patch}");
+    assert!(patch.contains("+  <title>new</title>"), "{patch}");
+    // the only text diff is the unmarked twin's
+    assert_eq!(patch.matches("<title>new</title>").count(), 1, "{patch}");
+    assert_eq!(repo.check_attr("Form.xml", "binary")?.as_deref(), Some("set"));
+    assert_eq!(repo.check_attr("Template.xml", "diff")?.as_deref(), Some("unset"));
+    assert_eq!(repo.check_attr("Module.xml", "diff")?, None);
+    Ok(())
+}
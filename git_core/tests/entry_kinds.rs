@@ -0,0 +1,132 @@
+//! Symlinks, submodules and mode changes in the status and when changes are discarded or
+//! staged, see `git_core::EntryKind` and `FileStatus::mode_change`. Windows checks links out
+//! as plain files and has no executable bit, so unix only.
+#![cfg(unix)]
+
+use std::{
+    fs::Permissions,
+    os::unix::fs::{PermissionsExt, symlink},
+    path::Path,
+};
+
+use git_core::{EntryKind, FileStatus, Repo, ReportFormat, report, testing::SimulatedRemote};
+use git2::{Repository, Signature};
+
+fn not_staged<'a>(files: &'a [FileStatus], path: &str) -> &'a FileStatus {
+    files
+        .iter()
+        .find(|file| file.new_file == path)
+        .unwrap_or_else(|| panic!("{path} not among the unstaged changes"))
+}
+
+#[test]
+fn a_retargeted_symlink_is_classified_and_discarded_as_a_link() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    let link = local.config.path.join("current");
+    symlink("README.md", &link).unwrap();
+    repo.add(["current"])?;
+    repo.commit("link the readme")?;
+    std::fs::remove_file(&link).unwrap();
+    symlink("elsewhere.md", &link).unwrap();
+
+    let status = repo.status()?;
+
+    let file = not_staged(&status.not_staged, "current");
+    assert_eq!(file.entry_kind, EntryKind::Symlink);
+    assert!(status.to_string().contains("current (symlink)"), "{status}");
+
+    repo.discard_changes(&["current"])?;
+
+    assert!(std::fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+    assert_eq!(std::fs::read_link(&link).unwrap(), Path::new("README.md"));
+    assert!(repo.status()?.not_staged.is_empty());
+    Ok(())
+}
+
+#[test]
+fn a_submodule_on_a_new_commit_is_classified_and_left_alone() -> Result<(), git2::Error> {
+    let library = SimulatedRemote::new("main")?;
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let raw = Repository::open(&local.config.path)?;
+    let mut submodule = raw.submodule(&library.url(), Path::new("library"), true)?;
+    submodule.clone(None)?;
+    submodule.add_finalize()?;
+    let repo = Repo::open(&local.config)?;
+    repo.commit("add the library")?;
+    // a commit made inside the submodule checkout
+    let inner = Repository::open(local.config.path.join("library"))?;
+    std::fs::write(local.config.path.join("library/fix.txt"), "fix\n").unwrap();
+    let mut index = inner.index()?;
+    index.add_path(Path::new("fix.txt"))?;
+    let tree = inner.find_tree(index.write_tree()?)?;
+    let author = Signature::now("Test Developer", "test@example.com")?;
+    let parent = inner.head()?.peel_to_commit()?;
+    let fixed = inner.commit(Some("HEAD"), &author, &author, "fix", &tree, &[&parent])?;
+
+    let status = repo.status()?;
+
+    let file = not_staged(&status.not_staged, "library");
+    assert_eq!(file.entry_kind, EntryKind::Submodule);
+    assert!(status.to_string().contains("library (submodule)"), "{status}");
+
+    assert_eq!(repo.add_all()?, Vec::<String>::new());
+    repo.discard_changes(&["library"])?;
+
+    assert_eq!(inner.head()?.target(), Some(fixed));
+    assert_eq!(not_staged(&repo.status()?.not_staged, "library").entry_kind, EntryKind::Submodule);
+    repo.add(["library"])?;
+    assert!(repo.status()?.staged.iter().any(|file| file.new_file == "library"));
+    Ok(())
+}
+
+#[test]
+fn a_file_made_executable_shows_its_mode_change() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    remote.push_commit("main", "build.sh", "#!/bin/sh\n", "build script")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    let script = local.config.path.join("build.sh");
+    std::fs::write(local.config.path.join("README.md"), "changed\n").unwrap();
+    std::fs::set_permissions(&script, Permissions::from_mode(0o755)).unwrap();
+
+    let status = repo.status()?;
+
+    let file = not_staged(&status.not_staged, "build.sh");
+    let expected = Some(("100644".to_string(), "100755".to_string()));
+    assert_eq!(file.mode_change(), expected);
+    assert_eq!(not_staged(&status.not_staged, "README.md").mode_change(), None);
+    assert!(status.to_string().contains("build.sh (mode changed 100644 -> 100755)"), "{status}");
+    let mut json = Vec::new();
+    report::write_status(&status, ReportFormat::Json, &mut json)?;
+    let json = String::from_utf8(json).unwrap();
+    assert!(json.contains(r#""old_mode":"100644","new_mode":"100755""#), "{json}");
+    assert!(json.contains(r#""old_mode":null,"new_mode":null"#), "{json}");
+
+    repo.add(["build.sh"])?;
+
+    let status = repo.status()?;
+    let staged = status.staged.iter().find(|file| file.new_file == "build.sh").unwrap();
+    assert_eq!(staged.mode_change(), expected);
+    Ok(())
+}
+
+#[test]
+fn a_file_replaced_by_a_symlink_changes_its_type_and_mode() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    remote.push_commit("main", "current.md", "1.0\n", "notes")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    let link = local.config.path.join("current.md");
+    std::fs::remove_file(&link).unwrap();
+    symlink("README.md", &link).unwrap();
+
+    let status = repo.status()?;
+
+    let file = not_staged(&status.not_staged, "current.md");
+    assert_eq!(file.status, git2::Delta::Typechange);
+    assert_eq!(file.mode_change(), Some(("100644".to_string(), "120000".to_string())));
+    Ok(())
+}
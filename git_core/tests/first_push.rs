@@ -0,0 +1,73 @@
+//! Publishing a new repository: init on the default branch, commit and push it, see
+//! `Config::default_branch`.
+
+use git_core::{
+    Config,
+    Repo,
+    testing::{SimulatedRemote, TempDir},
+};
+use git2::{ErrorCode, Oid, Repository};
+
+fn config(dir: &TempDir, default_branch: &str) -> Config {
+    Config {
+        path: dir.path().join("repo"),
+        username: "Test Developer".to_string(),
+        email: "test@example.com".to_string(),
+        default_branch: Some(default_branch.to_string()),
+        ..Config::default()
+    }
+}
+
+/// A new repository at `config` with one commit and `remote` as origin.
+fn committed(config: &Config, remote: &SimulatedRemote) -> Result<Oid, git2::Error> {
+    let repo = Repo::init(config, None)?;
+    std::fs::write(config.path.join("Configuration.xml"), "<Configuration/>\n").unwrap();
+    repo.add(["Configuration.xml"])?;
+    let commit = repo.commit("first version")?;
+    repo.add_remote("origin", &remote.url())?;
+    Ok(commit)
+}
+
+#[test]
+fn init_commit_and_push_main() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::empty("main")?;
+    let dir = TempDir::new("git-core-first-push")?;
+    let config = config(&dir, "main");
+    let commit = committed(&config, &remote)?;
+
+    Repo::open(&config)?.push()?;
+
+    assert_eq!(remote.ref_target("refs/heads/main"), Some(commit));
+    let raw = Repository::open(&config.path)?;
+    assert_eq!(raw.head()?.name(), Some("refs/heads/main"));
+    let upstream = raw.branch_upstream_name("refs/heads/main")?;
+    assert_eq!(upstream.as_str(), Some("refs/remotes/origin/main"));
+    assert_eq!(raw.refname_to_id("refs/remotes/origin/main")?, commit);
+    Ok(())
+}
+
+#[test]
+fn a_branch_other_than_the_remote_default_is_renamed_first() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::empty("main")?;
+    let dir = TempDir::new("git-core-first-push")?;
+    let config = config(&dir, "master");
+    let commit = committed(&config, &remote)?;
+    let repo = Repo::open(&config)?;
+    let published = Config {
+        default_branch: Some("main".to_string()),
+        ..config.clone()
+    };
+
+    let e = Repo::open(&published)?.push().expect_err("master isn't the default branch");
+    assert_eq!(e.code(), ErrorCode::Invalid, "{e}");
+    assert!(e.message().contains("rename it first"), "{e}");
+    assert_eq!(remote.ref_target("refs/heads/master"), None);
+
+    repo.rename_current_branch("main")?;
+    Repo::open(&published)?.push()?;
+
+    assert_eq!(remote.ref_target("refs/heads/main"), Some(commit));
+    let upstream = Repository::open(&config.path)?.branch_upstream_name("refs/heads/main")?;
+    assert_eq!(upstream.as_str(), Some("refs/remotes/origin/main"));
+    Ok(())
+}
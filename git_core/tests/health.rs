@@ -0,0 +1,218 @@
+//! One broken clone per issue `Repo::health_check` reports.
+
+use std::{
+    fs::File,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use git_core::{
+    Config,
+    FileStatus,
+    Repo,
+    testing::{SimulatedRemote, TempDir, Workspace},
+};
+use git2::Repository;
+
+fn clone() -> Result<(SimulatedRemote, Workspace), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    Ok((remote, local))
+}
+
+/// The codes of the issues found in the clone of `config`.
+fn issues(config: &Config) -> Result<Vec<&'static str>, git2::Error> {
+    Ok(Repo::open(config)?.health_check().iter().map(|issue| issue.code).collect())
+}
+
+fn git_dir(local: &Workspace) -> std::path::PathBuf {
+    local.config.path.join(".git")
+}
+
+/// Creates `path` with a modification time two days ago.
+fn write_stale(path: &Path) {
+    let file = File::create(path).unwrap();
+    file.set_modified(SystemTime::now() - Duration::from_secs(2 * 24 * 60 * 60)).unwrap();
+}
+
+#[test]
+fn a_fresh_clone_is_healthy() -> Result<(), git2::Error> {
+    let (_remote, local) = clone()?;
+    assert_eq!(issues(&local.config)?, Vec::<&str>::new());
+    Ok(())
+}
+
+#[test]
+fn head_unresolved() -> Result<(), git2::Error> {
+    let (_remote, local) = clone()?;
+    std::fs::write(git_dir(&local).join("HEAD"), format!("{}\n", "1".repeat(40))).unwrap();
+    assert!(issues(&local.config)?.contains(&"head_unresolved"));
+    Ok(())
+}
+
+#[test]
+fn index_corrupt() -> Result<(), git2::Error> {
+    let (_remote, local) = clone()?;
+    std::fs::write(git_dir(&local).join("index"), "not an index").unwrap();
+    assert_eq!(issues(&local.config)?, ["index_corrupt"]);
+    Ok(())
+}
+
+#[test]
+fn index_missing() -> Result<(), git2::Error> {
+    let (_remote, local) = clone()?;
+    std::fs::remove_file(git_dir(&local).join("index")).unwrap();
+    assert_eq!(issues(&local.config)?, ["index_missing"]);
+    Ok(())
+}
+
+#[test]
+fn remote_without_url() -> Result<(), git2::Error> {
+    let (_remote, local) = clone()?;
+    Repository::open(&local.config.path)?
+        .config()?
+        .set_str("remote.mirror.pushurl", "https://example.com/mirror.git")?;
+    assert_eq!(issues(&local.config)?, ["remote_without_url"]);
+    Ok(())
+}
+
+#[test]
+fn upstream_missing() -> Result<(), git2::Error> {
+    let (_remote, local) = clone()?;
+    Repository::open(&local.config.path)?.find_reference("refs/remotes/origin/main")?.delete()?;
+    assert_eq!(issues(&local.config)?, ["upstream_missing"]);
+    Ok(())
+}
+
+#[test]
+fn stale_index_lock() -> Result<(), git2::Error> {
+    let (_remote, local) = clone()?;
+    write_stale(&git_dir(&local).join("index.lock"));
+    assert_eq!(issues(&local.config)?, ["stale_index_lock"]);
+    Ok(())
+}
+
+#[test]
+fn stale_merge_head() -> Result<(), git2::Error> {
+    let (_remote, local) = clone()?;
+    write_stale(&git_dir(&local).join("MERGE_HEAD"));
+    assert_eq!(issues(&local.config)?, ["stale_merge_head"]);
+    Ok(())
+}
+
+#[test]
+fn autocrlf_mismatch() -> Result<(), git2::Error> {
+    let (_remote, local) = clone()?;
+    let expecting = |autocrlf| Config {
+        autocrlf: Some(autocrlf),
+        ..local.config.clone()
+    };
+    let mut config = Repository::open(&local.config.path)?.config()?;
+
+    // unset is false
+    assert_eq!(issues(&expecting(false))?, Vec::<&str>::new());
+    assert_eq!(issues(&expecting(true))?, ["autocrlf_mismatch"]);
+    // any spelling of a boolean git accepts
+    config.set_str("core.autocrlf", "yes")?;
+    assert_eq!(issues(&expecting(true))?, Vec::<&str>::new());
+    config.set_str("core.autocrlf", "off")?;
+    assert_eq!(issues(&expecting(false))?, Vec::<&str>::new());
+    assert_eq!(issues(&expecting(true))?, ["autocrlf_mismatch"]);
+
+    config.set_str("core.autocrlf", "input")?;
+    for expected in [true, false] {
+        let repo_config = expecting(expected);
+        let issues = Repo::open(&repo_config)?.health_check();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].code, "autocrlf_mismatch");
+        assert!(issues[0].description.contains("'input'"), "{}", issues[0].description);
+    }
+    Ok(())
+}
+
+#[test]
+fn missing_objects() -> Result<(), git2::Error> {
+    let (_remote, local) = clone()?;
+    let repo = Repo::open(&local.config)?;
+    std::fs::write(local.config.path.join("lost.txt"), "lost\n").unwrap();
+    repo.add(["lost.txt"])?;
+    repo.commit("add a file")?;
+    let blob = Repository::open(&local.config.path)?
+        .head()?
+        .peel_to_tree()?
+        .get_name("lost.txt")
+        .map(|entry| entry.id().to_string())
+        .unwrap();
+    std::fs::remove_file(git_dir(&local).join("objects").join(&blob[..2]).join(&blob[2..]))
+        .unwrap();
+
+    assert_eq!(issues(&local.config)?, ["missing_objects"]);
+    Ok(())
+}
+
+const NONE: Vec<String> = Vec::new();
+
+/// The new paths among the staged, unstaged and untracked files of the clone.
+fn status_paths(repo: &Repo<'_>) -> Result<[Vec<String>; 3], git2::Error> {
+    let status = repo.status()?;
+    let paths = |files: &[FileStatus]| files.iter().map(|file| file.new_file.clone()).collect();
+    Ok([paths(&status.staged), paths(&status.not_staged), paths(&status.untracked)])
+}
+
+#[test]
+fn a_rebuilt_index_holds_head_and_drops_what_was_staged() -> Result<(), git2::Error> {
+    let (remote, local) = clone()?;
+    remote.push_commit("main", "Catalog.xml", "<Catalog/>\n", "catalog")?;
+    let repo = Repo::open(&local.config)?;
+    repo.prune_report()?;
+    repo.pull("main")?;
+    std::fs::write(local.config.path.join("Catalog.xml"), "<Catalog changed/>\n").unwrap();
+    std::fs::write(local.config.path.join("Form.xml"), "<Form/>\n").unwrap();
+    repo.add(["Catalog.xml", "Form.xml"])?;
+    std::fs::write(git_dir(&local).join("index"), "").unwrap();
+    assert_eq!(issues(&local.config)?, ["index_corrupt"]);
+
+    let files = repo.rebuild_index()?;
+
+    let raw = Repository::open(&local.config.path)?;
+    assert_eq!(files, raw.head()?.peel_to_tree()?.len());
+    assert_eq!(issues(&local.config)?, Vec::<&str>::new());
+    assert_eq!(
+        status_paths(&repo)?,
+        [NONE, vec!["Catalog.xml".to_string()], vec!["Form.xml".to_string()]]
+    );
+    Ok(())
+}
+
+#[test]
+fn a_missing_index_is_rebuilt() -> Result<(), git2::Error> {
+    let (_remote, local) = clone()?;
+    std::fs::remove_file(git_dir(&local).join("index")).unwrap();
+    let repo = Repo::open(&local.config)?;
+
+    repo.rebuild_index()?;
+
+    assert_eq!(issues(&local.config)?, Vec::<&str>::new());
+    assert_eq!(status_paths(&repo)?, [NONE, NONE, NONE]);
+    Ok(())
+}
+
+#[test]
+fn the_index_of_a_repository_without_commits_is_rebuilt_empty() -> Result<(), git2::Error> {
+    let dir = TempDir::new("git-core-health")?;
+    let config = Config {
+        path: dir.path().join("repo"),
+        ..Config::default()
+    };
+    Repository::init(&config.path)?;
+    std::fs::write(config.path.join("Form.xml"), "<Form/>\n").unwrap();
+    let repo = Repo::open(&config)?;
+    repo.add(["Form.xml"])?;
+    std::fs::write(config.path.join(".git").join("index"), "not an index").unwrap();
+
+    assert_eq!(repo.rebuild_index()?, 0);
+
+    assert!(Repository::open(&config.path)?.index()?.is_empty());
+    assert!(config.path.join("Form.xml").exists());
+    Ok(())
+}
@@ -0,0 +1,70 @@
+//! Settings from the user's home directory, and `Config::extra_ignore_patterns` on top of
+//! them. libgit2 looks up the home directory once per process, so this file holds a single
+//! test setting a fake HOME before libgit2 is first used.
+
+use std::{path::Path, process::Command};
+
+use git_core::{
+    PushDefault,
+    Repo,
+    testing::{SimulatedRemote, TempDir},
+};
+
+/// The untracked files of the clone of `config`, sorted.
+fn untracked(repo: &Repo<'_>) -> Result<Vec<String>, git2::Error> {
+    let mut files: Vec<_> =
+        repo.status()?.untracked.into_iter().map(|file| file.new_file).collect();
+    files.sort();
+    Ok(files)
+}
+
+/// The untracked files command-line git lists in `dir` with `home` as HOME.
+fn git_untracked(dir: &Path, home: &Path) -> Vec<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["status", "--porcelain", "--untracked-files=all"])
+        .env("HOME", home)
+        .env_remove("XDG_CONFIG_HOME")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let mut files: Vec<_> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .filter_map(|line| line.strip_prefix("?? ").map(str::to_string))
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn global_settings_are_read_from_home() -> Result<(), git2::Error> {
+    let home = TempDir::new("git-core-home")?;
+    std::fs::write(
+        home.path().join(".gitconfig"),
+        "[core]\n\texcludesfile = ~/global-ignore\n[push]\n\tdefault = nothing\n",
+    )
+    .unwrap();
+    std::fs::write(home.path().join("global-ignore"), "*.log\n").unwrap();
+    // SAFETY: the only test of this binary, nothing else reads the environment meanwhile
+    unsafe {
+        std::env::set_var("HOME", home.path());
+        std::env::remove_var("XDG_CONFIG_HOME");
+    }
+
+    let remote = SimulatedRemote::new("main")?;
+    let mut local = remote.clone_workspace()?;
+    for file in ["dump.log", "form.tmp", "Form.xml"] {
+        std::fs::write(local.config.path.join(file), "new\n").unwrap();
+    }
+
+    let repo = Repo::open(&local.config)?;
+    assert_eq!(untracked(&repo)?, ["Form.xml", "form.tmp"]);
+    assert_eq!(untracked(&repo)?, git_untracked(&local.config.path, home.path()));
+    assert_eq!(repo.effective_config()?.push_default, Some(PushDefault::Nothing));
+
+    local.config.extra_ignore_patterns = vec!["*.tmp".to_string()];
+    assert_eq!(untracked(&Repo::open(&local.config)?)?, ["Form.xml"]);
+    Ok(())
+}
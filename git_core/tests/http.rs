@@ -0,0 +1,200 @@
+//! Extra HTTP headers reach the server on clone, fetch and push, and wherever the refs
+//! the server advertises are read, see `git_core::http`.
+//! A local server records the requests and answers each with 404, which is enough for
+//! libgit2 to have sent the headers.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+
+use git_core::{
+    Config,
+    OperationKind,
+    Repo,
+    http,
+    testing::{SimulatedRemote, TempDir},
+};
+use git2::Repository;
+
+const HEADERS: [&str; 2] = ["Private-Token: glpat-7f3a9c", "X-Tenant: acme"];
+
+/// The URL of a repository on a server sending the head of every request it gets.
+fn recording_server() -> (String, Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/repo.git", listener.local_addr().unwrap());
+    let (sender, requests) = mpsc::channel();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { return };
+            let mut head = String::new();
+            let mut reader = BufReader::new(&stream);
+            while reader.read_line(&mut head).is_ok_and(|read| read > 2) {}
+            let _ = stream.write_all(
+                b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            );
+            if sender.send(head).is_err() {
+                return;
+            }
+        }
+    });
+    (url, requests)
+}
+
+fn config_with_headers(config: &Config) -> Config {
+    Config {
+        custom_headers: HEADERS.iter().map(|header| header.to_string()).collect(),
+        ..config.clone()
+    }
+}
+
+/// The first request the server got.
+fn first_request(requests: &Receiver<String>) -> String {
+    requests.recv_timeout(Duration::from_secs(10)).expect("no request reached the server")
+}
+
+fn assert_headers_sent(request: &str) {
+    for header in HEADERS {
+        assert!(request.contains(&format!("{header}\r\n")), "{header} missing from {request}");
+    }
+}
+
+#[test]
+fn clone_sends_the_headers() -> Result<(), git2::Error> {
+    let (url, requests) = recording_server();
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let config = Config {
+        path: local.config.path.with_file_name("http-clone"),
+        ..config_with_headers(&local.config)
+    };
+
+    Repo::clone_from(&url, &config).err().expect("the server has no repository");
+
+    assert_headers_sent(&first_request(&requests));
+    Ok(())
+}
+
+#[test]
+fn fetch_and_push_send_the_headers() -> Result<(), git2::Error> {
+    let (url, requests) = recording_server();
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    Repository::open(&local.config.path)?.remote_set_url("origin", &url)?;
+    let config = config_with_headers(&local.config);
+    let repo = Repo::open(&config)?;
+
+    repo.prune_report().expect_err("the server has no repository");
+    let fetch = first_request(&requests);
+    assert!(fetch.contains("service=git-upload-pack"), "{fetch}");
+    assert_headers_sent(&fetch);
+
+    repo.push().expect_err("the server has no repository");
+    let push = first_request(&requests);
+    assert!(push.contains("service=git-receive-pack"), "{push}");
+    assert_headers_sent(&push);
+    Ok(())
+}
+
+#[test]
+fn ls_remote_and_prune_send_the_headers() -> Result<(), git2::Error> {
+    let (url, requests) = recording_server();
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    Repository::open(&local.config.path)?.remote_set_url("origin", &url)?;
+    let config = config_with_headers(&local.config);
+    let repo = Repo::open(&config)?;
+
+    repo.ls_remote_head("origin", "main").expect_err("the server has no repository");
+    let ls_remote = first_request(&requests);
+    assert!(ls_remote.contains("service=git-upload-pack"), "{ls_remote}");
+    assert_headers_sent(&ls_remote);
+
+    repo.prune("origin").expect_err("the server has no repository");
+    assert_headers_sent(&first_request(&requests));
+    Ok(())
+}
+
+#[test]
+fn push_preflight_sends_the_headers() -> Result<(), git2::Error> {
+    let (url, requests) = recording_server();
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    Repository::open(&local.config.path)?.remote_set_url("origin", &url)?;
+    let config = config_with_headers(&local.config);
+
+    let issues = Repo::open(&config)?.preflight(OperationKind::Push);
+
+    assert!(issues.iter().any(|issue| issue.code == "unreachable"), "{issues:?}");
+    let preflight = first_request(&requests);
+    assert!(preflight.contains("service=git-receive-pack"), "{preflight}");
+    assert_headers_sent(&preflight);
+    Ok(())
+}
+
+#[test]
+fn first_push_asks_for_the_default_branch_with_the_headers() -> Result<(), git2::Error> {
+    let (url, requests) = recording_server();
+    let dir = TempDir::new("git-core-http")?;
+    let config = Config {
+        path: dir.path().join("repo"),
+        username: "Test Developer".to_string(),
+        email: "test@example.com".to_string(),
+        ..config_with_headers(&Config::default())
+    };
+    let repo = Repo::init(&config, None)?;
+    std::fs::write(config.path.join("a.txt"), "a\n").unwrap();
+    repo.add(["a.txt"])?;
+    repo.commit("first version")?;
+    repo.add_remote("origin", &url)?;
+
+    repo.push().expect_err("the server has no repository");
+
+    let default_branch = first_request(&requests);
+    assert!(default_branch.contains("service=git-upload-pack"), "{default_branch}");
+    assert_headers_sent(&default_branch);
+    Ok(())
+}
+
+#[test]
+fn headers_with_a_nul_are_dropped() -> Result<(), git2::Error> {
+    let (url, requests) = recording_server();
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    Repository::open(&local.config.path)?.remote_set_url("origin", &url)?;
+    let config = Config {
+        custom_headers: vec!["X-Tenant: acme".to_string(), "X-Bad: a\0b".to_string()],
+        ..local.config.clone()
+    };
+
+    Repo::open(&config)?.prune_report().expect_err("the server has no repository");
+
+    let request = first_request(&requests);
+    assert!(request.contains("X-Tenant: acme\r\n"), "{request}");
+    assert!(!request.contains("X-Bad"), "{request}");
+    Ok(())
+}
+
+#[test]
+fn parsed_headers_refuse_injected_lines_without_echoing_them() {
+    let headers = http::parse_headers("Private-Token: glpat-7f3a9c\r\n\nX-Tenant: acme\n").unwrap();
+    assert_eq!(headers, HEADERS);
+
+    let e = http::parse_headers("X-Tenant: acme\rInjected: glpat-7f3a9c").unwrap_err();
+    assert!(e.message().contains("X-Tenant"), "{e}");
+    assert!(!e.message().contains("glpat"), "{e}");
+    let e = http::parse_headers("glpat-7f3a9c").unwrap_err();
+    assert!(!e.message().contains("glpat"), "{e}");
+}
+
+#[test]
+fn redact_hides_the_header_values() {
+    let headers = HEADERS.map(str::to_string);
+
+    let redacted = http::redact("rejected Private-Token: glpat-7f3a9c for tenant acme", &headers);
+
+    assert_eq!(redacted, "rejected Private-Token: *** for tenant ***");
+}
@@ -0,0 +1,86 @@
+//! New repositories seeded from a template, see `Repo::init`.
+
+use std::path::Path;
+
+use git_core::{Config, InitTemplate, Repo, testing::TempDir};
+use git2::{ErrorCode, ObjectType, Repository, TreeWalkMode, TreeWalkResult};
+
+fn config(dir: &TempDir) -> Config {
+    Config {
+        path: dir.path().join("repo"),
+        username: "Test Developer".to_string(),
+        email: "test@example.com".to_string(),
+        ..Config::default()
+    }
+}
+
+/// The paths in the tree of the HEAD commit, which must be the only commit.
+fn initial_commit_files(path: &Path) -> Result<Vec<String>, git2::Error> {
+    let repo = Repository::open(path)?;
+    let head = repo.head()?.peel_to_commit()?;
+    assert_eq!(head.parent_count(), 0);
+    let mut files = Vec::new();
+    head.tree()?.walk(TreeWalkMode::PreOrder, |dir, entry| {
+        if entry.kind() == Some(ObjectType::Blob) {
+            files.push(format!("{dir}{}", entry.name().unwrap()));
+        }
+        TreeWalkResult::Ok
+    })?;
+    files.sort();
+    Ok(files)
+}
+
+#[test]
+fn builtin_template_is_the_initial_commit_on_main() -> Result<(), git2::Error> {
+    let dir = TempDir::new("git-core-init")?;
+    let config = config(&dir);
+
+    Repo::init(&config, Some(&InitTemplate::named("1c-config")))?;
+
+    assert_eq!(initial_commit_files(&config.path)?, [".gitattributes", ".gitignore", "README.md"]);
+    let repo = Repository::open(&config.path)?;
+    assert_eq!(repo.head()?.shorthand(), Some("main"));
+    assert_eq!(repo.head()?.peel_to_commit()?.message(), Some("Initial commit"));
+    assert!(repo.statuses(None)?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn directory_template_keeps_relative_paths() -> Result<(), git2::Error> {
+    let dir = TempDir::new("git-core-init")?;
+    let template = dir.path().join("template");
+    std::fs::create_dir_all(template.join("src/Catalogs")).unwrap();
+    std::fs::create_dir_all(template.join(".git")).unwrap();
+    std::fs::write(template.join(".git/HEAD"), "not a template file\n").unwrap();
+    std::fs::write(template.join("README.md"), "# Configuration\n").unwrap();
+    std::fs::write(template.join("src/Catalogs/Goods.xml"), "<Catalog/>\n").unwrap();
+    let config = config(&dir);
+    let mut init = InitTemplate::named(template.to_str().unwrap());
+    init.message = "Start the configuration".to_string();
+
+    Repo::init(&config, Some(&init))?;
+
+    assert_eq!(initial_commit_files(&config.path)?, ["README.md", "src/Catalogs/Goods.xml"]);
+    let repo = Repository::open(&config.path)?;
+    assert_eq!(repo.head()?.peel_to_commit()?.message(), Some("Start the configuration"));
+    Ok(())
+}
+
+#[test]
+fn existing_files_are_not_overwritten() -> Result<(), git2::Error> {
+    let dir = TempDir::new("git-core-init")?;
+    let config = config(&dir);
+    std::fs::create_dir_all(&config.path).unwrap();
+    std::fs::write(config.path.join("README.md"), "our notes\n").unwrap();
+
+    let e = Repo::init(&config, Some(&InitTemplate::named("1c-config")))
+        .err()
+        .expect("README.md is in the way");
+
+    assert_eq!(e.code(), ErrorCode::Exists, "{e}");
+    assert!(e.message().contains("README.md' already exists"), "{e}");
+    assert_eq!(std::fs::read_to_string(config.path.join("README.md")).unwrap(), "our notes\n");
+    assert!(!config.path.join(".git").exists());
+    assert!(!config.path.join(".gitignore").exists());
+    Ok(())
+}
@@ -0,0 +1,150 @@
+//! Advisory locks shared through the `locks` branch of origin, see `git_core::git_locks`.
+
+use std::{sync::Barrier, thread};
+
+use git_core::{
+    Config,
+    Repo,
+    testing::{GitDaemon, SimulatedRemote, Workspace},
+};
+use git2::Repository;
+
+/// A clone of `remote` committing as `username`.
+fn clone_as(remote: &SimulatedRemote, username: &str) -> Result<Workspace, git2::Error> {
+    let mut workspace = remote.clone_workspace()?;
+    workspace.config = Config {
+        username: username.to_string(),
+        ..workspace.config
+    };
+    Ok(workspace)
+}
+
+/// A clone of `remote` committing as `username` that reaches it through `daemon`, where
+/// the locks are pushed rather than stored.
+fn served_clone_as(
+    remote: &SimulatedRemote,
+    daemon: &GitDaemon,
+    username: &str,
+) -> Result<Workspace, git2::Error> {
+    let workspace = clone_as(remote, username)?;
+    Repository::open(&workspace.config.path)?.remote_set_url("origin", daemon.url())?;
+    Ok(workspace)
+}
+
+/// Alice and Bob lock three forms each at the same time, all six locks must arrive.
+fn race_for_three_forms_each(alice: &Workspace, bob: &Workspace) -> Result<(), git2::Error> {
+    let start = Barrier::new(2);
+
+    thread::scope(|scope| {
+        let lock = |workspace: &Workspace| {
+            let start = &start;
+            let config = workspace.config.clone();
+            scope.spawn(move || -> Result<(), git2::Error> {
+                let repo = Repo::open(&config)?;
+                start.wait();
+                for form in ["Catalog.xml", "Document.xml", "Report.xml"] {
+                    repo.lock_file(&format!("{}/{form}", config.username), &config.username)?;
+                }
+                Ok(())
+            })
+        };
+        let alice = lock(alice);
+        let bob = lock(bob);
+        alice.join().unwrap().and(bob.join().unwrap())
+    })?;
+
+    let repo = Repo::open(&alice.config)?;
+    repo.prune_report()?;
+    let mut locks: Vec<_> =
+        repo.list_locks()?.into_iter().map(|lock| (lock.owner, lock.path)).collect();
+    locks.sort();
+    assert_eq!(locks.len(), 6, "{locks:?}");
+    assert!(locks.iter().all(|(owner, path)| path.starts_with(&format!("{owner}/"))));
+    Ok(())
+}
+
+#[test]
+fn racing_clones_both_get_their_locks() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+
+    race_for_three_forms_each(&clone_as(&remote, "alice")?, &clone_as(&remote, "bob")?)
+}
+
+#[test]
+fn racing_clones_both_get_their_locks_pushed() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let daemon = remote.serve()?;
+
+    race_for_three_forms_each(
+        &served_clone_as(&remote, &daemon, "alice")?,
+        &served_clone_as(&remote, &daemon, "bob")?,
+    )
+}
+
+#[test]
+fn locks_are_not_listed_as_branches() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let daemon = remote.serve()?;
+    let local = served_clone_as(&remote, &daemon, "alice")?;
+    let repo = Repo::open(&local.config)?;
+
+    repo.lock_file("Catalog.xml", "alice")?;
+
+    assert_eq!(remote.ref_target("refs/heads/locks"), repo.resolve_commit("origin/locks").ok());
+    let branches = repo.branch_list()?;
+    assert_eq!(
+        branches.local.iter().map(|branch| branch.name.as_str()).collect::<Vec<_>>(),
+        ["main"]
+    );
+    assert!(branches.remote_only.is_empty(), "{branches:?}");
+    Ok(())
+}
+
+#[test]
+fn racing_clones_get_a_file_once() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let clones = [clone_as(&remote, "alice")?, clone_as(&remote, "bob")?];
+    let start = Barrier::new(2);
+
+    let results: Vec<_> = thread::scope(|scope| {
+        let threads: Vec<_> = clones
+            .iter()
+            .map(|workspace| {
+                let start = &start;
+                scope.spawn(move || {
+                    let repo = Repo::open(&workspace.config)?;
+                    start.wait();
+                    repo.lock_file("Catalog.xml", &workspace.config.username)
+                })
+            })
+            .collect();
+        threads.into_iter().map(|thread| thread.join().unwrap()).collect()
+    });
+
+    let refused: Vec<_> = results.iter().filter_map(|result| result.as_ref().err()).collect();
+    assert_eq!(refused.len(), 1, "{results:?}");
+    assert!(refused[0].message().contains("already locked by"), "{}", refused[0]);
+    let repo = Repo::open(&clones[0].config)?;
+    repo.prune_report()?;
+    assert_eq!(repo.list_locks()?.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn status_without_readable_locks_still_shows_the_files() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    // the tracking ref of the locks points at a blob, no commit to read them from
+    let git = Repository::open(&local.config.path)?;
+    let blob = git.blob(b"not a commit")?;
+    git.reference("refs/remotes/origin/locks", blob, true, "corrupt")?;
+    std::fs::write(local.config.path.join("a.txt"), "changed\n").unwrap();
+
+    assert!(repo.list_locks().is_err());
+    let status = repo.status()?;
+
+    assert_eq!(status.untracked.len() + status.not_staged.len(), 1);
+    assert!(status.not_staged.iter().chain(&status.untracked).all(|file| file.locked_by.is_none()));
+    Ok(())
+}
@@ -0,0 +1,76 @@
+//! Notes attached to commits, and carried along by push and fetch with
+//! `Config::sync_notes`, see `git_core::git_notes`.
+
+use git_core::{Config, Repo, testing::SimulatedRemote};
+use git2::ErrorCode;
+
+#[test]
+fn notes_are_added_shown_and_removed_per_namespace() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    let head = repo.resolve_commit("HEAD")?;
+
+    repo.note_add("HEAD", "review", "reviewed-by: Lead", false)?;
+
+    assert_eq!(repo.note_read("HEAD", "review")?.as_deref(), Some("reviewed-by: Lead"));
+    assert_eq!(repo.note_read(&head.to_string(), "review")?.as_deref(), Some("reviewed-by: Lead"));
+    assert_eq!(repo.note_read("HEAD", "")?, None);
+    assert_eq!(repo.resolve_commit("HEAD")?, head, "notes don't rewrite the commit");
+
+    let e = repo.note_add("HEAD", "review", "again", false).expect_err("a note is there");
+    assert_eq!(e.code(), ErrorCode::Exists, "{e}");
+    repo.note_add("HEAD", "review", "reviewed-by: Other", true)?;
+    assert_eq!(repo.note_read("HEAD", "review")?.as_deref(), Some("reviewed-by: Other"));
+
+    repo.note_add("HEAD", "", "default namespace", false)?;
+    repo.note_remove("HEAD", "review")?;
+    assert_eq!(repo.note_read("HEAD", "review")?, None);
+    assert_eq!(repo.note_read("HEAD", "commits")?.as_deref(), Some("default namespace"));
+    let e = repo.note_remove("HEAD", "review").expect_err("the note is gone");
+    assert_eq!(e.code(), ErrorCode::NotFound, "{e}");
+    Ok(())
+}
+
+#[test]
+fn notes_survive_a_push_and_a_fetch() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let ours = remote.clone_workspace()?;
+    let theirs = remote.clone_workspace()?;
+    let syncing = |config: &Config| Config {
+        sync_notes: true,
+        ..config.clone()
+    };
+    let (ours_config, theirs_config) = (syncing(&ours.config), syncing(&theirs.config));
+    let repo = Repo::open(&ours_config)?;
+    std::fs::write(ours.config.path.join("a.txt"), "ours\n").unwrap();
+    repo.add(["a.txt"])?;
+    let commit = repo.commit("ours")?;
+    repo.note_add("HEAD", "review", "reviewed-by: Lead", false)?;
+
+    repo.push()?;
+    let other = Repo::open(&theirs_config)?;
+    other.prune_report()?;
+    other.pull("main")?;
+
+    assert_eq!(remote.ref_target("refs/heads/main"), Some(commit));
+    assert!(remote.ref_target("refs/notes/review").is_some());
+    assert_eq!(other.note_read("HEAD", "review")?.as_deref(), Some("reviewed-by: Lead"));
+    Ok(())
+}
+
+#[test]
+fn notes_stay_local_without_sync_notes() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    std::fs::write(local.config.path.join("a.txt"), "ours\n").unwrap();
+    repo.add(["a.txt"])?;
+    repo.commit("ours")?;
+    repo.note_add("HEAD", "review", "reviewed-by: Lead", false)?;
+
+    repo.push()?;
+
+    assert_eq!(remote.ref_target("refs/notes/review"), None);
+    Ok(())
+}
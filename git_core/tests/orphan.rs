@@ -0,0 +1,90 @@
+//! Branches with a history of their own, see `Repo::branch_create_orphan`.
+
+use git_core::{Repo, testing::SimulatedRemote};
+use git2::{ErrorCode, Repository};
+
+const NOTES: &[(&str, &[u8])] =
+    &[("README.md", b"# Release notes\n"), ("2026/10/1.0.md", b"First release\n")];
+
+#[test]
+fn the_branch_starts_without_a_parent() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    let raw = Repository::open(&local.config.path)?;
+    let head = raw.head()?.target();
+
+    let id = repo.branch_create_orphan("notes", "start the notes", NOTES, false)?;
+
+    let commit = raw.find_commit(id)?;
+    assert_eq!(commit.parent_count(), 0);
+    assert_eq!(commit.message(), Some("start the notes"));
+    assert_eq!(raw.refname_to_id("refs/heads/notes")?, id);
+    let tree = commit.tree()?;
+    assert_eq!(tree.len(), 2);
+    let nested = tree.get_path("2026/10/1.0.md".as_ref())?.to_object(&raw)?;
+    assert_eq!(nested.as_blob().unwrap().content(), b"First release\n");
+    // nothing checked out
+    assert_eq!(raw.head()?.target(), head);
+    assert!(!local.config.path.join("2026").exists());
+    Ok(())
+}
+
+#[test]
+fn checking_it_out_keeps_the_uncommitted_files() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    remote.push_commit("main", "Catalog.xml", "<Catalog/>\n", "catalog")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    let dir = &local.config.path;
+    std::fs::write(dir.join("draft.txt"), "not committed\n").unwrap();
+
+    let orphan = repo.branch_create_orphan("notes", "start the notes", NOTES, true)?;
+
+    let raw = Repository::open(dir)?;
+    assert_eq!(raw.head()?.name(), Some("refs/heads/notes"));
+    assert_eq!(std::fs::read_to_string(dir.join("2026/10/1.0.md")).unwrap(), "First release\n");
+    assert_eq!(std::fs::read_to_string(dir.join("draft.txt")).unwrap(), "not committed\n");
+    // main's files are no part of the new history
+    assert!(!dir.join("Catalog.xml").exists());
+
+    std::fs::write(dir.join("2026/10/1.1.md"), "Second release\n").unwrap();
+    repo.add(["2026/10/1.1.md"])?;
+    let next = repo.commit("1.1")?;
+    assert_eq!(raw.find_commit(next)?.parent_ids().collect::<Vec<_>>(), [orphan]);
+    Ok(())
+}
+
+#[test]
+fn a_modified_file_in_the_way_fails_without_creating_the_branch() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    remote.push_commit("main", "Catalog.xml", "<Catalog/>\n", "catalog")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    let dir = &local.config.path;
+    std::fs::write(dir.join("Catalog.xml"), "<Catalog changed/>\n").unwrap();
+
+    let e = repo
+        .branch_create_orphan("notes", "start the notes", NOTES, true)
+        .expect_err("a modified file is in the way");
+
+    assert_eq!(e.code(), ErrorCode::Conflict, "{e}");
+    let raw = Repository::open(dir)?;
+    assert!(raw.find_reference("refs/heads/notes").is_err());
+    assert_eq!(raw.head()?.name(), Some("refs/heads/main"));
+    assert_eq!(std::fs::read_to_string(dir.join("Catalog.xml")).unwrap(), "<Catalog changed/>\n");
+    Ok(())
+}
+
+#[test]
+fn an_existing_branch_is_refused() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+
+    let e = Repo::open(&local.config)?
+        .branch_create_orphan("main", "start over", NOTES, false)
+        .expect_err("main exists");
+
+    assert_eq!(e.code(), ErrorCode::Exists, "{e}");
+    Ok(())
+}
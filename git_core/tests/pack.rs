@@ -0,0 +1,97 @@
+//! The pack a push sends, see `git_core::git_pack`.
+
+use std::{num::NonZeroUsize, thread};
+
+use git_core::{
+    PushReport,
+    Repo,
+    testing::{SimulatedRemote, Workspace},
+};
+use git2::{ConfigLevel, ErrorCode, Oid, Repository};
+
+/// Commits `forms` generated forms of about `size` bytes each, much alike as the
+/// exported forms of a configuration are.
+fn commit_forms(local: &Workspace, forms: usize, size: usize) -> Result<Oid, git2::Error> {
+    let repo = Repo::open(&local.config)?;
+    let dir = local.config.path.join("Forms");
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut paths = Vec::new();
+    for form in 0..forms {
+        let mut xml = format!("<Form name=\"Form{form}\">\n");
+        let mut item = 0;
+        while xml.len() < size {
+            xml.push_str(&format!(
+                "  <InputField name=\"Field{item}\" id=\"{}\"><DataPath>Object.Field{item}</DataPath></InputField>\n",
+                form * 7919 + item
+            ));
+            item += 1;
+        }
+        xml.push_str("</Form>\n");
+        let path = format!("Forms/Form{form}.xml");
+        std::fs::write(dir.join(format!("Form{form}.xml")), xml).unwrap();
+        paths.push(path);
+    }
+    repo.add(paths.iter().map(String::as_str))?;
+    repo.commit("add the forms")
+}
+
+#[test]
+fn the_threshold_only_lasts_for_the_push() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let mut local = remote.clone_workspace()?;
+    local.config.big_file_threshold = Some(1024);
+    let raw = Repository::open(&local.config.path)?;
+    raw.config()?.set_i64("core.bigFileThreshold", 4096)?;
+    let commit = commit_forms(&local, 3, 4096)?;
+
+    Repo::open(&local.config)?.push()?;
+
+    let config = raw.config()?.open_level(ConfigLevel::Local)?;
+    assert_eq!(config.get_i64("core.bigFileThreshold")?, 4096);
+    assert!(config.get_i64("pack.deltaCacheSize").is_err());
+    assert_eq!(remote.ref_target("refs/heads/main"), Some(commit));
+    Ok(())
+}
+
+#[test]
+fn a_failed_push_restores_the_settings_too() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let mut local = remote.clone_workspace()?;
+    local.config.big_file_threshold = Some(1024);
+    commit_forms(&local, 1, 4096)?;
+    remote.push_commit("main", "theirs.txt", "theirs\n", "pushed meanwhile")?;
+
+    let e = Repo::open(&local.config)?.push().expect_err("the remote moved on");
+    assert_eq!(e.code(), ErrorCode::NotFastForward, "{e}");
+
+    let config = Repository::open(&local.config.path)?.config()?.open_level(ConfigLevel::Local)?;
+    assert!(config.get_i64("core.bigFileThreshold").is_err());
+    assert!(config.get_i64("pack.deltaCacheSize").is_err());
+    Ok(())
+}
+
+/// How long pushing 200 forms of 64 KiB to a git daemon takes packed by one thread and by
+/// all of them:
+/// `cargo test -p git_core --release --test pack -- --ignored --nocapture`.
+#[test]
+#[ignore = "benchmark"]
+fn benchmark_pack_threads() -> Result<(), git2::Error> {
+    let cores = thread::available_parallelism().map_or(4, NonZeroUsize::get);
+    let mut threads = vec![1, 2, cores];
+    threads.sort_unstable();
+    threads.dedup();
+    for threads in threads {
+        let remote = SimulatedRemote::new("main")?;
+        let daemon = remote.serve()?;
+        let mut local = remote.clone_workspace()?;
+        Repository::open(&local.config.path)?.remote_set_url("origin", daemon.url())?;
+        local.config.pack_threads = u32::try_from(threads).unwrap();
+        commit_forms(&local, 200, 64 * 1024)?;
+
+        let report: PushReport = Repo::open(&local.config)?.push()?;
+
+        println!("{threads:>3} threads: {report}");
+        assert!(remote.ref_target("refs/heads/main").is_some());
+    }
+    Ok(())
+}
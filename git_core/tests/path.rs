@@ -0,0 +1,132 @@
+//! Catalogs on Windows shares, long paths and paths typed with either separator. A
+//! `\\?\` prefixed local path stands in for a share, the tests can't count on one.
+//! Unix file names that aren't UTF-8, which Windows can't have.
+
+#[cfg(windows)]
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+#[cfg(windows)]
+use git_core::{
+    Config,
+    Repo,
+    path::{is_network_path, normalize, with_network_hint},
+    testing::{SimulatedRemote, TempDir},
+};
+#[cfg(unix)]
+use git_core::{Repo, testing::SimulatedRemote};
+#[cfg(windows)]
+use git2::{ErrorClass, ErrorCode};
+
+/// `path` as a `\\?\` long path, separators unified.
+#[cfg(windows)]
+fn verbatim(path: &Path) -> PathBuf {
+    let path = path.to_str().unwrap().replace('/', "\\");
+    PathBuf::from(format!(r"\\?\{}", path.trim_start_matches(r"\\?\")))
+}
+
+#[cfg(windows)]
+#[test]
+fn mixed_separators_are_unified() {
+    assert_eq!(normalize(Path::new(r"C:/repos\project/src")), Path::new(r"C:\repos\project\src"));
+    assert_eq!(
+        normalize(Path::new(r"//fileserver/share\repos/project")),
+        Path::new(r"\\fileserver\share\repos\project")
+    );
+}
+
+#[cfg(windows)]
+#[test]
+fn unc_and_long_path_prefixes_are_kept() {
+    assert_eq!(
+        normalize(Path::new(r"\\fileserver\share\repos")),
+        Path::new(r"\\fileserver\share\repos")
+    );
+    assert_eq!(normalize(Path::new(r"\\?\C:/repos/project")), Path::new(r"\\?\C:\repos\project"));
+    assert_eq!(
+        normalize(Path::new(r"\\?\UNC\fileserver/share/repos")),
+        Path::new(r"\\?\UNC\fileserver\share\repos")
+    );
+}
+
+#[cfg(windows)]
+#[test]
+fn network_paths_are_recognized() {
+    assert!(is_network_path(Path::new(r"\\fileserver\share\repos")));
+    assert!(is_network_path(Path::new("//fileserver/share/repos")));
+    assert!(is_network_path(Path::new(r"\\?\UNC\fileserver\share\repos")));
+    assert!(!is_network_path(Path::new(r"\\?\C:\repos")));
+    assert!(!is_network_path(Path::new(r"C:\repos")));
+    assert!(!is_network_path(Path::new("Z:/mapped/repos")));
+}
+
+#[cfg(windows)]
+#[test]
+fn share_errors_carry_a_hint() {
+    let e = git2::Error::new(ErrorCode::Locked, ErrorClass::Index, "index is locked");
+
+    let hinted = with_network_hint(Path::new(r"\\fileserver\share\repo"), e);
+
+    assert_eq!(hinted.code(), ErrorCode::Locked);
+    assert!(hinted.message().starts_with("index is locked (network catalog"), "{hinted}");
+    let local = git2::Error::new(ErrorCode::Locked, ErrorClass::Index, "index is locked");
+    assert_eq!(with_network_hint(Path::new(r"C:\repo"), local).message(), "index is locked");
+}
+
+#[cfg(windows)]
+#[test]
+fn clone_and_open_through_a_long_path() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let dir = TempDir::new("git-core-path")?;
+    // past the 260 characters Win32 paths are limited to without the prefix
+    let deep = (0..12).fold(verbatim(dir.path()), |path, level| {
+        path.join(format!("catalog-level-{level:02}-of-the-configuration"))
+    });
+    assert!(deep.as_os_str().len() > 300);
+    let config = Config {
+        username: "Test Developer".to_string(),
+        email: "test@example.com".to_string(),
+        path: deep.join("repo"),
+        ..Config::default()
+    };
+
+    let repo = Repo::clone_from(&remote.url(), &config)?;
+    std::fs::write(config.path.join("a.txt"), "deep\n").unwrap();
+    repo.add(["a.txt"])?;
+    repo.commit("a commit deep down")?;
+
+    // typed with forward slashes, the prefix still holds
+    let mixed = Config {
+        path: PathBuf::from(
+            config.path.to_str().unwrap().replace('\\', "/").replacen("//?/", r"\\?\", 1),
+        ),
+        ..config.clone()
+    };
+    let reopened = Repo::open(&mixed)?;
+    assert_eq!(reopened.resolve_commit("HEAD")?, repo.resolve_commit("HEAD")?);
+    let settings = git2::Repository::open(&config.path)?.config()?;
+    assert!(settings.get_bool("core.longpaths")?);
+    assert!(settings.get_bool("core.protectNTFS")?);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn non_utf8_names_keep_their_readable_parts() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    for name in [&b"caf\xe9.xml"[..], b"na\xefve.xml"] {
+        std::fs::write(local.config.path.join(OsStr::from_bytes(name)), "<Form/>\n").unwrap();
+    }
+
+    let status = repo.status()?;
+
+    let mut untracked: Vec<_> =
+        status.untracked.iter().map(|file| file.new_file.as_str()).collect();
+    untracked.sort();
+    assert_eq!(untracked, ["caf\u{FFFD}.xml", "na\u{FFFD}ve.xml"]);
+    assert!(status.to_string().contains("caf\u{FFFD}.xml"), "{status}");
+    Ok(())
+}
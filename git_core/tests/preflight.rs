@@ -0,0 +1,235 @@
+//! Every blocking issue [`Repo::preflight`] reports is the error the operation itself
+//! fails with, see `git_core::git_preflight`.
+
+use std::any::Any;
+
+use git_core::{
+    Config,
+    OperationKind,
+    Repo,
+    testing::{SimulatedRemote, TempDir},
+};
+use git2::{Repository, Signature};
+
+/// A repository set up for a case, with whatever has to outlive it.
+struct Fixture {
+    config: Config,
+    _keep: Vec<Box<dyn Any>>,
+}
+
+/// A clone with a change staged, ready to commit and push.
+fn clone_with_staged_change() -> Result<Fixture, git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    std::fs::write(local.config.path.join("a.txt"), "a\n").unwrap();
+    Repo::open(&local.config)?.add(["a.txt"])?;
+    Ok(Fixture {
+        config: local.config.clone(),
+        _keep: vec![Box::new(remote), Box::new(local)],
+    })
+}
+
+/// A repository made by command-line git, on `master`, without a remote.
+fn fresh_repository(commit: bool) -> Result<Fixture, git2::Error> {
+    let dir = TempDir::new("git-core-preflight")?;
+    let config = Config {
+        username: "Test Developer".to_string(),
+        email: "test@example.com".to_string(),
+        path: dir.path().join("repo"),
+        ..Config::default()
+    };
+    let repo = Repository::init(&config.path)?;
+    std::fs::write(config.path.join("a.txt"), "a\n").unwrap();
+    let mut index = repo.index()?;
+    index.add_path("a.txt".as_ref())?;
+    index.write()?;
+    if commit {
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let author = Signature::now("Test Developer", "test@example.com")?;
+        repo.commit(Some("HEAD"), &author, &author, "initial commit", &tree, &[])?;
+    }
+    Ok(Fixture {
+        config,
+        _keep: vec![Box::new(dir)],
+    })
+}
+
+struct Case {
+    name: &'static str,
+    operation: OperationKind,
+    /// The code of the single blocking issue, `None` when the operation goes through.
+    code: Option<&'static str>,
+    setup: fn() -> Result<Fixture, git2::Error>,
+}
+
+const CASES: &[Case] = &[
+    Case {
+        name: "ready to commit",
+        operation: OperationKind::Commit,
+        code: None,
+        setup: clone_with_staged_change,
+    },
+    Case {
+        name: "ready to push",
+        operation: OperationKind::Push,
+        code: None,
+        setup: || {
+            let fixture = clone_with_staged_change()?;
+            Repo::open(&fixture.config)?.commit("change")?;
+            Ok(fixture)
+        },
+    },
+    Case {
+        name: "read-only",
+        operation: OperationKind::Commit,
+        code: Some("read_only"),
+        setup: || {
+            let mut fixture = clone_with_staged_change()?;
+            fixture.config.read_only = true;
+            Ok(fixture)
+        },
+    },
+    Case {
+        name: "no identity",
+        operation: OperationKind::Commit,
+        code: Some("identity"),
+        setup: || {
+            let mut fixture = clone_with_staged_change()?;
+            fixture.config.username = String::new();
+            Ok(fixture)
+        },
+    },
+    Case {
+        name: "conflicts left",
+        operation: OperationKind::Commit,
+        code: Some("conflicts"),
+        setup: || {
+            let remote = SimulatedRemote::new("main")?;
+            remote.push_commit("other", "README.md", "theirs\n", "their change")?;
+            let local = remote.clone_workspace()?;
+            std::fs::write(local.config.path.join("README.md"), "ours\n").unwrap();
+            let repo = Repo::open(&local.config)?;
+            repo.add(["README.md"])?;
+            repo.commit("our change")?;
+            let raw = Repository::open(&local.config.path)?;
+            let theirs = raw.find_reference("refs/remotes/origin/other")?;
+            raw.merge(&[&raw.reference_to_annotated_commit(&theirs)?], None, None)?;
+            Ok(Fixture {
+                config: local.config.clone(),
+                _keep: vec![Box::new(remote), Box::new(local)],
+            })
+        },
+    },
+    Case {
+        name: "first commit on another branch than the default",
+        operation: OperationKind::Commit,
+        code: Some("default_branch"),
+        setup: || {
+            let mut fixture = fresh_repository(false)?;
+            fixture.config.default_branch = Some("main".to_string());
+            Ok(fixture)
+        },
+    },
+    Case {
+        name: "staged file locked by someone else",
+        operation: OperationKind::Commit,
+        code: Some("locked"),
+        setup: || {
+            let remote = SimulatedRemote::new("main")?;
+            let other = remote.clone_workspace()?;
+            let other_config = Config {
+                username: "Other Developer".to_string(),
+                ..other.config.clone()
+            };
+            Repo::open(&other_config)?.lock_file("README.md", "Other Developer")?;
+            let local = remote.clone_workspace()?;
+            let config = Config {
+                block_locked_commits: true,
+                ..local.config.clone()
+            };
+            std::fs::write(config.path.join("README.md"), "ours\n").unwrap();
+            Repo::open(&config)?.add(["README.md"])?;
+            Ok(Fixture {
+                config,
+                _keep: vec![Box::new(remote), Box::new(other), Box::new(local)],
+            })
+        },
+    },
+    Case {
+        name: "detached HEAD",
+        operation: OperationKind::Push,
+        code: Some("detached_head"),
+        setup: || {
+            let fixture = clone_with_staged_change()?;
+            let raw = Repository::open(&fixture.config.path)?;
+            raw.set_head_detached(raw.head()?.peel_to_commit()?.id())?;
+            Ok(fixture)
+        },
+    },
+    Case {
+        name: "no commits",
+        operation: OperationKind::Push,
+        code: Some("no_commits"),
+        setup: || fresh_repository(false),
+    },
+    Case {
+        name: "no remote",
+        operation: OperationKind::Push,
+        code: Some("no_remote"),
+        setup: || fresh_repository(true),
+    },
+    Case {
+        name: "unreachable remote",
+        operation: OperationKind::Push,
+        code: Some("unreachable"),
+        setup: || {
+            let fixture = clone_with_staged_change()?;
+            Repo::open(&fixture.config)?.commit("change")?;
+            let missing = fixture.config.path.with_file_name("missing");
+            let url = format!("file://{}", missing.display());
+            Repository::open(&fixture.config.path)?.remote_set_url("origin", &url)?;
+            Ok(fixture)
+        },
+    },
+    Case {
+        name: "first push of another branch than the remote's default",
+        operation: OperationKind::Push,
+        code: Some("default_branch"),
+        setup: || {
+            let remote = SimulatedRemote::new("main")?;
+            let mut fixture = fresh_repository(true)?;
+            Repo::open(&fixture.config)?.add_remote("origin", &remote.url())?;
+            fixture._keep.push(Box::new(remote));
+            Ok(fixture)
+        },
+    },
+];
+
+#[test]
+fn blocking_issues_are_the_errors_of_the_operation() -> Result<(), git2::Error> {
+    for case in CASES {
+        let fixture = (case.setup)()?;
+        let repo = Repo::open(&fixture.config)?;
+
+        let blocking = repo
+            .preflight(case.operation)
+            .into_iter()
+            .filter(|issue| issue.blocking)
+            .map(|issue| (issue.code, issue.message))
+            .collect::<Vec<_>>();
+        let result = match case.operation {
+            OperationKind::Commit => repo.commit("change").map(drop),
+            OperationKind::Push => repo.push().map(drop),
+            OperationKind::CommitAndPush => unreachable!("covered by the other two"),
+        };
+
+        match (case.code, result) {
+            (None, Ok(())) => assert_eq!(blocking, [], "{}", case.name),
+            (Some(code), Err(e)) =>
+                assert_eq!(blocking, [(code, e.message().to_string())], "{}", case.name),
+            (None, Err(e)) => panic!("{}: the operation failed: {e}", case.name),
+            (Some(code), Ok(())) => panic!("{}: the operation ignored {code}", case.name),
+        }
+    }
+    Ok(())
+}
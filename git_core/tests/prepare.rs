@@ -0,0 +1,71 @@
+//! Committing in two steps, see `git_core::git_prepare`.
+
+use git_core::{Repo, testing::SimulatedRemote};
+use git2::ErrorCode;
+
+#[test]
+fn finish_makes_the_prepared_commit() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    let head = repo.resolve_commit("HEAD")?;
+    std::fs::write(local.config.path.join("a.txt"), "a\n").unwrap();
+    repo.add(["a.txt"])?;
+
+    let prepared = repo.prepare_commit("add a")?;
+    assert_eq!(prepared.files, ["a.txt"]);
+    assert_eq!(prepared.parent, Some(head));
+    // nothing is committed yet and the index stays usable
+    assert_eq!(repo.resolve_commit("HEAD")?, head);
+    std::fs::write(local.config.path.join("b.txt"), "b\n").unwrap();
+    repo.add(["b.txt"])?;
+    let prepared = repo.prepare_commit("add a and b")?;
+
+    let commit = repo.finish_commit(&prepared)?;
+
+    assert_eq!(repo.resolve_commit("HEAD")?, commit);
+    assert_eq!(repo.parents("HEAD")?, [head]);
+    assert_eq!(repo.commit_raw_message("HEAD")?, "add a and b");
+    assert_eq!(repo.diff_names(Some("HEAD~1"), Some("HEAD"))?, ["a.txt", "b.txt"]);
+    Ok(())
+}
+
+#[test]
+fn finish_fails_when_the_index_changed() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    let head = repo.resolve_commit("HEAD")?;
+    std::fs::write(local.config.path.join("a.txt"), "a\n").unwrap();
+    repo.add(["a.txt"])?;
+    let prepared = repo.prepare_commit("add a")?;
+
+    // staged by another window while 1C inspected the commit
+    std::fs::write(local.config.path.join("a.txt"), "changed\n").unwrap();
+    repo.add(["a.txt"])?;
+    let e = repo.finish_commit(&prepared).expect_err("the index changed");
+
+    assert_eq!(e.code(), ErrorCode::Modified, "{e}");
+    assert!(e.message().contains("index changed"), "{e}");
+    assert_eq!(repo.resolve_commit("HEAD")?, head);
+    Ok(())
+}
+
+#[test]
+fn finish_fails_when_head_moved() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    std::fs::write(local.config.path.join("a.txt"), "a\n").unwrap();
+    repo.add(["a.txt"])?;
+    let prepared = repo.prepare_commit("add a")?;
+
+    // the same tree committed meanwhile
+    let moved = repo.commit("add a elsewhere")?;
+    let e = repo.finish_commit(&prepared).expect_err("HEAD moved");
+
+    assert_eq!(e.code(), ErrorCode::Modified, "{e}");
+    assert!(e.message().contains("HEAD moved"), "{e}");
+    assert_eq!(repo.resolve_commit("HEAD")?, moved);
+    Ok(())
+}
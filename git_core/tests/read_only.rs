@@ -0,0 +1,192 @@
+//! A [`Config::read_only`] clone refuses every operation that changes it, before touching
+//! anything, while reading and fetching keep working.
+
+use std::path::Path;
+
+use git_core::{
+    Config,
+    OperationKind,
+    PreparedCommit,
+    PullResult,
+    PushAtomicity,
+    ReadOnlyRefusal,
+    Repo,
+    testing::{SimulatedRemote, TempDir},
+};
+use git2::{ErrorCode, Oid, Repository};
+
+/// Everything an operation could change: the refs, HEAD, the index, the config and the
+/// working tree files.
+fn snapshot(path: &Path) -> Result<Vec<String>, git2::Error> {
+    let repo = Repository::open(path)?;
+    let mut state = Vec::new();
+    for reference in repo.references()? {
+        let reference = reference?;
+        state.push(format!("{} {:?}", reference.name().unwrap_or_default(), reference.target()));
+    }
+    state.sort();
+    let head = repo.find_reference("HEAD")?;
+    state.push(format!("HEAD {:?} {:?}", head.symbolic_target(), head.target()));
+    for file in ["index", "config"] {
+        let content = std::fs::read(repo.path().join(file)).unwrap_or_default();
+        state.push(format!("{file} {}", String::from_utf8_lossy(&content)));
+    }
+    let mut files = std::fs::read_dir(path)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    files.sort();
+    state.push(format!("files {files:?}"));
+    Ok(state)
+}
+
+type Operation = fn(&mut Repo<'_>) -> Result<(), git2::Error>;
+
+/// Every public method of [`Repo`] that writes to the repository or the server.
+const MUTATING: &[(&str, Operation)] = &[
+    ("add", |repo| repo.add(["new.txt"]).map(drop)),
+    ("add_all", |repo| repo.add_all().map(drop)),
+    ("commit", |repo| repo.commit("change").map(drop)),
+    ("commit_with_coauthors", |repo| {
+        let coauthors = [("Other".to_string(), "other@example.com".to_string())];
+        repo.commit_with_coauthors("change", &coauthors).map(drop)
+    }),
+    ("commit_signed_off", |repo| repo.commit_signed_off("change").map(drop)),
+    ("add_and_amend", |repo| repo.add_and_amend(&["new.txt"]).map(drop)),
+    ("prepare_commit", |repo| repo.prepare_commit("change").map(drop)),
+    ("finish_commit", |repo| {
+        let prepared = PreparedCommit {
+            tree: Oid::zero(),
+            parent: None,
+            message: "change".to_string(),
+            files: Vec::new(),
+        };
+        repo.finish_commit(&prepared).map(drop)
+    }),
+    ("write_identity", |repo| repo.write_identity("Other", "other@example.com")),
+    ("checkout", |repo| repo.checkout("feature")),
+    ("checkout_local", |repo| repo.checkout_local("feature")),
+    ("checkout_paths", |repo| repo.checkout_paths("feature", &["f.txt"]).map(drop)),
+    ("checkout_paths_from", |repo| repo.checkout_paths_from("feature", &["f.txt"]).map(drop)),
+    ("checkout_tag", |repo| repo.checkout_tag("v1").map(drop)),
+    ("discard_changes", |repo| repo.discard_changes(&["README.md"])),
+    ("stage_lines", |repo| repo.stage_lines("README.md", &[(1, 1)]).map(drop)),
+    ("stash_save_paths", |repo| repo.stash_save_paths(None, &["README.md"]).map(drop)),
+    ("pull", |repo| repo.pull("main").map(drop)),
+    ("merge", |repo| repo.merge("feature", None)),
+    ("continue_operation", |repo| repo.continue_operation().map(drop)),
+    ("push", |repo| repo.push().map(drop)),
+    ("push_to", |repo| repo.push_to("main", "review/main", None)),
+    ("push_refspecs", |repo| {
+        repo.push_refspecs("origin", &["main"], PushAtomicity::NonAtomic).map(drop)
+    }),
+    ("prune", |repo| repo.prune("origin").map(drop)),
+    ("add_remote", |repo| repo.add_remote("mirror", "https://example.com/mirror.git")),
+    ("set_remote_push_url", |repo| {
+        repo.set_remote_push_url("origin", "https://example.com/push.git")
+    }),
+    ("set_push_remote", |repo| repo.set_push_remote("main", "origin")),
+    ("rename_current_branch", |repo| repo.rename_current_branch("renamed")),
+    ("branch_create_orphan", |repo| {
+        repo.branch_create_orphan("orphan", "orphan", &[("o.txt", b"o\n")], false).map(drop)
+    }),
+    ("delete_stale_branches", |repo| repo.delete_stale_branches(false).map(drop)),
+    ("lock_file", |repo| repo.lock_file("README.md", "Test Developer")),
+    ("unlock_file", |repo| repo.unlock_file("README.md")),
+    ("note_add", |repo| repo.note_add("HEAD", "review", "ok", false).map(drop)),
+    ("note_remove", |repo| repo.note_remove("HEAD", "review")),
+    ("bisect_start", |repo| repo.bisect_start("HEAD~1", "HEAD").map(drop)),
+    ("bisect_mark", |repo| repo.bisect_mark(Oid::zero(), true).map(drop)),
+    ("bisect_reset", |repo| repo.bisect_reset()),
+    ("set_sparse_checkout", |repo| repo.set_sparse_checkout(&["docs".to_string()]).map(drop)),
+    ("bundle_import", |repo| repo.bundle_import(Path::new("missing.bundle")).map(drop)),
+    ("rebuild_index", |repo| repo.rebuild_index().map(drop)),
+    ("clear_locks", |repo| repo.clear_locks().map(drop)),
+];
+
+#[test]
+fn every_mutating_operation_is_refused() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    remote.push_commit("feature", "f.txt", "f\n", "feature work")?;
+    let local = remote.clone_workspace()?;
+    let writable = Repository::open(&local.config.path)?;
+    let head = writable.head()?.peel_to_commit()?;
+    writable.tag_lightweight("v1", head.as_object(), false)?;
+    std::fs::write(local.config.path.join("README.md"), "changed\n").unwrap();
+    std::fs::write(local.config.path.join("new.txt"), "new\n").unwrap();
+    // something to fast-forward to, so pull gets to its write phase
+    remote.push_commit("main", "a.txt", "theirs\n", "their change")?;
+    Repo::open(&local.config)?.prune_report()?;
+
+    let config = Config {
+        read_only: true,
+        ..local.config.clone()
+    };
+    let mut repo = Repo::open(&config)?;
+    let before = snapshot(&config.path)?;
+
+    for (name, operation) in MUTATING {
+        let e = operation(&mut repo).expect_err(name);
+        assert_eq!(ReadOnlyRefusal::from_error(&e), Some(ReadOnlyRefusal), "{name}: {e}");
+        assert_eq!(snapshot(&config.path)?, before, "{name} changed the repository");
+    }
+    Ok(())
+}
+
+#[test]
+fn reading_and_fetching_keep_working() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let config = Config {
+        read_only: true,
+        ..local.config.clone()
+    };
+    let repo = Repo::open(&config)?;
+    std::fs::write(config.path.join("README.md"), "changed\n").unwrap();
+
+    let theirs = remote.push_commit("main", "a.txt", "theirs\n", "their change")?;
+    repo.prune_report()?;
+
+    assert_eq!(repo.status()?.not_staged.len(), 1);
+    assert_eq!(repo.current_branch()?.local_name(), "main");
+    assert_eq!(repo.resolve_commit("origin/main")?, theirs);
+    assert_eq!(repo.incoming("main")?.len(), 1);
+    assert!(repo.diff(None, None)?.contains("+changed"));
+    assert!(repo.preflight(OperationKind::Commit).iter().any(|issue| issue.blocking));
+    // nothing to write when already up to date
+    let writable = Repo::open(&local.config)?;
+    writable.pull("main")?;
+    assert!(matches!(repo.pull("main")?, PullResult::UpToDate));
+    Ok(())
+}
+
+#[test]
+fn init_is_refused() -> Result<(), git2::Error> {
+    let dir = TempDir::new("git-core-read-only")?;
+    let config = Config {
+        path: dir.path().join("repo"),
+        read_only: true,
+        ..Config::default()
+    };
+
+    let e = Repo::init(&config, None).err().expect("init writes a repository");
+
+    assert_eq!(ReadOnlyRefusal::from_error(&e), Some(ReadOnlyRefusal), "{e}");
+    assert!(!config.path.exists());
+    Ok(())
+}
+
+#[test]
+fn a_held_lock_file_is_no_refusal() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    std::fs::write(local.config.path.join("new.txt"), "new\n").unwrap();
+    std::fs::write(local.config.path.join(".git/index.lock"), "").unwrap();
+
+    let e = repo.add(["new.txt"]).expect_err("another process holds the index");
+
+    assert_eq!(e.code(), ErrorCode::Locked, "{e}");
+    assert_eq!(ReadOnlyRefusal::from_error(&e), None, "{e}");
+    Ok(())
+}
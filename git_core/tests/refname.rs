@@ -0,0 +1,95 @@
+//! Branch names as typed into a form, see `git_core::refname`.
+
+use git_core::{Repo, refname, testing::SimulatedRemote};
+use git2::{ErrorCode, Reference};
+
+/// The classic ways a typed name goes wrong, what is reported and the name suggested.
+const INVALID: &[(&str, &str, &str)] = &[
+    ("", "empty", "new-branch"),
+    ("my feature", "spaces", "my-feature"),
+    ("  padded  ", "spaces", "padded"),
+    ("tab\tname", "spaces", "tab-name"),
+    ("bell\u{7}", "control characters", "bell"),
+    ("fix~1", "'~'", "fix-1"),
+    ("fix^2", "'^'", "fix-2"),
+    ("release:1.0", "':'", "release-1.0"),
+    ("what?", "'?'", "what"),
+    ("feature*", "'*'", "feature"),
+    ("[wip]fix", "'['", "wip]fix"),
+    ("back\\slash", "'\\'", "back-slash"),
+    ("a..b", "'..'", "a.b"),
+    ("feature@{1}", "'@{'", "feature-1}"),
+    ("@", "reserved", "new-branch"),
+    ("HEAD", "reserved", "new-branch"),
+    ("-flag", "starts with '-'", "flag"),
+    ("/feature", "'/'", "feature"),
+    ("feature/", "'/'", "feature"),
+    ("team//feature", "'/'", "team/feature"),
+    ("release.", "ends with '.'", "release"),
+    ("release...", "'..'", "release"),
+    (".hidden", "starts with '.'", "hidden"),
+    ("team/.hidden", "starts with '.'", "team/hidden"),
+    ("feature.lock", "'.lock'", "feature"),
+    ("team.lock/feature", "'.lock'", "team/feature"),
+    ("Задача 42.", "spaces", "Задача-42"),
+];
+
+#[test]
+fn classic_invalid_names_are_explained() {
+    for (name, reason, suggestion) in INVALID {
+        let problem = refname::problem(name).unwrap_or_else(|| panic!("'{name}' passed"));
+        assert!(problem.contains(reason), "'{name}': {problem}");
+        assert_eq!(refname::suggest(name, false), *suggestion, "'{name}'");
+        assert!(Reference::is_valid_name(&format!("refs/heads/{suggestion}")), "{suggestion}");
+        assert_eq!(refname::problem(suggestion), None, "'{suggestion}'");
+    }
+}
+
+#[test]
+fn valid_names_pass_unchanged() {
+    let valid = ["main", "feature/JIRA-123", "release-1.0", "v1.2.3", "задача-42", "a.b/c_d", "@x"];
+    for name in valid {
+        assert_eq!(refname::problem(name), None, "'{name}'");
+        assert_eq!(refname::suggest(name, false), name);
+        assert!(refname::check(name, false).is_ok());
+    }
+}
+
+#[test]
+fn transliteration_suggests_latin_names() {
+    assert_eq!(refname::suggest("Задача 42", true), "Zadacha-42");
+    assert_eq!(refname::suggest("исправление/Счёт-фактура", true), "ispravlenie/Schet-faktura");
+    assert_eq!(refname::suggest("Їжак", true), "Yizhak");
+    // valid already, but not ASCII
+    assert_eq!(refname::suggest("задача", true), "zadacha");
+    assert_eq!(refname::suggest("задача", false), "задача");
+}
+
+#[test]
+fn check_names_the_problem_and_the_suggestion() {
+    let e = refname::check("my feature", false).unwrap_err();
+
+    assert_eq!(e.code(), ErrorCode::InvalidSpec);
+    assert_eq!(
+        e.message(),
+        "'my feature' is not a valid branch name: it contains spaces, try 'my-feature'"
+    );
+}
+
+#[test]
+fn branches_with_invalid_names_are_not_created() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+
+    let e = repo.rename_current_branch("my feature").expect_err("the name has a space");
+    assert_eq!(e.code(), ErrorCode::InvalidSpec, "{e}");
+    assert_eq!(repo.current_branch()?.local_name(), "main");
+
+    let e = repo
+        .branch_create_orphan("docs..old", "docs", &[("a.txt", b"a\n")], false)
+        .expect_err("the name has '..'");
+    assert_eq!(e.code(), ErrorCode::InvalidSpec, "{e}");
+    assert!(repo.resolve_commit("docs.old").is_err());
+    Ok(())
+}
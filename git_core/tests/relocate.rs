@@ -0,0 +1,53 @@
+//! A repository moved between calls is found again and reopened, see
+//! `git_core::git_relocate`.
+
+use git_core::{Config, Repo, RepositoryMoved, testing::SimulatedRemote};
+use git2::ErrorCode;
+
+#[test]
+fn a_moved_repository_is_found_and_reopened_clean() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let id = Repo::open(&local.config)?.repository_id()?;
+    assert!(id.is_some());
+    let moved = local.config.path.with_file_name("moved");
+    std::fs::rename(&local.config.path, &moved).unwrap();
+
+    let e = Repo::open(&local.config).err().expect("the catalog is gone");
+    assert_eq!(e.code(), ErrorCode::NotFound, "{e}");
+    let found = RepositoryMoved::search(&local.config.path, id);
+    assert_eq!(found.candidates, std::slice::from_ref(&moved));
+    assert!(found.to_string().contains(&format!("it may now be at '{}'", moved.display())));
+
+    let config = Config {
+        path: moved.clone(),
+        ..local.config.clone()
+    };
+    let repo = Repo::relocate(&config, &moved, id)?;
+
+    let status = repo.status()?;
+    assert_eq!(status.branch_name, "main");
+    assert!(status.staged.is_empty() && status.not_staged.is_empty(), "{status}");
+    assert!(status.untracked.is_empty(), "{status}");
+    assert_eq!(repo.repository_id()?, id);
+    Ok(())
+}
+
+#[test]
+fn another_repository_is_refused() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let id = Repo::open(&local.config)?.repository_id()?;
+    // a history of its own, `SimulatedRemote::new` starts every one with the same commit
+    let other = SimulatedRemote::empty("main")?;
+    other.push_commit("main", "other.txt", "other\n", "another history")?;
+    let elsewhere = other.clone_workspace()?;
+
+    let e = Repo::relocate(&local.config, &elsewhere.config.path, id)
+        .err()
+        .expect("a different repository");
+
+    assert_eq!(e.code(), ErrorCode::Invalid, "{e}");
+    assert!(e.message().ends_with("holds a different repository"), "{e}");
+    Ok(())
+}
@@ -0,0 +1,290 @@
+//! Push, pull and fetch against simulated remotes, see `git_core::testing`. Network
+//! features ship with tests here.
+
+use git_core::{
+    ChangeKind,
+    PullResult,
+    PushAtomicity,
+    Repo,
+    Upstream,
+    testing::{SimulatedRemote, Workspace},
+};
+use git2::{ErrorCode, Oid, Repository};
+
+#[test]
+fn push_updates_the_remote_branch() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+
+    std::fs::write(local.config.path.join("a.txt"), "ours\n").unwrap();
+    repo.add(["a.txt"])?;
+    let ours = repo.commit("local change")?;
+    repo.push()?;
+
+    assert_eq!(remote.ref_target("refs/heads/main"), Some(ours));
+    Ok(())
+}
+
+#[test]
+fn pull_fast_forwards_to_the_fetched_commit() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+
+    let theirs = remote.push_commit("main", "a.txt", "theirs\n", "concurrent change")?;
+    repo.prune_report()?;
+    let PullResult::FastForwarded { new_id, files, .. } = repo.pull("main")? else {
+        panic!("expected a fast-forward");
+    };
+
+    assert_eq!(new_id, theirs);
+    assert_eq!(repo.resolve_commit("HEAD")?, theirs);
+    assert_eq!(files.iter().map(|file| file.path.as_str()).collect::<Vec<_>>(), ["a.txt"]);
+    assert_eq!(std::fs::read_to_string(local.config.path.join("a.txt")).unwrap(), "theirs\n");
+    Ok(())
+}
+
+/// A clone whose `main` is one pull behind a commit adding, changing and deleting a file.
+fn behind_a_three_file_commit(remote: &SimulatedRemote) -> Result<Workspace, git2::Error> {
+    remote.push_commit("main", "old.txt", "old\n", "old file")?;
+    let local = remote.clone_workspace()?;
+    let other = remote.clone_workspace()?;
+    std::fs::write(other.config.path.join("README.md"), "changed\nand extended\n").unwrap();
+    std::fs::write(other.config.path.join("new.txt"), "one\ntwo\nthree\n").unwrap();
+    std::fs::remove_file(other.config.path.join("old.txt")).unwrap();
+    let raw = Repository::open(&other.config.path)?;
+    let mut index = raw.index()?;
+    index.update_all(["."], None)?;
+    index.add_path("new.txt".as_ref())?;
+    index.write()?;
+    let repo = Repo::open(&other.config)?;
+    repo.commit("touch three files")?;
+    repo.push()?;
+    Repo::open(&local.config)?.prune_report()?;
+    Ok(local)
+}
+
+#[test]
+fn pull_lists_the_files_it_changed() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = behind_a_three_file_commit(&remote)?;
+
+    let PullResult::FastForwarded { files, truncated, .. } =
+        Repo::open(&local.config)?.pull("main")?
+    else {
+        panic!("expected a fast-forward");
+    };
+
+    let files: Vec<_> = files
+        .iter()
+        .map(|file| (file.path.as_str(), file.change, file.insertions, file.deletions))
+        .collect();
+    assert_eq!(
+        files,
+        [
+            ("README.md", ChangeKind::Modified, 2, 1),
+            ("new.txt", ChangeKind::Added, 3, 0),
+            ("old.txt", ChangeKind::Deleted, 0, 1),
+        ]
+    );
+    assert!(!truncated);
+    Ok(())
+}
+
+#[test]
+fn pull_caps_the_file_list() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let mut local = behind_a_three_file_commit(&remote)?;
+    local.config.pull_file_limit = 2;
+
+    let PullResult::FastForwarded { files, truncated, .. } =
+        Repo::open(&local.config)?.pull("main")?
+    else {
+        panic!("expected a fast-forward");
+    };
+
+    assert_eq!(files.len(), 2);
+    assert!(truncated);
+    Ok(())
+}
+
+#[test]
+fn an_upstream_deleted_on_the_server_is_reported() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    remote.push_commit("feature", "f.txt", "f\n", "feature work")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    repo.checkout("feature")?;
+    assert_eq!(repo.current_branch()?.upstream, Upstream::Configured("origin/feature".into()));
+    let head = repo.resolve_commit("HEAD")?;
+
+    remote.delete_branch("feature")?;
+    assert_eq!(repo.prune_report()?, ["refs/remotes/origin/feature"]);
+
+    let branch = repo.current_branch()?;
+    assert_eq!(branch.local_name(), "feature");
+    assert_eq!(branch.upstream, Upstream::Gone("origin/feature".into()));
+    assert_eq!(branch.upstream_name(), None);
+    let status = repo.status()?;
+    assert_eq!(status.upstream_gone.as_deref(), Some("origin/feature"));
+    assert!(status.to_string().contains("\nupstream origin/feature is gone"), "{status}");
+    let PullResult::UpstreamGone { upstream } = repo.pull("feature")? else {
+        panic!("expected the upstream to be gone");
+    };
+    assert_eq!(upstream, "origin/feature");
+    assert_eq!(repo.resolve_commit("HEAD")?, head);
+    Ok(())
+}
+
+#[test]
+fn fetch_prunes_branches_deleted_on_the_server() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    remote.push_commit("feature", "f.txt", "f\n", "feature work")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+
+    remote.delete_branch("feature")?;
+    let pruned = repo.prune_report()?;
+
+    assert_eq!(pruned, ["refs/remotes/origin/feature"]);
+    assert!(repo.resolve_commit("origin/feature").is_err());
+    Ok(())
+}
+
+#[test]
+fn an_unreachable_remote_does_not_stop_the_others() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let fork = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let raw = Repository::open(&local.config.path)?;
+    let missing = local.config.path.with_file_name("no-such-remote.git");
+    raw.remote("broken", &missing.to_string_lossy())?;
+    raw.remote("fork", &fork.url())?;
+    let ours = remote.push_commit("main", "a.txt", "ours\n", "on origin")?;
+    let theirs = fork.push_commit("main", "b.txt", "theirs\n", "on the fork")?;
+    let repo = Repo::open(&local.config)?;
+
+    let report = repo.fetch_all_report()?;
+
+    let result =
+        |name: &str| &report.iter().find(|(remote_name, _)| remote_name == name).unwrap().1;
+    assert!(result("broken").is_err());
+    assert_eq!(result("fork").as_ref().unwrap().updated, ["refs/remotes/fork/main"]);
+    assert_eq!(result("origin").as_ref().unwrap().updated, ["refs/remotes/origin/main"]);
+    assert_eq!(repo.resolve_commit("fork/main")?, theirs);
+    assert_eq!(repo.resolve_commit("origin/main")?, ours);
+
+    // the summary fails naming the remote, after fetching the others
+    let theirs = fork.push_commit("main", "b.txt", "again\n", "on the fork again")?;
+    let e = repo.prune_report().expect_err("'broken' can't be fetched");
+    assert!(e.message().starts_with("fetching 'broken' failed: "), "{e}");
+    assert!(!e.message().contains("'fork'"), "{e}");
+    assert_eq!(repo.resolve_commit("fork/main")?, theirs);
+    Ok(())
+}
+
+/// `main` fast-forwards the remote while `release` doesn't, someone pushed to it meanwhile.
+fn diverged_release(remote: &SimulatedRemote) -> Result<(Workspace, Oid, Oid), git2::Error> {
+    remote.push_commit("release", "r.txt", "r\n", "release work")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    repo.checkout("release")?;
+    std::fs::write(local.config.path.join("r.txt"), "ours\n").unwrap();
+    repo.add(["r.txt"])?;
+    repo.commit("our release fix")?;
+    repo.checkout("main")?;
+    std::fs::write(local.config.path.join("a.txt"), "ours\n").unwrap();
+    repo.add(["a.txt"])?;
+    let main = repo.commit("our main change")?;
+    let theirs = remote.push_commit("release", "r.txt", "theirs\n", "their release fix")?;
+    Ok((local, main, theirs))
+}
+
+#[test]
+fn atomic_push_refuses_a_remote_ref_we_have_not_fetched() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let before = remote.ref_target("refs/heads/main");
+    let (local, _, theirs) = diverged_release(&remote)?;
+    let repo = Repo::open(&local.config)?;
+
+    let e = repo
+        .push_refspecs("origin", &["main", "release"], PushAtomicity::Atomic)
+        .expect_err("their release fix isn't fetched");
+
+    assert_eq!(e.code(), ErrorCode::NotFastForward, "{e}");
+    assert_eq!(
+        e.message(),
+        "atomic push to 'origin' refused, nothing was pushed: \
+         refs/heads/release (the remote has commits we don't, fetch first)"
+    );
+    assert_eq!(remote.ref_target("refs/heads/main"), before);
+    assert_eq!(remote.ref_target("refs/heads/release"), Some(theirs));
+    Ok(())
+}
+
+#[test]
+fn atomic_push_refuses_a_non_fast_forward_and_a_moved_tag() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let before = remote.ref_target("refs/heads/main");
+    let (local, main, theirs) = diverged_release(&remote)?;
+    let raw = Repository::open(&local.config.path)?;
+    let first = raw.find_object(before.unwrap(), None)?;
+    raw.tag_lightweight("v1", &first, false)?;
+    let repo = Repo::open(&local.config)?;
+    repo.push_refspecs("origin", &["v1"], PushAtomicity::NonAtomic)?;
+    raw.tag_lightweight("v1", &raw.find_object(main, None)?, true)?;
+    repo.prune_report()?;
+
+    let e = repo
+        .push_refspecs("origin", &["main", "release", "v1"], PushAtomicity::Atomic)
+        .expect_err("release diverged and v1 moved");
+
+    assert_eq!(
+        e.message(),
+        "atomic push to 'origin' refused, nothing was pushed: \
+         refs/heads/release (non-fast-forward), refs/tags/v1 (the tag already exists)"
+    );
+    assert_eq!(remote.ref_target("refs/heads/main"), before);
+    assert_eq!(remote.ref_target("refs/heads/release"), Some(theirs));
+    assert_eq!(remote.ref_target("refs/tags/v1"), before);
+    Ok(())
+}
+
+#[test]
+fn atomic_push_updates_fast_forwards_and_forced_refs_together() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let (local, main, _) = diverged_release(&remote)?;
+    let raw = Repository::open(&local.config.path)?;
+    raw.tag_lightweight("v2", &raw.find_object(main, None)?, false)?;
+    let repo = Repo::open(&local.config)?;
+    repo.prune_report()?;
+    let ours = repo.resolve_commit("release")?;
+
+    let updated =
+        repo.push_refspecs("origin", &["main", "+release", "v2"], PushAtomicity::Atomic)?;
+
+    assert_eq!(updated, ["refs/heads/main", "refs/heads/release", "refs/tags/v2"]);
+    assert_eq!(remote.ref_target("refs/heads/main"), Some(main));
+    assert_eq!(remote.ref_target("refs/heads/release"), Some(ours));
+    assert_eq!(remote.ref_target("refs/tags/v2"), Some(main));
+    Ok(())
+}
+
+#[test]
+fn non_atomic_push_still_refuses_a_non_fast_forward() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let before = remote.ref_target("refs/heads/main");
+    let (local, _, theirs) = diverged_release(&remote)?;
+    let repo = Repo::open(&local.config)?;
+
+    let e = repo
+        .push_refspecs("origin", &["main", "release"], PushAtomicity::NonAtomic)
+        .expect_err("release isn't a fast-forward");
+
+    // libgit2 checks before sending anything, so main stays too
+    assert_eq!(e.code(), ErrorCode::NotFastForward, "{e}");
+    assert_eq!(remote.ref_target("refs/heads/main"), before);
+    assert_eq!(remote.ref_target("refs/heads/release"), Some(theirs));
+    Ok(())
+}
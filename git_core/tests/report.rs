@@ -0,0 +1,93 @@
+//! Reports streamed into a writer, see `git_core::report`. The allocator of this test
+//! binary tracks the peak of the memory Rust holds, so it runs alone in its own.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use git_core::{Repo, ReportFormat, testing::SimulatedRemote};
+
+struct Counting;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+fn grow(size: usize) {
+    let now = CURRENT.fetch_add(size, Ordering::SeqCst) + size;
+    PEAK.fetch_max(now, Ordering::SeqCst);
+}
+
+// SAFETY: every call is passed on to the system allocator unchanged
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        CURRENT.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new.is_null() {
+            CURRENT.fetch_sub(layout.size(), Ordering::SeqCst);
+            grow(new_size);
+        }
+        new
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// The most memory allocated at once while `f` ran, beyond what was allocated before.
+fn peak_during<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    let base = CURRENT.load(Ordering::SeqCst);
+    PEAK.store(base, Ordering::SeqCst);
+    let result = f();
+    (result, PEAK.load(Ordering::SeqCst) - base)
+}
+
+#[test]
+fn large_diffs_stream_in_bounded_memory() -> Result<(), git2::Error> {
+    const FILES: usize = 60;
+    const LINES: usize = 2000;
+    const BOUND: usize = 2 << 20;
+
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    let content = |version: &str| -> String {
+        (0..LINES).map(|line| format!("{version} line {line:05} of a generated module\n")).collect()
+    };
+    let paths: Vec<_> = (0..FILES).map(|file| format!("Module{file:03}.bsl")).collect();
+    for path in &paths {
+        std::fs::write(local.config.path.join(path), content("old")).unwrap();
+    }
+    repo.add(&paths)?;
+    repo.commit("generated modules")?;
+    for path in &paths {
+        std::fs::write(local.config.path.join(path), content("new")).unwrap();
+    }
+
+    // held as one string the patch alone is many times the bound
+    let (patch, whole) = peak_during(|| repo.diff(None, None));
+    let patch_len = patch?.len();
+    assert!(patch_len > 4 * BOUND, "{patch_len}");
+    assert!(whole > patch_len, "{whole}");
+
+    for format in [ReportFormat::Text, ReportFormat::Json, ReportFormat::Html] {
+        let (written, streamed) =
+            peak_during(|| repo.write_diff(None, None, format, std::io::sink()));
+        let written = usize::try_from(written?).unwrap();
+        assert!(written > patch_len / 2, "{format:?} wrote {written}");
+        assert!(streamed < BOUND, "{format:?} held {streamed} bytes at once");
+    }
+    Ok(())
+}
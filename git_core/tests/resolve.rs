@@ -0,0 +1,106 @@
+//! Revisions as users type them, see `Repo::resolve`.
+
+use git_core::{Repo, ResolvedRev, testing::SimulatedRemote};
+use git2::{ErrorCode, ObjectType, Repository, Signature};
+
+#[test]
+fn branches_tags_and_relative_revisions_resolve() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let first =
+        remote.push_commit("main", "Form.xml", "<Form/>\n", "add the form\n\nwith a body")?;
+    let second = remote.push_commit("main", "Form.xml", "<Form changed/>\n", "change the form")?;
+    let local = remote.clone_workspace()?;
+    let raw = Repository::open(&local.config.path)?;
+    let signature = Signature::now("Test Developer", "test@example.com")?;
+    let tag = raw.tag("v1.0", &raw.find_object(first, None)?, &signature, "release 1.0", false)?;
+    let repo = Repo::open(&local.config)?;
+
+    assert_eq!(
+        repo.resolve("main")?,
+        ResolvedRev {
+            id: second,
+            kind: ObjectType::Commit,
+            ref_name: Some("refs/heads/main".to_string()),
+            summary: Some("change the form".to_string()),
+        }
+    );
+    let head_parent = repo.resolve("HEAD~1")?;
+    assert_eq!((head_parent.id, head_parent.ref_name), (first, None));
+    assert_eq!(head_parent.summary.as_deref(), Some("add the form"));
+    let annotated = repo.resolve("v1.0")?;
+    assert_eq!((annotated.id, annotated.kind), (tag, ObjectType::Tag));
+    assert_eq!(annotated.ref_name.as_deref(), Some("refs/tags/v1.0"));
+    assert_eq!(annotated.summary.as_deref(), Some("release 1.0"));
+    assert_eq!(repo.resolve("v1.0^{}")?.id, first);
+    assert_eq!(repo.resolve(&repo.short_id(second)?)?.id, second);
+    assert_eq!(repo.resolve("HEAD:Form.xml")?.kind, ObjectType::Blob);
+    assert_eq!(repo.resolve("HEAD^{tree}")?.kind, ObjectType::Tree);
+    assert_eq!(repo.resolve(":/add the")?.id, first);
+    Ok(())
+}
+
+#[test]
+fn the_sides_of_a_conflict_resolve() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    remote.push_commit("main", "Form.xml", "<Form/>\n", "add the form")?;
+    let local = remote.clone_workspace()?;
+    remote.push_commit("main", "Form.xml", "<Form theirs/>\n", "their change")?;
+    let repo = Repo::open(&local.config)?;
+    std::fs::write(local.config.path.join("Form.xml"), "<Form ours/>\n").unwrap();
+    repo.add(["Form.xml"])?;
+    repo.commit("our change")?;
+    repo.prune_report()?;
+    let raw = Repository::open(&local.config.path)?;
+    let theirs = raw.find_annotated_commit(raw.refname_to_id("refs/remotes/origin/main")?)?;
+    raw.merge(&[&theirs], None, None)?;
+    let repo = Repo::open(&local.config)?;
+
+    let [conflict] = repo.conflicts()?.try_into().unwrap();
+
+    assert_eq!(conflict.path, "Form.xml");
+    // the sides of the conflict are the file at HEAD and at MERGE_HEAD
+    let ours = repo.resolve("HEAD:Form.xml")?;
+    let theirs = repo.resolve("MERGE_HEAD:Form.xml")?;
+    assert_eq!((Some(ours.id), Some(theirs.id)), (conflict.ours, conflict.theirs));
+    assert_eq!(ours.kind, ObjectType::Blob);
+    assert_eq!(raw.find_blob(ours.id)?.content(), b"<Form ours/>\n");
+    assert_eq!(raw.find_blob(theirs.id)?.content(), b"<Form theirs/>\n");
+    assert_eq!(repo.resolve("MERGE_HEAD")?.summary.as_deref(), Some("their change"));
+    Ok(())
+}
+
+#[test]
+fn unusable_revisions_say_why() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+
+    let e = repo.resolve("no-such-branch").expect_err("nothing has that name");
+    assert_eq!(e.code(), ErrorCode::NotFound, "{e}");
+    assert_eq!(e.message(), "'no-such-branch' names no commit, branch or tag");
+
+    let e = repo.resolve("HEAD~50").expect_err("the history is shorter");
+    assert_eq!(e.code(), ErrorCode::NotFound, "{e}");
+    assert!(e.message().starts_with("'HEAD~50': "), "{e}");
+
+    let e = repo.resolve("origin/main..main").expect_err("a range");
+    assert_eq!(e.code(), ErrorCode::InvalidSpec, "{e}");
+    assert!(e.message().contains("CompareBranches"), "{e}");
+    Ok(())
+}
+
+#[test]
+fn an_ambiguous_short_id_asks_for_more() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let raw = Repository::open(&local.config.path)?;
+    // both ids start with 6088742
+    raw.blob(b"form 6520\n")?;
+    raw.blob(b"form 9284\n")?;
+
+    let e = Repo::open(&local.config)?.resolve("6088742").expect_err("two blobs share it");
+
+    assert_eq!(e.code(), ErrorCode::Ambiguous, "{e}");
+    assert_eq!(e.message(), "'6088742' is a short id of several objects, type more of it");
+    Ok(())
+}
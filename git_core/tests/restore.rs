@@ -0,0 +1,138 @@
+//! Taking paths from another revision without merging it, see `Repo::checkout_paths_from`.
+
+use std::path::Path;
+
+use git_core::{
+    Repo,
+    RestoredPath,
+    testing::{SimulatedRemote, Workspace},
+};
+use git2::{Oid, Repository, ResetType};
+
+/// A clone on `main` with the files of `files` committed on `feature` only.
+fn clone_with_feature(files: &[(&str, &str)]) -> Result<(SimulatedRemote, Workspace), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    remote.push_commit("main", "Goods.xml", "<Catalog/>\n", "catalog")?;
+    let local = remote.clone_workspace()?;
+    let raw = Repository::open(&local.config.path)?;
+    let main = raw.head()?.peel_to_commit()?;
+    let feature = commit_files(&local, files)?;
+    raw.branch("feature", &raw.find_commit(feature)?, false)?;
+    raw.reset(main.as_object(), ResetType::Hard, None)?;
+    Ok((remote, local))
+}
+
+fn commit_files(local: &Workspace, files: &[(&str, &str)]) -> Result<Oid, git2::Error> {
+    let repo = Repo::open(&local.config)?;
+    for (path, content) in files {
+        let path = local.config.path.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+    repo.add(files.iter().map(|(path, _)| *path))?;
+    repo.commit("feature work")
+}
+
+fn restored(path: &str, files: usize) -> RestoredPath {
+    RestoredPath { path: path.to_string(), files }
+}
+
+fn read(dir: &Path, path: &str) -> String {
+    std::fs::read_to_string(dir.join(path)).unwrap()
+}
+
+#[test]
+fn found_paths_are_taken_and_missing_ones_reported() -> Result<(), git2::Error> {
+    let (_remote, local) = clone_with_feature(&[
+        ("Goods.xml", "<Catalog changed/>\n"),
+        ("Catalogs/Prices/Prices.xml", "<Catalog/>\n"),
+        ("Catalogs/Prices/Ext/ObjectModule.bsl", "Procedure Post()\n"),
+        ("Reports/Sales/Ext/Template.xml", "<Template/>\n"),
+    ])?;
+    let dir = &local.config.path;
+    let repo = Repo::open(&local.config)?;
+
+    let results = repo.checkout_paths_from(
+        "feature",
+        &["Catalogs", "Reports/Sales/Ext/Template.xml", "Missing.xml", "Goods.xml"],
+    )?;
+
+    assert_eq!(
+        results,
+        [
+            restored("Catalogs", 2),
+            restored("Reports/Sales/Ext/Template.xml", 1),
+            restored("Missing.xml", 0),
+            restored("Goods.xml", 1),
+        ]
+    );
+    assert_eq!(read(dir, "Catalogs/Prices/Ext/ObjectModule.bsl"), "Procedure Post()\n");
+    assert_eq!(read(dir, "Reports/Sales/Ext/Template.xml"), "<Template/>\n");
+    assert_eq!(read(dir, "Goods.xml"), "<Catalog changed/>\n");
+    assert!(!dir.join("Missing.xml").exists());
+    let status = repo.status()?;
+    let mut staged: Vec<_> = status.staged.iter().map(|file| file.new_file.as_str()).collect();
+    staged.sort_unstable();
+    assert_eq!(
+        staged,
+        [
+            "Catalogs/Prices/Ext/ObjectModule.bsl",
+            "Catalogs/Prices/Prices.xml",
+            "Goods.xml",
+            "Reports/Sales/Ext/Template.xml",
+        ]
+    );
+    assert!(status.not_staged.is_empty(), "{:?}", status.not_staged);
+    Ok(())
+}
+
+#[test]
+fn nothing_found_takes_nothing() -> Result<(), git2::Error> {
+    let (_remote, local) = clone_with_feature(&[("Goods.xml", "<Catalog changed/>\n")])?;
+    let repo = Repo::open(&local.config)?;
+
+    let results = repo.checkout_paths_from("feature", &["Missing.xml"])?;
+
+    assert_eq!(results, [restored("Missing.xml", 0)]);
+    assert_eq!(read(&local.config.path, "Goods.xml"), "<Catalog/>\n");
+    assert!(repo.status()?.staged.is_empty());
+    Ok(())
+}
+
+#[test]
+fn an_unknown_revision_fails() -> Result<(), git2::Error> {
+    let (_remote, local) = clone_with_feature(&[("Goods.xml", "<Catalog changed/>\n")])?;
+
+    let e = Repo::open(&local.config)?
+        .checkout_paths_from("no-such-branch", &["Goods.xml"])
+        .expect_err("there is no such revision");
+
+    assert_eq!(e.code(), git2::ErrorCode::NotFound, "{e}");
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn the_executable_bit_is_taken_along() -> Result<(), git2::Error> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let raw = Repository::open(&local.config.path)?;
+    let main = raw.head()?.peel_to_commit()?;
+    let script = local.config.path.join("build.sh");
+    std::fs::write(&script, "#!/bin/sh\n").unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let repo = Repo::open(&local.config)?;
+    repo.add(["build.sh"])?;
+    let feature = repo.commit("build script")?;
+    raw.branch("feature", &raw.find_commit(feature)?, false)?;
+    raw.reset(main.as_object(), ResetType::Hard, None)?;
+    assert!(!script.exists());
+
+    repo.checkout_paths_from("feature", &["build.sh"])?;
+
+    let mode = std::fs::metadata(&script).unwrap().permissions().mode();
+    assert_eq!(mode & 0o111, 0o111, "{mode:o}");
+    Ok(())
+}
@@ -0,0 +1,109 @@
+//! The repository's own git settings win over the crate's defaults, see
+//! `git_core::git_settings`.
+
+use git_core::{PushDefault, Repo, testing::SimulatedRemote};
+use git2::{ErrorCode, Repository};
+
+#[test]
+fn unset_settings_are_reported_as_none() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+
+    let settings = repo.effective_config()?;
+
+    assert_eq!(settings.hooks_path, None);
+    assert_eq!(settings.commit_template, None);
+    assert_eq!(repo.commit_template()?, None);
+    Ok(())
+}
+
+#[test]
+fn hooks_path_is_taken_from_the_working_tree() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    Repository::open(&local.config.path)?.config()?.set_str("core.hooksPath", ".githooks")?;
+
+    let settings = Repo::open(&local.config)?.effective_config()?;
+
+    assert_eq!(settings.hooks_path, Some(local.config.path.join(".githooks")));
+    Ok(())
+}
+
+#[test]
+fn commit_template_is_read_from_the_configured_file() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let template = "JIRA-: \n\n# what changed and why\n";
+    std::fs::write(local.config.path.join("message.txt"), template).unwrap();
+    Repository::open(&local.config.path)?.config()?.set_str("commit.template", "message.txt")?;
+    let repo = Repo::open(&local.config)?;
+
+    assert_eq!(repo.commit_template()?.as_deref(), Some(template));
+
+    std::fs::remove_file(local.config.path.join("message.txt")).unwrap();
+    let e = repo.commit_template().expect_err("the template is gone");
+    assert!(e.message().starts_with("failed to read commit.template"), "{e}");
+    Ok(())
+}
+
+#[test]
+fn push_default_upstream_pushes_to_a_differently_named_upstream() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let raw = Repository::open(&local.config.path)?;
+    let head = raw.head()?.peel_to_commit()?;
+    raw.branch("work", &head, false)?.set_upstream(Some("origin/main"))?;
+    let repo = Repo::open(&local.config)?;
+    repo.checkout("work")?;
+    std::fs::write(local.config.path.join("a.txt"), "ours\n").unwrap();
+    repo.add(["a.txt"])?;
+    let commit = repo.commit("work on main")?;
+
+    // `simple`, which refuses a differently named upstream
+    raw.config()?.set_str("push.default", "simple")?;
+    let e = repo.push().expect_err("the upstream is named differently");
+    assert!(e.message().contains("push.default is 'simple'"), "{e}");
+    raw.config()?.set_str("push.default", "upstream")?;
+    assert_eq!(repo.effective_config()?.push_default, Some(PushDefault::Upstream));
+    repo.push()?;
+
+    assert_eq!(remote.ref_target("refs/heads/main"), Some(commit));
+    assert_eq!(remote.ref_target("refs/heads/work"), None);
+    Ok(())
+}
+
+#[test]
+fn push_default_nothing_refuses_to_push() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    Repository::open(&local.config.path)?.config()?.set_str("push.default", "nothing")?;
+    let repo = Repo::open(&local.config)?;
+    let before = remote.ref_target("refs/heads/main");
+    std::fs::write(local.config.path.join("a.txt"), "ours\n").unwrap();
+    repo.add(["a.txt"])?;
+    repo.commit("not pushed")?;
+
+    let e = repo.push().expect_err("push.default is nothing");
+
+    assert_eq!(e.code(), ErrorCode::Invalid, "{e}");
+    assert_eq!(remote.ref_target("refs/heads/main"), before);
+    Ok(())
+}
+
+#[test]
+fn fetch_prune_false_keeps_deleted_branches() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    remote.push_commit("feature", "f.txt", "f\n", "feature work")?;
+    let local = remote.clone_workspace()?;
+    Repository::open(&local.config.path)?.config()?.set_bool("fetch.prune", false)?;
+    let repo = Repo::open(&local.config)?;
+
+    remote.delete_branch("feature")?;
+    let pruned = repo.prune_report()?;
+
+    assert_eq!(repo.effective_config()?.fetch_prune, Some(false));
+    assert!(pruned.is_empty(), "{pruned:?}");
+    assert!(repo.resolve_commit("origin/feature").is_ok());
+    Ok(())
+}
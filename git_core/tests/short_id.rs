@@ -0,0 +1,50 @@
+//! Ids shown to people, see `Repo::short_id`.
+
+use git_core::{Repo, testing::SimulatedRemote};
+use git2::Repository;
+
+#[test]
+fn short_ids_resolve_back_to_the_full_id() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    remote.push_commit("main", "Form.xml", "<Form/>\n", "form")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    let head = repo.resolve_commit("HEAD")?;
+
+    let short = repo.short_id(head)?;
+
+    assert_eq!(short.len(), 7, "{short}");
+    assert!(head.to_string().starts_with(&short));
+    assert_eq!(repo.resolve_commit(&short)?, head);
+    Ok(())
+}
+
+#[test]
+fn short_ids_grow_until_they_are_unambiguous() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let raw = Repository::open(&local.config.path)?;
+    // both ids start with 6088742
+    let first = raw.blob(b"form 6520\n")?;
+    let second = raw.blob(b"form 9284\n")?;
+    let repo = Repo::open(&local.config)?;
+
+    assert_eq!(repo.short_id(first)?, "6088742b");
+    assert_eq!(repo.short_id(second)?, "60887429");
+    assert_eq!(second.to_string(), "60887429697931c5f41f1887b41d5a71213a3e89");
+    Ok(())
+}
+
+#[test]
+fn commits_carry_both_ids() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let commit = remote.push_commit("main", "Form.xml", "<Form/>\n", "form")?;
+    let repo = Repo::open(&local.config)?;
+
+    let [incoming] = repo.incoming("main")?.try_into().unwrap();
+
+    assert_eq!(incoming.id, commit);
+    assert_eq!(incoming.short_id, repo.short_id(commit)?);
+    Ok(())
+}
@@ -0,0 +1,133 @@
+//! What `Repo::status` reports about the files themselves.
+
+use std::{
+    fs::File,
+    time::{Duration, SystemTime},
+};
+
+use git_core::{
+    Repo,
+    git_status::LFS_POINTER_MAX_SIZE,
+    testing::{SimulatedRemote, Workspace},
+};
+
+const POINTER: &str = "version https://git-lfs.github.com/spec/v1\n\
+                       oid sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393\n\
+                       size 12345\n";
+
+#[test]
+fn lfs_pointers_are_recognized_by_size_and_header() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    let write = |path: &str, content: &str| {
+        std::fs::write(local.config.path.join(path), content).unwrap();
+    };
+    // the header on a file too big to be a pointer, as a real file may start
+    let big = format!("{POINTER}{}", "x".repeat(LFS_POINTER_MAX_SIZE));
+    write("model.bin", POINTER);
+    write("big.bin", &big);
+    write("plain.txt", "version control notes\n");
+    repo.add(["model.bin", "big.bin", "plain.txt"])?;
+    write("untracked.bin", POINTER);
+
+    let status = repo.status()?;
+
+    let lfs_pointer = |path: &str| {
+        let file = status
+            .staged
+            .iter()
+            .chain(&status.untracked)
+            .find(|file| file.new_file == path)
+            .unwrap_or_else(|| panic!("{path} not in the status"));
+        file.lfs_pointer
+    };
+    assert!(lfs_pointer("model.bin"));
+    assert!(!lfs_pointer("big.bin"));
+    assert!(!lfs_pointer("plain.txt"));
+    // not hashed yet, read from the working tree
+    assert!(lfs_pointer("untracked.bin"));
+    Ok(())
+}
+
+/// A clone with `a.txt` committed, HEAD on main.
+fn committed_clone(remote: &SimulatedRemote) -> Result<Workspace, git2::Error> {
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    std::fs::write(local.config.path.join("a.txt"), "a\n").unwrap();
+    repo.add(["a.txt"])?;
+    repo.commit("add a")?;
+    Ok(local)
+}
+
+/// Sets the mtime of `path` in `local` to `time`.
+fn set_mtime(local: &Workspace, path: &str, time: SystemTime) {
+    let file = File::options().write(true).open(local.config.path.join(path)).unwrap();
+    file.set_modified(time).unwrap();
+}
+
+#[test]
+fn fingerprint_changes_with_branch_switches_and_commits() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = committed_clone(&remote)?;
+    let repo = Repo::open(&local.config)?;
+    let mut last = repo.status_fingerprint()?;
+    let mut changed = |repo: &Repo| -> Result<bool, git2::Error> {
+        let fingerprint = repo.status_fingerprint()?;
+        Ok(std::mem::replace(&mut last, fingerprint) != last)
+    };
+
+    // a branch at the same commit and the same index: only the ref name differs
+    let git = git2::Repository::open(&local.config.path)?;
+    let head = git.head()?.peel_to_commit()?;
+    git.branch("feature", &head, false)?;
+    repo.checkout("feature")?;
+    assert!(changed(&repo)?, "switching to a branch at the same commit");
+    // an empty commit: same files, same index
+    let signature = git2::Signature::now("Test Developer", "test@example.com")?;
+    git.commit(Some("HEAD"), &signature, &signature, "empty", &head.tree()?, &[&head])?;
+    assert!(changed(&repo)?, "an empty commit");
+    repo.checkout("main")?;
+    assert!(changed(&repo)?, "switching back");
+    Ok(())
+}
+
+#[test]
+fn fingerprint_boundaries() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = committed_clone(&remote)?;
+    let repo = Repo::open(&local.config)?;
+    let write = |path: &str, content: &str| {
+        std::fs::write(local.config.path.join(path), content).unwrap();
+    };
+    let past = SystemTime::now() - Duration::from_secs(3600);
+    let fingerprint = repo.status_fingerprint()?;
+
+    // touching an unmodified file: nothing changes
+    set_mtime(&local, "a.txt", SystemTime::now());
+    assert_eq!(repo.status_fingerprint()?, fingerprint);
+
+    write("a.txt", "modified\n");
+    set_mtime(&local, "a.txt", past);
+    let modified = repo.status_fingerprint()?;
+    assert_ne!(modified, fingerprint);
+
+    // touching a modified file changes it, although the status doesn't
+    set_mtime(&local, "a.txt", past + Duration::from_secs(1));
+    let touched = repo.status_fingerprint()?;
+    assert_ne!(touched, modified);
+
+    // editing it again under the same mtime is missed
+    write("a.txt", "modified again\n");
+    set_mtime(&local, "a.txt", past + Duration::from_secs(1));
+    assert_eq!(repo.status_fingerprint()?, touched);
+
+    // a new file deeper in an untracked directory is missed, the directory counts once
+    std::fs::create_dir_all(local.config.path.join("new/forms")).unwrap();
+    write("new/forms/one.xml", "1\n");
+    let untracked = repo.status_fingerprint()?;
+    assert_ne!(untracked, touched);
+    write("new/forms/two.xml", "2\n");
+    assert_eq!(repo.status_fingerprint()?, untracked);
+    Ok(())
+}
@@ -0,0 +1,212 @@
+//! Snapshots of the legacy status output, the text `Status` returns and the JSON and HTML
+//! reports, which 1C code parses. Any change to them breaks existing configurations.
+
+use git_core::{
+    Repo,
+    ReportFormat,
+    StatusBuilder,
+    StatusPolicy,
+    StatusSummary,
+    report,
+    testing::{SimulatedRemote, Workspace},
+};
+use git2::{Repository, StatusOptions};
+
+const TEXT: &str = "on branch feature
+upstream origin/feature is gone
+Changes to be committed:
+\tadded: added.txt
+\tmodified: both.txt (locked by Other Developer)
+\tadded: model.bin (LFS object)
+\tdeleted: old.txt
+\trenamed: moved.txt --> renamed.txt
+Changes not staged for commit:
+\tmodified: both.txt (locked by Other Developer)
+\tmodified: unstaged.txt
+Untracked files:
+\tuntracked: untracked.txt
+\tuntracked: Справочник.xml";
+
+const HTML_BODY: &str = "<h2>On branch feature</h2>
+<p>Upstream origin/feature is gone</p>
+<section><h3>Changes to be committed</h3><ul>
+<li>added: added.txt</li>
+<li>modified: both.txt (locked by Other Developer)</li>
+<li>added: model.bin (LFS object)</li>
+<li>deleted: old.txt</li>
+<li>renamed: moved.txt --&gt; renamed.txt</li>
+</ul></section>
+<section><h3>Changes not staged for commit</h3><ul>
+<li>modified: both.txt (locked by Other Developer)</li>
+<li>modified: unstaged.txt</li>
+</ul></section>
+<section><h3>Untracked files</h3><ul>
+<li>untracked: untracked.txt</li>
+<li>untracked: Справочник.xml</li>
+</ul></section>
+</body></html>
+";
+
+/// A clone with a change of every kind: staged, unstaged, both, renamed, deleted,
+/// untracked, an LFS pointer and a file locked by someone else, on a branch whose upstream
+/// was deleted.
+fn changed_workspace(remote: &SimulatedRemote) -> Result<Workspace, git2::Error> {
+    remote.push_commit("feature", "old.txt", "old\n", "old file")?;
+    remote.push_commit(
+        "feature",
+        "moved.txt",
+        "content to be moved\n".repeat(10).as_str(),
+        "moved",
+    )?;
+    remote.push_commit("feature", "both.txt", "both\n", "both")?;
+    remote.push_commit("feature", "unstaged.txt", "unstaged\n", "unstaged")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    repo.checkout("feature")?;
+    let other = remote.clone_workspace()?;
+    let other_config = git_core::Config {
+        username: "Other Developer".to_string(),
+        ..other.config.clone()
+    };
+    Repo::open(&other_config)?.lock_file("both.txt", "Other Developer")?;
+
+    let write = |path: &str, content: &str| {
+        std::fs::write(local.config.path.join(path), content).unwrap();
+    };
+    write("added.txt", "added\n");
+    write("Справочник.xml", "<Имя/>\n");
+    write("model.bin", "version https://git-lfs.github.com/spec/v1\nsize 1\n");
+    std::fs::remove_file(local.config.path.join("old.txt")).unwrap();
+    std::fs::rename(local.config.path.join("moved.txt"), local.config.path.join("renamed.txt"))
+        .unwrap();
+    write("both.txt", "staged\n");
+    let raw = Repository::open(&local.config.path)?;
+    let mut index = raw.index()?;
+    index.add_path("added.txt".as_ref())?;
+    index.add_path("model.bin".as_ref())?;
+    index.add_path("both.txt".as_ref())?;
+    index.add_path("renamed.txt".as_ref())?;
+    index.remove_path("moved.txt".as_ref())?;
+    index.remove_path("old.txt".as_ref())?;
+    index.write()?;
+    write("both.txt", "staged and changed again\n");
+    write("unstaged.txt", "changed\n");
+    write("untracked.txt", "untracked\n");
+
+    repo.prune_report()?;
+    remote.delete_branch("feature")?;
+    repo.prune_report()?;
+    Ok(local)
+}
+
+/// A JSON status entry with the fields every legacy entry has.
+fn entry(
+    status: &str,
+    old_file: &str,
+    new_file: &str,
+    lfs: bool,
+    locked_by: Option<&str>,
+) -> String {
+    let locked_by = locked_by.map_or("null".to_string(), |owner| format!("\"{owner}\""));
+    format!(
+        r#"{{"status":"{status}","old_file":"{old_file}","new_file":"{new_file}","entry_kind":"file","old_mode":null,"new_mode":null,"lfs_pointer":{lfs},"locked_by":{locked_by}}}"#
+    )
+}
+
+fn report(summary: &StatusSummary, format: ReportFormat) -> Result<String, git2::Error> {
+    let mut out = Vec::new();
+    report::write_status(summary, format, &mut out)?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+#[test]
+fn text_snapshot() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = changed_workspace(&remote)?;
+    let status = Repo::open(&local.config)?.status()?;
+
+    assert_eq!(status.to_string(), TEXT);
+    assert_eq!(report(&status, ReportFormat::Text)?, TEXT);
+    Ok(())
+}
+
+#[test]
+fn clean_text_snapshot() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+
+    let status = Repo::open(&local.config)?.status()?;
+
+    assert_eq!(status.to_string(), "on branch main\nnothing to commit, working tree clean");
+    Ok(())
+}
+
+#[test]
+fn json_snapshot() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = changed_workspace(&remote)?;
+    let status = Repo::open(&local.config)?.status()?;
+
+    let staged = [
+        entry("added", "added.txt", "added.txt", false, None),
+        entry("modified", "both.txt", "both.txt", false, Some("Other Developer")),
+        entry("added", "model.bin", "model.bin", true, None),
+        entry("deleted", "old.txt", "old.txt", false, None),
+        entry("renamed", "moved.txt", "renamed.txt", false, None),
+    ];
+    let not_staged = [
+        entry("modified", "both.txt", "both.txt", false, Some("Other Developer")),
+        entry("modified", "unstaged.txt", "unstaged.txt", false, None),
+    ];
+    let untracked = [
+        entry("untracked", "untracked.txt", "untracked.txt", false, None),
+        entry("untracked", "Справочник.xml", "Справочник.xml", false, None),
+    ];
+    let expected = format!(
+        r#"{{"branch_name":"feature","staged":[{}],"not_staged":[{}],"untracked":[{}],"upstream_gone":"origin/feature"}}"#,
+        staged.join(","),
+        not_staged.join(","),
+        untracked.join(",")
+    );
+    assert_eq!(report(&status, ReportFormat::Json)?, expected);
+    Ok(())
+}
+
+#[test]
+fn html_snapshot() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = changed_workspace(&remote)?;
+    let status = Repo::open(&local.config)?.status()?;
+
+    let html = report(&status, ReportFormat::Html)?;
+
+    let (_, body) = html.split_once("<body>").expect("an HTML document");
+    assert_eq!(body, HTML_BODY);
+    Ok(())
+}
+
+#[test]
+fn add_entry_shim_matches_the_builder() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = changed_workspace(&remote)?;
+    let raw = Repository::open(&local.config.path)?;
+    let mut options = StatusOptions::new();
+    options.include_untracked(true).renames_head_to_index(true);
+    let statuses = raw.statuses(Some(&mut options))?;
+
+    let mut legacy = StatusSummary::new("feature".to_string());
+    for entry in statuses.iter() {
+        legacy.add_entry(&entry);
+    }
+    let mut builder = StatusBuilder::new("feature".to_string(), StatusPolicy::default());
+    builder.add_statuses(&statuses);
+
+    assert_eq!(legacy.to_string(), builder.build().to_string());
+    // the lock and LFS annotations come from Repo::status, the sections are the same
+    let plain = TEXT
+        .replace("upstream origin/feature is gone\n", "")
+        .replace(" (locked by Other Developer)", "")
+        .replace(" (LFS object)", "");
+    assert_eq!(legacy.to_string(), plain);
+    Ok(())
+}
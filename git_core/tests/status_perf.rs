@@ -0,0 +1,149 @@
+//! The ways a status scans the working tree, see `StatusPerfMode` and
+//! `StatusPolicy::include_untracked`.
+
+use std::{
+    fs::File,
+    path::Path,
+    time::{Duration, Instant, SystemTime},
+};
+
+use git_core::{
+    Config,
+    Repo,
+    StatusPerfMode,
+    StatusPolicy,
+    testing::{SimulatedRemote, Workspace},
+};
+use git2::{IndexAddOption, Repository};
+
+/// A clone with `dirs` directories of `files` forms each committed on top.
+fn clone_with_forms(
+    dirs: usize,
+    files: usize,
+) -> Result<(SimulatedRemote, Workspace), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    for dir in 0..dirs {
+        let dir_path = local.config.path.join(format!("Catalogs/Catalog{dir}/Forms"));
+        std::fs::create_dir_all(&dir_path).unwrap();
+        for file in 0..files {
+            std::fs::write(
+                dir_path.join(format!("Form{file}.xml")),
+                format!("<Form {dir} {file}/>\n"),
+            )
+            .unwrap();
+        }
+    }
+    let raw = Repository::open(&local.config.path)?;
+    let mut index = raw.index()?;
+    index.add_all(["Catalogs"], IndexAddOption::DEFAULT, None)?;
+    index.write()?;
+    Repo::open(&local.config)?.commit("add the forms")?;
+    Ok((remote, local))
+}
+
+fn with_mode(config: &Config, mode: StatusPerfMode) -> Config {
+    Config {
+        status_perf_mode: mode,
+        ..config.clone()
+    }
+}
+
+/// Moves the modification time of `path` an hour back, its content unchanged. Back rather
+/// than ahead, files newer than the index are hashed on every status anyway.
+fn touch(path: &Path) -> SystemTime {
+    let modified = SystemTime::now() - Duration::from_secs(60 * 60);
+    File::options().write(true).open(path).unwrap().set_modified(modified).unwrap();
+    modified
+}
+
+#[test]
+fn both_modes_report_the_same() -> Result<(), git2::Error> {
+    let (_remote, local) = clone_with_forms(3, 4)?;
+    let dir = &local.config.path;
+    std::fs::write(dir.join("Catalogs/Catalog0/Forms/Form0.xml"), "<Form changed/>\n").unwrap();
+    std::fs::remove_file(dir.join("Catalogs/Catalog1/Forms/Form1.xml")).unwrap();
+    std::fs::write(dir.join("Catalogs/Catalog2/Forms/New.xml"), "<Form/>\n").unwrap();
+    touch(&dir.join("Catalogs/Catalog2/Forms/Form2.xml"));
+
+    let conservative =
+        Repo::open(&with_mode(&local.config, StatusPerfMode::Conservative))?.status()?;
+    let fast = Repo::open(&with_mode(&local.config, StatusPerfMode::Fast))?.status()?;
+
+    assert_eq!(fast.to_string(), conservative.to_string());
+    let changed: Vec<_> = fast.not_staged.iter().map(|file| file.new_file.as_str()).collect();
+    assert_eq!(changed, ["Catalogs/Catalog0/Forms/Form0.xml", "Catalogs/Catalog1/Forms/Form1.xml"]);
+    assert_eq!(fast.untracked.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn the_fast_mode_records_touched_files_as_unchanged() -> Result<(), git2::Error> {
+    let (_remote, local) = clone_with_forms(1, 2)?;
+    let touched = "Catalogs/Catalog0/Forms/Form1.xml";
+    let modified = touch(&local.config.path.join(touched));
+    let modified = modified.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
+    let indexed_mtime = || -> Result<u64, git2::Error> {
+        let index = Repository::open(&local.config.path)?.index()?;
+        Ok(index.get_path(Path::new(touched), 0).unwrap().mtime.seconds().try_into().unwrap())
+    };
+
+    Repo::open(&with_mode(&local.config, StatusPerfMode::Conservative))?.status()?;
+    assert_ne!(indexed_mtime()?, modified);
+    let status = Repo::open(&with_mode(&local.config, StatusPerfMode::Fast))?.status()?;
+
+    assert!(status.not_staged.is_empty(), "{status}");
+    assert_eq!(indexed_mtime()?, modified);
+    Ok(())
+}
+
+#[test]
+fn untracked_files_can_be_left_out() -> Result<(), git2::Error> {
+    let (_remote, local) = clone_with_forms(1, 2)?;
+    let dir = &local.config.path;
+    std::fs::write(dir.join("Catalogs/Catalog0/Forms/Form0.xml"), "<Form changed/>\n").unwrap();
+    std::fs::write(dir.join("new.txt"), "new\n").unwrap();
+    let repo = Repo::open(&local.config)?;
+
+    let status = repo.status_with(StatusPolicy {
+        include_untracked: false,
+        ..StatusPolicy::default()
+    })?;
+
+    assert!(status.untracked.is_empty(), "{status}");
+    assert_eq!(status.not_staged.len(), 1, "{status}");
+    assert_eq!(repo.status()?.untracked.len(), 1);
+    Ok(())
+}
+
+/// How long the status of 20,000 forms takes in either mode, clean and with every file
+/// touched: `cargo test -p git_core --release --test status_perf -- --ignored --nocapture`.
+/// The fast mode is meant for a cold cache on NTFS, which this warm one can't show.
+#[test]
+#[ignore = "benchmark"]
+fn benchmark_status_modes() -> Result<(), git2::Error> {
+    for mode in [StatusPerfMode::Conservative, StatusPerfMode::Fast] {
+        let (_remote, local) = clone_with_forms(200, 100)?;
+        let config = with_mode(&local.config, mode);
+        let time = |what: &str| -> Result<(), git2::Error> {
+            let repo = Repo::open(&config)?;
+            let started = Instant::now();
+            let status = repo.status()?;
+            println!("{mode}, {what}: {:.2?}", started.elapsed());
+            assert!(status.not_staged.is_empty(), "{status}");
+            Ok(())
+        };
+
+        time("clean")?;
+        for dir in 0..200 {
+            for file in 0..100 {
+                touch(
+                    &local.config.path.join(format!("Catalogs/Catalog{dir}/Forms/Form{file}.xml")),
+                );
+            }
+        }
+        time("every file touched")?;
+        time("every file touched, again")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,108 @@
+//! When the clone last talked to each remote, see `git_core::git_sync`.
+
+use std::{
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use git_core::{RemoteSync, Repo, testing::SimulatedRemote};
+
+fn now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64
+}
+
+fn state_file(repo: &Repo<'_>) -> PathBuf {
+    repo.common_dir().join("addin-state.json")
+}
+
+fn never_synced() -> Vec<RemoteSync> {
+    vec![RemoteSync {
+        remote: "origin".to_string(),
+        ..RemoteSync::default()
+    }]
+}
+
+#[test]
+fn fetch_push_and_pull_are_recorded() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    let before = now();
+
+    repo.prune_report()?;
+    std::fs::write(local.config.path.join("a.txt"), "ours\n").unwrap();
+    repo.add(["a.txt"])?;
+    repo.commit("ours")?;
+    assert_eq!(repo.sync_state()?.ahead_behind, Some((1, 0)));
+    repo.push()?;
+    repo.pull("main")?;
+
+    let state = repo.sync_state()?;
+    let [origin] = state.remotes.as_slice() else {
+        panic!("expected origin only: {state:?}");
+    };
+    let after = now();
+    for time in [origin.last_fetch, origin.last_push, origin.last_pull] {
+        assert!(time.is_some_and(|time| (before..=after).contains(&time)), "{origin:?}");
+    }
+    assert_eq!(state.last_fetch(), origin.last_fetch);
+    assert_eq!(state.ahead_behind, Some((0, 0)));
+    Ok(())
+}
+
+#[test]
+fn a_missing_state_file_is_never_synced() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    let _ = std::fs::remove_file(state_file(&repo));
+
+    let state = repo.sync_state()?;
+
+    assert_eq!(state.remotes, never_synced());
+    assert_eq!(state.last_fetch(), None);
+    assert_eq!(state.ahead_behind, Some((0, 0)));
+    Ok(())
+}
+
+#[test]
+fn a_corrupt_state_file_is_never_synced_and_replaced() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+
+    for corrupt in ["", "{\"origin\":{\"fetch\":17", "[1, 2]", "\"origin\"", "\u{0}\u{1}binary"] {
+        std::fs::write(state_file(&repo), corrupt).unwrap();
+        assert_eq!(repo.sync_state()?.remotes, never_synced(), "{corrupt:?}");
+    }
+
+    repo.prune_report()?;
+
+    assert!(repo.sync_state()?.remotes[0].last_fetch.is_some());
+    Ok(())
+}
+
+#[test]
+fn values_that_are_not_times_are_never_synced() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    std::fs::write(
+        state_file(&repo),
+        r#"{"origin":{"fetch":"yesterday","pull":-5,"push":1.5},"gone":{"fetch":1700000000}}"#,
+    )
+    .unwrap();
+    assert_eq!(repo.sync_state()?.remotes, never_synced());
+
+    std::fs::write(state_file(&repo), r#"{"origin":{"fetch":1700000000,"push":null}}"#).unwrap();
+
+    assert_eq!(
+        repo.sync_state()?.remotes,
+        [RemoteSync {
+            remote: "origin".to_string(),
+            last_fetch: Some(1_700_000_000),
+            ..RemoteSync::default()
+        }]
+    );
+    Ok(())
+}
@@ -0,0 +1,84 @@
+//! Fetching from one remote and pushing to another, see `Repo::push_remote`.
+
+use git_core::{
+    Repo,
+    testing::{SimulatedRemote, Workspace},
+};
+use git2::Repository;
+
+/// A clone of `upstream` with `fork` added as a second remote.
+fn clone_with_fork(
+    upstream: &SimulatedRemote,
+    fork: &SimulatedRemote,
+) -> Result<Workspace, git2::Error> {
+    let local = upstream.clone_workspace()?;
+    Repo::open(&local.config)?.add_remote("fork", &fork.url())?;
+    Ok(local)
+}
+
+#[test]
+fn pulls_from_the_upstream_and_pushes_to_the_fork() -> Result<(), git2::Error> {
+    let upstream = SimulatedRemote::new("main")?;
+    let fork = SimulatedRemote::new("main")?;
+    let local = clone_with_fork(&upstream, &fork)?;
+    let repo = Repo::open(&local.config)?;
+    repo.set_push_remote("main", "fork")?;
+    assert_eq!(repo.push_remote("refs/heads/main")?, "fork");
+
+    let theirs = upstream.push_commit("main", "theirs.txt", "theirs\n", "upstream work")?;
+    repo.prune_report()?;
+    repo.pull("main")?;
+    std::fs::write(local.config.path.join("ours.txt"), "ours\n").unwrap();
+    repo.add(["ours.txt"])?;
+    let ours = repo.commit("our work")?;
+    repo.push()?;
+
+    assert_eq!(fork.ref_target("refs/heads/main"), Some(ours));
+    assert_eq!(upstream.ref_target("refs/heads/main"), Some(theirs));
+    let raw = Repository::open(&local.config.path)?;
+    assert_eq!(raw.find_commit(ours)?.parent_id(0)?, theirs);
+    // fetching still follows the upstream
+    assert_eq!(
+        raw.branch_upstream_name("refs/heads/main")?.as_str(),
+        Some("refs/remotes/origin/main")
+    );
+    Ok(())
+}
+
+#[test]
+fn push_default_applies_to_branches_without_their_own() -> Result<(), git2::Error> {
+    let upstream = SimulatedRemote::new("main")?;
+    let fork = SimulatedRemote::new("main")?;
+    let local = clone_with_fork(&upstream, &fork)?;
+    let repo = Repo::open(&local.config)?;
+    Repository::open(&local.config.path)?.config()?.set_str("remote.pushDefault", "fork")?;
+    assert_eq!(repo.push_remote("refs/heads/main")?, "fork");
+
+    repo.set_push_remote("main", "origin")?;
+    assert_eq!(repo.push_remote("refs/heads/main")?, "origin");
+    repo.set_push_remote("main", "")?;
+    assert_eq!(repo.push_remote("refs/heads/main")?, "fork");
+
+    std::fs::write(local.config.path.join("ours.txt"), "ours\n").unwrap();
+    repo.add(["ours.txt"])?;
+    let ours = repo.commit("our work")?;
+    let before = upstream.ref_target("refs/heads/main");
+    repo.push()?;
+
+    assert_eq!(fork.ref_target("refs/heads/main"), Some(ours));
+    assert_eq!(upstream.ref_target("refs/heads/main"), before);
+    Ok(())
+}
+
+#[test]
+fn an_unknown_push_remote_is_refused() -> Result<(), git2::Error> {
+    let upstream = SimulatedRemote::new("main")?;
+    let local = upstream.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+
+    repo.set_push_remote("main", "fork").expect_err("there is no fork remote");
+    repo.set_push_remote("missing", "origin").expect_err("there is no such branch");
+
+    assert_eq!(repo.push_remote("refs/heads/main")?, "origin");
+    Ok(())
+}
@@ -0,0 +1,171 @@
+//! Commit signatures checked against an SSH allowed signers file, see
+//! `git_core::git_verify`. The keys are made with the `ssh-keygen` on the path.
+
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use git_core::{
+    Config,
+    PullResult,
+    Repo,
+    VerificationStatus,
+    VerifyPolicy,
+    testing::{SimulatedRemote, TempDir},
+};
+use git2::{ErrorCode, Oid, Repository, Signature};
+
+fn ssh_keygen_failed(e: impl std::fmt::Display) -> git2::Error {
+    git2::Error::from_str(&format!("ssh-keygen failed: {e}"))
+}
+
+/// A new passphrase-less ed25519 key in `dir`, the path of its private half.
+fn generate_key(dir: &Path, name: &str) -> Result<PathBuf, git2::Error> {
+    let key = dir.join(name);
+    let status = Command::new("ssh-keygen")
+        .args(["-q", "-t", "ed25519", "-N", "", "-C", name, "-f"])
+        .arg(&key)
+        .status()
+        .map_err(ssh_keygen_failed)?;
+    if !status.success() {
+        return Err(ssh_keygen_failed(status));
+    }
+    Ok(key)
+}
+
+/// An allowed signers file trusting `key` for `principal`.
+fn allowed_signers(dir: &Path, principal: &str, key: &Path) -> Result<PathBuf, git2::Error> {
+    let public = std::fs::read_to_string(key.with_extension("pub")).map_err(ssh_keygen_failed)?;
+    let file = dir.join("allowed_signers");
+    std::fs::write(&file, format!("{principal} {public}")).map_err(ssh_keygen_failed)?;
+    Ok(file)
+}
+
+/// Commits `content` as `path` on top of HEAD signed with `key`, like `git commit -S` with
+/// `gpg.format = ssh`, and moves the current branch to it.
+fn commit_signed(
+    repo_path: &Path,
+    path: &str,
+    content: &str,
+    key: &Path,
+) -> Result<Oid, git2::Error> {
+    let repo = Repository::open(repo_path)?;
+    let head = repo.head()?;
+    let parent = head.peel_to_commit()?;
+    let mut tree = repo.treebuilder(Some(&parent.tree()?))?;
+    tree.insert(path, repo.blob(content.as_bytes())?, 0o100_644)?;
+    let tree = repo.find_tree(tree.write()?)?;
+    let author = Signature::now("Test Developer", "test@example.com")?;
+    let buffer = repo.commit_create_buffer(&author, &author, "signed change", &tree, &[&parent])?;
+
+    let mut sign = Command::new("ssh-keygen")
+        .args(["-Y", "sign", "-n", "git", "-f"])
+        .arg(key)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(ssh_keygen_failed)?;
+    sign.stdin.take().unwrap().write_all(&buffer).map_err(ssh_keygen_failed)?;
+    let output = sign.wait_with_output().map_err(ssh_keygen_failed)?;
+    if !output.status.success() {
+        return Err(ssh_keygen_failed(output.status));
+    }
+    let signature = String::from_utf8(output.stdout).map_err(ssh_keygen_failed)?;
+
+    let oid = repo.commit_signed(buffer.as_str().unwrap(), &signature, None)?;
+    repo.reference(head.name().unwrap(), oid, true, "signed change")?;
+    Ok(oid)
+}
+
+#[test]
+fn ssh_signatures_are_checked_against_the_allowed_signers() -> Result<(), git2::Error> {
+    let keys = TempDir::new("git-core-keys")?;
+    let trusted = generate_key(keys.path(), "trusted")?;
+    let stranger = generate_key(keys.path(), "stranger")?;
+    let policy = VerifyPolicy {
+        allowed_signers: Some(allowed_signers(keys.path(), "dev@example.com", &trusted)?),
+        ..VerifyPolicy::default()
+    };
+
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    let initial = repo.resolve_commit("HEAD")?;
+    let valid = commit_signed(&local.config.path, "a.txt", "a\n", &trusted)?;
+    let unknown = commit_signed(&local.config.path, "b.txt", "b\n", &stranger)?;
+    std::fs::write(local.config.path.join("c.txt"), "c\n").unwrap();
+    repo.add(["c.txt"])?;
+    let unsigned = repo.commit("unsigned change")?;
+
+    let results = repo.verify_commits(&format!("{initial}..HEAD"), &policy)?;
+
+    let statuses = results.iter().map(|result| (result.commit, result.status.clone()));
+    assert_eq!(
+        statuses.collect::<Vec<_>>(),
+        [
+            (unsigned, VerificationStatus::Unsigned),
+            (unknown, VerificationStatus::UnknownKey),
+            (
+                valid,
+                VerificationStatus::Valid {
+                    signer: "dev@example.com".to_string()
+                }
+            ),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn ssh_signature_without_allowed_signers_is_invalid() -> Result<(), git2::Error> {
+    let keys = TempDir::new("git-core-keys")?;
+    let key = generate_key(keys.path(), "key")?;
+    let remote = SimulatedRemote::new("main")?;
+    let local = remote.clone_workspace()?;
+    let repo = Repo::open(&local.config)?;
+    commit_signed(&local.config.path, "a.txt", "a\n", &key)?;
+
+    let results = repo.verify_commits("HEAD^..HEAD", &VerifyPolicy::default())?;
+
+    assert_eq!(results.len(), 1);
+    assert!(matches!(results[0].status, VerificationStatus::Invalid(_)), "{}", results[0].status);
+    Ok(())
+}
+
+#[test]
+fn pull_of_a_protected_branch_requires_valid_signatures() -> Result<(), git2::Error> {
+    let keys = TempDir::new("git-core-keys")?;
+    let key = generate_key(keys.path(), "key")?;
+    let policy = VerifyPolicy {
+        allowed_signers: Some(allowed_signers(keys.path(), "dev@example.com", &key)?),
+        protected_branches: vec!["main".to_string()],
+        ..VerifyPolicy::default()
+    };
+    let remote = SimulatedRemote::new("main")?;
+    let author = remote.clone_workspace()?;
+    let reader = remote.clone_workspace()?;
+    let reader_config = Config {
+        verify_policy: Some(policy),
+        ..reader.config.clone()
+    };
+    let repo = Repo::open(&reader_config)?;
+
+    let signed = commit_signed(&author.config.path, "a.txt", "a\n", &key)?;
+    Repo::open(&author.config)?.push()?;
+    repo.prune_report()?;
+    let PullResult::FastForwarded { new_id, .. } = repo.pull("main")? else {
+        panic!("expected a fast-forward to the signed commit");
+    };
+    assert_eq!(new_id, signed);
+
+    let unsigned = remote.push_commit("main", "b.txt", "b\n", "unsigned change")?;
+    repo.prune_report()?;
+    let e = repo.pull("main").expect_err("the fetched commit isn't signed");
+    assert_eq!(e.code(), ErrorCode::Certificate, "{e}");
+    assert!(e.message().contains(&format!("{unsigned} unsigned change: unsigned")), "{e}");
+    assert_eq!(repo.resolve_commit("HEAD")?, signed);
+    Ok(())
+}
@@ -0,0 +1,40 @@
+//! A linked worktree opens like a clone and shares the main repository's state.
+
+use git_core::{Config, Repo, testing::SimulatedRemote};
+use git2::Repository;
+
+#[test]
+fn linked_worktree_opens_with_shared_state() -> Result<(), git2::Error> {
+    let remote = SimulatedRemote::new("main")?;
+    let main = remote.clone_workspace()?;
+    let worktree_path = main.config.path.with_file_name("feature-tree");
+    Repository::open(&main.config.path)?.worktree("feature", &worktree_path, None)?;
+    let config = Config {
+        path: worktree_path.clone(),
+        ..main.config.clone()
+    };
+
+    let worktree = Repo::open(&config)?;
+    let main_repo = Repo::open(&main.config)?;
+    assert!(worktree.is_worktree());
+    assert!(!main_repo.is_worktree());
+    assert_eq!(
+        worktree.common_dir().canonicalize().unwrap(),
+        main_repo.common_dir().canonicalize().unwrap()
+    );
+    assert_eq!(worktree.current_branch()?.local_name(), "feature");
+    assert_eq!(main_repo.current_branch()?.local_name(), "main");
+
+    // a commit in the worktree moves the branch both see, HEADs stay apart
+    std::fs::write(worktree_path.join("f.txt"), "feature\n").unwrap();
+    worktree.add(["f.txt"])?;
+    let commit = worktree.commit("feature work")?;
+    assert_eq!(main_repo.resolve_commit("feature")?, commit);
+    assert_ne!(main_repo.resolve_commit("HEAD")?, commit);
+    assert!(main_repo.status()?.untracked.is_empty());
+
+    // a fetch from the worktree counts for the whole clone
+    worktree.prune_report()?;
+    assert!(main_repo.sync_state()?.last_fetch().is_some());
+    Ok(())
+}
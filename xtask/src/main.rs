@@ -0,0 +1,62 @@
+//! Chores of the workspace, run as `cargo xtask <task>`:
+//!
+//! - `size [--max-kib N] [-- <cargo build args>]` builds the addin for release and fails
+//!   when its library is larger than N KiB, [`MAX_ADDIN_KIB`] by default. The library is
+//!   copied to every workstation by the 1C update mechanism, so growth should be a choice.
+
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Largest the release addin library may be, in KiB.
+const MAX_ADDIN_KIB: u64 = 8 * 1024;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("size") => size(args.collect()),
+        _ => Err("usage: cargo xtask size [--max-kib N] [-- <cargo build args>]".into()),
+    }
+}
+
+fn size(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let (own, build_args) = match args.iter().position(|arg| arg == "--") {
+        Some(split) => (&args[..split], &args[split + 1..]),
+        None => (&args[..], &[][..]),
+    };
+    let max_kib = match own {
+        [] => MAX_ADDIN_KIB,
+        [flag, value] if flag == "--max-kib" => value.parse()?,
+        _ => return Err(format!("unexpected arguments {own:?}").into()),
+    };
+
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().ok_or("no workspace root")?;
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let status = Command::new(cargo)
+        .current_dir(root)
+        .args(["build", "--release", "-p", "git_addin"])
+        .args(build_args)
+        .status()?;
+    if !status.success() {
+        return Err(format!("the release build failed with {status}").into());
+    }
+
+    let target =
+        std::env::var_os("CARGO_TARGET_DIR").map_or_else(|| root.join("target"), PathBuf::from);
+    let library = target.join("release").join(format!(
+        "{}git_addin{}",
+        std::env::consts::DLL_PREFIX,
+        std::env::consts::DLL_SUFFIX
+    ));
+    let kib = std::fs::metadata(&library)
+        .map_err(|e| format!("failed to read '{}': {e}", library.display()))?
+        .len()
+        .div_ceil(1024);
+    println!("{}: {kib} KiB of at most {max_kib} KiB", library.display());
+    if kib > max_kib {
+        return Err(format!("the addin grew past {max_kib} KiB").into());
+    }
+    Ok(())
+}